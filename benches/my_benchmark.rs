@@ -1,6 +1,8 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use eui48::MacAddress;
-use sqlite3_nettools::{mac::MacStyle, oui::Oui};
+use ipnet::IpNet;
+use rusqlite::Connection;
+use sqlite3_nettools::{mac::{MacAddr, MacStyle}, oui::{Oui, OuiDb, OuiMeta, SharedOuiDb, EMBEDDED_DB}, text::TextBuf};
+use std::fmt::Write;
 
 
 // #[bench]
@@ -13,7 +15,7 @@ use sqlite3_nettools::{mac::MacStyle, oui::Oui};
 //         (MacStyle::Colon, true),
 //     ];
 
-//     const MAC: MacAddress = MacAddress::from_bytes(&[0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]).unwrap();
+//     const MAC: MacAddr = MacAddr::from_bytes(&[0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]).unwrap();
 
 //     b.iter(|| {
 //         for (st, cap) in CASES.iter() {
@@ -33,11 +35,135 @@ fn criterion_benchmark(c: &mut Criterion) {
     ];
 
     
-    let mac: MacAddress = Oui::from_int(0x0000AABBCCDDEEFF).unwrap().as_mac();
+    let mac: MacAddr = Oui::from_int(0x0000AABBCCDDEEFF).unwrap().as_mac();
 
     c.bench_function("stringify macs", |b| b.iter(|| CASES.iter().map(|(st, cap)| {
         black_box(st.format(black_box(mac), black_box(*cap)))
     })));
+
+    // a /24, a /28, a /36, a /36 falling back to its /24, and a miss
+    let lookups: Vec<MacAddr> = [0x3CA6F6C434F8, 0x8C1CDA824C2E, 0x8C1F64CB2BCC, 0x2C279EFABBCC, 0xB0C55AAABBCC]
+        .into_iter()
+        .map(|i| Oui::from_int(i).unwrap().as_mac())
+        .collect();
+    lazy_static::initialize(&EMBEDDED_DB);
+
+    c.bench_function("oui lookups", |b| b.iter(|| for mac in &lookups {
+        black_box(EMBEDDED_DB.search_entry(black_box(*mac)));
+    }));
+
+    // the binary search and backward scan the prefix index replaced, for comparison
+    let sorted: Vec<(Oui, OuiMeta<&str>)> = EMBEDDED_DB.raw_prefixes().collect();
+    for mac in &lookups {
+        assert_eq!(backward_scan(&sorted, *mac).map(|(o, _)| *o), EMBEDDED_DB.search_prefix(*mac));
+    }
+    c.bench_function("oui lookups: backward scan", |b| b.iter(|| for mac in &lookups {
+        black_box(backward_scan(black_box(&sorted), black_box(*mac)));
+    }));
+
+    // the default layers (well-known addresses over the embedded database), where repeated lookups are cached
+    let layers = SharedOuiDb::default().load();
+    c.bench_function("oui layered lookups", |b| b.iter(|| for mac in &lookups {
+        black_box(layers.search_layered(black_box(*mac)));
+    }));
+
+    let batch: Vec<MacAddr> = lookups.iter().copied().cycle().take(10_000).collect();
+    let mut results = Vec::with_capacity(batch.len());
+    c.bench_function("oui batch lookups", |b| b.iter(|| {
+        EMBEDDED_DB.search_slice(black_box(&batch), &mut results);
+        black_box(&results);
+    }));
+
+    // the embedded database written back out as a manuf file. Compare the sequential and `rayon` parsers with
+    // `cargo bench -- --save-baseline seq "oui parse"`, then `cargo bench --features rayon -- --baseline seq "oui parse"`
+    let mut manuf = String::new();
+    for (o, om) in EMBEDDED_DB.raw_prefixes() {
+        match om.manuf_long() {
+            Some(long) => writeln!(manuf, "{o}\t{}\t{long}", om.manuf()).unwrap(),
+            None => writeln!(manuf, "{o}\t{}", om.manuf()).unwrap(),
+        }
+    }
+    assert_eq!(OuiDb::parse_from_string(&manuf).unwrap().len(), EMBEDDED_DB.len());
+    c.bench_function("oui parse manuf", |b| b.iter(|| {
+        black_box(OuiDb::parse_from_string(black_box(&manuf)).unwrap());
+    }));
+
+    let net: IpNet = "2001:db8:85a3::8a2e:370:7334/64".parse().unwrap();
+    c.bench_function("ip format: to_string", |b| b.iter(|| {
+        black_box(black_box(net).to_string());
+    }));
+    c.bench_function("ip format: stack buffer", |b| b.iter(|| {
+        let mut buf = TextBuf::<48>::new();
+        write!(buf, "{}", black_box(net)).unwrap();
+        black_box(buf);
+    }));
+    c.bench_function("ip bucket: to_string", |b| b.iter(|| {
+        black_box(black_box(net).trunc().to_string());
+    }));
+    c.bench_function("ip bucket: stack buffer", |b| b.iter(|| {
+        let mut buf = TextBuf::<48>::new();
+        write!(buf, "{}", black_box(net).trunc()).unwrap();
+        black_box(buf);
+    }));
+
+    // a loadable extension build can only open connections from within SQLite, run with `--no-default-features`
+    if cfg!(not(feature = "loadable-extension")) {
+        sql_benchmarks(c);
+    }
+}
+
+/// The OUI search before the prefix index: a binary search for the address, then a scan back through the sorted
+/// entries for one containing it, stopping at the first /24 that doesn't.
+fn backward_scan<'a>(entries: &'a [(Oui, OuiMeta<&'a str>)], mac: MacAddr) -> Option<&'a (Oui, OuiMeta<&'a str>)> {
+    let as_oui = Oui::from_addr(mac);
+    let mut i = match entries.binary_search_by_key(&as_oui, |(o, _om)| *o) {
+        Ok(i) => i,
+        Err(i) => i.checked_sub(1)?,
+    };
+    loop {
+        let entry = entries.get(i)?;
+        if entry.0.contains(&as_oui) {
+            return Some(entry);
+        } else if entry.0.length() <= 24 {
+            return None;
+        }
+        i = i.checked_sub(1)?;
+    }
+}
+
+/// Benchmarks the text-returning functions through SQLite, once per row of a 1000 row table.
+fn sql_benchmarks(c: &mut Criterion) {
+    let conn = Connection::open_in_memory().unwrap();
+    sqlite3_nettools::register(&conn).unwrap();
+    conn.execute_batch("
+        CREATE TABLE addrs AS
+        WITH RECURSIVE n(i) AS (SELECT 0 UNION ALL SELECT i + 1 FROM n WHERE i < 999)
+        SELECT printf('3c:a6:f6:%02x:%02x:%02x', i / 256, i % 256, i % 7) AS mac,
+               printf('2001:db8:%x::%x/64', i, i * 7) AS ip
+        FROM n;
+        ALTER TABLE addrs ADD COLUMN ipb BLOB;
+        UPDATE addrs SET ipb = IP_BLOBIFY(ip);
+    ").unwrap();
+
+    let queries = [
+        ("sql MAC_FORMAT", "SELECT MAC_FORMAT(mac, 'dash') FROM addrs"),
+        ("sql MAC_FORMAT template", "SELECT MAC_FORMAT(mac, 'xxxx.xxxx.xxxx') FROM addrs"),
+        ("sql MAC_PREFIX", "SELECT MAC_PREFIX(mac) FROM addrs"),
+        ("sql MAC_BUCKET", "SELECT MAC_BUCKET(mac, 28) FROM addrs"),
+        ("sql IP_FORMAT", "SELECT IP_FORMAT(ip) FROM addrs"),
+        ("sql IP_BUCKET", "SELECT IP_BUCKET(ip, 48) FROM addrs"),
+        ("sql IP_CONTAINS", "SELECT IP_CONTAINS(ip, '2001:db8::/40') FROM addrs"),
+        ("sql IPB_CONTAINS", "SELECT IPB_CONTAINS(ipb, X'20010DB800000000000000000000000028') FROM addrs"),
+    ];
+    for (name, sql) in queries {
+        let mut stmt = conn.prepare(sql).unwrap();
+        c.bench_function(name, |b| b.iter(|| {
+            let mut rows = stmt.query([]).unwrap();
+            while let Some(row) = rows.next().unwrap() {
+                black_box(row.get_ref(0).unwrap());
+            }
+        }));
+    }
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -1,17 +1,52 @@
-use std::path::Path;
+use std::{path::{Path, PathBuf}, time::SystemTime};
+
+use sha2::{Digest, Sha256};
+
+#[path = "src/oui/csv.rs"]
+mod csv;
+#[path = "src/oui/raw.rs"]
+#[allow(dead_code)] // error payloads are only read through Debug
+mod raw;
 
 const DB_LINK_URLS: &[&str] = &[
     "https://gitlab.com/wireshark/wireshark/-/raw/master/manuf",
     "https://www.wireshark.org/download/automated/data/manuf",
 ];
 
+const WKA_LINK_URLS: &[&str] = &[
+    "https://gitlab.com/wireshark/wireshark/-/raw/master/wka",
+    "https://raw.githubusercontent.com/wireshark/wireshark/master/wka",
+];
+
+const IEEE_CSV_URLS: &[&str] = &[
+    "https://standards-oui.ieee.org/oui/oui.csv",
+    "https://standards-oui.ieee.org/oui28/mam.csv",
+    "https://standards-oui.ieee.org/oui36/oui36.csv",
+    "https://standards-oui.ieee.org/iab/iab.csv",
+    "https://standards-oui.ieee.org/cid/cid.csv",
+];
+
+const SERVICES_URLS: &[&str] = &[
+    "https://gitlab.com/wireshark/wireshark/-/raw/master/services",
+    "https://raw.githubusercontent.com/wireshark/wireshark/master/services",
+];
+
+const PROTOCOLS_URLS: &[&str] = &[
+    "https://www.iana.org/assignments/protocol-numbers/protocol-numbers-1.csv",
+];
+
+const SPECIAL_REGISTRY_URLS: &[&str] = &[
+    "https://www.iana.org/assignments/iana-ipv4-special-registry/iana-ipv4-special-registry-1.csv",
+    "https://www.iana.org/assignments/iana-ipv6-special-registry/iana-ipv6-special-registry-1.csv",
+];
+
 #[derive(Debug)]
 struct DatabaseSourceError(Vec<(&'static str, ureq::Error)>);
 
 impl std::error::Error for DatabaseSourceError {}
 impl std::fmt::Display for DatabaseSourceError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        writeln!(f, "unable to fetch Wireshark OUI Database from any of the following links: ")?;
+        writeln!(f, "unable to fetch database from any of the following links: ")?;
         for (link, error) in &self.0 {
             write!(f, "\t{}: {}\n", link, error)?;
         }
@@ -19,13 +54,13 @@ impl std::fmt::Display for DatabaseSourceError {
     }
 }
 
-fn fetch_db_data() -> Result<String, DatabaseSourceError> {
+fn fetch_db_data(links: &[&'static str]) -> Result<(&'static str, String), DatabaseSourceError> {
     let mut error = Vec::new();
-    for link in DB_LINK_URLS {
+    for link in links {
         match ureq::get(link).call() {
             Ok(resp) => {
-                let text = resp.into_string().expect("unable to successfully parse fetch Wireshark OUI Database as UTF8");
-                return Ok(text);
+                let text = resp.into_string().expect("unable to successfully parse fetched database as UTF8");
+                return Ok((link, text));
             },
             Err(e) => {
                 error.push((*link, e))
@@ -35,12 +70,518 @@ fn fetch_db_data() -> Result<String, DatabaseSourceError> {
     Err(DatabaseSourceError(error))
 }
 
-fn main() {
-    // download Wireshark OUI database into OUT_DIR to embed within extension
-    let db_data = fetch_db_data().expect("unable to fetch Wireshark OUI Database");
+/// Fetches the database from the first of the links that responds, for [load_db]
+fn fetch_first(links: &[&'static str]) -> Result<(String, String), String> {
+    fetch_db_data(links).map(|(link, data)| (link.to_owned(), data)).map_err(|e| e.to_string())
+}
+
+/// Fetches and concatenates each of the links, for databases split across several files. All are required.
+fn fetch_all(links: &[&'static str]) -> Result<String, DatabaseSourceError> {
+    let mut text = String::new();
+    for link in links {
+        let resp = ureq::get(link).call().map_err(|e| DatabaseSourceError(vec![(*link, e)]))?;
+        text.push_str(&resp.into_string().expect("unable to successfully parse fetched database as UTF8"));
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+    }
+    Ok(text)
+}
+
+/// The fewest entries each database is expected to hold. A download cut short, or an error page served in place of the
+/// file, holds far fewer.
+const MIN_MANUF_ENTRIES: usize = 30_000;
+const MIN_IEEE_ENTRIES: usize = 30_000;
+const MIN_WKA_ENTRIES: usize = 100;
+
+/// Loads a database to embed: from a local copy when `{var}_PATH` is set, and otherwise by downloading it.
+///
+/// Downloads are checked before being embedded, as a partial download would otherwise embed a silently incomplete
+/// database. They must end with a complete line, match the SHA-256 pinned in `{var}_SHA256` (if set), parse, and hold at
+/// least `min_entries`. Each good download is cached in `NETTOOLS_CACHE_DIR` (or OUT_DIR, if unset), and a later bad one
+/// falls back to that cache.
+///
+/// Returns the source of the data (marked `(cached)` when the cache was used), the UTC date it was fetched (or for a
+/// local copy, last modified), and its entries.
+fn load_db<T>(
+    name: &str,
+    var: &str,
+    fetch: impl FnOnce() -> Result<(String, String), String>,
+    parse: impl Fn(&str) -> Result<Vec<T>, String>,
+    min_entries: usize,
+) -> (String, String, Vec<T>) {
+    if let Some((source, data)) = local_db_data(&format!("{var}_PATH")) {
+        let entries = parse(&data).unwrap_or_else(|e| panic!("unable to parse {name} database {source}: {e}"));
+        return (source.clone(), modified_date(Path::new(&source)), entries);
+    }
+
+    let sha_var = format!("{var}_SHA256");
+    println!("cargo:rerun-if-env-changed={sha_var}");
+    let pinned = std::env::var(&sha_var).ok().map(|h| h.trim().to_ascii_lowercase());
+    let check = |data: &str| -> Result<Vec<T>, String> {
+        if !data.ends_with('\n') {
+            return Err("the data ends partway through a line".to_owned());
+        }
+        if let Some(pinned) = &pinned {
+            let actual: String = Sha256::digest(data.as_bytes()).iter().map(|b| format!("{b:02x}")).collect();
+            if &actual != pinned {
+                return Err(format!("its SHA-256 {actual} does not match {sha_var} ({pinned})"));
+            }
+        }
+        let entries = parse(data)?;
+        if entries.len() < min_entries {
+            return Err(format!("only {} entries were found, expected at least {min_entries}", entries.len()));
+        }
+        Ok(entries)
+    };
+
+    let cache = cache_dir().join(format!("{name}.cache"));
+    let error = match fetch().and_then(|(source, data)| Ok((check(&data)?, source, data))) {
+        Ok((entries, source, data)) => {
+            // failing to cache only loses the fallback for later builds
+            let _ = std::fs::create_dir_all(cache_dir()).and_then(|_| std::fs::write(&cache, format!("{source}\n{data}")));
+            return (source, utc_date_today(), entries);
+        },
+        Err(e) => e,
+    };
+
+    // the cache was written when it was downloaded, so its modification date is when the data was fetched
+    let cached = std::fs::read_to_string(&cache).ok().and_then(|cached| {
+        let (source, data) = cached.split_once('\n')?;
+        Some((format!("{source} (cached)"), modified_date(&cache), check(data).ok()?))
+    });
+    match cached {
+        Some(cached) => {
+            println!("cargo:warning=using the {name} database cached by an earlier build, as the download failed: {error}");
+            cached
+        },
+        None => panic!("unable to download the {name} database, and no earlier download is cached (set {var}_PATH to build from a local copy): {error}"),
+    }
+}
+
+/// Where downloaded databases are cached for later builds to fall back on: `NETTOOLS_CACHE_DIR` if set, so the cache
+/// can outlive `cargo clean` or be shared between checkouts, and otherwise OUT_DIR.
+fn cache_dir() -> PathBuf {
+    println!("cargo:rerun-if-env-changed=NETTOOLS_CACHE_DIR");
+    match std::env::var_os("NETTOOLS_CACHE_DIR").filter(|d| !d.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("OUT_DIR").unwrap()),
+    }
+}
 
+/// Reads a local copy of a database in place of downloading it, when the environment variable is set to its path. This
+/// allows offline and hermetic builds, such as from a copy vendored alongside the build.
+fn local_db_data(var: &str) -> Option<(String, String)> {
+    println!("cargo:rerun-if-env-changed={var}");
+    let path = std::env::var_os(var)?;
+    let path = Path::new(&path);
+    println!("cargo:rerun-if-changed={}", path.display());
+    let text = std::fs::read_to_string(path).unwrap_or_else(|e| panic!("unable to read {var} file {}: {e}", path.display()));
+    Some((path.display().to_string(), text))
+}
+
+/// A parsed OUI database entry, owning its strings until written by [write_binary_db]
+struct Entry {
+    address: u64,
+    length: u8,
+    /// The IEEE registry's code, or 0 where the database doesn't list it
+    registry: u8,
+    short: String,
+    long: Option<String>,
+    comment: Option<String>,
+    registrant_address: Option<String>,
+    country: Option<String>,
+}
+
+/// Parses Wireshark's OUI (or well-known address) database, as `oui::OuiDb::parse_from_string` (or `parse_wka`) does
+fn parse_wireshark_db(db_data: &str, wka: bool) -> Result<Vec<Entry>, String> {
+    db_data.lines()
+        .enumerate()
+        .filter_map(|(lnum, l)| raw::wireshark_line(l).map(|l| (lnum, l)))
+        .map(|(lnum, l)| {
+            let fields = raw::wireshark_fields(l)
+                .map_err(|len| format!("invalid number of fields ({len}) in wireshark oui database (line {lnum}: {l:?})"))?;
+            let (address, length) = raw::parse_prefix(fields.prefix, wka)
+                .map_err(|e| format!("invalid prefix in wireshark oui database (line {lnum}: {l:?}): {e:?}"))?;
+            Ok(Entry {
+                address,
+                length,
+                registry: 0,
+                short: fields.short.to_owned(),
+                long: fields.long.map(str::to_owned),
+                comment: fields.comment.map(str::to_owned),
+                registrant_address: None,
+                country: None,
+            })
+        })
+        .collect()
+}
+
+/// Parses the concatenated IEEE registry CSVs, as `oui::OuiDb::parse_ieee_csv` does
+fn parse_ieee_db(db_data: &str) -> Result<Vec<Entry>, String> {
+    db_data.lines()
+        .enumerate()
+        .filter_map(|(lnum, l)| raw::ieee_line(l).map(|l| (lnum, l)))
+        .map(|(lnum, l)| {
+            let entry = raw::ieee_entry(l).map_err(|e| format!("invalid ieee registry record (line {lnum}: {l:?}): {e:?}"))?;
+            Ok(Entry {
+                address: entry.address,
+                length: entry.length,
+                registry: entry.registry,
+                short: entry.name.to_string(),
+                long: Some(entry.name.to_string()),
+                comment: None,
+                registrant_address: entry.registrant.as_deref().map(str::to_owned),
+                country: entry.country().map(str::to_owned),
+            })
+        })
+        .collect()
+}
+
+/// Serializes entries into the binary format read by `oui::OuiDb::from_binary`
+fn write_binary_db(mut entries: Vec<Entry>) -> Vec<u8> {
+    // stable sort, so dedup keeps the first of any duplicates
+    entries.sort_by_key(|e| (e.address, e.length));
+    entries.dedup_by_key(|e| (e.address, e.length));
+
+    let binary = entries.iter().map(|e| (
+        e.address,
+        e.length,
+        e.registry,
+        e.short.as_str(),
+        [e.long.as_deref(), e.comment.as_deref(), e.registrant_address.as_deref(), e.country.as_deref()],
+    ));
+    raw::write_binary(entries.len(), binary).unwrap_or_else(|e| panic!("unable to write binary oui database: {e:?}"))
+}
+
+/// A dataset embedded in the extension when its cargo feature is enabled, keeping the extension's size opt-in.
+///
+/// Packs are loaded with [load_db], using their `{var}_PATH` and `{var}_SHA256` variables, then written to OUT_DIR as
+/// `file` for the crate to embed. Where the pack came from is recorded in `{var}_DB_SOURCE`, and the date it was fetched
+/// (as returned by [load_db]) in `{var}_DB_DATE`.
+struct DataPack {
+    name: &'static str,
+    feature: &'static str,
+    var: &'static str,
+    file: &'static str,
+    fetch: fn() -> Result<(String, String), String>,
+    parse: PackParser,
+    min_entries: usize,
+}
+
+/// How a pack is parsed, and so how it's written for the crate to embed
+enum PackParser {
+    /// Into an OUI database, written in the binary format read by `oui::OuiDb::from_binary`. As `src/oui.rs` always
+    /// embeds these, an empty database is written when the pack is disabled.
+    Oui(fn(&str) -> Result<Vec<Entry>, String>),
+    /// Into rows of fields, written for `src/packs.rs`: one entry per line, with its fields separated by tabs
+    Rows(fn(&str) -> Result<Vec<Vec<String>>, String>),
+}
+
+/// Each pack, in order of precedence: when packs share a file, the last enabled one is embedded.
+const DATA_PACKS: &[DataPack] = &[
+    DataPack {
+        name: "manuf",
+        feature: "manuf-db",
+        var: "NETTOOLS_MANUF",
+        file: "oui_db.bin",
+        fetch: || fetch_first(DB_LINK_URLS),
+        parse: PackParser::Oui(|data| parse_wireshark_db(data, false)),
+        min_entries: MIN_MANUF_ENTRIES,
+    },
+    DataPack {
+        name: "ieee",
+        feature: "ieee-oui-db",
+        var: "NETTOOLS_MANUF",
+        file: "oui_db.bin",
+        fetch: || fetch_all(IEEE_CSV_URLS).map(|data| (IEEE_CSV_URLS.join(" "), data)).map_err(|e| e.to_string()),
+        parse: PackParser::Oui(parse_ieee_db),
+        min_entries: MIN_IEEE_ENTRIES,
+    },
+    DataPack {
+        name: "wka",
+        feature: "wka-db",
+        var: "NETTOOLS_WKA",
+        file: "wka_db.bin",
+        fetch: || fetch_first(WKA_LINK_URLS),
+        parse: PackParser::Oui(|data| parse_wireshark_db(data, true)),
+        min_entries: MIN_WKA_ENTRIES,
+    },
+    DataPack {
+        name: "services",
+        feature: "services-db",
+        var: "NETTOOLS_SERVICES",
+        file: "services.tsv",
+        fetch: || fetch_first(SERVICES_URLS),
+        parse: PackParser::Rows(parse_services),
+        min_entries: 5_000,
+    },
+    DataPack {
+        name: "protocols",
+        feature: "protocols-db",
+        var: "NETTOOLS_PROTOCOLS",
+        file: "protocols.tsv",
+        fetch: || fetch_first(PROTOCOLS_URLS),
+        parse: PackParser::Rows(parse_protocols),
+        min_entries: 140,
+    },
+    DataPack {
+        name: "special-registry",
+        feature: "special-registry-db",
+        var: "NETTOOLS_SPECIAL_REGISTRY",
+        file: "special-registry.tsv",
+        fetch: || fetch_all(SPECIAL_REGISTRY_URLS).map(|data| (SPECIAL_REGISTRY_URLS.join(" "), data)).map_err(|e| e.to_string()),
+        parse: PackParser::Rows(parse_special_registry),
+        min_entries: 20,
+    },
+];
+
+impl DataPack {
+    fn enabled(&self) -> bool {
+        std::env::var_os(format!("CARGO_FEATURE_{}", self.feature.to_ascii_uppercase().replace('-', "_"))).is_some()
+    }
+
+    /// Loads the pack and writes it to OUT_DIR
+    fn embed(&self, out_dir: &Path) {
+        let (source, date, data) = match self.parse {
+            PackParser::Oui(parse) => {
+                let (source, date, mut entries) = load_db(self.name, self.var, self.fetch, parse, self.min_entries);
+                if std::env::var_os("CARGO_FEATURE_OUI_SHORT_NAMES").is_some() {
+                    for e in &mut entries {
+                        e.long = None;
+                        e.comment = None;
+                        e.registrant_address = None;
+                    }
+                }
+                (source, date, write_binary_db(entries))
+            },
+            PackParser::Rows(parse) => {
+                let (source, date, entries) = load_db(self.name, self.var, self.fetch, parse, self.min_entries);
+                let mut tsv = String::new();
+                for fields in entries {
+                    // fields are written one per column, so can't hold tabs or line breaks of their own
+                    let fields: Vec<String> = fields.iter().map(|f| f.split_whitespace().collect::<Vec<_>>().join(" ")).collect();
+                    tsv.push_str(&fields.join("\t"));
+                    tsv.push('\n');
+                }
+                (source, date, tsv.into_bytes())
+            },
+        };
+        std::fs::write(out_dir.join(self.file), data).unwrap_or_else(|e| panic!("unable to write {} pack file: {e}", self.name));
+        println!("cargo:rustc-env={}_DB_SOURCE={source}", self.var);
+        println!("cargo:rustc-env={}_DB_DATE={date}", self.var);
+    }
+
+    /// Writes an empty OUI database in place of a disabled pack
+    fn embed_empty(&self, out_dir: &Path) {
+        std::fs::write(out_dir.join(self.file), write_binary_db(Vec::new()))
+            .unwrap_or_else(|e| panic!("unable to write {} pack file: {e}", self.name));
+        println!("cargo:rustc-env={}_DB_SOURCE=", self.var);
+        println!("cargo:rustc-env={}_DB_DATE=", self.var);
+    }
+}
+
+/// Splits CSV text into records, keeping line breaks within quoted fields (as in IANA's registries) in their record
+fn csv_records(text: &str) -> Vec<&str> {
+    let mut records = Vec::new();
+    let (mut start, mut quoted) = (0, false);
+    for (i, b) in text.bytes().enumerate() {
+        match b {
+            b'"' => quoted = !quoted,
+            b'\n' if !quoted => {
+                records.push(text[start..i].trim_end_matches('\r'));
+                start = i + 1;
+            },
+            _ => {},
+        }
+    }
+    records.push(&text[start..]);
+    records.into_iter().filter(|r| !r.trim().is_empty()).collect()
+}
+
+/// Removes IANA's footnote references, such as the `[2]` in `192.0.0.0/24 [2]`
+fn strip_footnotes(s: &str) -> String {
+    let mut out = String::new();
+    let mut rest = s;
+    while let Some(open) = rest.find('[') {
+        let close = rest[open..].find(']').map(|c| open + c);
+        match close {
+            Some(close) if close > open + 1 && rest[open + 1..close].bytes().all(|b| b.is_ascii_digit()) => {
+                out.push_str(&rest[..open]);
+                rest = &rest[close + 1..];
+            },
+            _ => {
+                out.push_str(&rest[..=open]);
+                rest = &rest[open + 1..];
+            },
+        }
+    }
+    out.push_str(rest);
+    out.trim().to_owned()
+}
+
+/// Parses Wireshark's `services` file, with lines such as `http  80/tcp/udp/sctp  # World Wide Web HTTP`
+///
+/// Entries are written as `name, first port, last port, transports (slash separated), description`
+fn parse_services(data: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut entries = Vec::new();
+    for (lnum, l) in data.lines().enumerate() {
+        let (l, comment) = l.split_once('#').map_or((l, ""), |(l, c)| (l, c.trim()));
+        let mut fields = l.split_whitespace();
+        let (Some(name), Some(spec)) = (fields.next(), fields.next()) else { continue };
+        let bad = || format!("invalid port in services file (line {lnum}: {l:?})");
+        let (ports, transports) = spec.split_once('/').ok_or_else(bad)?;
+        let (first, last) = ports.split_once('-').unwrap_or((ports, ports));
+        let (first, last): (u16, u16) = (first.parse().map_err(|_| bad())?, last.parse().map_err(|_| bad())?);
+        if first > last || transports.is_empty() {
+            return Err(bad());
+        }
+        entries.push(vec![name.to_owned(), first.to_string(), last.to_string(), transports.to_ascii_lowercase(), comment.to_owned()]);
+    }
+    Ok(entries)
+}
+
+/// Parses IANA's protocol numbers registry CSV. Unassigned ranges (such as `146-252`) are skipped.
+///
+/// Entries are written as `number, keyword, protocol, IPv6 extension header (0 or 1)`
+fn parse_protocols(data: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut entries = Vec::new();
+    for (rnum, r) in csv_records(data).into_iter().enumerate() {
+        let fields = csv::csv_fields(r).filter(|f| f.len() >= 4)
+            .ok_or_else(|| format!("malformed csv record in protocol numbers registry (record {rnum}: {r:?})"))?;
+        let Ok(number) = fields[0].trim().parse::<u8>() else { continue };
+        let keyword = strip_footnotes(&fields[1]);
+        let keyword = keyword.trim_end_matches("(deprecated)").trim();
+        let ipv6_ext = fields[3].trim() == "Y";
+        entries.push(vec![number.to_string(), keyword.to_owned(), strip_footnotes(&fields[2]), u8::from(ipv6_ext).to_string()]);
+    }
+    Ok(entries)
+}
+
+/// Parses IANA's IPv4 and IPv6 special-purpose address registry CSVs, concatenated. Blocks listed together (such as
+/// `192.0.0.170/32, 192.0.0.171/32`) are written as separate entries.
+///
+/// Entries are written as `network, name, RFC, source, destination, forwardable, globally reachable, reserved by
+/// protocol`, with the flags as `1`, `0`, or empty for `N/A`
+fn parse_special_registry(data: &str) -> Result<Vec<Vec<String>>, String> {
+    let flag = |f: &str| match strip_footnotes(f).as_str() {
+        "True" => Ok("1"),
+        "False" => Ok("0"),
+        "N/A" | "" => Ok(""),
+        other => Err(format!("unknown flag {other:?} in special-purpose address registry")),
+    };
+    let mut entries = Vec::new();
+    for (rnum, r) in csv_records(data).into_iter().enumerate() {
+        let fields = csv::csv_fields(r).filter(|f| f.len() >= 10)
+            .ok_or_else(|| format!("malformed csv record in special-purpose address registry (record {rnum}: {r:?})"))?;
+        if fields[0] == "Address Block" {
+            continue;
+        }
+        let flags = fields[5..10].iter().map(|f| flag(f)).collect::<Result<Vec<_>, _>>()?;
+        for block in strip_footnotes(&fields[0]).split(',').map(str::trim) {
+            let valid = block.split_once('/').is_some_and(|(addr, len)| match (addr.parse(), len.parse::<u8>()) {
+                (Ok(std::net::IpAddr::V4(_)), Ok(len)) => len <= 32,
+                (Ok(std::net::IpAddr::V6(_)), Ok(len)) => len <= 128,
+                _ => false,
+            });
+            if !valid {
+                return Err(format!("invalid address block {block:?} in special-purpose address registry (record {rnum})"));
+            }
+            let mut entry = vec![block.to_owned(), strip_footnotes(&fields[1]), fields[2].trim().to_owned()];
+            entry.extend(flags.iter().map(|f| f.to_string()));
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// Formats the current UTC date as `YYYY-MM-DD`
+fn utc_date_today() -> String {
+    utc_date(SystemTime::now())
+}
+
+/// Formats the UTC date a file was last modified as `YYYY-MM-DD`, or today's if the filesystem doesn't record it
+fn modified_date(path: &Path) -> String {
+    std::fs::metadata(path).and_then(|m| m.modified()).map_or_else(|_| utc_date_today(), utc_date)
+}
+
+/// Formats a time's UTC date as `YYYY-MM-DD`
+fn utc_date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("time is before the unix epoch")
+        .as_secs();
+
+    // civil-from-days, see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = (secs / 86400) as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+fn main() {
     let out_dir = std::env::var_os("OUT_DIR").unwrap();
-    let db_path = Path::new(&out_dir).join("wireshark_oui_db.txt");
 
-    std::fs::write(db_path, db_data).expect("unable to write wireshark db file");
+    // declaring these stops cargo from re-running the build (and downloading again) whenever any package file changes
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/oui/csv.rs");
+    println!("cargo:rerun-if-changed=src/oui/raw.rs");
+
+    // download each enabled dataset (unless a local copy is given), and parse it into OUT_DIR to embed within extension
+    let mut written: Vec<&str> = Vec::new();
+    for pack in DATA_PACKS.iter().rev().filter(|p| p.enabled()) {
+        if !written.contains(&pack.file) {
+            pack.embed(Path::new(&out_dir));
+            written.push(pack.file);
+        }
+    }
+    for pack in DATA_PACKS.iter().filter(|p| matches!(p.parse, PackParser::Oui(_))) {
+        if !written.contains(&pack.file) {
+            pack.embed_empty(Path::new(&out_dir));
+            written.push(pack.file);
+        }
+    }
+
+    // and how the extension itself was built, for NETTOOLS_BUILD_INFO
+    println!("cargo:rustc-env=NETTOOLS_BUILD_DATE={}", utc_date_today());
+    println!("cargo:rustc-env=NETTOOLS_GIT_HASH={}", git_hash().unwrap_or_else(|| "unknown".to_owned()));
+    println!("cargo:rustc-env=NETTOOLS_FEATURES={}", enabled_features().join(","));
+}
+
+/// Finds the commit being built, when built from a git checkout with git available
+fn git_hash() -> Option<String> {
+    // rebuild on checkouts and commits, through the current branch's ref. cargo treats a missing path as always
+    // changed, so only existing paths are watched: a packed ref lives in packed-refs, and a loose ref that doesn't
+    // exist yet is caught by watching the directory it will be written to
+    let git = Path::new(".git");
+    let head = git.join("HEAD");
+    let mut watch = vec![head.clone(), git.join("packed-refs")];
+    if let Some(branch) = std::fs::read_to_string(&head).ok().as_deref().and_then(|h| h.trim().strip_prefix("ref: ")) {
+        let branch = git.join(branch);
+        watch.extend(branch.ancestors().find(|p| p.exists()).filter(|p| p.starts_with(git)).map(Path::to_path_buf));
+    }
+    for path in watch.iter().filter(|p| p.exists()) {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
+
+    let output = std::process::Command::new("git").args(["rev-parse", "--short=12", "HEAD"]).output().ok()?;
+    let hash = String::from_utf8(output.stdout).ok()?;
+    (output.status.success() && !hash.trim().is_empty()).then(|| hash.trim().to_owned())
+}
+
+/// Lists the crate's enabled features, as named in Cargo.toml
+fn enabled_features() -> Vec<String> {
+    let mut features: Vec<String> = std::env::vars()
+        .filter_map(|(var, _)| var.strip_prefix("CARGO_FEATURE_").map(|f| f.to_ascii_lowercase().replace('_', "-")))
+        .filter(|f| f != "default")
+        .collect();
+    features.sort();
+    features
 }
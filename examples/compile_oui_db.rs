@@ -0,0 +1,45 @@
+//! Compiles OUI databases into the compact binary format, for fast loading with `OUI_LOAD`.
+//!
+//! Usage: `cargo run --release --example compile_oui_db -- <output> [input...]`
+//!
+//! Inputs may be Wireshark `manuf` files or IEEE registry CSVs, and are merged with earlier files taking precedence.
+//! Without any inputs, the database embedded at build time is compiled.
+
+use std::process::ExitCode;
+
+use sqlite3_nettools::oui::{MergePrecedence, OuiDb, EMBEDDED_DB};
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1);
+    let Some(output) = args.next() else {
+        eprintln!("usage: compile_oui_db <output> [input...]");
+        return ExitCode::FAILURE;
+    };
+
+    let inputs: Vec<String> = args.collect();
+    let db = if inputs.is_empty() {
+        OuiDb::clone(&EMBEDDED_DB)
+    } else {
+        let mut db = OuiDb::default();
+        for input in &inputs {
+            let parsed = std::fs::read_to_string(input)
+                .map_err(|e| e.to_string())
+                .and_then(|txt| OuiDb::parse_any(&txt).map_err(|e| e.to_string()));
+            match parsed {
+                Ok(input_db) => db = db.merge(&input_db, MergePrecedence::Ours),
+                Err(e) => {
+                    eprintln!("unable to read {input:?}: {e}");
+                    return ExitCode::FAILURE;
+                },
+            }
+        }
+        db
+    };
+
+    if let Err(e) = db.save_binary_file(&output) {
+        eprintln!("unable to write {output:?}: {e}");
+        return ExitCode::FAILURE;
+    }
+    eprintln!("wrote {} prefixes to {output:?}", db.len());
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,23 @@
+//! MAC address and EUI-64 parsing, checking that each formatted address parses back to itself.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sqlite3_nettools::oui::{parse_eui, parse_mac_addr, parse_mac_addr_extend};
+use sqlite3_nettools_core::{parse_mac, MacStyle};
+
+/// The styles [parse_mac] reads back, unlike the IPv6 interface identifier forms
+const PARSEABLE: [MacStyle; 5] = [MacStyle::Plain, MacStyle::Dashed, MacStyle::Colon, MacStyle::Dots, MacStyle::Prefixed];
+
+fuzz_target!(|s: &str| {
+    let _ = parse_eui(s);
+    let _ = parse_mac_addr_extend(s, true);
+
+    if let Ok(mac) = parse_mac_addr(s) {
+        for style in PARSEABLE {
+            for uppercase in [false, true] {
+                let formatted = style.format_mac(mac.to_array(), uppercase);
+                assert_eq!(parse_mac(&formatted, false), Ok(mac.to_array()), "{s:?} formatted as {formatted:?}");
+            }
+        }
+    }
+});
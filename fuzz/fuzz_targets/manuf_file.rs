@@ -0,0 +1,17 @@
+//! Whole OUI databases in each format read by `OUI_LOAD`: Wireshark manuf and well-known address files, IEEE registry
+//! CSVs, and the binary format, checking that each parsed database survives the binary format unchanged.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sqlite3_nettools::oui::OuiDb;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = OuiDb::from_binary(data);
+
+    let Ok(txt) = std::str::from_utf8(data) else { return };
+    for db in [OuiDb::parse_any(txt), OuiDb::parse_wka(txt)].into_iter().flatten() {
+        let Ok(bin) = db.to_binary() else { continue };
+        let read = OuiDb::from_binary(&bin).expect("reading a database just written");
+        assert!(db.raw_prefixes().eq(read.raw_prefixes()));
+    }
+});
@@ -0,0 +1,16 @@
+//! IP address and network arguments, given as text or as blobs, checking that each parses back from its text form.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sqlite3_nettools::exports::inet::UserNetAddr;
+
+fuzz_target!(|data: &[u8]| {
+    let blob = UserNetAddr::from_blob(data);
+    let text = std::str::from_utf8(data).ok().and_then(|s| s.parse::<UserNetAddr>().ok());
+
+    for una in blob.ok().into_iter().chain(text) {
+        let s = una.to_string();
+        let parsed: UserNetAddr = s.parse().unwrap_or_else(|e| panic!("{una:?} formatted as {s:?}: {e}"));
+        assert_eq!(parsed.to_string(), s);
+    }
+});
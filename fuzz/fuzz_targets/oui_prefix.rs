@@ -0,0 +1,13 @@
+//! OUI prefixes, as written in manuf and well-known address files, checking that each parses back from its long form.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sqlite3_nettools::oui::Oui;
+
+fuzz_target!(|s: &str| {
+    let parsed = [s.parse::<Oui>(), Oui::parse_wka(s)];
+    for oui in parsed.into_iter().flatten() {
+        let long = format!("{oui:#?}");
+        assert_eq!(Oui::parse_wka(&long).ok(), Some(oui), "{s:?} formatted as {long:?}");
+    }
+});
@@ -1,5 +1,5 @@
 
-.load target/release/sqlite3_inet
+.load target/release/libsqlite3_nettools
 
 -- CREATE TABLE test_macs(mac TEXT);
 -- -- INSERT INTO test_ouis(mac) VALUES('CC-15-31-19-C8-64');
@@ -0,0 +1,10 @@
+//! The MAC address formatting and parsing used by `sqlite3-nettools`, without depending on `std` or allocating.
+//!
+//! This allows other tools, such as firmware, to format addresses exactly as the SQLite extension does.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+mod parse;
+mod style;
+
+pub use parse::{hex_value, parse_eui, parse_mac, EuiBytes, ParseError};
+pub use style::{MacStyle, MacText, UnknownStyle};
@@ -0,0 +1,91 @@
+use core::fmt;
+
+/// Why a MAC address or EUI-64 could not be parsed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    /// The address has too many hex digits, or too few for its width
+    InvalidLength,
+    /// The address holds a character that is neither a hex digit nor a separator (`-`, `.`, `:`)
+    InvalidCharacter(char),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidLength => f.write_str("MAC address has a bad character length"),
+            ParseError::InvalidCharacter(c) => write!(f, "Found an invalid character in MAC: {c:?}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
+
+/// A MAC address or EUI-64, by its raw bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EuiBytes {
+    Mac48([u8; 6]),
+    Eui64([u8; 8]),
+}
+
+/// Reads the (at most 16) hex digits of an address, skipping any separators and a leading `0x`. Returns their value,
+/// and how many digits there were.
+pub fn hex_value(s: &str) -> Result<(u64, usize), ParseError> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    let mut value = 0u64;
+    let mut digits = 0;
+    for c in s.chars() {
+        if let Some(digit) = c.to_digit(16) {
+            if digits == 16 {
+                return Err(ParseError::InvalidLength);
+            }
+            value = (value << 4) | u64::from(digit);
+            digits += 1;
+        } else if !matches!(c, '-' | '.' | ':') {
+            return Err(ParseError::InvalidCharacter(c));
+        }
+    }
+    Ok((value, digits))
+}
+
+/// Parses a MAC address in any of the formats written by [MacStyle](crate::MacStyle), as well as with any other
+/// placement of separators.
+///
+/// With `zero_extend`, fewer than 12 digits are accepted and padded with trailing zeros, such as for an OUI prefix.
+///
+/// ```
+/// # use sqlite3_nettools_core::parse_mac;
+/// assert_eq!(parse_mac("aa-bb-cc-dd-ee-ff", false), Ok([0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]));
+/// assert_eq!(parse_mac("aa:bb:cc", true), Ok([0xAA, 0xBB, 0xCC, 0, 0, 0]));
+/// assert!(parse_mac("aa:bb:cc", false).is_err());
+/// ```
+pub fn parse_mac(s: &str, zero_extend: bool) -> Result<[u8; 6], ParseError> {
+    let (value, digits) = hex_value(s)?;
+    if digits > 12 || (digits < 12 && !zero_extend) {
+        return Err(ParseError::InvalidLength);
+    }
+    let [_, _, bytes @ ..] = (value << (4 * (12 - digits))).to_be_bytes();
+    Ok(bytes)
+}
+
+/// Parses either a 48-bit MAC address or a 64-bit EUI, as with [parse_mac]. The width of the address is determined by
+/// the number of hex digits present (12 or 16).
+pub fn parse_eui(s: &str) -> Result<EuiBytes, ParseError> {
+    match hex_value(s)? {
+        (value, 12) => {
+            let [_, _, bytes @ ..] = value.to_be_bytes();
+            Ok(EuiBytes::Mac48(bytes))
+        },
+        (value, 16) => Ok(EuiBytes::Eui64(value.to_be_bytes())),
+        _ => Err(ParseError::InvalidLength),
+    }
+}
+
+#[test]
+fn parsing() {
+    assert_eq!(parse_mac("0xaabbccddeeff", false), Ok([0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]));
+    assert_eq!(parse_mac("aabb.ccdd.eeff.0011", false), Err(ParseError::InvalidLength));
+    assert_eq!(parse_mac("aa:bb:cc:dd:ee:fg", false), Err(ParseError::InvalidCharacter('g')));
+    assert_eq!(parse_eui("aabb.ccdd.eeff.0011"), Ok(EuiBytes::Eui64([0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF, 0x00, 0x11])));
+    assert_eq!(parse_eui("aa:bb:cc:dd:ee:ff:00"), Err(ParseError::InvalidLength));
+}
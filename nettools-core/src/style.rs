@@ -0,0 +1,376 @@
+use core::{fmt, ops::Deref, str::FromStr};
+
+use crate::EuiBytes;
+
+/// A named MAC address format, such as `aa:bb:cc:dd:ee:ff` or `aabb.ccdd.eeff`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MacStyle {
+    Plain,
+    Dashed,
+    Colon,
+    Dots,
+    Prefixed,
+    InterfaceId,
+    LinkLocal,
+}
+
+struct StyleDescription<const N: usize> {
+    base: [u8; 25],
+    length: usize,
+    offsets: [usize; N],
+}
+
+macro_rules! style_desc {
+    ($style: ident, $base: ident, $len: expr, $offset: ident) => {
+        (MacStyle::$style, StyleDescription {
+            base: MacStyle::$base,
+            length: $len,
+            offsets: MacStyle::$offset,
+        })
+    }
+}
+
+impl MacStyle {
+    const BASE_PLAIN:      [u8; 25] = *b"############@@@@@@@@@@@@@";
+    const BASE_DASHED:     [u8; 25] = *b"##-##-##-##-##-##@@@@@@@@";
+    const BASE_COLON:      [u8; 25] = *b"##:##:##:##:##:##@@@@@@@@";
+    const BASE_DOTS:       [u8; 25] = *b"####.####.####@@@@@@@@@@@";
+    const BASE_PREFIXED:   [u8; 25] = *b"0x############@@@@@@@@@@@";
+    const BASE_INTF_ID:    [u8; 25] = *b"####:##ff:fe##:####@@@@@@";
+    const BASE_LINK_LOCAL: [u8; 25] = *b"fe80::####:##ff:fe##:####";
+
+    const BASE_EUI64_PLAIN:      [u8; 25] = *b"################@@@@@@@@@";
+    const BASE_EUI64_DASHED:     [u8; 25] = *b"##-##-##-##-##-##-##-##@@";
+    const BASE_EUI64_COLON:      [u8; 25] = *b"##:##:##:##:##:##:##:##@@";
+    const BASE_EUI64_DOTS:       [u8; 25] = *b"####.####.####.####@@@@@@";
+    const BASE_EUI64_PREFIXED:   [u8; 25] = *b"0x################@@@@@@@";
+    const BASE_EUI64_INTF_ID:    [u8; 25] = *b"####:####:####:####@@@@@@";
+    const BASE_EUI64_LINK_LOCAL: [u8; 25] = *b"fe80::####:####:####:####";
+
+    const OFFSETS_NONE: [usize; 2*6] = [0,1,2,3,4,5,6,7,8,9,10,11];
+    const OFFSETS_NONE_PREFIXED: [usize; 2*6] = [2,3,4,5,6,7,8,9,10,11,12,13];
+    const OFFSETS_BYTE: [usize; 2*6] = [0,1,3,4,6,7,9,10,12,13,15,16];
+    const OFFSETS_SHORT: [usize; 2*6] = [0,1,2,3,5,6,7,8,10,11,12,13];
+    const OFFSETS_INTF_ID: [usize; 2*6] = [0,1,2,3,5,6,12,13,15,16,17,18];
+    const OFFSETS_LINK_LOCAL: [usize; 2*6] = [6,7,8,9,11,12,18,19,21,22,23,24];
+
+    const OFFSETS_EUI64_NONE: [usize; 2*8] = [0,1,2,3,4,5,6,7,8,9,10,11,12,13,14,15];
+    const OFFSETS_EUI64_NONE_PREFIXED: [usize; 2*8] = [2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17];
+    const OFFSETS_EUI64_BYTE: [usize; 2*8] = [0,1,3,4,6,7,9,10,12,13,15,16,18,19,21,22];
+    const OFFSETS_EUI64_SHORT: [usize; 2*8] = [0,1,2,3,5,6,7,8,10,11,12,13,15,16,17,18];
+    const OFFSETS_EUI64_LINK_LOCAL: [usize; 2*8] = [6,7,8,9,11,12,13,14,16,17,18,19,21,22,23,24];
+
+    const FMT_TABLE: &'static [(MacStyle, StyleDescription<12>)] = &[
+        style_desc!(Plain, BASE_PLAIN, 12, OFFSETS_NONE),
+        style_desc!(Dashed, BASE_DASHED, 17, OFFSETS_BYTE),
+        style_desc!(Colon, BASE_COLON, 17, OFFSETS_BYTE),
+        style_desc!(Dots, BASE_DOTS, 14, OFFSETS_SHORT),
+        style_desc!(Prefixed, BASE_PREFIXED, 14, OFFSETS_NONE_PREFIXED),
+        style_desc!(InterfaceId, BASE_INTF_ID, 19, OFFSETS_INTF_ID),
+        style_desc!(LinkLocal, BASE_LINK_LOCAL, 25, OFFSETS_LINK_LOCAL),
+    ];
+
+    const FMT_TABLE_EUI64: &'static [(MacStyle, StyleDescription<16>)] = &[
+        style_desc!(Plain, BASE_EUI64_PLAIN, 16, OFFSETS_EUI64_NONE),
+        style_desc!(Dashed, BASE_EUI64_DASHED, 23, OFFSETS_EUI64_BYTE),
+        style_desc!(Colon, BASE_EUI64_COLON, 23, OFFSETS_EUI64_BYTE),
+        style_desc!(Dots, BASE_EUI64_DOTS, 19, OFFSETS_EUI64_SHORT),
+        style_desc!(Prefixed, BASE_EUI64_PREFIXED, 18, OFFSETS_EUI64_NONE_PREFIXED),
+        style_desc!(InterfaceId, BASE_EUI64_INTF_ID, 19, OFFSETS_EUI64_SHORT),
+        style_desc!(LinkLocal, BASE_EUI64_LINK_LOCAL, 25, OFFSETS_EUI64_LINK_LOCAL),
+    ];
+
+    #[inline(always)]
+    const fn table_idx(&self) -> usize {
+        match self {
+            MacStyle::Plain => 0,
+            MacStyle::Dashed => 1,
+            MacStyle::Colon => 2,
+            MacStyle::Dots => 3,
+            MacStyle::Prefixed => 4,
+            MacStyle::InterfaceId => 5,
+            MacStyle::LinkLocal => 6,
+        }
+    }
+
+    #[inline(always)]
+    const fn fmt_desc(&self) -> &'static StyleDescription<12> {
+        &MacStyle::FMT_TABLE[self.table_idx()].1
+    }
+
+    #[inline(always)]
+    const fn fmt_desc_eui64(&self) -> &'static StyleDescription<16> {
+        &MacStyle::FMT_TABLE_EUI64[self.table_idx()].1
+    }
+
+    /// The length of a MAC address when serialized into a string
+    #[inline(always)]
+    pub const fn length(&self) -> usize {
+        self.fmt_desc().length
+    }
+
+    /// The length of an EUI-64 when serialized into a string
+    #[inline(always)]
+    pub const fn length_eui64(&self) -> usize {
+        self.fmt_desc_eui64().length
+    }
+
+    /// A template string of a MAC address. Only the first `MacStyle::length()` bytes will be used, the rest is padding.
+    #[inline(always)]
+    pub const fn base(&self) -> [u8; 25] {
+        self.fmt_desc().base
+    }
+
+    /// Whether this style is an IPv6 interface identifier, which flips the universal/local bit of the address.
+    #[inline(always)]
+    const fn is_ipv6_style(&self) -> bool {
+        matches!(self, MacStyle::InterfaceId | MacStyle::LinkLocal)
+    }
+
+    /// Writes each nibble of `value` into `arr` at the positions in `offsets`, most significant nibble first.
+    #[inline(always)]
+    const fn _format_mac<const UPPERCASE: bool, const N: usize>(
+        value: u64,
+        offsets: [usize; N],
+        mut arr: [u8; 25],
+    ) -> [u8; 25] {
+        let nibbles: [u8; 16] = if UPPERCASE {
+            *b"0123456789ABCDEF"
+        } else {
+            *b"0123456789abcdef"
+        };
+        let mut i = 0;
+        while i < N {
+            let ind = offsets[i];
+            let off = 4 * (N - 1 - i);
+            arr[ind] = nibbles[((value >> off) & 0xf) as usize];
+            i += 1;
+        }
+        arr
+    }
+
+    /// Uppercases any template characters (such as `fe80::` and `ff:fe`) left in a formatted address.
+    #[inline(always)]
+    const fn _uppercase_template(mut fmtd: [u8; 25]) -> [u8; 25] {
+        let mut i = 0;
+        while i < fmtd.len() {
+            if fmtd[i].is_ascii_lowercase() {
+                fmtd[i] = fmtd[i].to_ascii_uppercase();
+            }
+            i += 1;
+        }
+
+        // above version is const
+        // fmtd.make_ascii_uppercase();
+        fmtd
+    }
+
+    /// Formats a MAC address into a string of at most 25 bytes, without allocating.
+    ///
+    /// ```
+    /// # use sqlite3_nettools_core::MacStyle;
+    /// let mac = [0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF];
+    /// assert_eq!(MacStyle::Dashed.format_mac(mac, false).as_str(), "aa-bb-cc-dd-ee-ff");
+    /// assert_eq!(MacStyle::LinkLocal.format_mac(mac, true).as_str(), "FE80::A8BB:CCFF:FEDD:EEFF");
+    /// ```
+    pub const fn format_mac(&self, mac: [u8; 6], uppercase: bool) -> MacText {
+        let (bytes, len) = self.format_internal(mac, uppercase);
+        MacText { bytes, len: len as u8 }
+    }
+
+    /// Formats a 64-bit EUI into a string of at most 25 bytes, without allocating.
+    pub const fn format_eui64(&self, eui: [u8; 8], uppercase: bool) -> MacText {
+        let (bytes, len) = self.format_eui64_internal(eui, uppercase);
+        MacText { bytes, len: len as u8 }
+    }
+
+    /// Formats a MAC address into a string of at most 25 bytes, from anything convertible to its bytes (such as
+    /// `sqlite3_nettools::mac::MacAddr`).
+    pub fn format(&self, mac: impl Into<[u8; 6]>, uppercase: bool) -> MacText {
+        self.format_mac(mac.into(), uppercase)
+    }
+
+    /// Formats either a MAC address or 64-bit EUI into a string of at most 25 bytes, from anything convertible to their
+    /// bytes (such as `sqlite3_nettools::mac::Eui`).
+    pub fn format_eui(&self, eui: impl Into<EuiBytes>, uppercase: bool) -> MacText {
+        match eui.into() {
+            EuiBytes::Mac48(mac) => self.format_mac(mac, uppercase),
+            EuiBytes::Eui64(eui) => self.format_eui64(eui, uppercase),
+        }
+    }
+
+    /// An const version of `MacStyle::format_mac`. Returns a byte buffer, with a string length.
+    /// 
+    /// For use in a const context, the function omits:
+    /// - Trimming output to formatted length: see `MacStyle::length`, or the second value in the returned tuple
+    /// - UTF8 validity: While the trimmed output should always be UTF8, it is not checked in this function.
+    /// 
+    /// # Example
+    /// ```
+    /// # use sqlite3_nettools_core::MacStyle;
+    /// let style = MacStyle::Colon;
+    /// let (raw, len) = style.format_internal([0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF], false);
+    /// let trimmed = &raw[..len];
+    /// assert_eq!(trimmed, b"aa:bb:cc:dd:ee:ff");
+    /// # assert_eq!(style.length(), len);
+    /// ```
+    #[inline(always)]
+    pub const fn format_internal(&self, mac: [u8; 6], uppercase: bool) -> ([u8; 25], usize) {
+        let mut as_u64 = u64::from_be_bytes([0, 0, mac[0], mac[1], mac[2], mac[3], mac[4], mac[5]]);
+        if self.is_ipv6_style() {
+            as_u64 ^= 0x0000_0200_0000_0000;
+        }
+
+        let style = self.fmt_desc();
+        let mut fmtd = match uppercase {
+            true  => MacStyle::_format_mac::<true , 12>(as_u64, style.offsets, style.base),
+            false => MacStyle::_format_mac::<false, 12>(as_u64, style.offsets, style.base),
+        };
+
+        if uppercase && self.is_ipv6_style() {
+            // ensure the fe80:: prefix and ff:fe internal bytes are capitalized
+            fmtd = MacStyle::_uppercase_template(fmtd);
+        }
+
+        (fmtd, style.length)
+    }
+
+    /// An const version of `MacStyle::format_eui64`. Returns a byte buffer, with a string length.
+    ///
+    /// See `MacStyle::format_internal` for the caveats of this function.
+    ///
+    /// # Example
+    /// ```
+    /// # use sqlite3_nettools_core::MacStyle;
+    /// let style = MacStyle::Dots;
+    /// let (raw, len) = style.format_eui64_internal([0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF, 0x00, 0x11], false);
+    /// assert_eq!(&raw[..len], b"aabb.ccdd.eeff.0011");
+    /// # assert_eq!(style.length_eui64(), len);
+    /// ```
+    #[inline(always)]
+    pub const fn format_eui64_internal(&self, eui: [u8; 8], uppercase: bool) -> ([u8; 25], usize) {
+        let mut as_u64 = u64::from_be_bytes(eui);
+        if self.is_ipv6_style() {
+            as_u64 ^= 0x0200_0000_0000_0000;
+        }
+
+        let style = self.fmt_desc_eui64();
+        let mut fmtd = match uppercase {
+            true  => MacStyle::_format_mac::<true , 16>(as_u64, style.offsets, style.base),
+            false => MacStyle::_format_mac::<false, 16>(as_u64, style.offsets, style.base),
+        };
+
+        if uppercase && self.is_ipv6_style() {
+            // ensure the fe80:: prefix is capitalized
+            fmtd = MacStyle::_uppercase_template(fmtd);
+        }
+
+        (fmtd, style.length)
+    }
+}
+
+impl MacStyle {
+    /// Each style's names, as accepted by `MAC_FORMAT` and [MacStyle::from_str]. The first is the style's own name.
+    pub const NAMES: &'static [(&'static str, MacStyle)] = &[
+        ("colon", MacStyle::Colon),
+        ("hex", MacStyle::Colon),
+        ("hexstring", MacStyle::Colon),
+        ("hexadecimal", MacStyle::Prefixed),
+        ("bare", MacStyle::Plain),
+        ("dot", MacStyle::Dots),
+        ("dash", MacStyle::Dashed),
+        ("canonical", MacStyle::Dashed),
+        ("interface-id", MacStyle::InterfaceId),
+        ("link-local", MacStyle::LinkLocal),
+    ];
+}
+
+/// Parses a style by name, ignoring case: `colon` (or `hex`, `hexstring`), `hexadecimal`, `bare`, `dot`, `dash` (or
+/// `canonical`), `interface-id`, or `link-local`.
+///
+/// ```
+/// # use sqlite3_nettools_core::MacStyle;
+/// let style: MacStyle = "Dash".parse().unwrap();
+/// assert_eq!(style.format_mac([0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF], false).as_str(), "aa-bb-cc-dd-ee-ff");
+/// assert!("dashes".parse::<MacStyle>().is_err());
+/// ```
+impl FromStr for MacStyle {
+    type Err = UnknownStyle;
+    fn from_str(s: &str) -> Result<MacStyle, UnknownStyle> {
+        MacStyle::NAMES.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s))
+            .map(|(_, style)| *style)
+            .ok_or(UnknownStyle)
+    }
+}
+
+/// A style name not recognized by [MacStyle::from_str]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnknownStyle;
+
+impl fmt::Display for UnknownStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unknown MAC address style")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnknownStyle {}
+
+/// A formatted MAC address or EUI-64, held inline rather than allocated.
+#[derive(Clone, Copy)]
+pub struct MacText {
+    bytes: [u8; 25],
+    len: u8,
+}
+
+impl MacText {
+    pub fn as_str(&self) -> &str {
+        // SAFETY: every style's base string is ASCII, and formatting only writes ASCII hex digits into it
+        unsafe { core::str::from_utf8_unchecked(&self.bytes[..self.len as usize]) }
+    }
+}
+
+impl Deref for MacText {
+    type Target = str;
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for MacText {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq for MacText {
+    fn eq(&self, other: &MacText) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+impl Eq for MacText {}
+
+impl fmt::Display for MacText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for MacText {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+#[test]
+fn style_formatting() {
+    let mac = [0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF];
+    assert_eq!(MacStyle::Plain.format_mac(mac, true).as_str(), "AABBCCDDEEFF");
+    assert_eq!(MacStyle::Dots.format_mac(mac, false).as_str(), "aabb.ccdd.eeff");
+    assert_eq!(MacStyle::InterfaceId.format_mac(mac, false).as_str(), "a8bb:ccff:fedd:eeff");
+
+    let eui = [0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF, 0x00, 0x11];
+    assert_eq!(MacStyle::Colon.format_eui64(eui, true).as_str(), "AA:BB:CC:DD:EE:FF:00:11");
+    assert_eq!(MacStyle::Prefixed.format_eui64(eui, false).as_str(), "0xaabbccddeeff0011");
+}
@@ -0,0 +1,217 @@
+//! Command line companion to the extension, for quick lookups and for producing the files its loaders consume.
+//!
+//! Build with `cargo build --release --no-default-features --features cli --bin nettools`.
+
+#[cfg(feature = "loadable-extension")]
+compile_error!("the nettools binary opens its own connections, build it with `--no-default-features --features cli`");
+
+use std::{io::Read, net::IpAddr, process::ExitCode, str::FromStr};
+
+use ipnet::IpNet;
+use rusqlite::{types::ValueRef, Connection};
+use sqlite3_nettools::oui::{MergePrecedence, OuiDb, EMBEDDED_DB};
+
+const USAGE: &str = "usage:
+    nettools format <mac> [format]        Formats a MAC address, as with MAC_FORMAT
+    nettools lookup <mac>...              Looks up the OUI of each MAC address, as with MAC_INFO
+    nettools subnet <network> [mask]      Describes a network, given in CIDR form or with a separate mask
+    nettools compile <output> [input...]  Compiles OUI databases into the binary format read by OUI_LOAD
+    nettools sql <database> [sql]         Runs SQL (or SQL read from stdin) on a database, with the functions registered";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let Some(command) = Command::parse(&args) else {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    };
+
+    match command.run() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("nettools: {e}");
+            ExitCode::FAILURE
+        },
+    }
+}
+
+/// A subcommand, with its arguments
+#[derive(Debug, PartialEq)]
+enum Command<'a> {
+    Format(&'a str, Option<&'a str>),
+    Lookup(&'a [&'a str]),
+    Subnet(&'a str, Option<&'a str>),
+    Compile(&'a str, &'a [&'a str]),
+    /// The database, and the SQL to run, or None to read it from stdin
+    Sql(&'a str, Option<&'a str>),
+}
+
+impl<'a> Command<'a> {
+    /// Parses the arguments following the program name, or returns None if they don't match the usage.
+    fn parse(args: &'a [&'a str]) -> Option<Command<'a>> {
+        Some(match args {
+            ["format", mac] => Command::Format(mac, None),
+            ["format", mac, fmt] => Command::Format(mac, Some(fmt)),
+            ["lookup", macs @ ..] if !macs.is_empty() => Command::Lookup(macs),
+            ["subnet", network] => Command::Subnet(network, None),
+            ["subnet", network, mask] => Command::Subnet(network, Some(mask)),
+            ["compile", output, inputs @ ..] => Command::Compile(output, inputs),
+            ["sql", database] => Command::Sql(database, None),
+            ["sql", database, sql] => Command::Sql(database, Some(sql)),
+            _ => return None,
+        })
+    }
+
+    fn run(&self) -> Result<(), String> {
+        match *self {
+            Command::Format(mac, None) => query_one("SELECT MAC_FORMAT(?1)", &[mac]),
+            Command::Format(mac, Some(fmt)) => query_one("SELECT MAC_FORMAT(?1, ?2)", &[mac, fmt]),
+            Command::Lookup(macs) => lookup(macs),
+            Command::Subnet(network, mask) => subnet(network, mask),
+            Command::Compile(output, inputs) => compile(output, inputs),
+            Command::Sql(database, None) => read_stdin().and_then(|sql| run_sql(database, &sql)),
+            Command::Sql(database, Some(sql)) => run_sql(database, sql),
+        }
+    }
+}
+
+/// Opens an in-memory connection with every function registered.
+fn memory_connection() -> Result<Connection, String> {
+    let conn = Connection::open_in_memory().map_err(|e| e.to_string())?;
+    sqlite3_nettools::register(&conn).map_err(|e| e.to_string())?;
+    Ok(conn)
+}
+
+/// Runs a query returning a single value, and prints it.
+fn query_one(sql: &str, params: &[&str]) -> Result<(), String> {
+    let conn = memory_connection()?;
+    let value = conn.query_row(sql, rusqlite::params_from_iter(params), |row| row.get_ref(0).map(display_value))
+        .map_err(|e| e.to_string())?;
+    println!("{value}");
+    Ok(())
+}
+
+fn lookup(macs: &[&str]) -> Result<(), String> {
+    let conn = memory_connection()?;
+    let mut stmt = conn.prepare("SELECT MAC_INFO(?1)").map_err(|e| e.to_string())?;
+    for mac in macs {
+        let info = stmt.query_row([mac], |row| row.get_ref(0).map(display_value)).map_err(|e| format!("{mac}: {e}"))?;
+        println!("{mac}\t{info}");
+    }
+    Ok(())
+}
+
+fn subnet(network: &str, mask: Option<&str>) -> Result<(), String> {
+    let net = match mask {
+        None => IpNet::from_str(network).map_err(|e| format!("{network:?}: {e}"))?,
+        Some(mask) => {
+            let addr = IpAddr::from_str(network).map_err(|e| format!("{network:?}: {e}"))?;
+            let mask = IpAddr::from_str(mask).map_err(|e| format!("{mask:?}: {e}"))?;
+            let prefix_len = ipnet::ip_mask_to_prefix(mask).map_err(|e| format!("{mask}: {e}"))?;
+            IpNet::new(addr, prefix_len).map_err(|e| format!("{mask}: {e}"))?
+        },
+    };
+
+    let host_bits = u32::from(net.max_prefix_len() - net.prefix_len());
+    let addresses = 1u128.checked_shl(host_bits).map_or_else(|| "2^128".to_owned(), |n| n.to_string());
+    let mut hosts = net.hosts();
+
+    println!("network\t{}", net.trunc());
+    println!("netmask\t{}", net.netmask());
+    println!("hostmask\t{}", net.hostmask());
+    if let IpNet::V4(_) = net {
+        println!("broadcast\t{}", net.broadcast());
+    }
+    if let Some(first) = hosts.next() {
+        println!("first_host\t{first}");
+        println!("last_host\t{}", hosts.next_back().unwrap_or(first));
+    }
+    println!("addresses\t{addresses}");
+    Ok(())
+}
+
+/// Compiles OUI databases into the binary format, as with the `compile_oui_db` example.
+fn compile(output: &str, inputs: &[&str]) -> Result<(), String> {
+    let db = if inputs.is_empty() {
+        OuiDb::clone(&EMBEDDED_DB)
+    } else {
+        let mut db = OuiDb::default();
+        for input in inputs {
+            let txt = std::fs::read_to_string(input).map_err(|e| format!("unable to read {input:?}: {e}"))?;
+            let input_db = OuiDb::parse_any(&txt).map_err(|e| format!("unable to parse {input:?}: {e}"))?;
+            db = db.merge(&input_db, MergePrecedence::Ours);
+        }
+        db
+    };
+
+    db.save_binary_file(output).map_err(|e| format!("unable to write {output:?}: {e}"))?;
+    eprintln!("wrote {} prefixes to {output:?}", db.len());
+    Ok(())
+}
+
+fn read_stdin() -> Result<String, String> {
+    let mut sql = String::new();
+    std::io::stdin().read_to_string(&mut sql).map_err(|e| format!("unable to read stdin: {e}"))?;
+    Ok(sql)
+}
+
+/// Runs each statement on the database, printing any rows as tab separated values.
+fn run_sql(database: &str, sql: &str) -> Result<(), String> {
+    let conn = Connection::open(database).map_err(|e| format!("unable to open {database:?}: {e}"))?;
+    sqlite3_nettools::register(&conn).map_err(|e| e.to_string())?;
+
+    let mut batch = rusqlite::Batch::new(&conn, sql);
+    while let Some(mut stmt) = batch.next().map_err(|e| e.to_string())? {
+        let columns = stmt.column_count();
+        let mut rows = stmt.query([]).map_err(|e| e.to_string())?;
+        while let Some(row) = rows.next().map_err(|e| e.to_string())? {
+            let values = (0..columns)
+                .map(|i| row.get_ref(i).map(display_value))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+            println!("{}", values.join("\t"));
+        }
+    }
+    Ok(())
+}
+
+/// Formats a value for printing, with NULL spelled out and blobs as hex literals.
+fn display_value(value: ValueRef<'_>) -> String {
+    match value {
+        ValueRef::Null => "NULL".to_owned(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(f) => f.to_string(),
+        ValueRef::Text(t) => String::from_utf8_lossy(t).into_owned(),
+        ValueRef::Blob(b) => format!("X'{}'", b.iter().map(|b| format!("{b:02X}")).collect::<String>()),
+    }
+}
+
+#[test]
+fn command_args() {
+    assert_eq!(Command::parse(&["format", "3ca6f6c434f8"]), Some(Command::Format("3ca6f6c434f8", None)));
+    assert_eq!(Command::parse(&["format", "3ca6f6c434f8", "dot"]), Some(Command::Format("3ca6f6c434f8", Some("dot"))));
+    assert_eq!(Command::parse(&["lookup", "a", "b"]), Some(Command::Lookup(&["a", "b"])));
+    assert_eq!(Command::parse(&["subnet", "10.0.0.1", "255.0.0.0"]), Some(Command::Subnet("10.0.0.1", Some("255.0.0.0"))));
+    assert_eq!(Command::parse(&["compile", "oui.bin"]), Some(Command::Compile("oui.bin", &[])));
+    assert_eq!(Command::parse(&["sql", ":memory:"]), Some(Command::Sql(":memory:", None)));
+    for args in [&[][..], &["lookup"], &["format"], &["format", "a", "b", "c"], &["sql", "a", "b", "c"], &["help"]] {
+        assert_eq!(Command::parse(args), None, "{args:?}");
+    }
+}
+
+#[test]
+fn command_smoke() {
+    let run = |args: &[&str]| Command::parse(args).unwrap().run();
+    run(&["format", "3ca6f6c434f8", "dot"]).unwrap();
+    run(&["lookup", "3c:a6:f6:c4:34:f8", "02:00:00:00:00:01"]).unwrap();
+    run(&["subnet", "192.168.1.7/24"]).unwrap();
+    run(&["subnet", "2001:db8::1", "ffff:ffff::"]).unwrap();
+    run(&["sql", ":memory:", "SELECT MAC_MANUF('3ca6f6c434f8'), IP_FORMAT('10.0.0.1'); SELECT 1"]).unwrap();
+
+    assert!(run(&["format", "not a mac"]).is_err());
+    assert!(run(&["lookup", "3c:a6:f6:c4:34:f8", "zz"]).unwrap_err().starts_with("zz: "));
+    assert!(run(&["subnet", "10.0.0.1", "255.0.255.0"]).is_err());
+    assert!(run(&["sql", ":memory:", "SELECT NO_SUCH_FUNCTION()"]).is_err());
+    assert!(run(&["compile", "unused.bin", "/does/not/exist"]).unwrap_err().starts_with("unable to read"));
+}
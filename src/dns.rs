@@ -0,0 +1,427 @@
+use std::{
+    io,
+    io::{Read, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket},
+    time::Duration,
+};
+
+/// The record types the resolver can look up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordType {
+    A = 1,
+    Ptr = 12,
+    Aaaa = 28,
+}
+
+/// A record from the answer section of a response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Record {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    /// A domain name, without its trailing dot
+    Ptr(String),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DnsError {
+    #[error("no DNS server is configured, and none is listed in /etc/resolv.conf. Set one with NETTOOLS_SET('dns_server', ...)")]
+    NoServer,
+    #[error("invalid domain name {0:?}")]
+    BadName(String),
+    #[error("DNS query to {0} failed: {1}")]
+    Io(SocketAddr, io::Error),
+    #[error("DNS query to {0} timed out")]
+    Timeout(SocketAddr),
+    #[error("malformed DNS response from {0}: {1}")]
+    Malformed(SocketAddr, &'static str),
+    #[error("DNS server {0} failed to answer (response code {1})")]
+    ServerFailure(SocketAddr, u8),
+}
+
+const HEADER_LEN: usize = 12;
+/// The class of internet records
+const CLASS_IN: u16 = 1;
+/// The response code for a name that doesn't exist
+const RCODE_NXDOMAIN: u8 = 3;
+/// The header flag set on responses truncated to fit a UDP datagram
+const FLAG_TC: u8 = 0x02;
+
+/// How long to wait for each response, and how many times to send the query.
+const TIMEOUT: Duration = Duration::from_secs(2);
+const ATTEMPTS: usize = 2;
+
+/// A minimal stub resolver, sending recursive queries over UDP to a single server, and retrying over TCP when the
+/// response is truncated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resolver {
+    server: SocketAddr,
+}
+
+impl Resolver {
+    pub fn new(server: SocketAddr) -> Resolver {
+        Resolver { server }
+    }
+
+    /// The DNS server queries are sent to.
+    pub fn server(&self) -> SocketAddr {
+        self.server
+    }
+
+    /// Uses the first nameserver listed in `/etc/resolv.conf`.
+    ///
+    /// Other platforms' configuration isn't read, so on Windows this always fails with [DnsError::NoServer].
+    pub fn system() -> Result<Resolver, DnsError> {
+        let conf = std::fs::read_to_string("/etc/resolv.conf").map_err(|_| DnsError::NoServer)?;
+        conf.lines()
+            .filter_map(|l| l.trim().strip_prefix("nameserver"))
+            // scoped IPv6 addresses (fe80::1%eth0) can't be parsed by std, so are skipped
+            .find_map(|addr| addr.trim().parse::<IpAddr>().ok())
+            .map(|addr| Resolver::new(SocketAddr::new(addr, 53)))
+            .ok_or(DnsError::NoServer)
+    }
+
+    /// Looks up records of a type, following any CNAMEs the server includes. Returns None when the name doesn't exist,
+    /// and an empty list when it exists without records of that type.
+    pub fn query(&self, name: &str, rtype: RecordType) -> Result<Option<Vec<Record>>, DnsError> {
+        let _span = span!(DEBUG, "dns_query", server = %self.server, name, rtype = ?rtype);
+        let id = query_id();
+        let query = encode_query(id, name, rtype)?;
+
+        let bind: SocketAddr = match self.server {
+            SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+            SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+        };
+        let io_err = |e| DnsError::Io(self.server, e);
+        let socket = UdpSocket::bind(bind).map_err(io_err)?;
+        socket.connect(self.server).map_err(io_err)?;
+        socket.set_read_timeout(Some(TIMEOUT)).map_err(io_err)?;
+
+        let mut buf = [0u8; 4096];
+        for _ in 0..ATTEMPTS {
+            socket.send(&query).map_err(io_err)?;
+            loop {
+                let len = match socket.recv(&mut buf) {
+                    Ok(len) => len,
+                    Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => break,
+                    Err(e) => return Err(io_err(e)),
+                };
+                // ignore stray responses, such as a late answer to an earlier attempt
+                if let Some(answer) = decode_response(id, &query[HEADER_LEN..], &buf[..len], rtype) {
+                    if buf[2] & FLAG_TC != 0 {
+                        event!(DEBUG, "dns response truncated, retrying over tcp");
+                        return self.query_tcp(id, &query, rtype);
+                    }
+                    event!(DEBUG, ok = answer.is_ok(), "received dns response");
+                    return answer.map_err(|e| self.response_error(e));
+                }
+            }
+        }
+        event!(WARN, "dns query timed out");
+        Err(DnsError::Timeout(self.server))
+    }
+
+    /// Sends a query over TCP, for answers too large for a UDP datagram.
+    fn query_tcp(&self, id: u16, query: &[u8], rtype: RecordType) -> Result<Option<Vec<Record>>, DnsError> {
+        let io_err = |e: io::Error| match e.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => DnsError::Timeout(self.server),
+            _ => DnsError::Io(self.server, e),
+        };
+        let mut stream = TcpStream::connect_timeout(&self.server, TIMEOUT).map_err(io_err)?;
+        stream.set_read_timeout(Some(TIMEOUT)).map_err(io_err)?;
+        stream.set_write_timeout(Some(TIMEOUT)).map_err(io_err)?;
+
+        // messages over TCP are prefixed with their length
+        let mut msg = Vec::with_capacity(2 + query.len());
+        msg.extend_from_slice(&(query.len() as u16).to_be_bytes());
+        msg.extend_from_slice(query);
+        stream.write_all(&msg).map_err(io_err)?;
+
+        let mut len = [0u8; 2];
+        stream.read_exact(&mut len).map_err(io_err)?;
+        let mut resp = vec![0u8; u16::from_be_bytes(len) as usize];
+        stream.read_exact(&mut resp).map_err(io_err)?;
+
+        let answer = decode_response(id, &query[HEADER_LEN..], &resp, rtype)
+            .ok_or(DnsError::Malformed(self.server, "response doesn't match the query"))?;
+        event!(DEBUG, ok = answer.is_ok(), "received dns response over tcp");
+        answer.map_err(|e| self.response_error(e))
+    }
+
+    fn response_error(&self, e: ResponseError) -> DnsError {
+        match e {
+            ResponseError::Malformed(why) => DnsError::Malformed(self.server, why),
+            ResponseError::Rcode(rcode) => DnsError::ServerFailure(self.server, rcode),
+        }
+    }
+}
+
+/// Picks a query ID that's hard to guess, as protection against spoofed responses.
+fn query_id() -> u16 {
+    use std::hash::{BuildHasher, Hasher};
+
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    hasher.write_u128(now.as_nanos());
+    hasher.finish() as u16
+}
+
+/// Writes a recursive query for a single question.
+fn encode_query(id: u16, name: &str, rtype: RecordType) -> Result<Vec<u8>, DnsError> {
+    let bad_name = || DnsError::BadName(name.to_owned());
+    let trimmed = name.strip_suffix('.').unwrap_or(name);
+    if trimmed.is_empty() || trimmed.len() > 253 {
+        return Err(bad_name());
+    }
+
+    let mut msg = Vec::with_capacity(HEADER_LEN + trimmed.len() + 6);
+    msg.extend_from_slice(&id.to_be_bytes());
+    // a standard query, with recursion desired, and one question
+    msg.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in trimmed.split('.') {
+        if label.is_empty() || label.len() > 63 || !label.is_ascii() {
+            return Err(bad_name());
+        }
+        msg.push(label.len() as u8);
+        msg.extend_from_slice(label.as_bytes());
+    }
+    msg.push(0);
+    msg.extend_from_slice(&(rtype as u16).to_be_bytes());
+    msg.extend_from_slice(&CLASS_IN.to_be_bytes());
+    Ok(msg)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum ResponseError {
+    Malformed(&'static str),
+    Rcode(u8),
+}
+
+/// Reads a domain name at `at`, following compression pointers, returning it and the offset following it.
+fn read_name(msg: &[u8], mut at: usize) -> Result<(String, usize), ResponseError> {
+    let malformed = ResponseError::Malformed("bad domain name");
+    let mut name = String::new();
+    let mut end = None;
+    // each pointer must go backwards, so this can't loop forever
+    let mut limit = at;
+    loop {
+        let len = *msg.get(at).ok_or(ResponseError::Malformed("truncated domain name"))? as usize;
+        match len {
+            0 => break,
+            0xc0.. => {
+                let ptr = ((len & 0x3f) << 8) | *msg.get(at + 1).ok_or(ResponseError::Malformed("truncated domain name"))? as usize;
+                end.get_or_insert(at + 2);
+                if ptr >= limit {
+                    return Err(malformed);
+                }
+                limit = ptr;
+                at = ptr;
+            },
+            64.. => return Err(malformed),
+            len => {
+                let label = msg.get(at + 1..at + 1 + len).ok_or(ResponseError::Malformed("truncated domain name"))?;
+                if !name.is_empty() {
+                    name.push('.');
+                }
+                name.extend(label.iter().map(|b| char::from(*b)));
+                at += 1 + len;
+            },
+        }
+    }
+    Ok((name, end.unwrap_or(at + 1)))
+}
+
+/// Reads a response to the query with this ID and question, or None if it's a response to some other query.
+fn decode_response(id: u16, question: &[u8], msg: &[u8], rtype: RecordType) -> Option<Result<Option<Vec<Record>>, ResponseError>> {
+    if msg.len() < HEADER_LEN || msg[0..2] != id.to_be_bytes() || msg[2] & 0x80 == 0 {
+        return None;
+    }
+    if msg.get(HEADER_LEN..HEADER_LEN + question.len()).is_none_or(|q| !q.eq_ignore_ascii_case(question)) {
+        return None;
+    }
+    Some(decode_answers(msg, HEADER_LEN + question.len(), rtype))
+}
+
+fn decode_answers(msg: &[u8], mut at: usize, rtype: RecordType) -> Result<Option<Vec<Record>>, ResponseError> {
+    match msg[3] & 0x0f {
+        0 => {},
+        RCODE_NXDOMAIN => return Ok(None),
+        rcode => return Err(ResponseError::Rcode(rcode)),
+    }
+
+    let answers = u16::from_be_bytes([msg[6], msg[7]]);
+    let mut records = Vec::new();
+    for _ in 0..answers {
+        let (_, next) = read_name(msg, at)?;
+        let header = msg.get(next..next + 10).ok_or(ResponseError::Malformed("truncated record"))?;
+        let kind = u16::from_be_bytes([header[0], header[1]]);
+        let class = u16::from_be_bytes([header[2], header[3]]);
+        let len = u16::from_be_bytes([header[8], header[9]]) as usize;
+        let data_at = next + 10;
+        let data = msg.get(data_at..data_at + len).ok_or(ResponseError::Malformed("truncated record"))?;
+        at = data_at + len;
+
+        // CNAMEs lead to the records asked for, which the server includes after them
+        if class != CLASS_IN || kind != rtype as u16 {
+            continue;
+        }
+        records.push(match rtype {
+            RecordType::A => Record::A(<[u8; 4]>::try_from(data).map_err(|_| ResponseError::Malformed("bad A record"))?.into()),
+            RecordType::Aaaa => Record::Aaaa(<[u8; 16]>::try_from(data).map_err(|_| ResponseError::Malformed("bad AAAA record"))?.into()),
+            RecordType::Ptr => Record::Ptr(read_name(msg, data_at)?.0),
+        });
+    }
+    Ok(Some(records))
+}
+
+/// The name queried for an address's PTR record, such as `4.3.2.1.in-addr.arpa`.
+pub fn reverse_name(addr: IpAddr) -> String {
+    reversed_labels(addr, "in-addr.arpa", "ip6.arpa")
+}
+
+/// Writes an address's labels in reverse, as used in reverse DNS and DNSBL queries: octets for IPv4, and nibbles for
+/// IPv6. The labels are followed by the IPv4 or IPv6 zone.
+pub fn reversed_labels(addr: IpAddr, zone_v4: &str, zone_v6: &str) -> String {
+    match addr {
+        IpAddr::V4(v4) => {
+            let [a, b, c, d] = v4.octets();
+            format!("{d}.{c}.{b}.{a}.{zone_v4}")
+        },
+        IpAddr::V6(v6) => {
+            let mut name = String::with_capacity(64 + zone_v6.len());
+            for byte in v6.octets().iter().rev() {
+                name.push_str(&format!("{:x}.{:x}.", byte & 0xf, byte >> 4));
+            }
+            name.push_str(zone_v6);
+            name
+        },
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_server {
+    use std::{io::{Read, Write}, net::{SocketAddr, TcpListener, UdpSocket}, thread::JoinHandle};
+
+    type Records = &'static [(&'static str, u16, &'static [u8])];
+
+    /// Answers queries on a local port from a fixed table of names, types, and record data, until `count` queries have
+    /// been answered. Names not in the table are answered with NXDOMAIN.
+    pub fn serve(records: Records, count: usize) -> (SocketAddr, JoinHandle<()>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            for _ in 0..count {
+                let (len, from) = socket.recv_from(&mut buf).unwrap();
+                socket.send_to(&respond(records, &buf[..len], false), from).unwrap();
+            }
+        });
+        (addr, handle)
+    }
+
+    /// Answers a single query over UDP with a truncated response, and then over TCP in full.
+    pub fn serve_truncated(records: Records) -> (SocketAddr, JoinHandle<()>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        let listener = TcpListener::bind(addr).unwrap();
+        let handle = std::thread::spawn(move || {
+            let mut buf = [0u8; 512];
+            let (len, from) = socket.recv_from(&mut buf).unwrap();
+            socket.send_to(&respond(records, &buf[..len], true), from).unwrap();
+
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut len = [0u8; 2];
+            stream.read_exact(&mut len).unwrap();
+            let mut query = vec![0u8; u16::from_be_bytes(len) as usize];
+            stream.read_exact(&mut query).unwrap();
+            let resp = respond(records, &query, false);
+            stream.write_all(&(resp.len() as u16).to_be_bytes()).unwrap();
+            stream.write_all(&resp).unwrap();
+        });
+        (addr, handle)
+    }
+
+    /// Builds the response to a query. Truncated responses set the TC flag and omit their answers.
+    fn respond(records: Records, query: &[u8], truncated: bool) -> Vec<u8> {
+        let (name, end) = super::read_name(query, super::HEADER_LEN).unwrap();
+        let qtype = u16::from_be_bytes([query[end], query[end + 1]]);
+        let answers: Vec<_> = records.iter()
+            .filter(|(n, t, _)| !truncated && n.eq_ignore_ascii_case(&name) && *t == qtype)
+            .collect();
+
+        let mut resp = query[..end + 4].to_vec();
+        resp[2] |= 0x80;
+        if truncated {
+            resp[2] |= super::FLAG_TC;
+        }
+        resp[3] = match records.iter().any(|(n, _, _)| n.eq_ignore_ascii_case(&name)) {
+            true => 0x80,
+            false => 0x80 | super::RCODE_NXDOMAIN,
+        };
+        resp[6..8].copy_from_slice(&(answers.len() as u16).to_be_bytes());
+        for (_, t, data) in answers {
+            // the answer's name points back to the question
+            resp.extend_from_slice(&[0xc0, super::HEADER_LEN as u8]);
+            resp.extend_from_slice(&t.to_be_bytes());
+            resp.extend_from_slice(&[0, 1, 0, 0, 0, 60]);
+            resp.extend_from_slice(&(data.len() as u16).to_be_bytes());
+            resp.extend_from_slice(data);
+        }
+        resp
+    }
+}
+
+#[test]
+fn reversed_names() {
+    assert_eq!(reverse_name("192.0.2.5".parse().unwrap()), "5.2.0.192.in-addr.arpa");
+    assert_eq!(
+        reverse_name("2001:db8::567:89ab".parse().unwrap()),
+        "b.a.9.8.7.6.5.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa",
+    );
+    assert_eq!(reversed_labels("127.0.0.2".parse().unwrap(), "zen.example", "zen.example"), "2.0.0.127.zen.example");
+}
+
+#[test]
+fn queries() {
+    static RECORDS: &[(&str, u16, &[u8])] = &[
+        ("host.example", 1, &[192, 0, 2, 5]),
+        ("host.example", 1, &[192, 0, 2, 6]),
+        ("5.2.0.192.in-addr.arpa", 12, b"\x04host\x07example\x00"),
+    ];
+    let (server, handle) = test_server::serve(RECORDS, 4);
+    let resolver = Resolver::new(server);
+
+    assert_eq!(resolver.query("host.example", RecordType::A).unwrap(), Some(vec![
+        Record::A(Ipv4Addr::new(192, 0, 2, 5)),
+        Record::A(Ipv4Addr::new(192, 0, 2, 6)),
+    ]));
+    assert_eq!(resolver.query("HOST.example.", RecordType::Aaaa).unwrap(), Some(vec![]));
+    assert_eq!(resolver.query("missing.example", RecordType::A).unwrap(), None);
+    assert_eq!(resolver.query(&reverse_name("192.0.2.5".parse().unwrap()), RecordType::Ptr).unwrap(),
+        Some(vec![Record::Ptr("host.example".to_owned())]));
+    handle.join().unwrap();
+
+    assert!(matches!(encode_query(0, "bad..name", RecordType::A), Err(DnsError::BadName(_))));
+    assert!(matches!(encode_query(0, &"a".repeat(64), RecordType::A), Err(DnsError::BadName(_))));
+}
+
+#[test]
+fn truncated_responses() {
+    static RECORDS: &[(&str, u16, &[u8])] = &[("host.example", 1, &[192, 0, 2, 5])];
+    let (server, handle) = test_server::serve_truncated(RECORDS);
+    assert_eq!(Resolver::new(server).query("host.example", RecordType::A).unwrap(),
+        Some(vec![Record::A(Ipv4Addr::new(192, 0, 2, 5))]));
+    handle.join().unwrap();
+}
+
+#[test]
+fn compressed_names() {
+    // "a.example" at 12, then "b" pointing to "example" within it
+    let mut msg = vec![0u8; HEADER_LEN];
+    msg.extend_from_slice(b"\x01a\x07example\x00\x01b\xc0\x0e");
+    assert_eq!(read_name(&msg, 12), Ok(("a.example".to_owned(), 23)));
+    assert_eq!(read_name(&msg, 23), Ok(("b.example".to_owned(), 27)));
+
+    // pointers must go backwards
+    msg.extend_from_slice(b"\xc0\x1b");
+    assert!(read_name(&msg, 27).is_err());
+}
@@ -0,0 +1,104 @@
+use std::{collections::HashMap, sync::{Arc, PoisonError, RwLock}};
+
+use crate::{mac::MacAddr, oui::{parse_mac_addr, ParseMacError}};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ParseEthersError {
+    #[error("malformed ethers record, expected an address and a hostname (line {0}: {1:?})")]
+    Record(usize, String),
+    #[error("error parsing address in ethers record (line {0}: {2:?})")]
+    Address(usize, #[source] ParseMacError, String),
+}
+
+/// Parses an address from an ethers file, where octets may omit their leading zeros (`8:0:20:1:2:3`).
+fn parse_ethers_addr(s: &str) -> Result<MacAddr, ParseMacError> {
+    let octets: Vec<&str> = s.split([':', '-']).collect();
+    match octets.len() == 6 && octets.iter().all(|o| (1..=2).contains(&o.len())) {
+        true => parse_mac_addr(&octets.iter().map(|o| format!("{o:0>2}")).collect::<Vec<_>>().join(":")),
+        false => parse_mac_addr(s),
+    }
+}
+
+/// Hostnames for whole MAC addresses, as listed by ethers files (such as `/etc/ethers`, see `ethers(5)`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ethers(HashMap<MacAddr, String>);
+
+impl Ethers {
+    /// Parse an ethers file into memory, naming each address by its hostname.
+    ///
+    /// Each line holds an address and a hostname (or IP address), separated by whitespace, and anything following a
+    /// `#` is a comment. Octets may omit their leading zeros, as in `8:0:20:1:2:3`. When an address is listed more than
+    /// once, the first is kept.
+    pub fn parse(txt: &str) -> Result<Ethers, ParseEthersError> {
+        let mut names = HashMap::new();
+        for (lnum, l) in txt.split('\n').enumerate() {
+            let l = l.split('#').next().unwrap_or_default().trim();
+            if l.is_empty() {
+                continue;
+            }
+            let mut fields = l.split_whitespace();
+            let (Some(addr), Some(host), None) = (fields.next(), fields.next(), fields.next()) else {
+                return Err(ParseEthersError::Record(lnum, l.to_owned()));
+            };
+            let mac = parse_ethers_addr(addr)
+                .map_err(|e| ParseEthersError::Address(lnum, e, l.to_owned()))?;
+            names.entry(mac).or_insert_with(|| host.to_owned());
+        }
+        Ok(Ethers(names))
+    }
+
+    /// Adds the names from another file, keeping this one's name for any address both list.
+    pub fn merge(&mut self, other: Ethers) {
+        for (mac, host) in other.0 {
+            self.0.entry(mac).or_insert(host);
+        }
+    }
+
+    /// The hostname listed for an address.
+    pub fn name(&self, mac: MacAddr) -> Option<&str> {
+        self.0.get(&mac).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// A connection's ethers names, shared by its functions. Loading files swaps in a whole new set of names.
+#[derive(Debug, Clone, Default)]
+pub struct SharedEthers(Arc<RwLock<Arc<Ethers>>>);
+
+impl SharedEthers {
+    /// Returns a snapshot of the current names.
+    pub fn load(&self) -> Arc<Ethers> {
+        // the lock only guards an Arc swap, so a poisoned lock can't hold partially updated names
+        self.0.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Replaces the names, such as with those of newly loaded files.
+    pub fn set(&self, ethers: Ethers) {
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(ethers);
+    }
+}
+
+#[test]
+fn parse_ethers_hosts() {
+    let ethers = Ethers::parse("# hosts on the lab network
+8:0:20:1:2:3\tsun-box
+3c:a6:f6:c4:34:f8   laptop.example  # Alice's laptop
+3C-A6-F6-C4-34-F8 duplicate
+02:00:00:00:00:01 192.0.2.1
+").unwrap();
+    let host = |mac: &str| ethers.name(parse_mac_addr(mac).unwrap());
+    assert_eq!(host("08:00:20:01:02:03"), Some("sun-box"));
+    assert_eq!(host("3c:a6:f6:c4:34:f8"), Some("laptop.example"));
+    assert_eq!(host("3c:a6:f6:c4:34:f9"), None);
+    assert_eq!(host("02:00:00:00:00:01"), Some("192.0.2.1"));
+    assert_eq!(ethers.len(), 3);
+
+    assert!(matches!(Ethers::parse("3c:a6:f6:c4:34:f8\n"), Err(ParseEthersError::Record(0, _))));
+    assert!(matches!(Ethers::parse("\n3c:a6:f6:c4:34 host\n"), Err(ParseEthersError::Address(1, _, _))));
+}
@@ -0,0 +1,93 @@
+use std::ffi::c_int;
+
+use rusqlite::{ffi, types::{Value, ValueRef}};
+
+use crate::exports::error::ArgError;
+
+/// A function reading its arguments through [RawArgs], for those accepting lists bound as pointers.
+pub type RawFunction = fn(&RawArgs<'_>) -> rusqlite::Result<Value>;
+
+/// A function's arguments as SQLite passes them, as with rusqlite's `Context`, but also allowing lists bound with
+/// rusqlite's `array` feature to be read. SQLite passes these as pointers, which `Context` reads as NULL.
+pub struct RawArgs<'a>(&'a [*mut ffi::sqlite3_value]);
+
+impl<'a> RawArgs<'a> {
+    /// Wraps the arguments of a function call.
+    ///
+    /// # Safety
+    /// Each value must be an argument of the call in progress, valid for `'a`.
+    pub unsafe fn new(args: &'a [*mut ffi::sqlite3_value]) -> RawArgs<'a> {
+        RawArgs(args)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Reads the argument at `idx`, as with `Context::get_raw`.
+    pub fn get_raw(&self, idx: usize) -> ValueRef<'a> {
+        let value = self.0[idx];
+        // SAFETY: the values are the call's arguments, as required by `new`, and are read as rusqlite reads them
+        unsafe {
+            match ffi::sqlite3_value_type(value) {
+                ffi::SQLITE_INTEGER => ValueRef::Integer(ffi::sqlite3_value_int64(value)),
+                ffi::SQLITE_FLOAT => ValueRef::Real(ffi::sqlite3_value_double(value)),
+                ffi::SQLITE_TEXT => ValueRef::Text(bytes(ffi::sqlite3_value_text(value), ffi::sqlite3_value_bytes(value))),
+                ffi::SQLITE_BLOB => ValueRef::Blob(bytes(ffi::sqlite3_value_blob(value).cast(), ffi::sqlite3_value_bytes(value))),
+                _ => ValueRef::Null,
+            }
+        }
+    }
+
+    /// Reads the list bound to the argument at `idx` with rusqlite's `array` feature (`rusqlite::vtab::array::Array`).
+    /// Returns None for any other value, including pointers bound by other means.
+    ///
+    /// The list is read as rusqlite stores it, so it must have been bound through the same rusqlite as this crate's,
+    /// such as by an application registering the functions with [register](crate::register).
+    pub fn get_list(&self, idx: usize) -> Option<&'a [Value]> {
+        // SAFETY: SQLite only returns the pointer when it was bound under rusqlite's type name, as an `Rc<Vec<Value>>`
+        // it keeps alive until the statement is reset or rebound
+        unsafe {
+            let list = ffi::sqlite3_value_pointer(self.0[idx], c"rarray".as_ptr()).cast::<Vec<Value>>();
+            list.as_ref().map(Vec::as_slice)
+        }
+    }
+
+    /// An error for the argument at `idx`, as with [ArgError::invalid].
+    pub fn invalid(&self, idx: usize, source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> rusqlite::Error {
+        ArgError::from_value(idx, self.get_raw(idx), source).into()
+    }
+}
+
+/// Reads `len` bytes from a value's text or blob, which SQLite gives as a null pointer when empty.
+unsafe fn bytes<'a>(data: *const u8, len: c_int) -> &'a [u8] {
+    match (data.is_null(), usize::try_from(len)) {
+        (false, Ok(len)) => std::slice::from_raw_parts(data, len),
+        _ => &[],
+    }
+}
+
+/// Sets the result of a function call to its returned value, or error.
+///
+/// # Safety
+/// `ctx` must be the context of the call in progress.
+pub unsafe fn set_result(ctx: *mut ffi::sqlite3_context, result: rusqlite::Result<Value>) {
+    let len = |n: usize| c_int::try_from(n).unwrap_or(c_int::MAX);
+    match result {
+        Ok(Value::Null) => ffi::sqlite3_result_null(ctx),
+        Ok(Value::Integer(i)) => ffi::sqlite3_result_int64(ctx, i),
+        Ok(Value::Real(r)) => ffi::sqlite3_result_double(ctx, r),
+        Ok(Value::Text(s)) => ffi::sqlite3_result_text(ctx, s.as_ptr().cast(), len(s.len()), ffi::SQLITE_TRANSIENT()),
+        Ok(Value::Blob(b)) => ffi::sqlite3_result_blob(ctx, b.as_ptr().cast(), len(b.len()), ffi::SQLITE_TRANSIENT()),
+        Err(e) => {
+            let msg = match e {
+                rusqlite::Error::SqliteFailure(_, Some(msg)) => msg,
+                e => e.to_string(),
+            };
+            ffi::sqlite3_result_error(ctx, msg.as_ptr().cast(), len(msg.len()));
+        },
+    }
+}
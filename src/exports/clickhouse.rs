@@ -0,0 +1,199 @@
+use std::{fmt::Write, net::{IpAddr, Ipv4Addr, Ipv6Addr}};
+
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+use rusqlite::types::ValueRef;
+
+use crate::{exports::error::{arg, text_arg, ArgError}, text::{SqlText, TextBuf}};
+#[cfg(test)]
+use crate::test_util::{query_err, query_row, query_text};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ClickHouseError {
+    #[error("IPv4 address out of range (got {0}). Expected an integer within [0, 4294967295]")]
+    Ipv4Range(i64),
+    #[error("Expected a 16 byte IPv6 address blob, but received {0} bytes")]
+    Ipv6BlobLength(usize),
+    #[error("CIDR prefix length out of range (got {0}). Expected an integer within [0, 255]")]
+    CidrRange(i64),
+}
+
+/// Reads a CIDR prefix length. As in ClickHouse, lengths longer than the address are clamped by the caller.
+fn cidr_arg(ctx: &rusqlite::functions::Context<'_>, idx: usize) -> rusqlite::Result<Option<u8>> {
+    let Some(cidr): Option<i64> = arg(ctx, idx)? else { return Ok(None); };
+    u8::try_from(cidr)
+        .map(Some)
+        .map_err(|_| ArgError::invalid(ctx, idx, ClickHouseError::CidrRange(cidr)))
+}
+
+/// Reads an IPv6 address from text, or a 16 byte blob as with ClickHouse's `FixedString(16)`. IPv4 text addresses are
+/// read as IPv4-mapped IPv6 addresses.
+fn ipv6_arg(ctx: &rusqlite::functions::Context<'_>, idx: usize) -> rusqlite::Result<Option<Ipv6Addr>> {
+    match ctx.get_raw(idx) {
+        ValueRef::Null => Ok(None),
+        ValueRef::Blob(dat) => <[u8; 16]>::try_from(dat)
+            .map(|octets| Some(Ipv6Addr::from(octets)))
+            .map_err(|_| ArgError::invalid(ctx, idx, ClickHouseError::Ipv6BlobLength(dat.len()))),
+        ValueRef::Text(_) => {
+            let addr: IpAddr = text_arg(ctx, idx)?.unwrap_or_default().parse()
+                .map_err(|e| ArgError::invalid(ctx, idx, e))?;
+            Ok(Some(match addr {
+                IpAddr::V4(v4) => v4.to_ipv6_mapped(),
+                IpAddr::V6(v6) => v6,
+            }))
+        },
+        _ => Err(ArgError::invalid(ctx, idx, "expected text or a 16 byte blob")),
+    }
+}
+
+/// Writes the first and last addresses of a network as a JSON array, standing in for ClickHouse's tuples.
+fn range_json(net: IpNet) -> String {
+    serde_json::json!([net.network().to_string(), net.broadcast().to_string()]).to_string()
+}
+
+/// IPv4NumToString(NULL|int) -> NULL|text
+///
+/// Writes an IPv4 address from its integer form, as with ClickHouse's function of the same name.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`IPv4NumToString(3232235521)`|`'192.168.0.1'`|
+/// |`IPv4NumToString(0)`|`'0.0.0.0'`|
+/// |`IPv4NumToString(-1)`|error|
+pub fn ipv4_num_to_string(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<SqlText<TextBuf<16>>>> {
+    let Some(num): Option<i64> = arg(ctx, 0)? else { return Ok(None); };
+    let addr = u32::try_from(num)
+        .map(Ipv4Addr::from)
+        .map_err(|_| ArgError::invalid(ctx, 0, ClickHouseError::Ipv4Range(num)))?;
+
+    let mut text = TextBuf::new();
+    write!(text, "{addr}").unwrap();
+    Ok(Some(SqlText(text)))
+}
+
+/// IPv4StringToNum(NULL|ipv4) -> NULL|int
+///
+/// Reads an IPv4 address into its integer form, as with ClickHouse's function of the same name. Malformed addresses
+/// are an error, as in current ClickHouse releases.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`IPv4StringToNum('192.168.0.1')`|`3232235521`|
+/// |`IPv4StringToNum('::1')`|error|
+pub fn ipv4_string_to_num(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<i64>> {
+    let Some(s) = text_arg(ctx, 0)? else { return Ok(None); };
+    let addr: Ipv4Addr = s.parse().map_err(|e| ArgError::invalid(ctx, 0, e))?;
+    Ok(Some(u32::from(addr) as i64))
+}
+
+/// IPv6NumToString(NULL|blob) -> NULL|text
+///
+/// Writes an IPv6 address from its 16 byte binary form, as with ClickHouse's function of the same name. IPv4-mapped
+/// addresses are written as `::ffff:a.b.c.d`.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`IPv6NumToString(X'2A0206B8000000000000000000000011')`|`'2a02:6b8::11'`|
+/// |`IPv6NumToString(X'00000000000000000000FFFFC1FC1101')`|`'::ffff:193.252.17.1'`|
+/// |`IPv6NumToString(X'C0A80001')`|error|
+pub fn ipv6_num_to_string(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<SqlText<TextBuf<48>>>> {
+    let addr = match ctx.get_raw(0) {
+        ValueRef::Text(_) => return Err(ArgError::invalid(ctx, 0, "expected a 16 byte blob")),
+        _ => ipv6_arg(ctx, 0)?,
+    };
+    let Some(addr) = addr else { return Ok(None); };
+
+    let mut text = TextBuf::new();
+    write!(text, "{addr}").unwrap();
+    Ok(Some(SqlText(text)))
+}
+
+/// IPv4CIDRToRange(NULL|ipv4, NULL|cidr) -> NULL|json
+///
+/// Returns the first and last addresses of the network containing the address, as with ClickHouse's function of the
+/// same name. As SQLite has no tuples, these are returned as a JSON array. Prefix lengths over 32 are treated as 32.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`IPv4CIDRToRange('192.168.5.2', 16)`|`'["192.168.0.0","192.168.255.255"]'`|
+/// |`IPv4CIDRToRange('192.168.5.2', 16) ->> 1`|`'192.168.255.255'`|
+/// |`IPv4CIDRToRange('10.0.0.1', 40)`|`'["10.0.0.1","10.0.0.1"]'`|
+pub fn ipv4_cidr_to_range(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let Some(s) = text_arg(ctx, 0)? else { return Ok(None); };
+    let Some(cidr) = cidr_arg(ctx, 1)? else { return Ok(None); };
+    let addr: Ipv4Addr = s.parse().map_err(|e| ArgError::invalid(ctx, 0, e))?;
+
+    let net = Ipv4Net::new(addr, cidr.min(32)).expect("prefix length was clamped");
+    Ok(Some(range_json(IpNet::V4(net))))
+}
+
+/// IPv6CIDRToRange(NULL|ipv6, NULL|cidr) -> NULL|json
+///
+/// Returns the first and last addresses of the network containing the address, as with ClickHouse's function of the
+/// same name. The address may be text, or a 16 byte blob. As SQLite has no tuples, these are returned as a JSON array.
+/// Prefix lengths over 128 are treated as 128.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`IPv6CIDRToRange('2001:0db8:0000:85a3:0000:0000:ac1f:8001', 32)`|`'["2001:db8::","2001:db8:ffff:ffff:ffff:ffff:ffff:ffff"]'`|
+/// |`IPv6CIDRToRange(X'20010DB8000085A300000000AC1F8001', 128) ->> 0`|`'2001:db8:0:85a3::ac1f:8001'`|
+pub fn ipv6_cidr_to_range(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let Some(addr) = ipv6_arg(ctx, 0)? else { return Ok(None); };
+    let Some(cidr) = cidr_arg(ctx, 1)? else { return Ok(None); };
+
+    let net = Ipv6Net::new(addr, cidr.min(128)).expect("prefix length was clamped");
+    Ok(Some(range_json(IpNet::V6(net))))
+}
+
+/// isIPAddressInRange(NULL|address, NULL|prefix) -> NULL|bool
+///
+/// Tests if an address is within a network given in CIDR notation, as with ClickHouse's function of the same name. An
+/// address and network of different IP versions are never in range.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`isIPAddressInRange('127.0.0.1', '127.0.0.0/8')`|`TRUE`|
+/// |`isIPAddressInRange('127.0.0.1', 'ffff::/16')`|`FALSE`|
+/// |`isIPAddressInRange('::ffff:192.168.0.1', '::ffff:192.168.0.4/128')`|`FALSE`|
+/// |`isIPAddressInRange('127.0.0.1', '127.0.0.0')`|error|
+pub fn is_ip_address_in_range(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<bool>> {
+    let Some(addr) = text_arg(ctx, 0)? else { return Ok(None); };
+    let Some(prefix) = text_arg(ctx, 1)? else { return Ok(None); };
+
+    let addr: IpAddr = addr.parse().map_err(|e| ArgError::invalid(ctx, 0, e))?;
+    let prefix: IpNet = prefix.parse().map_err(|e| ArgError::invalid(ctx, 1, e))?;
+    Ok(Some(prefix.contains(&addr)))
+}
+
+#[test]
+fn clickhouse_functions() {
+    use crate::{FunctionGroup, Registration};
+
+    let conn = rusqlite::Connection::open_in_memory().unwrap();
+    Registration::builder().group(FunctionGroup::ClickHouse).register(&conn).unwrap();
+    let int = |sql: &str| query_row::<Option<i64>>(&conn, sql);
+
+    assert_eq!(query_text(&conn, "SELECT IPv4NumToString(3232235521)").as_deref(), Some("192.168.0.1"));
+    assert_eq!(int("SELECT IPv4StringToNum('192.168.0.1')"), Some(3232235521));
+    assert_eq!(int("SELECT IPv4StringToNum(IPv4NumToString(4294967295))"), Some(4294967295));
+    assert_eq!(query_text(&conn, "SELECT IPv6NumToString(X'2A0206B8000000000000000000000011')").as_deref(), Some("2a02:6b8::11"));
+    assert_eq!(query_text(&conn, "SELECT IPv6NumToString(X'00000000000000000000FFFFC1FC1101')").as_deref(), Some("::ffff:193.252.17.1"));
+
+    assert_eq!(query_text(&conn, "SELECT IPv4CIDRToRange('192.168.5.2', 16)").as_deref(), Some(r#"["192.168.0.0","192.168.255.255"]"#));
+    assert_eq!(query_text(&conn, "SELECT IPv4CIDRToRange('10.0.0.1', 40)").as_deref(), Some(r#"["10.0.0.1","10.0.0.1"]"#));
+    assert_eq!(query_text(&conn, "SELECT IPv6CIDRToRange('2001:0db8:0000:85a3:0000:0000:ac1f:8001', 32)").as_deref(),
+        Some(r#"["2001:db8::","2001:db8:ffff:ffff:ffff:ffff:ffff:ffff"]"#));
+
+    assert_eq!(int("SELECT isIPAddressInRange('127.0.0.1', '127.0.0.0/8')"), Some(1));
+    assert_eq!(int("SELECT isIPAddressInRange('127.0.0.1', 'ffff::/16')"), Some(0));
+    assert_eq!(int("SELECT isIPAddressInRange(NULL, '127.0.0.0/8')"), None);
+
+    assert!(query_err(&conn, "SELECT IPv4NumToString(-1)").starts_with("argument 1 (-1)"));
+    assert!(query_err(&conn, "SELECT IPv6NumToString(X'C0A80001')").starts_with("argument 1 (X'C0A80001')"));
+    assert!(query_err(&conn, "SELECT isIPAddressInRange('127.0.0.1', '127.0.0.0')").starts_with("argument 2 ('127.0.0.0')"));
+}
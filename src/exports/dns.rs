@@ -0,0 +1,174 @@
+use std::net::IpAddr;
+
+use crate::{
+    dns::{reverse_name, reversed_labels, DnsError, Record, RecordType, Resolver},
+    exports::{error::{text_arg, ArgError}, inet::address_arg},
+    settings::SharedSettings,
+};
+#[cfg(test)]
+use crate::test_util::{conn, query_text};
+
+/// The resolver for a connection: its `dns_server` setting, or the system's configured server.
+fn resolver(settings: &SharedSettings) -> Result<Resolver, DnsError> {
+    match settings.load().dns_server {
+        Some(server) => Ok(Resolver::new(server)),
+        None => Resolver::system(),
+    }
+}
+
+fn dns_err(e: DnsError) -> rusqlite::Error {
+    rusqlite::Error::UserFunctionError(Box::new(e))
+}
+
+/// # DNSBL_CHECK(NULL|ip, NULL|zone) -> NULL|json
+/// Checks whether an address is listed by a DNS-based blocklist, returning the listing's return codes as a JSON array
+/// of addresses, or NULL when it isn't listed.
+///
+/// The blocklist is queried as described by RFC 5782: an IPv4 address's octets are reversed and prepended to the zone
+/// (`5.2.0.192.zen.spamhaus.org`), as are an IPv6 address's nibbles. Blocklists use the return codes (usually within
+/// `127.0.0.0/8`) to say why an address is listed.
+///
+/// Queries are sent to the connection's `dns_server` setting (see [NETTOOLS_SET](crate::exports::settings::set)),
+/// defaulting to the system's configured DNS server. Note that some blocklists refuse queries sent through public
+/// resolvers, answering with an error code such as `127.255.255.254`, which is returned as any other code is.
+///
+/// As this makes network requests, it is [direct-only](crate::exports#direct-only-functions). This function is only
+/// available when the extension is built with the `dns` feature.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`DNSBL_CHECK('127.0.0.2', 'zen.spamhaus.org')`               | `'["127.0.0.2","127.0.0.4","127.0.0.10"]'` |
+/// |`DNSBL_CHECK('192.0.2.1', 'zen.spamhaus.org')`               | `NULL` |
+/// |`DNSBL_CHECK('127.0.0.2', 'zen.spamhaus.org') ->> '$[0]'`    | `'127.0.0.2'` |
+/// |`DNSBL_CHECK('10.0.0.0/8', 'zen.spamhaus.org')`              | N/A - A query error is raised, as only addresses are accepted |
+pub fn dnsbl_check(settings: &SharedSettings, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let Some(addr) = address_arg(ctx, 0)? else { return Ok(None); };
+    let Some(zone) = text_arg(ctx, 1)? else { return Ok(None); };
+    let zone = zone.trim().trim_matches('.');
+
+    let name = reversed_labels(addr, zone, zone);
+    let records = resolver(settings)
+        .and_then(|r| r.query(&name, RecordType::A))
+        .map_err(|e| match e {
+            DnsError::BadName(_) => ArgError::invalid(ctx, 1, e),
+            e => dns_err(e),
+        })?;
+
+    let mut codes: Vec<IpAddr> = records.into_iter().flatten()
+        .filter_map(|r| match r {
+            Record::A(v4) => Some(IpAddr::V4(v4)),
+            _ => None,
+        })
+        .collect();
+    if codes.is_empty() {
+        return Ok(None);
+    }
+    codes.sort();
+    codes.dedup();
+    let codes: Vec<String> = codes.iter().map(IpAddr::to_string).collect();
+    Ok(Some(serde_json::Value::from(codes).to_string()))
+}
+
+#[test]
+fn dnsbl_lookups() {
+    static RECORDS: &[(&str, u16, &[u8])] = &[
+        ("2.0.0.127.bl.example", 1, &[127, 0, 0, 4]),
+        ("2.0.0.127.bl.example", 1, &[127, 0, 0, 2]),
+        ("1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.bl.example", 1, &[127, 0, 0, 3]),
+    ];
+    let (server, handle) = crate::dns::test_server::serve(RECORDS, 3);
+
+    let conn = conn();
+    conn.query_row("SELECT NETTOOLS_SET('dns_server', ?1)", [server.to_string()], |_| Ok(())).unwrap();
+
+    assert_eq!(query_text(&conn, "SELECT DNSBL_CHECK('127.0.0.2', 'bl.example.')").as_deref(), Some(r#"["127.0.0.2","127.0.0.4"]"#));
+    assert_eq!(query_text(&conn, "SELECT DNSBL_CHECK('2001:db8::1', 'bl.example')").as_deref(), Some(r#"["127.0.0.3"]"#));
+    assert_eq!(query_text(&conn, "SELECT DNSBL_CHECK('192.0.2.1', 'bl.example')"), None);
+    assert_eq!(query_text(&conn, "SELECT DNSBL_CHECK(NULL, 'bl.example')"), None);
+    handle.join().unwrap();
+
+    let err = conn.query_row("SELECT DNSBL_CHECK('192.0.2.1', 'bad..zone')", [], |r| r.get::<_, Option<String>>(0)).unwrap_err();
+    assert!(err.to_string().starts_with("argument 2 ('bad..zone')"));
+}
+
+/// # FCRDNS(NULL|ip) -> NULL|json
+/// Checks an address's forward-confirmed reverse DNS: that the name its PTR record gives resolves back to the same
+/// address. Returns a JSON object with the `hostname`, and whether it was `confirmed`, or NULL when the address has no
+/// PTR record.
+///
+/// IPv4 addresses are confirmed against the name's A records, and IPv6 addresses against its AAAA records. When an
+/// address has several PTR records, the first confirmed name is returned (or the first name, if none are).
+///
+/// Queries are sent to the connection's `dns_server` setting, as with [DNSBL_CHECK](crate::exports::dns::dnsbl_check).
+/// As this makes network requests, it is [direct-only](crate::exports#direct-only-functions). This function is only
+/// available when the extension is built with the `dns` feature.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`FCRDNS('8.8.8.8')`                          | `'{"confirmed":true,"hostname":"dns.google"}'` |
+/// |`FCRDNS('192.0.2.1')`                        | `NULL` |
+/// |`FCRDNS(ip) ->> '$.confirmed'`               | `1` |
+pub fn fcrdns(settings: &SharedSettings, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let Some(addr) = address_arg(ctx, 0)? else { return Ok(None); };
+    let resolver = resolver(settings).map_err(dns_err)?;
+
+    let names: Vec<String> = resolver.query(&reverse_name(addr), RecordType::Ptr).map_err(dns_err)?
+        .into_iter().flatten()
+        .filter_map(|r| match r {
+            Record::Ptr(name) => Some(name),
+            _ => None,
+        })
+        .collect();
+    let Some(first) = names.first() else { return Ok(None); };
+
+    let forward = match addr {
+        IpAddr::V4(_) => RecordType::A,
+        IpAddr::V6(_) => RecordType::Aaaa,
+    };
+    let mut confirmed = None;
+    for name in &names {
+        // a name that doesn't resolve (or isn't a valid name) can't confirm the address
+        let addrs = match resolver.query(name, forward) {
+            Ok(addrs) => addrs.unwrap_or_default(),
+            Err(DnsError::BadName(_)) => continue,
+            Err(e) => return Err(dns_err(e)),
+        };
+        let matches = addrs.iter().any(|r| match r {
+            Record::A(v4) => IpAddr::V4(*v4) == addr,
+            Record::Aaaa(v6) => IpAddr::V6(*v6) == addr,
+            Record::Ptr(_) => false,
+        });
+        if matches {
+            confirmed = Some(name);
+            break;
+        }
+    }
+
+    Ok(Some(serde_json::json!({
+        "hostname": confirmed.unwrap_or(first),
+        "confirmed": confirmed.is_some(),
+    }).to_string()))
+}
+
+#[test]
+fn fcrdns_lookups() {
+    static RECORDS: &[(&str, u16, &[u8])] = &[
+        ("5.2.0.192.in-addr.arpa", 12, b"\x05other\x07example\x00"),
+        ("5.2.0.192.in-addr.arpa", 12, b"\x04host\x07example\x00"),
+        ("other.example", 1, &[198, 51, 100, 1]),
+        ("host.example", 1, &[192, 0, 2, 5]),
+        ("6.2.0.192.in-addr.arpa", 12, b"\x06forged\x07example\x00"),
+    ];
+    // three lookups for the confirmed address (trying both names), two for the forged one, and one without a PTR
+    let (server, handle) = crate::dns::test_server::serve(RECORDS, 6);
+
+    let conn = conn();
+    conn.query_row("SELECT NETTOOLS_SET('dns_server', ?1)", [server.to_string()], |_| Ok(())).unwrap();
+
+    assert_eq!(query_text(&conn, "SELECT FCRDNS('192.0.2.5')").as_deref(), Some(r#"{"confirmed":true,"hostname":"host.example"}"#));
+    assert_eq!(query_text(&conn, "SELECT FCRDNS('192.0.2.6')").as_deref(), Some(r#"{"confirmed":false,"hostname":"forged.example"}"#));
+    assert_eq!(query_text(&conn, "SELECT FCRDNS('192.0.2.7')"), None);
+    handle.join().unwrap();
+}
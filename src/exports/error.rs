@@ -0,0 +1,144 @@
+use std::{error::Error, fmt::Write};
+
+use rusqlite::{ffi, functions::Context, types::{FromSql, FromSqlError, ValueRef}};
+
+/// An error caused by one of a function's arguments, naming its position and (an excerpt of) its value.
+///
+/// Arguments are numbered from 1 in the message, as they are written in SQL:
+/// `argument 2 ('10.0.0.300'): invalid IP address syntax`.
+#[derive(Debug, thiserror::Error)]
+#[error("argument {} ({excerpt}): {source}", .index + 1)]
+pub struct ArgError {
+    /// The position of the argument, counting from 0
+    pub index: usize,
+    /// The argument's value as an SQL literal, truncated if long
+    pub excerpt: String,
+    #[source]
+    pub source: Box<dyn Error + Send + Sync>,
+}
+
+impl ArgError {
+    pub fn new(ctx: &Context<'_>, index: usize, source: impl Into<Box<dyn Error + Send + Sync>>) -> ArgError {
+        match index < ctx.len() {
+            true => ArgError::from_value(index, ctx.get_raw(index), source),
+            false => ArgError { index, excerpt: "missing".to_owned(), source: source.into() },
+        }
+    }
+
+    /// An error for an argument with the given value, for functions reading their arguments without a `Context`.
+    pub fn from_value(index: usize, value: ValueRef<'_>, source: impl Into<Box<dyn Error + Send + Sync>>) -> ArgError {
+        ArgError { index, excerpt: excerpt(value), source: source.into() }
+    }
+
+    /// An error for an argument of an unsupported type or value, as a `rusqlite::Error` to be returned from a function.
+    pub fn invalid(ctx: &Context<'_>, index: usize, source: impl Into<Box<dyn Error + Send + Sync>>) -> rusqlite::Error {
+        ArgError::new(ctx, index, source).into()
+    }
+}
+
+/// Argument errors are raised with the generic `SQLITE_ERROR` result code, rather than one matching the reason (such as
+/// `SQLITE_MISMATCH` or `SQLITE_RANGE`). rusqlite reports a function's error by setting its result code and then its
+/// message, and setting the message resets the code to `SQLITE_ERROR`, so the message is what names the problem.
+impl From<ArgError> for rusqlite::Error {
+    fn from(e: ArgError) -> rusqlite::Error {
+        rusqlite::Error::SqliteFailure(ffi::Error::new(ffi::SQLITE_ERROR), Some(e.to_string()))
+    }
+}
+
+/// Formats a value as an SQL literal for error messages, truncating long text and blobs.
+fn excerpt(value: ValueRef<'_>) -> String {
+    const MAX_CHARS: usize = 32;
+    const MAX_BYTES: usize = 16;
+
+    match value {
+        ValueRef::Null => "NULL".to_owned(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(r) => r.to_string(),
+        ValueRef::Text(t) => {
+            let s = String::from_utf8_lossy(t);
+            let mut chars = s.chars();
+            let head: String = chars.by_ref().take(MAX_CHARS).collect();
+            let more = if chars.next().is_some() { "..." } else { "" };
+            format!("'{}{more}'", head.replace('\'', "''"))
+        },
+        ValueRef::Blob(b) => {
+            let mut s = String::from("X'");
+            b.iter().take(MAX_BYTES).for_each(|byte| write!(s, "{byte:02X}").expect("writing to a String"));
+            if b.len() > MAX_BYTES {
+                s.push_str("...");
+            }
+            s.push('\'');
+            s
+        },
+    }
+}
+
+/// Reads the argument at `idx`, as with `Context::get`, but naming the argument and its value on a type mismatch.
+pub fn arg<T: FromSql>(ctx: &Context<'_>, idx: usize) -> rusqlite::Result<T> {
+    ctx.get(idx).map_err(|e| match e {
+        rusqlite::Error::FromSqlConversionFailure(_, _, source) => ArgError::invalid(ctx, idx, source),
+        rusqlite::Error::IntegralValueOutOfRange(_, _) => ArgError::invalid(ctx, idx, "integer out of range"),
+        rusqlite::Error::InvalidFunctionParameterType(_, ty) => ArgError::invalid(ctx, idx, format!("unsupported type {ty}")),
+        e => e,
+    })
+}
+
+/// Reads the optional text argument at `idx`. Missing arguments and NULL are read as None.
+pub fn text_arg<'a>(ctx: &'a Context<'_>, idx: usize) -> rusqlite::Result<Option<&'a str>> {
+    if ctx.len() <= idx {
+        return Ok(None);
+    }
+    ctx.get_raw(idx).as_str_or_null().map_err(|e| match e {
+        FromSqlError::InvalidType => ArgError::invalid(ctx, idx, "expected text"),
+        e => ArgError::invalid(ctx, idx, e),
+    })
+}
+
+/// An error with one element of a JSON array argument, naming its position in the array (counting from 0, as with
+/// SQLite's `->` operator).
+#[derive(Debug, thiserror::Error)]
+#[error("element {index}: {source}")]
+pub struct ElementError {
+    pub index: usize,
+    #[source]
+    pub source: Box<dyn Error + Send + Sync>,
+}
+
+impl ElementError {
+    pub fn new(index: usize, source: impl Into<Box<dyn Error + Send + Sync>>) -> ElementError {
+        ElementError { index, source: source.into() }
+    }
+}
+
+/// Reads the optional argument at `idx` as text holding a JSON array, for the functions applied to each of an array's
+/// elements.
+pub fn json_array_arg(ctx: &Context<'_>, idx: usize) -> rusqlite::Result<Option<Vec<serde_json::Value>>> {
+    let Some(s) = text_arg(ctx, idx)? else { return Ok(None); };
+    json_array(s).map(Some).map_err(|e| ArgError::invalid(ctx, idx, e))
+}
+
+/// Parses text holding a JSON array, as read by [json_array_arg].
+pub fn json_array(s: &str) -> Result<Vec<serde_json::Value>, Box<dyn Error + Send + Sync>> {
+    match serde_json::from_str(s)? {
+        serde_json::Value::Array(elems) => Ok(elems),
+        _ => Err("expected a JSON array".into()),
+    }
+}
+
+#[test]
+fn arg_errors() {
+    use rusqlite::{functions::FunctionFlags, Connection};
+
+    let conn = Connection::open_in_memory().unwrap();
+    conn.create_scalar_function("PORT", 2, FunctionFlags::SQLITE_UTF8, |ctx| {
+        let port: i64 = arg(ctx, 1)?;
+        u16::try_from(port).map_err(|e| ArgError::invalid(ctx, 1, e))
+    }).unwrap();
+    let err = |sql: &str| conn.query_row(sql, [], |r| r.get::<_, u16>(0)).unwrap_err().to_string();
+
+    assert_eq!(err("SELECT PORT(NULL, 70000)"), "argument 2 (70000): out of range integral type conversion attempted");
+    assert_eq!(err("SELECT PORT(NULL, 'http')"), "argument 2 ('http'): unsupported type Text");
+    assert_eq!(err("SELECT PORT(NULL, X'00112233445566778899AABBCCDDEEFF00')").split(':').next(),
+        Some("argument 2 (X'00112233445566778899AABBCCDDEEFF...')"));
+    assert!(err(&format!("SELECT PORT(NULL, '{}')", "it''s".repeat(10))).starts_with("argument 2 ('it''sit''s"));
+}
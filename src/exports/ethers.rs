@@ -0,0 +1,112 @@
+use crate::{ethers::SharedEthers, exports::mac::mac_arg, mac::Eui};
+#[cfg(not(feature = "wasm"))]
+use crate::{
+    ethers::{Ethers, ParseEthersError},
+    exports::error::{text_arg, ArgError},
+};
+#[cfg(test)]
+use crate::test_util::{conn, TempPath};
+
+#[cfg(not(feature = "wasm"))]
+#[derive(Debug, thiserror::Error)]
+pub enum EthersLoadError {
+    #[error("ETHERS_LOAD requires at least one path")]
+    NoPaths,
+    #[error("Unable to read ethers file {0:?}: {1}")]
+    Io(String, #[source] std::io::Error),
+    #[error("Unable to parse ethers file {0:?}: {1}")]
+    Parse(String, #[source] ParseEthersError),
+}
+
+/// # ETHERS_LOAD(NULL|path, [path...]) -> NULL|count
+/// Loads one or more ethers files (such as `/etc/ethers`), naming the addresses looked up by
+/// [ETHERS_NAME](crate::exports::ethers::name) on this connection. Returns the number of addresses loaded.
+///
+/// Each line of an ethers file holds an address and a hostname (or IP address), separated by whitespace, as described
+/// by `ethers(5)`. Octets may omit their leading zeros (`8:0:20:1:2:3`), and anything following a `#` is a comment.
+/// Multiple files are merged, such as to combine a site-wide file with a local one. If files share an address, the
+/// earliest listed file wins.
+///
+/// Loading replaces any files previously loaded, as [OUI_LOAD](crate::exports::oui::load) does for OUI databases. The
+/// files are read in full before being swapped in, so a failed load leaves the current names in place.
+///
+/// As this reads from the filesystem, it is [direct-only](crate::exports#direct-only-functions).
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`ETHERS_LOAD('/etc/ethers')`                       | `214` |
+/// |`ETHERS_LOAD('ethers.local', '/etc/ethers')`       | `231` |
+/// |`ETHERS_LOAD(NULL)`                                | `NULL` |
+/// |`ETHERS_LOAD('/does/not/exist')`                   | N/A - A query error is raised with an appropriate error message |
+#[cfg(not(feature = "wasm"))]
+pub fn load(ethers: &SharedEthers, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<i64>> {
+    if ctx.is_empty() {
+        return Err(rusqlite::Error::UserFunctionError(Box::new(EthersLoadError::NoPaths)));
+    }
+    let mut paths = Vec::with_capacity(ctx.len());
+    for i in 0..ctx.len() {
+        let Some(path) = text_arg(ctx, i)? else { return Ok(None); };
+        paths.push(path);
+    }
+
+    let _span = span!(INFO, "ethers_load", paths = ?paths);
+    let mut names = Ethers::default();
+    for (i, path) in paths.iter().enumerate() {
+        let txt = std::fs::read_to_string(path)
+            .map_err(|e| ArgError::invalid(ctx, i, EthersLoadError::Io((*path).to_owned(), e)))?;
+        let file_names = Ethers::parse(&txt)
+            .map_err(|e| ArgError::invalid(ctx, i, EthersLoadError::Parse((*path).to_owned(), e)))?;
+        names.merge(file_names);
+    }
+
+    let count = names.len();
+    ethers.set(names);
+    event!(INFO, entries = count, "loaded ethers files");
+
+    Ok(Some(count as i64))
+}
+
+/// # ETHERS_NAME(NULL|mac) -> NULL|hostname
+/// Returns the hostname given to a MAC address by the ethers files loaded with
+/// [ETHERS_LOAD](crate::exports::ethers::load), or NULL if it isn't listed (or no files are loaded).
+///
+/// Only whole addresses match, so an EUI-64 is never listed.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`ETHERS_NAME('08:00:20:01:02:03')`       | `'sun-box'` |
+/// |`ETHERS_NAME('8:0:20:1:2:3')`            | N/A - A query error is raised, as only ethers files may omit leading zeros |
+/// |`ETHERS_NAME('02:00:00:00:00:01')`       | `NULL` |
+/// |`SELECT src_mac, ETHERS_NAME(src_mac), MAC_MANUF(src_mac) FROM flows` | `'08:00:20:01:02:03'`, `'sun-box'`, `'Oracle'` |
+pub fn name(ethers: &SharedEthers, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let Some(eui) = mac_arg(ctx, 0)? else { return Ok(None); };
+    let Eui::Mac48(mac) = eui else { return Ok(None); };
+    Ok(ethers.load().name(mac).map(str::to_owned))
+}
+
+#[test]
+fn ethers_lookups() {
+    let dir = TempPath::new("ethers");
+    std::fs::create_dir_all(&dir).unwrap();
+    let (local, site) = (dir.join("ethers.local"), dir.join("ethers"));
+    std::fs::write(&local, "3c:a6:f6:c4:34:f8 laptop.local\n").unwrap();
+    std::fs::write(&site, "# site hosts\n8:0:20:1:2:3 sun-box\n3c:a6:f6:c4:34:f8 laptop\n").unwrap();
+
+    let conn = conn();
+    let name = |mac: &str| conn.query_row("SELECT ETHERS_NAME(?1)", [mac], |r| r.get::<_, Option<String>>(0)).unwrap();
+    assert_eq!(name("08:00:20:01:02:03"), None);
+
+    let count: i64 = conn.query_row("SELECT ETHERS_LOAD(?1, ?2)", [local.to_str(), site.to_str()], |r| r.get(0)).unwrap();
+    assert_eq!(count, 2);
+    assert_eq!(name("0800.2001.0203").as_deref(), Some("sun-box"));
+    assert_eq!(name("3c-a6-f6-c4-34-f8").as_deref(), Some("laptop.local"));
+    assert_eq!(name("3c:a6:f6:c4:34:f8:00:00"), None);
+    assert_eq!(name("02:00:00:00:00:01"), None);
+
+    // a failed load keeps the names already loaded
+    let err = conn.query_row("SELECT ETHERS_LOAD(?1)", [dir.join("missing").to_str()], |_| Ok(())).unwrap_err();
+    assert!(err.to_string().contains("Unable to read ethers file"));
+    assert_eq!(name("08:00:20:01:02:03").as_deref(), Some("sun-box"));
+}
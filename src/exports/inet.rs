@@ -1,16 +1,53 @@
-use std::{net::{IpAddr, AddrParseError, Ipv4Addr, Ipv6Addr}, str::FromStr, fmt};
+use std::{net::{IpAddr, AddrParseError, Ipv4Addr, Ipv6Addr}, str::FromStr, fmt::{self, Write}, collections::{BTreeMap, HashSet}};
 
 use ipnet::{IpNet, Ipv4Net, Ipv6Net};
-use rusqlite::types::ValueRef;
+use rusqlite::{functions::Aggregate, types::{Value, ValueRef}};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    exports::{bound::RawArgs, error::{arg, json_array, json_array_arg, text_arg, ArgError, ElementError}, mac::mac_arg},
+    mac::Eui,
+    settings::{Ipv6Style, SharedSettings},
+    text::{SqlText, TextBuf},
+};
+#[cfg(test)]
+use crate::test_util::{conn, query_err, query_row, query_text};
 
 #[derive(thiserror::Error, Debug)]
 pub enum InetError {
     #[error("Attempted to convert blob into IP Address/Network that has bad size {} (blob contents: {:x?}). Blobs of size 4,5,16,17 are expected (v4/v6 address bytes, optional prefix length)", Vec::len(.0), if .0.len() < 20 { .0.as_slice() } else { &.0[..20] })]
     UnrecognizedBlobLength(Vec<u8>),
+    #[error("Blob IP network has an out of range prefix length {0} (blob contents: {1:x?})")]
+    BlobPrefixLength(u8, Vec<u8>),
     #[error("Attempt to use an invalid network mask")]
     InvalidNetworkMask(UserNetAddr, String),
-    #[error("Found multiple network mask lenghts for one address. Address field provided {0}, but recieved additional mask {1:?} in argument {2}")]
-    MultipleNetworkMasks(UserNetAddr, usize, String),
+    #[error("Found multiple network mask lengths for one address. Address field provided {0}, but received an additional mask")]
+    MultipleNetworkMasks(UserNetAddr),
+    #[error("Expected an IP address, but received network {0}")]
+    ExpectedAddress(UserNetAddr),
+    #[error("Port number out of range (got {0}). Expected an integer within [0, 65535]")]
+    PortRange(i64),
+    #[error("Unrecognized IP protocol {0:?}. Provide a protocol number, or one of `tcp`, `udp`, `icmp`, `icmpv6`, `sctp`")]
+    UnrecognizedProtocol(String),
+    #[error("Parent network changed within the group, from {0} to {1}. Group by the parent network")]
+    ParentChanged(IpNet, IpNet),
+    #[error("Bad rollup prefix lengths {0:?}. Expected a comma separated list of prefix lengths within [0, 128], such as `8,16,24`")]
+    BadRollupLevels(String),
+    #[error("Prefix length out of range (got {0}). Expected an integer within [0, {1}]")]
+    PrefixLengthRange(i64, u8),
+    #[error("Expected an IPv6 /64 prefix for a stable privacy address, but received {0}")]
+    StablePrivacyPrefix(UserNetAddr),
+    #[error("Stable privacy secret keys must be at least 16 bytes (128 bits), but received {0} bytes")]
+    StablePrivacyKey(usize),
+    #[error("DAD counter out of range (got {0}). Expected an integer within [0, 255]")]
+    DadCounterRange(i64),
+    #[error("Invalid address range {0:?}. Expected the first and last addresses of the same IP version, such as `10.0.0.1-10.0.0.9`")]
+    InvalidRange(String),
+    #[error("Expected a finite time in Unix seconds, but received {0}")]
+    UlaTime(f64),
+    #[error("Expected TRUE or FALSE for should_truncate, but received {0}. Give a mask length as the second of three arguments, or in CIDR notation")]
+    TruncateFlag(i64),
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -31,6 +68,31 @@ impl UserNetAddr {
             UserNetAddr::Network(netw) => net.trunc().contains(netw),
         }
     }
+    /// Reads an address from its raw bytes (4 or 16), or a network from its address bytes followed by a prefix length
+    /// byte (5 or 17).
+    pub fn from_blob(dat: &[u8]) -> Result<UserNetAddr, InetError> {
+        let (addr, len) = match dat.len() {
+            4 | 5 => (IpAddr::from(<[u8; 4]>::try_from(&dat[..4]).expect("length was matched")), dat.get(4)),
+            16 | 17 => (IpAddr::from(<[u8; 16]>::try_from(&dat[..16]).expect("length was matched")), dat.get(16)),
+            _ => return Err(InetError::UnrecognizedBlobLength(dat.to_vec())),
+        };
+        match len {
+            None => Ok(UserNetAddr::Address(addr)),
+            Some(&len) => IpNet::new(addr, len)
+                .map(UserNetAddr::Network)
+                .map_err(|_| InetError::BlobPrefixLength(len, dat.to_vec())),
+        }
+    }
+
+    /// Writes the address as its raw bytes, followed by a prefix length byte for networks. The inverse of [from_blob](Self::from_blob).
+    pub fn to_blob(&self) -> Vec<u8> {
+        let mut v = ip_octets(self.address());
+        if let UserNetAddr::Network(net) = self {
+            v.push(net.prefix_len());
+        }
+        v
+    }
+
     fn from_ctx(ctx: &rusqlite::functions::Context<'_>, net: usize, mask: Option<usize>) -> rusqlite::Result<Option<UserNetAddr>> {
         if ctx.len() <= net { return Ok(None); }
 
@@ -38,42 +100,16 @@ impl UserNetAddr {
         let netraw = ctx.get_raw(net);
         let mut una: UserNetAddr = match netraw {
             ValueRef::Null => return Ok(None),
-            ValueRef::Blob(dat) if dat.len() == 4 => { // IPv4
-                let raw: [u8; 4] = dat.try_into().unwrap();
-                UserNetAddr::Address(IpAddr::from(raw))
-            },
-            ValueRef::Blob(dat) if dat.len() == 5 => { // IPv4/CIDR
-                let raw: [u8; 4] = dat[..4].try_into().unwrap();
-                let len = dat[4];
-
-                let network = Ipv4Net::new(Ipv4Addr::from(raw), len)
-                    .map_err(|e| rusqlite::Error::UserFunctionError(Box::new(e)))?;
-
-                UserNetAddr::Network(IpNet::V4(network))
-            },
-            ValueRef::Blob(dat) if dat.len() == 16 => { // IPv6
-                let raw: [u8; 16] = dat.try_into().unwrap();
-                UserNetAddr::Address(IpAddr::from(raw))
-            },
-            ValueRef::Blob(dat) if dat.len() == 16 => { // IPv6/CIDR
-                let raw: [u8; 16] = dat[..4].try_into().unwrap();
-                let len = dat[16];
-
-                let network = Ipv6Net::new(Ipv6Addr::from(raw), len)
-                    .map_err(|e| rusqlite::Error::UserFunctionError(Box::new(e)))?;
-
-                UserNetAddr::Network(IpNet::V6(network))
-            },
-            ValueRef::Blob(b) => return Err(rusqlite::Error::UserFunctionError(Box::new(InetError::UnrecognizedBlobLength(b.to_vec())))),
+            ValueRef::Blob(dat) => UserNetAddr::from_blob(dat)
+                .map_err(|e| ArgError::invalid(ctx, net, e))?,
             ValueRef::Real(_) | ValueRef::Integer(_) => {
                 // don't support turning integers or floats into addresses or networks
-                let _s: String = ctx.get(net)?;
-                unreachable!("pre-validated that this type is not a string")
+                return Err(ArgError::invalid(ctx, net, "expected text or a blob"));
             },
             ValueRef::Text(_) => {
                 // delegate to existing from_str impl
-                UserNetAddr::from_str(netraw.as_str().unwrap())
-                    .map_err(|e| rusqlite::Error::UserFunctionError(Box::new(e)))?
+                UserNetAddr::from_str(text_arg(ctx, net)?.unwrap_or_default())
+                    .map_err(|e| ArgError::invalid(ctx, net, e))?
             }
         };
 
@@ -98,11 +134,11 @@ impl UserNetAddr {
                             // we are within proper range as an integer
                             Some(i as u8)
                         } else {
-                            return Err(rusqlite::Error::UserFunctionError(Box::new(ipnet::PrefixLenError)));
+                            return Err(ArgError::invalid(ctx, mask_idx, ipnet::PrefixLenError));
                         }
                     },
                     ValueRef::Text(_) => {
-                        let s = ctx.get_raw(mask_idx).as_str()?;
+                        let s = text_arg(ctx, mask_idx)?.unwrap_or_default();
 
                         // IP mask or stringified integer
                         match u8::from_str(s) {
@@ -110,27 +146,25 @@ impl UserNetAddr {
                                 if n <= max_len as u8 {
                                     Some(n)
                                 } else {
-                                    return Err(rusqlite::Error::UserFunctionError(Box::new(ipnet::PrefixLenError)));
+                                    return Err(ArgError::invalid(ctx, mask_idx, ipnet::PrefixLenError));
                                 }
                             },
                             Err(_pie) => {
                                 match Ipv4Addr::from_str(s) {
                                     Ok(mask) => {
                                         Some(ipnet::ipv4_mask_to_prefix(mask)
-                                            .map_err(|e| rusqlite::Error::UserFunctionError(Box::new(e)))?)
+                                            .map_err(|e| ArgError::invalid(ctx, mask_idx, e))?)
                                     },
                                     Err(_) => {
                                         // IPv6 network masks aren't a thing - so error out
-                                        return Err(rusqlite::Error::UserFunctionError(Box::new(InetError::InvalidNetworkMask(una, s.to_owned()))))
+                                        return Err(ArgError::invalid(ctx, mask_idx, InetError::InvalidNetworkMask(una, s.to_owned())))
                                     }
                                 }
                             },
                         }
                     },
                     ValueRef::Real(_) | ValueRef::Blob(_) => {
-                        // manually trigger a bad type error
-                        let _: String = ctx.get(mask_idx)?;
-                        unreachable!()
+                        return Err(ArgError::invalid(ctx, mask_idx, "expected a prefix length or a network mask"));
                     },
                 };
 
@@ -143,7 +177,7 @@ impl UserNetAddr {
                             una = UserNetAddr::Network(IpNet::V6(Ipv6Net::new(addr, prefixlen).expect("prefix length was pre-validated")));
                         },
                         UserNetAddr::Network(_) => {
-                            return Err(rusqlite::Error::UserFunctionError(Box::new(InetError::MultipleNetworkMasks(una, mask_idx, format!("{:?}", ctx.get_raw(mask_idx))))));
+                            return Err(ArgError::invalid(ctx, mask_idx, InetError::MultipleNetworkMasks(una)));
                         }
                     }
                 }
@@ -171,6 +205,20 @@ impl FromStr for UserNetAddr {
     }
 }
 
+/// Serialized in its string form, such as `10.1.2.3` or `2001:db8::/32`
+#[cfg(feature = "serde")]
+impl serde::Serialize for UserNetAddr {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for UserNetAddr {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<UserNetAddr, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
 impl fmt::Display for UserNetAddr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -182,33 +230,38 @@ impl fmt::Display for UserNetAddr {
     }
 }
 
-/// Receives a subnet mask from the context object provided. The subnet value must always be provided (null is allowed), the mask index must be provided but it's value is optional.
+/// Receives a subnet mask from the context object provided. The subnet value must always be provided (null is allowed), the mask index is optional, as is its value.
+///
+/// The mask may be given as an address (`255.255.252.0`), or as an integer mask length (`22`).
 ///
 /// This function short-circuits: if CIDR notation is found in the subn_idx, then mask_idx will not be observed.
-fn normalize_mask(ctx: &rusqlite::functions::Context<'_>, subn_idx: usize, mask_idx: usize) -> rusqlite::Result<Option<IpNet>> {
-    let Some(subn) = ctx.get_raw(subn_idx).as_str_or_null()? else { return Ok(None); };
+fn normalize_mask(ctx: &rusqlite::functions::Context<'_>, subn_idx: usize, mask_idx: Option<usize>) -> rusqlite::Result<Option<IpNet>> {
+    let Some(subn) = text_arg(ctx, subn_idx)? else { return Ok(None); };
 
     let parse_err = match IpNet::from_str(subn) {
         Ok(subn) => return Ok(Some(subn)),
         Err(e) => e,
     };
 
-    let mask = (ctx.len() >= mask_idx + 1).then(|| ctx.get_raw(mask_idx))
-        .map(|v| v.as_str_or_null()).transpose()?.flatten();
-
-    let subnet: IpNet = match mask {
-        None => return Err(rusqlite::Error::UserFunctionError(Box::new(parse_err))),
-        Some(mask) => {
-            let network: IpAddr = subn.parse()
-                .map_err(|e| rusqlite::Error::UserFunctionError(Box::new(e)))?;
-            let mask: IpAddr = mask.parse()
-                .map_err(|e| rusqlite::Error::UserFunctionError(Box::new(e)))?;
-            let prefix_len = ipnet::ip_mask_to_prefix(mask)
-                .map_err(|e| rusqlite::Error::UserFunctionError(Box::new(e)))?;
-            IpNet::new(network, prefix_len)
-                .map_err(|e| rusqlite::Error::UserFunctionError(Box::new(e)))?
-        }
+    let mask_idx = match mask_idx {
+        Some(idx) if idx < ctx.len() && ctx.get_raw(idx) != ValueRef::Null => idx,
+        _ => return Err(ArgError::invalid(ctx, subn_idx, parse_err)),
+    };
+    let network: IpAddr = subn.parse()
+        .map_err(|e| ArgError::invalid(ctx, subn_idx, e))?;
+
+    let prefix_len = match ctx.get_raw(mask_idx) {
+        ValueRef::Integer(len) => u8::try_from(len)
+            .map_err(|_| ArgError::invalid(ctx, mask_idx, "expected a mask length from 0 to 128"))?,
+        _ => {
+            let mask: IpAddr = text_arg(ctx, mask_idx)?.unwrap_or_default().parse()
+                .map_err(|e| ArgError::invalid(ctx, mask_idx, e))?;
+            ipnet::ip_mask_to_prefix(mask)
+                .map_err(|e| ArgError::invalid(ctx, mask_idx, e))?
+        },
     };
+    let subnet = IpNet::new(network, prefix_len)
+        .map_err(|e| ArgError::invalid(ctx, mask_idx, e))?;
 
     Ok(Some(subnet))
 }
@@ -217,35 +270,93 @@ fn normalize_mask(ctx: &rusqlite::functions::Context<'_>, subn_idx: usize, mask_
 ///
 /// Formats an IPv4/IPv6 address (with optional mask) to a normalized form.
 ///
-/// If the last argument is TRUE, then the address will be truncated when a network mask is provided.
+/// If the last argument is TRUE, then the address will be truncated when a network mask is provided. In the two argument
+/// form, an integer second argument is taken as this flag rather than a mask length, so must be `0` or `1`. Give a mask
+/// length as the second of three arguments, or in CIDR notation, instead.
+///
+/// IPv6 addresses are always written in the compressed style. To use the connection's `ipv6_style` setting instead,
+/// see [IP_FORMAT_DEFAULT](crate::exports::inet::format_default).
 ///
 /// # Examples
 /// |Call|Result|
 /// |-|-|
-/// |`IP_FORMAT('192.168.003.002')`|`'192.168.3.2'`|
+/// |`IP_FORMAT('192.168.3.2')`|`'192.168.3.2'`|
 /// |`IP_FORMAT('192.168.3.2/16')`|`'192.168.3.2/16'`|
 /// |`IP_FORMAT('10.2.3.1', '255.255.255.0')`|`'10.2.3.1/24'`|
 /// |`IP_FORMAT('10.2.3.1', '255.255.255.0', TRUE)`|`'10.2.3.0/24'`|
 /// |`IP_FORMAT('fe80:0:0:0:2:03:0:aabb/10')`|`'fe80::2:3:0:aabb/10'`|
 /// |`IP_FORMAT('fe80:0:0:0:2:03:0:aabb/10', TRUE)`|`'fe80::/10'`|
-pub fn format(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
-    match normalize_mask(ctx, 0, 1) {
+/// |`IP_FORMAT('10.2.3.1', 24)`|N/A - A query error is raised, use `IP_FORMAT('10.2.3.1', 24, FALSE)` or `'10.2.3.1/24'`|
+/// |`IP_FORMAT('192.168.003.002')`|N/A - A query error is raised, as octets with leading zeros may be read as octal|
+pub fn format(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<SqlText<TextBuf<48>>>> {
+    format_styled(ctx, Ipv6Style::Compressed)
+}
+
+/// IP_FORMAT_DEFAULT(NULL|ip) -> NULL|ip
+///
+/// Formats an IPv4/IPv6 address or network as the one argument form of [IP_FORMAT](crate::exports::inet::format) does,
+/// writing IPv6 addresses in the connection's `ipv6_style` setting (see [NETTOOLS_SET](crate::exports::settings::set)).
+///
+/// As the result depends on the setting, this isn't deterministic, and can't be used in indexes or generated columns.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`IP_FORMAT_DEFAULT('fe80:0:0:0:2:03:0:aabb/10')`|`'fe80::2:3:0:aabb/10'`|
+/// |`IP_FORMAT_DEFAULT('fe80::2:3:0:aabb')`, after `NETTOOLS_SET('ipv6_style', 'expanded')`|`'fe80:0000:0000:0000:0002:0003:0000:aabb'`|
+pub fn format_default(settings: &SharedSettings, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<SqlText<TextBuf<48>>>> {
+    format_styled(ctx, settings.load().ipv6_style.unwrap_or_default())
+}
+
+/// Formats as documented on [IP_FORMAT](crate::exports::inet::format), writing IPv6 addresses in the given style.
+fn format_styled(ctx: &rusqlite::functions::Context<'_>, style: Ipv6Style) -> rusqlite::Result<Option<SqlText<TextBuf<48>>>> {
+    // the longest IPv6 network, `ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff/128`, is 43 bytes
+    let mut text = TextBuf::new();
+    // with two arguments, an integer second argument is `should_truncate` rather than a mask length
+    let (mask_idx, truncate_idx) = match (ctx.len(), ctx.get_raw(ctx.len() - 1)) {
+        (1, _) => (None, None),
+        (2, ValueRef::Integer(_)) => (None, Some(1)),
+        (2, _) => (Some(1), None),
+        _ => (Some(1), Some(2)),
+    };
+    let should_truncate = match truncate_idx.map(|idx| (idx, ctx.get_raw(idx))) {
+        None | Some((_, ValueRef::Null)) => false,
+        Some((_, ValueRef::Integer(flag @ (0 | 1)))) => flag == 1,
+        Some((idx, ValueRef::Integer(flag))) => return Err(ArgError::invalid(ctx, idx, InetError::TruncateFlag(flag))),
+        Some((idx, _)) => arg::<bool>(ctx, idx)?,
+    };
+    match normalize_mask(ctx, 0, mask_idx) {
         // it was successfully parsed as a subnet mask
-        Ok(Some(mut net)) => Ok(Some({
-            let should_truncate: Option<bool> = ctx.get(ctx.len()-1).ok().flatten();
-            if should_truncate.unwrap_or(false) {
+        Ok(Some(mut net)) => {
+            if should_truncate {
                 net = net.trunc();
             }
-            net.to_string()
-        })),
+            write_addr(&mut text, net.addr(), style).unwrap();
+            write!(text, "/{}", net.prefix_len()).unwrap();
+        },
         _ => {
             // it should be an address, or something is misaligned
-            let Some(addrstr) = ctx.get_raw(0).as_str_or_null()? else { return Ok(None); };
+            let Some(addrstr) = text_arg(ctx, 0)? else { return Ok(None); };
             let addr = IpAddr::from_str(addrstr)
-                .map_err(|e| rusqlite::Error::UserFunctionError(Box::new(e)))?;
-            Ok(Some(addr.to_string()))
+                .map_err(|e| ArgError::invalid(ctx, 0, e))?;
+            write_addr(&mut text, addr, style).unwrap();
         }
     }
+    Ok(Some(SqlText(text)))
+}
+
+/// Writes an address, with IPv6 addresses written in the given style.
+fn write_addr(w: &mut impl Write, addr: IpAddr, style: Ipv6Style) -> fmt::Result {
+    match (addr, style) {
+        (IpAddr::V6(addr), Ipv6Style::Expanded) => {
+            for (i, seg) in addr.segments().iter().enumerate() {
+                let sep = if i == 0 { "" } else { ":" };
+                write!(w, "{sep}{seg:04x}")?;
+            }
+            Ok(())
+        },
+        (addr, _) => write!(w, "{addr}"),
+    }
 }
 
 /// IP_CONTAINS(ip_or_network, subnet, [NULL|mask|mask_length]) -> NULL|bool
@@ -264,16 +375,107 @@ pub fn format(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option
 /// |`IP_CONTAINS('128.231.59.7', '128.231.60.0', 22)`|`FALSE`|
 /// |`IP_CONTAINS('fe80::82fe:a2', 'fe80::/10')`|`TRUE`|
 pub fn contains(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<bool>> {
-    let Some(subject_str) = ctx.get_raw(0).as_str_or_null()? else { return Ok(None); };
+    let Some(subject_str) = text_arg(ctx, 0)? else { return Ok(None); };
 
     let subject: UserNetAddr = subject_str.parse()
-        .map_err(|e| rusqlite::Error::UserFunctionError(Box::new(e)))?;
+        .map_err(|e| ArgError::invalid(ctx, 0, e))?;
 
-    let Some(network) = normalize_mask(ctx, 1, 2)? else { return Ok(None); };
+    let Some(network) = normalize_mask(ctx, 1, Some(2))? else { return Ok(None); };
 
     Ok(Some(subject.within(network)))
 }
 
+/// IP_CONTAINS_ALL(NULL|json_array, subnet, [NULL|mask|mask_length]) -> NULL|json_array
+///
+/// Tests each address or network in a JSON array against the subnet, as with [IP_CONTAINS](crate::exports::inet::contains),
+/// returning a JSON array of the results in the same order. NULL elements give `null`, and any other non-text element is
+/// an error naming its position.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`IP_CONTAINS_ALL('["10.1.2.3", "192.0.2.1", null]', '10.0.0.0/8')`|`'[true,false,null]'`|
+/// |`IP_CONTAINS_ALL(doc -> '$.hosts', '10.0.0.0', '255.0.0.0')`|...|
+/// |`IP_CONTAINS_ALL('[]', '10.0.0.0/8')`|`'[]'`|
+pub fn contains_all(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let Some(elems) = json_array_arg(ctx, 0)? else { return Ok(None); };
+    let Some(network) = normalize_mask(ctx, 1, Some(2))? else { return Ok(None); };
+
+    let results = elems.iter().enumerate().map(|(i, elem)| match elem {
+        serde_json::Value::Null => Ok(serde_json::Value::Null),
+        serde_json::Value::String(s) => s.parse::<UserNetAddr>()
+            .map(|subject| subject.within(network).into())
+            .map_err(|e| ArgError::invalid(ctx, 0, ElementError::new(i, e))),
+        _ => Err(ArgError::invalid(ctx, 0, ElementError::new(i, "expected an address or network as text"))),
+    }).collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(Some(serde_json::Value::Array(results).to_string()))
+}
+
+/// IP_IN_ANY(NULL|ip_or_network, NULL|networks) -> NULL|bool
+///
+/// Tests if the IPv4/IPv6 address or network in the first argument is contained in any of a list of networks, as with
+/// [IP_CONTAINS](crate::exports::inet::contains). Networks are written in CIDR form, or as a single address. NULL
+/// elements are skipped, and any other non-text element is an error naming its position.
+///
+/// The list may be a JSON array, or a list bound by the host application with rusqlite's `array` feature (as read by
+/// its `rarray()` table). Binding spares writing the list out as text for each statement, such as for a watch-list
+/// that changes between queries. Bound lists are read as rusqlite stores them, so must be bound through the same
+/// rusqlite as this crate's, by an application registering the functions with [register](crate::register). Lists
+/// bound by other means, such as with SQLite's `carray` extension, are read as NULL.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`IP_IN_ANY('10.1.2.3', '["192.0.2.0/24", "10.0.0.0/8"]')`|`TRUE`|
+/// |`IP_IN_ANY('10.1.2.3', '["192.0.2.0/24", null, "10.1.2.4"]')`|`FALSE`|
+/// |`IP_IN_ANY('10.1.2.3', '[]')`|`FALSE`|
+/// |`IP_IN_ANY('10.1.2.3', ?1)`|`TRUE`, with `Rc::new(vec![Value::from("10.0.0.0/8".to_owned())])` bound to `?1`|
+pub fn in_any(args: &RawArgs<'_>) -> rusqlite::Result<Value> {
+    let subject: UserNetAddr = match args.get_raw(0) {
+        ValueRef::Null => return Ok(Value::Null),
+        ValueRef::Text(t) => std::str::from_utf8(t)
+            .map_err(|e| args.invalid(0, e))?
+            .parse()
+            .map_err(|e| args.invalid(0, e))?,
+        _ => return Err(args.invalid(0, "expected text")),
+    };
+
+    let not_text = |i| args.invalid(1, ElementError::new(i, "expected a network as text"));
+    let json;
+    let networks: Vec<Option<&str>> = match (args.get_list(1), args.get_raw(1)) {
+        (Some(list), _) => list.iter().enumerate().map(|(i, elem)| match elem {
+            Value::Null => Ok(None),
+            Value::Text(s) => Ok(Some(s.as_str())),
+            _ => Err(not_text(i)),
+        }).collect::<rusqlite::Result<_>>()?,
+        (None, ValueRef::Null) => return Ok(Value::Null),
+        (None, ValueRef::Text(t)) => {
+            json = std::str::from_utf8(t).map_err(Into::into).and_then(json_array)
+                .map_err(|e| args.invalid(1, e))?;
+            json.iter().enumerate().map(|(i, elem)| match elem {
+                serde_json::Value::Null => Ok(None),
+                serde_json::Value::String(s) => Ok(Some(s.as_str())),
+                _ => Err(not_text(i)),
+            }).collect::<rusqlite::Result<_>>()?
+        },
+        (None, _) => return Err(args.invalid(1, "expected a JSON array, or a bound list")),
+    };
+
+    // every network is parsed, so a malformed one is an error wherever it falls in the list
+    let mut contained = false;
+    for (i, network) in networks.into_iter().enumerate() {
+        let Some(network) = network else { continue; };
+        let network = match IpNet::from_str(network) {
+            Ok(network) => network,
+            Err(e) => network.parse::<IpAddr>().map(IpNet::from)
+                .map_err(|_| args.invalid(1, ElementError::new(i, e)))?,
+        };
+        contained |= subject.within(network);
+    }
+    Ok(Value::Integer(contained.into()))
+}
+
 /// Converts an IP address or Address portion of a CIDR subnet, into a binary blob.
 ///
 /// This has two primary uses:
@@ -285,30 +487,1260 @@ pub fn contains(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Opti
 /// |-|-|
 /// |`IP_BLOBIFY('127.0.0.1')`|...|
 pub fn blobify(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<Vec<u8>>> {
-    let Some(subject_str) = ctx.get_raw(0).as_str_or_null()? else { return Ok(None); };
+    let Some(subject_str) = text_arg(ctx, 0)? else { return Ok(None); };
     let subject: UserNetAddr = subject_str.parse()
-        .map_err(|e| rusqlite::Error::UserFunctionError(Box::new(e)))?;
+        .map_err(|e| ArgError::invalid(ctx, 0, e))?;
+
+    Ok(Some(subject.to_blob()))
+}
+
+/// Reads an address or network blob, as produced by [IP_BLOBIFY](crate::exports::inet::blobify). Text is refused
+/// rather than parsed, so the `IPB_` functions never fall back to string parsing.
+fn blob_arg(ctx: &rusqlite::functions::Context<'_>, idx: usize) -> rusqlite::Result<Option<UserNetAddr>> {
+    match ctx.get_raw(idx) {
+        ValueRef::Null => Ok(None),
+        ValueRef::Blob(dat) => UserNetAddr::from_blob(dat)
+            .map(Some)
+            .map_err(|e| ArgError::invalid(ctx, idx, e)),
+        _ => Err(ArgError::invalid(ctx, idx, "expected an address or network blob, see IP_BLOBIFY")),
+    }
+}
 
-    Ok(Some(match subject {
-        UserNetAddr::Address(a) => match a {
-            IpAddr::V4(ipv4) => ipv4.octets().to_vec(),
-            IpAddr::V6(ipv6) => ipv6.octets().to_vec(),
+/// IPB_CONTAINS(NULL|blob, NULL|network_blob) -> NULL|bool
+///
+/// Tests if the address or network blob in the first argument is contained in the network blob in the second, as
+/// with [IP_CONTAINS](crate::exports::inet::contains). Both must be blobs as produced by
+/// [IP_BLOBIFY](crate::exports::inet::blobify), as text is never parsed. An address blob in the second argument is
+/// treated as a single address network (`/32` or `/128`).
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`IPB_CONTAINS(IP_BLOBIFY('128.231.61.3'), IP_BLOBIFY('128.231.60.0/22'))`|`TRUE`|
+/// |`IPB_CONTAINS(X'80E73B07', X'80E73C0016')`|`FALSE`|
+/// |`IPB_CONTAINS(IP_BLOBIFY('fe80::1'), IP_BLOBIFY('fe80::/10'))`|`TRUE`|
+/// |`IPB_CONTAINS('128.231.61.3', IP_BLOBIFY('128.231.60.0/22'))`|error|
+pub fn blob_contains(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<bool>> {
+    let Some(subject) = blob_arg(ctx, 0)? else { return Ok(None); };
+    let Some(network) = blob_arg(ctx, 1)? else { return Ok(None); };
+
+    let network = match network {
+        UserNetAddr::Address(addr) => IpNet::from(addr),
+        UserNetAddr::Network(net) => net,
+    };
+    Ok(Some(subject.within(network)))
+}
+
+/// IP_BUCKET(NULL|ip, NULL|prefix_length) -> NULL|network
+///
+/// Truncates an IPv4/IPv6 address or network to the network of the given prefix length, written in CIDR notation. This
+/// groups addresses by their subnet, such as with `GROUP BY IP_BUCKET(src, 24)`. Networks already larger than the bucket
+/// keep their own prefix length. Blobs from [IP_BLOBIFY](crate::exports::inet::blobify) are also accepted, though
+/// [IPB_BUCKET](crate::exports::inet::blob_bucket) avoids writing text for them.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`IP_BUCKET('10.1.2.3', 24)`|`'10.1.2.0/24'`|
+/// |`IP_BUCKET('2001:db8:85a3::8a2e:370:7334', 48)`|`'2001:db8:85a3::/48'`|
+/// |`IP_BUCKET('10.1.0.0/16', 24)`|`'10.1.0.0/16'`|
+/// |`IP_BUCKET('10.1.2.3', 33)`|error|
+pub fn bucket(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<SqlText<TextBuf<48>>>> {
+    let Some(subject) = UserNetAddr::from_ctx(ctx, 0, None)? else { return Ok(None); };
+    let Some(bucket) = bucket_net(ctx, subject, 1)? else { return Ok(None); };
+
+    let mut text = TextBuf::new();
+    write!(text, "{bucket}").unwrap();
+    Ok(Some(SqlText(text)))
+}
+
+/// IPB_BUCKET(NULL|blob, NULL|prefix_length) -> NULL|network_blob
+///
+/// Truncates an address or network blob to the network of the given prefix length, returned as a network blob. This
+/// groups addresses by their subnet, such as with `GROUP BY IPB_BUCKET(src, 24)`. Networks already larger than the
+/// bucket keep their own prefix length.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`IPB_BUCKET(IP_BLOBIFY('10.1.2.3'), 24)`|`X'0A01020018'`|
+/// |`IPB_FORMAT(IPB_BUCKET(IP_BLOBIFY('2001:db8:85a3::8a2e:370:7334'), 48))`|`'2001:db8:85a3::/48'`|
+/// |`IPB_FORMAT(IPB_BUCKET(IP_BLOBIFY('10.1.0.0/16'), 24))`|`'10.1.0.0/16'`|
+/// |`IPB_BUCKET(IP_BLOBIFY('10.1.2.3'), 33)`|error|
+pub fn blob_bucket(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<Vec<u8>>> {
+    let Some(subject) = blob_arg(ctx, 0)? else { return Ok(None); };
+    let Some(bucket) = bucket_net(ctx, subject, 1)? else { return Ok(None); };
+
+    Ok(Some(UserNetAddr::Network(bucket).to_blob()))
+}
+
+/// Truncates an address or network to the prefix length in argument `len_idx`, as documented on
+/// [IP_BUCKET](crate::exports::inet::bucket).
+fn bucket_net(ctx: &rusqlite::functions::Context<'_>, subject: UserNetAddr, len_idx: usize) -> rusqlite::Result<Option<IpNet>> {
+    let Some(len): Option<i64> = arg(ctx, len_idx)? else { return Ok(None); };
+
+    let net = match subject {
+        UserNetAddr::Address(addr) => IpNet::from(addr),
+        UserNetAddr::Network(net) => net,
+    };
+    let len = u8::try_from(len).ok()
+        .filter(|len| *len <= net.max_prefix_len())
+        .ok_or_else(|| ArgError::invalid(ctx, len_idx, ipnet::PrefixLenError))?;
+    let bucket = IpNet::new(net.addr(), len.min(net.prefix_len())).expect("prefix length was pre-validated");
+    Ok(Some(bucket.trunc()))
+}
+
+/// IPB_FORMAT(NULL|blob) -> NULL|text
+///
+/// Writes an address or network blob in its text form. The inverse of [IP_BLOBIFY](crate::exports::inet::blobify).
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`IPB_FORMAT(X'7F000001')`|`'127.0.0.1'`|
+/// |`IPB_FORMAT(X'0A01020018')`|`'10.1.2.0/24'`|
+/// |`IPB_FORMAT(IP_BLOBIFY('fe80::2:3:0:aabb/10'))`|`'fe80::2:3:0:aabb/10'`|
+pub fn blob_format(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<SqlText<TextBuf<48>>>> {
+    let Some(subject) = blob_arg(ctx, 0)? else { return Ok(None); };
+    let mut text = TextBuf::new();
+    write!(text, "{subject}").unwrap();
+    Ok(Some(SqlText(text)))
+}
+
+/// Finds why text isn't an address or network accepted by the `IP_` functions, as a short reason listed by
+/// [IP_VALIDATE](crate::exports::inet::validate). Returns None when the text is valid.
+pub fn ip_problem(s: &str) -> Option<&'static str> {
+    if s.parse::<UserNetAddr>().is_ok() {
+        return None;
+    }
+    if s.trim().is_empty() {
+        return Some("empty");
+    }
+    if s.trim() != s {
+        return Some("whitespace");
+    }
+
+    let (addr, prefix) = match s.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (s, None),
+    };
+    if addr.starts_with('[') {
+        return Some(if addr.contains("]:") { "has-port" } else { "brackets" });
+    }
+    if addr.contains('%') {
+        return Some("zone-id");
+    }
+    let problem = match addr.split_once(':') {
+        Some((v4, port)) if v4.parse::<Ipv4Addr>().is_ok() && !port.contains(':') => Some("has-port"),
+        Some(_) => ipv6_problem(addr),
+        None if addr.contains('.') => ipv4_problem(addr),
+        None if addr.bytes().all(|b| b.is_ascii_digit()) => Some("octet-count"),
+        None => Some("bad-character"),
+    };
+    if problem.is_some() {
+        return problem;
+    }
+
+    let max_len = if addr.contains(':') { 128 } else { 32 };
+    match prefix {
+        None => Some("invalid"),
+        Some(prefix) if prefix.parse::<Ipv4Addr>().is_ok() => Some("mask-notation"),
+        Some(prefix) if prefix.is_empty() || !prefix.bytes().all(|b| b.is_ascii_digit()) => Some("bad-prefix"),
+        Some(prefix) if prefix.parse::<u8>().map_or(true, |len| len > max_len) => Some("prefix-out-of-range"),
+        Some(_) => Some("invalid"),
+    }
+}
+
+fn ipv4_problem(addr: &str) -> Option<&'static str> {
+    if addr.parse::<Ipv4Addr>().is_ok() {
+        return None;
+    }
+    if !addr.bytes().all(|b| b.is_ascii_digit() || b == b'.') {
+        return Some("bad-character");
+    }
+    let octets: Vec<&str> = addr.split('.').collect();
+    if octets.len() != 4 {
+        return Some("octet-count");
+    }
+    for octet in octets {
+        if octet.len() > 1 && octet.starts_with('0') {
+            return Some("leading-zero");
+        }
+        if octet.parse::<u8>().is_err() {
+            return Some("bad-octet");
+        }
+    }
+    Some("invalid")
+}
+
+fn ipv6_problem(addr: &str) -> Option<&'static str> {
+    if addr.parse::<std::net::Ipv6Addr>().is_ok() {
+        return None;
+    }
+    // an embedded IPv4 address may only be the final two groups
+    let (groups, v4_groups) = match addr.rsplit_once(':') {
+        Some((head, tail)) if tail.contains('.') => {
+            if head.contains('.') || tail.parse::<Ipv4Addr>().is_err() {
+                return Some("mixed-notation");
+            }
+            // keep an elision directly before the IPv4 address
+            let head = &addr[..addr.len() - tail.len()];
+            (if head.ends_with("::") { head } else { &head[..head.len() - 1] }, 2)
         },
-        UserNetAddr::Network(n) => match n {
-            IpNet::V4(netv4) => {
-                let mut v = netv4.addr().octets().to_vec();
-                v.push(netv4.prefix_len());
-                v
-            },
-            IpNet::V6(netv6) => {
-                let mut v = netv6.addr().octets().to_vec();
-                v.push(netv6.prefix_len());
-                v
+        _ if addr.contains('.') => return Some("mixed-notation"),
+        _ => (addr, 0),
+    };
+    if !groups.bytes().all(|b| b.is_ascii_hexdigit() || b == b':') {
+        return Some("bad-character");
+    }
+    let elisions = groups.matches("::").count();
+    if elisions > 1 || groups.contains(":::") {
+        return Some("multiple-elisions");
+    }
+
+    // the groups either side of an elision, which may have none
+    let parts: Vec<&str> = groups.split("::")
+        .filter(|side| !side.is_empty())
+        .flat_map(|side| side.split(':'))
+        .collect();
+    if parts.iter().any(|g| g.is_empty() || g.len() > 4) {
+        return Some("bad-group");
+    }
+    let count = parts.len() + v4_groups;
+    if (elisions == 0 && count != 8) || (elisions == 1 && count > 7) {
+        return Some("group-count");
+    }
+    Some("invalid")
+}
+
+/// IP_VALIDATE(NULL|ip) -> NULL|text
+///
+/// Checks an address or network without raising an error, returning NULL when it is valid, or a short reason why it
+/// isn't. Useful for data quality reports on ingested address columns, such as
+/// `SELECT IP_VALIDATE(addr) AS problem, count(*) FROM flows GROUP BY problem`.
+///
+/// Text is valid if accepted by functions such as [IP_FORMAT](crate::exports::inet::format) (an address, or a network
+/// in CIDR notation). Blobs are valid if in the encoding produced by [IP_BLOBIFY](crate::exports::inet::blobify).
+///
+/// |Reason|Meaning|
+/// |-|-|
+/// |`empty`|Empty, or only whitespace|
+/// |`whitespace`|Leading or trailing whitespace|
+/// |`bad-character`|A character that can't appear in an address|
+/// |`octet-count`|An IPv4 address without exactly four octets|
+/// |`bad-octet`|An IPv4 octet over 255, or empty|
+/// |`leading-zero`|An IPv4 octet with a leading zero, which some parsers read as octal|
+/// |`bad-group`|An IPv6 group longer than four digits, or empty outside of a `::`|
+/// |`group-count`|An IPv6 address with too many or too few groups|
+/// |`multiple-elisions`|An IPv6 address with more than one `::`|
+/// |`mixed-notation`|IPv4 notation in an IPv6 address, other than as its last 32 bits|
+/// |`zone-id`|An IPv6 zone (`%eth0`), which isn't supported|
+/// |`brackets`|An IPv6 address within brackets, as in URLs|
+/// |`has-port`|An address followed by a port|
+/// |`bad-prefix`|A prefix length that isn't a number|
+/// |`mask-notation`|A network mask in place of a prefix length, such as `/255.255.255.0`|
+/// |`prefix-out-of-range`|A prefix length over 32 (IPv4) or 128 (IPv6)|
+/// |`bad-blob-length`|A blob that isn't 4, 5, 16, or 17 bytes|
+/// |`wrong-type`|An integer or real number|
+/// |`invalid`|Invalid for any other reason|
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`IP_VALIDATE('10.1.2.3')`|`NULL`|
+/// |`IP_VALIDATE('10.1.2.300')`|`'bad-octet'`|
+/// |`IP_VALIDATE('10.1.2.0/33')`|`'prefix-out-of-range'`|
+/// |`IP_VALIDATE('::ffff:10.1.2')`|`'mixed-notation'`|
+/// |`IP_VALIDATE('10.1.2.3:443')`|`'has-port'`|
+/// |`IP_VALIDATE(X'0A0102')`|`'bad-blob-length'`|
+/// |`IP_VALIDATE(NULL)`|`NULL`|
+pub fn validate(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<&'static str>> {
+    Ok(match ctx.get_raw(0) {
+        ValueRef::Null => None,
+        ValueRef::Integer(_) | ValueRef::Real(_) => Some("wrong-type"),
+        ValueRef::Blob(dat) => match UserNetAddr::from_blob(dat) {
+            Ok(_) => None,
+            Err(InetError::BlobPrefixLength(_, _)) => Some("prefix-out-of-range"),
+            Err(_) => Some("bad-blob-length"),
+        },
+        ValueRef::Text(t) => match std::str::from_utf8(t) {
+            Ok(s) => ip_problem(s),
+            Err(_) => Some("bad-character"),
+        },
+    })
+}
+
+/// Pulls a port number out of the provided argument.
+fn port_arg(ctx: &rusqlite::functions::Context<'_>, idx: usize) -> rusqlite::Result<Option<u16>> {
+    let Some(port): Option<i64> = arg(ctx, idx)? else { return Ok(None); };
+    u16::try_from(port)
+        .map(Some)
+        .map_err(|_| ArgError::invalid(ctx, idx, InetError::PortRange(port)))
+}
+
+/// Pulls an IP protocol number out of the provided argument, accepting protocol numbers or common protocol names.
+fn protocol_arg(ctx: &rusqlite::functions::Context<'_>, idx: usize) -> rusqlite::Result<Option<u8>> {
+    match ctx.get_raw(idx) {
+        ValueRef::Null => Ok(None),
+        ValueRef::Integer(i) => u8::try_from(i)
+            .map(Some)
+            .map_err(|_| ArgError::invalid(ctx, idx, InetError::UnrecognizedProtocol(i.to_string()))),
+        ValueRef::Text(_) => {
+            let s = text_arg(ctx, idx)?.unwrap_or_default();
+            let proto = match s.trim().to_ascii_lowercase().as_str() {
+                "icmp" => 1,
+                "tcp" => 6,
+                "udp" => 17,
+                "icmpv6" | "ipv6-icmp" => 58,
+                "sctp" => 132,
+                other => other.parse::<u8>()
+                    .map_err(|_| ArgError::invalid(ctx, idx, InetError::UnrecognizedProtocol(s.to_owned())))?,
+            };
+            Ok(Some(proto))
+        },
+        ValueRef::Real(_) | ValueRef::Blob(_) => Err(ArgError::invalid(ctx, idx, "expected a protocol number or name")),
+    }
+}
+
+/// Pulls a network out of the provided argument, as text or a blob. Addresses are read as single address networks
+/// (`/32` or `/128`), and networks are truncated to their network address.
+fn network_arg(ctx: &rusqlite::functions::Context<'_>, idx: usize) -> rusqlite::Result<Option<IpNet>> {
+    Ok(UserNetAddr::from_ctx(ctx, idx, None)?.map(|una| match una {
+        UserNetAddr::Address(addr) => IpNet::from(addr),
+        UserNetAddr::Network(net) => net.trunc(),
+    }))
+}
+
+/// The network contained in both, if any. As two networks either nest or are disjoint, this is the smaller of the two
+/// when one contains the other.
+fn intersect(a: IpNet, b: IpNet) -> Option<IpNet> {
+    match (a.contains(&b), b.contains(&a)) {
+        (true, _) => Some(b),
+        (_, true) => Some(a),
+        _ => None,
+    }
+}
+
+/// CIDR_INTERSECT(NULL|network, NULL|network) -> NULL|network
+///
+/// Returns the network of addresses contained in both networks, or NULL if they don't overlap. As networks either
+/// nest or are disjoint, this is the smaller network whenever one contains the other. Networks may be text or blobs (as
+/// produced by [IP_BLOBIFY](crate::exports::inet::blobify)), and addresses are treated as single address networks.
+///
+/// The one argument form is an aggregate, intersecting every network in a group (see [CidrIntersect]).
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`CIDR_INTERSECT('10.0.0.0/8', '10.1.0.0/16')`|`'10.1.0.0/16'`|
+/// |`CIDR_INTERSECT('10.1.2.3/16', '10.0.0.0/8')`|`'10.1.0.0/16'`|
+/// |`CIDR_INTERSECT('10.0.0.0/16', '10.1.0.0/16')`|`NULL`|
+/// |`CIDR_INTERSECT('2001:db8::/32', '2001:db8::1')`|`'2001:db8::1/128'`|
+/// |`CIDR_INTERSECT('10.0.0.0/8', '::/0')`|`NULL`|
+pub fn cidr_intersect(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let Some(a) = network_arg(ctx, 0)? else { return Ok(None); };
+    let Some(b) = network_arg(ctx, 1)? else { return Ok(None); };
+
+    Ok(intersect(a, b).map(|net| net.to_string()))
+}
+
+/// CIDR_INTERSECT(NULL|network) -> NULL|network (aggregate)
+///
+/// Intersects every network in a group, returning the network contained in all of them, or NULL if any two don't
+/// overlap. NULLs are skipped, as with other aggregates. Useful for finding the effective scope where several
+/// overlapping scopes apply.
+///
+/// # Examples
+/// ```sql
+/// -- '10.1.2.0/24', the scope every policy of each device applies to
+/// SELECT device, CIDR_INTERSECT(scope) FROM policies GROUP BY device;
+/// ```
+pub struct CidrIntersect;
+
+/// The accumulated intersection: None before the first network, then Some of the intersection so far (which is None
+/// once two networks are found not to overlap).
+type Intersection = Option<Option<IpNet>>;
+
+impl Aggregate<Intersection, Option<String>> for CidrIntersect {
+    fn init(&self, _ctx: &mut rusqlite::functions::Context<'_>) -> rusqlite::Result<Intersection> {
+        Ok(None)
+    }
+
+    fn step(&self, ctx: &mut rusqlite::functions::Context<'_>, acc: &mut Intersection) -> rusqlite::Result<()> {
+        let Some(net) = network_arg(ctx, 0)? else { return Ok(()); };
+        *acc = Some(match *acc {
+            None => Some(net),
+            Some(prev) => prev.and_then(|prev| intersect(prev, net)),
+        });
+        Ok(())
+    }
+
+    fn finalize(&self, _ctx: &mut rusqlite::functions::Context<'_>, acc: Option<Intersection>) -> rusqlite::Result<Option<String>> {
+        Ok(acc.flatten().flatten().map(|net| net.to_string()))
+    }
+}
+
+/// Pulls the first and last addresses of a range out of the provided argument. Ranges are written as two addresses
+/// separated by a dash (`10.0.0.1-10.0.0.9`), and otherwise are read as with [network_arg].
+fn range_arg(ctx: &rusqlite::functions::Context<'_>, idx: usize) -> rusqlite::Result<Option<(IpAddr, IpAddr)>> {
+    if let ValueRef::Text(t) = ctx.get_raw(idx) {
+        if t.contains(&b'-') {
+            let s = text_arg(ctx, idx)?.unwrap_or_default();
+            let bad_range = || ArgError::invalid(ctx, idx, InetError::InvalidRange(s.to_owned()));
+            let (first, last) = s.split_once('-').ok_or_else(bad_range)?;
+            let first: IpAddr = first.trim().parse().map_err(|_| bad_range())?;
+            let last: IpAddr = last.trim().parse().map_err(|_| bad_range())?;
+            if first.is_ipv4() != last.is_ipv4() || first > last {
+                return Err(bad_range());
             }
+            return Ok(Some((first, last)));
         }
+    }
+    Ok(network_arg(ctx, idx)?.map(|net| (net.network(), net.broadcast())))
+}
+
+/// An address as an integer, with IPv4 addresses kept apart from IPv6 by their version.
+fn addr_int(addr: IpAddr) -> (bool, u128) {
+    match addr {
+        IpAddr::V4(v4) => (true, u32::from(v4) as u128),
+        IpAddr::V6(v6) => (false, u128::from(v6)),
+    }
+}
+
+/// IP_OVERLAP_COUNT(NULL|range, NULL|range) -> NULL|int|text
+///
+/// Counts the addresses shared by two networks or ranges, such as to size the overlap between conflicting
+/// allocations. Each may be a network (as text or a blob), a single address, or a range of addresses written as
+/// `first-last`. Networks and ranges of different IP versions share no addresses.
+///
+/// The count is an integer, unless too large for one (only possible for IPv6), when it is written as decimal text.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`IP_OVERLAP_COUNT('10.0.0.0/8', '10.1.0.0/16')`|`65536`|
+/// |`IP_OVERLAP_COUNT('10.0.0.0/24', '10.0.0.200-10.0.1.50')`|`56`|
+/// |`IP_OVERLAP_COUNT('10.0.0.0/24', '10.0.1.0/24')`|`0`|
+/// |`IP_OVERLAP_COUNT('2001:db8::/32', '2001:db8::/48')`|`1208925819614629174706176` (as text)|
+/// |`IP_OVERLAP_COUNT('10.0.0.0/8', '::/0')`|`0`|
+pub fn overlap_count(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<Value>> {
+    let Some((a_first, a_last)) = range_arg(ctx, 0)? else { return Ok(None); };
+    let Some((b_first, b_last)) = range_arg(ctx, 1)? else { return Ok(None); };
+
+    let (a_v4, a_first) = addr_int(a_first);
+    let (b_v4, b_first) = addr_int(b_first);
+    let (first, last) = (a_first.max(b_first), addr_int(a_last).1.min(addr_int(b_last).1));
+    if a_v4 != b_v4 || first > last {
+        return Ok(Some(Value::Integer(0)));
+    }
+
+    // every IPv6 address (::/0) is one more than fits in a u128
+    let count = (last - first).checked_add(1);
+    Ok(Some(match count.and_then(|c| i64::try_from(c).ok()) {
+        Some(c) => Value::Integer(c),
+        None => Value::Text(count.map_or_else(|| "340282366920938463463374607431768211456".to_owned(), |c| c.to_string())),
     }))
 }
 
+/// SUBNET_UTILIZATION(NULL|parent_network, NULL|ip) -> NULL|json (aggregate)
+///
+/// Measures how much of a parent network is in use, from the addresses observed within it. Returns a JSON object with:
+/// * `network` - The parent network
+/// * `used` - How many distinct observed addresses are within the parent. Addresses outside of it are ignored
+/// * `size` - How many addresses the parent holds, including any network and broadcast addresses. Written as a string
+///   when too large for a JSON number (for IPv6 networks of a /64 or larger)
+/// * `percent` - The percentage of the parent's addresses in use
+///
+/// The parent may be the same argument for every row, or a column the rows are grouped by, but must not change within a
+/// group. Rows where either argument is NULL are skipped, and a group without any returns NULL.
+///
+/// # Examples
+/// ```sql
+/// -- {"network":"10.0.0.0/24","used":3,"size":256,"percent":1.171875}
+/// SELECT SUBNET_UTILIZATION('10.0.0.0/24', ip) FROM leases;
+/// -- the utilization of each allocated subnet, from observed addresses
+/// SELECT subnet, SUBNET_UTILIZATION(subnet, ip) ->> 'percent' AS used
+/// FROM allocations JOIN leases ON IP_CONTAINS(ip, subnet) GROUP BY subnet;
+/// ```
+pub struct SubnetUtilization;
+
+/// The parent network of a group, and the distinct addresses seen within it so far.
+pub struct Utilization {
+    parent: Option<IpNet>,
+    used: HashSet<IpAddr>,
+}
+
+impl Aggregate<Utilization, Option<String>> for SubnetUtilization {
+    fn init(&self, _ctx: &mut rusqlite::functions::Context<'_>) -> rusqlite::Result<Utilization> {
+        Ok(Utilization { parent: None, used: HashSet::new() })
+    }
+
+    fn step(&self, ctx: &mut rusqlite::functions::Context<'_>, acc: &mut Utilization) -> rusqlite::Result<()> {
+        let Some(parent) = network_arg(ctx, 0)? else { return Ok(()); };
+        let Some(addr) = address_arg(ctx, 1)? else { return Ok(()); };
+
+        match acc.parent {
+            Some(prev) if prev != parent => return Err(ArgError::invalid(ctx, 0, InetError::ParentChanged(prev, parent))),
+            _ => acc.parent = Some(parent),
+        }
+        if parent.contains(&addr) {
+            acc.used.insert(addr);
+        }
+        Ok(())
+    }
+
+    fn finalize(&self, _ctx: &mut rusqlite::functions::Context<'_>, acc: Option<Utilization>) -> rusqlite::Result<Option<String>> {
+        let Some(Utilization { parent: Some(parent), used }) = acc else { return Ok(None); };
+
+        // every IPv6 address (::/0) is one more than fits in a u128
+        let host_bits = parent.max_prefix_len() - parent.prefix_len();
+        let size = 1u128.checked_shl(host_bits as u32);
+        let percent = used.len() as f64 / 2f64.powi(host_bits as i32) * 100.0;
+        let size = match size.and_then(|s| u64::try_from(s).ok()) {
+            Some(size) => serde_json::json!(size),
+            None => serde_json::json!(size.map_or_else(|| "340282366920938463463374607431768211456".to_owned(), |s| s.to_string())),
+        };
+
+        let info = serde_json::json!({
+            "network": parent.to_string(),
+            "used": used.len(),
+            "size": size,
+            "percent": percent,
+        });
+        Ok(Some(info.to_string()))
+    }
+}
+
+/// Reads the prefix lengths to roll addresses up to, as a single integer or comma separated text, in ascending order.
+fn rollup_levels(ctx: &rusqlite::functions::Context<'_>, idx: usize) -> rusqlite::Result<Vec<u8>> {
+    let bad = |levels: String| ArgError::invalid(ctx, idx, InetError::BadRollupLevels(levels));
+    let mut levels = match ctx.get_raw(idx) {
+        ValueRef::Integer(len) => vec![u8::try_from(len).map_err(|_| bad(len.to_string()))?],
+        ValueRef::Text(_) => {
+            let text = text_arg(ctx, idx)?.unwrap_or_default();
+            text.split(',')
+                .map(|len| len.trim().trim_start_matches('/').parse::<u8>())
+                .collect::<Result<Vec<u8>, _>>()
+                .map_err(|_| bad(text.to_owned()))?
+        },
+        _ => return Err(ArgError::invalid(ctx, idx, "expected a prefix length, or comma separated prefix lengths")),
+    };
+    if let Some(&len) = levels.iter().find(|&&len| len > 128) {
+        return Err(bad(len.to_string()));
+    }
+    levels.sort_unstable();
+    levels.dedup();
+    Ok(levels)
+}
+
+/// IP_ROLLUP(NULL|ip, levels) -> NULL|json (aggregate)
+///
+/// Counts addresses by the networks containing them, at each of several prefix lengths, in a single pass. The levels
+/// are a prefix length, or comma separated prefix lengths (such as `'8,16,24'`), and are read from the first row.
+/// Lengths longer than an address are skipped for it, so IPv4 and IPv6 addresses can share a list such as `'16,24,48'`.
+///
+/// Returns a JSON array with an object for each network holding an address, as a tree in depth-first order (each
+/// network is followed by the networks within it). Each object has:
+/// * `prefix` - The network
+/// * `length` - The network's prefix length
+/// * `count` - How many addresses (counting repeats) are within it
+/// * `parent` - The network containing it at the previous level, or NULL at the first level
+///
+/// NULL addresses are skipped, and a group without any returns NULL. Use `json_each` to read the networks as rows.
+///
+/// # Examples
+/// ```sql
+/// -- [{"count":3,"length":8,"parent":null,"prefix":"10.0.0.0/8"},{"count":2,"length":16,"parent":"10.0.0.0/8","prefix":"10.1.0.0/16"}, ...]
+/// SELECT IP_ROLLUP(src, '8,16,24') FROM flows;
+/// -- a heat map of /24s
+/// SELECT value ->> 'prefix', value ->> 'count'
+/// FROM json_each((SELECT IP_ROLLUP(src, '8,16,24') FROM flows)) WHERE value ->> 'length' = 24;
+/// ```
+pub struct IpRollup;
+
+/// The prefix lengths rolled up to, and the number of addresses seen within each network so far.
+pub struct Rollup {
+    levels: Vec<u8>,
+    counts: BTreeMap<IpNet, u64>,
+}
+
+impl Aggregate<Option<Rollup>, Option<String>> for IpRollup {
+    fn init(&self, _ctx: &mut rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<Rollup>> {
+        Ok(None)
+    }
+
+    fn step(&self, ctx: &mut rusqlite::functions::Context<'_>, acc: &mut Option<Rollup>) -> rusqlite::Result<()> {
+        let Some(addr) = address_arg(ctx, 0)? else { return Ok(()); };
+        let rollup = match acc {
+            Some(rollup) => rollup,
+            None => acc.insert(Rollup { levels: rollup_levels(ctx, 1)?, counts: BTreeMap::new() }),
+        };
+
+        let max_len = IpNet::from(addr).max_prefix_len();
+        for &len in rollup.levels.iter().take_while(|&&len| len <= max_len) {
+            let net = IpNet::new(addr, len).expect("prefix length was checked").trunc();
+            *rollup.counts.entry(net).or_default() += 1;
+        }
+        Ok(())
+    }
+
+    fn finalize(&self, _ctx: &mut rusqlite::functions::Context<'_>, acc: Option<Option<Rollup>>) -> rusqlite::Result<Option<String>> {
+        let Some(Rollup { levels, counts }) = acc.flatten() else { return Ok(None); };
+
+        // networks sort by address, then by length, so each follows the network containing it
+        let nodes = counts.iter().map(|(net, count)| {
+            let parent = levels.iter().rev()
+                .find(|&&len| len < net.prefix_len())
+                .map(|&len| IpNet::new(net.addr(), len).expect("shorter than an existing prefix").trunc().to_string());
+            serde_json::json!({
+                "prefix": net.to_string(),
+                "length": net.prefix_len(),
+                "count": count,
+                "parent": parent,
+            })
+        }).collect();
+        Ok(Some(serde_json::Value::Array(nodes).to_string()))
+    }
+}
+
+/// Reads a prefix length no longer than `max`, or `default` when the argument is omitted or NULL.
+fn prefix_len_arg(ctx: &rusqlite::functions::Context<'_>, idx: usize, max: u8, default: u8) -> rusqlite::Result<u8> {
+    if idx >= ctx.len() {
+        return Ok(default);
+    }
+    let Some(len): Option<i64> = arg(ctx, idx)? else { return Ok(default); };
+    u8::try_from(len).ok()
+        .filter(|&len| len <= max)
+        .ok_or_else(|| ArgError::invalid(ctx, idx, InetError::PrefixLengthRange(len, max)))
+}
+
+/// COUNT_DISTINCT_PREFIXES(NULL|ip, length\[, ipv6_length]) -> int (aggregate)
+///
+/// Counts the distinct networks of a prefix length that the addresses in a group fall within, such as how many /24s a
+/// scan came from, without truncating each address to its network first. IPv4 addresses use `length`, and IPv6
+/// addresses use `ipv6_length`, defaulting to 64. The lengths are read from the first row.
+///
+/// NULL addresses are skipped, and a group without any counts 0.
+///
+/// # Examples
+/// ```sql
+/// -- the breadth of each source's scanning, by distinct destination /24s (and /64s)
+/// SELECT src, COUNT_DISTINCT_PREFIXES(dst, 24) FROM flows GROUP BY src;
+/// -- distinct IPv6 /48s
+/// SELECT COUNT_DISTINCT_PREFIXES(dst, 24, 48) FROM flows;
+/// ```
+pub struct CountDistinctPrefixes;
+
+/// The prefix lengths counted by, and the networks seen so far.
+pub struct DistinctPrefixes {
+    v4_len: u8,
+    v6_len: u8,
+    seen: HashSet<IpNet>,
+}
+
+impl Aggregate<Option<DistinctPrefixes>, i64> for CountDistinctPrefixes {
+    fn init(&self, _ctx: &mut rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<DistinctPrefixes>> {
+        Ok(None)
+    }
+
+    fn step(&self, ctx: &mut rusqlite::functions::Context<'_>, acc: &mut Option<DistinctPrefixes>) -> rusqlite::Result<()> {
+        let Some(addr) = address_arg(ctx, 0)? else { return Ok(()); };
+        let prefixes = match acc {
+            Some(prefixes) => prefixes,
+            None => acc.insert(DistinctPrefixes {
+                v4_len: prefix_len_arg(ctx, 1, 32, 24)?,
+                v6_len: prefix_len_arg(ctx, 2, 128, 64)?,
+                seen: HashSet::new(),
+            }),
+        };
+
+        let len = match addr {
+            IpAddr::V4(_) => prefixes.v4_len,
+            IpAddr::V6(_) => prefixes.v6_len,
+        };
+        prefixes.seen.insert(IpNet::new(addr, len).expect("prefix length was checked").trunc());
+        Ok(())
+    }
+
+    fn finalize(&self, _ctx: &mut rusqlite::functions::Context<'_>, acc: Option<Option<DistinctPrefixes>>) -> rusqlite::Result<i64> {
+        Ok(acc.flatten().map_or(0, |prefixes| prefixes.seen.len() as i64))
+    }
+}
+
+/// Reads the argument as bytes, from text (as UTF-8) or a blob.
+fn bytes_arg<'a>(ctx: &'a rusqlite::functions::Context<'_>, idx: usize) -> rusqlite::Result<Option<&'a [u8]>> {
+    match ctx.get_raw(idx) {
+        ValueRef::Null => Ok(None),
+        ValueRef::Text(b) | ValueRef::Blob(b) => Ok(Some(b)),
+        _ => Err(ArgError::invalid(ctx, idx, "expected text or a blob")),
+    }
+}
+
+/// Whether an interface identifier is reserved by RFC 5453, so can't be used for an address: the subnet-router
+/// anycast identifier, the reserved subnet anycast identifiers, and the proxy mobile IPv6 identifiers.
+fn is_reserved_iid(iid: u64) -> bool {
+    iid == 0
+        || (0xfdff_ffff_ffff_ff80..=0xfdff_ffff_ffff_ffff).contains(&iid)
+        || (0x0200_5eff_fe00_0000..=0x0200_5eff_feff_ffff).contains(&iid)
+}
+
+/// Computes an RFC 7217 stable privacy address within a /64 prefix.
+///
+/// The interface identifier is the low 64 bits of the SHA-256 digest of the prefix's 8 bytes, the interface, the DAD
+/// counter as a single byte, and the secret key. As RFC 7217 requires, an identifier reserved by RFC 5453 is replaced
+/// by trying the next DAD counter.
+fn stable_privacy(prefix: [u8; 8], iface: &[u8], key: &[u8], mut dad_counter: u8) -> Option<Ipv6Addr> {
+    loop {
+        let mut data = Vec::with_capacity(prefix.len() + iface.len() + 1 + key.len());
+        data.extend_from_slice(&prefix);
+        data.extend_from_slice(iface);
+        data.push(dad_counter);
+        data.extend_from_slice(key);
+
+        let digest = Sha256::digest(&data);
+        let iid = u64::from_be_bytes(digest[24..].try_into().unwrap());
+        if !is_reserved_iid(iid) {
+            let mut octets = [0u8; 16];
+            octets[..8].copy_from_slice(&prefix);
+            octets[8..].copy_from_slice(&iid.to_be_bytes());
+            return Some(Ipv6Addr::from(octets));
+        }
+        dad_counter = dad_counter.checked_add(1)?;
+    }
+}
+
+/// IP6_STABLE_PRIVACY(NULL|prefix, NULL|net_iface, NULL|secret_key, [NULL|dad_counter]) -> NULL|ipv6
+///
+/// Computes the semantically opaque address a host would choose within a prefix, as described by RFC 7217. This allows
+/// the addresses of managed hosts to be predicted (or matched) from the secret key they were configured with.
+///
+/// The prefix is an IPv6 /64 network, or an address within it. The interface may be text (such as an interface name) or
+/// a blob (such as from `MAC_BLOBIFY`), and the secret key at least 16 bytes of text or a blob. The DAD counter defaults
+/// to 0, and is incremented after each address conflict.
+///
+/// RFC 7217 leaves the hash function and the encoding of its inputs to each implementation. This uses SHA-256 over the
+/// prefix's 8 bytes, the interface, the DAD counter as a single byte, and the secret key, in that order, taking the
+/// least significant 64 bits as the interface identifier. Operating systems may encode these differently, so addresses
+/// chosen by a host's own implementation won't necessarily match.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`IP6_STABLE_PRIVACY('2001:db8:1:2::/64', 'eth0', 'an example secret key')`|`'2001:db8:1:2:...'`|
+/// |`IP6_STABLE_PRIVACY('2001:db8:1:2::/64', MAC_BLOBIFY('aa-bb-cc-dd-ee-ff'), X'00112233445566778899AABBCCDDEEFF', 1)`|`'2001:db8:1:2:...'`|
+/// |`IP6_STABLE_PRIVACY('2001:db8::/48', 'eth0', 'an example secret key')`|error|
+pub fn stable_privacy_address(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<SqlText<TextBuf<48>>>> {
+    let Some(prefix) = UserNetAddr::from_ctx(ctx, 0, None)? else { return Ok(None); };
+    let Some(iface) = bytes_arg(ctx, 1)? else { return Ok(None); };
+    let Some(key) = bytes_arg(ctx, 2)? else { return Ok(None); };
+    let dad_counter = match ctx.len() > 3 {
+        true => arg::<Option<i64>>(ctx, 3)?,
+        false => None,
+    };
+
+    let addr = match prefix {
+        UserNetAddr::Address(IpAddr::V6(addr)) => addr,
+        UserNetAddr::Network(IpNet::V6(net)) if net.prefix_len() == 64 => net.addr(),
+        _ => return Err(ArgError::invalid(ctx, 0, InetError::StablePrivacyPrefix(prefix))),
+    };
+    if key.len() < 16 {
+        return Err(ArgError::invalid(ctx, 2, InetError::StablePrivacyKey(key.len())));
+    }
+    let dad_counter = dad_counter.unwrap_or(0);
+    let dad_counter = u8::try_from(dad_counter)
+        .map_err(|_| ArgError::invalid(ctx, 3, InetError::DadCounterRange(dad_counter)))?;
+
+    let prefix: [u8; 8] = addr.octets()[..8].try_into().unwrap();
+    let Some(addr) = stable_privacy(prefix, iface, key, dad_counter) else { return Ok(None); };
+
+    let mut text = TextBuf::new();
+    write!(text, "{addr}").unwrap();
+    Ok(Some(SqlText(text)))
+}
+
+/// Builds the RFC 4193 unique local /48 prefix for a 40-bit global ID: `fd` followed by the global ID.
+fn ula_prefix(global_id: u64) -> Ipv6Net {
+    let mut octets = [0u8; 16];
+    octets[0] = 0xfd;
+    octets[1..6].copy_from_slice(&global_id.to_be_bytes()[3..]);
+    Ipv6Net::new(Ipv6Addr::from(octets), 48).expect("48 is a valid prefix length")
+}
+
+/// Chooses a pseudo-random 40-bit global ID, from the current time and the standard library's randomly keyed hasher.
+fn random_global_id() -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    hasher.write_u128(now.as_nanos());
+    hasher.finish() & 0xff_ffff_ffff
+}
+
+/// IP6_ULA_GENERATE(\[NULL|seed]) -> NULL|ipv6_network
+/// IP6_ULA_GENERATE(NULL|mac, NULL|time) -> NULL|ipv6_network
+///
+/// Generates an RFC 4193 unique local address (ULA) prefix: a /48 within `fd00::/8`, with a 40-bit global ID.
+///
+/// With a MAC address and a time (as Unix seconds, such as `unixepoch('subsec')`), the global ID is derived as RFC 4193
+/// §3.2.2 describes: the least significant 40 bits of the SHA-1 digest of the time in 64-bit NTP format, followed by
+/// the EUI-64 of the address (see [MAC_TO_EUI64](crate::exports::mac::to_eui64)). The same address and time always give
+/// the same prefix, as any tool following the RFC would.
+///
+/// With a seed (text or a blob), the global ID is the least significant 40 bits of the seed's SHA-1 digest, so a seed
+/// of the NTP time and EUI-64 already concatenated gives the same prefix. Without either, the global ID is
+/// pseudo-random, and each call returns a new prefix.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`IP6_ULA_GENERATE()`|`'fd3c:8a51:7e09::/48'` (random)|
+/// |`IP6_ULA_GENERATE('3c:a6:f6:c4:34:f8', unixepoch('subsec'))`|a /48 unique to the interface and time|
+/// |`IP6_ULA_GENERATE('lab network')`|the same /48 on every call|
+/// |`IP6_ULA_GENERATE(NULL)`|`NULL`|
+pub fn ula_generate(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<SqlText<TextBuf<48>>>> {
+    let digest = match ctx.len() {
+        0 => None,
+        1 => {
+            let Some(seed) = bytes_arg(ctx, 0)? else { return Ok(None); };
+            Some(Sha1::digest(seed))
+        },
+        _ => {
+            let (Some(mac), Some(time)) = (mac_arg(ctx, 0)?, arg::<Option<f64>>(ctx, 1)?) else { return Ok(None); };
+            let ntp = ntp_timestamp(time).ok_or_else(|| ArgError::invalid(ctx, 1, InetError::UlaTime(time)))?;
+            let eui = match mac {
+                Eui::Mac48(mac) => crate::mac::eui48_to_eui64(mac.to_array(), false),
+                Eui::Eui64(eui) => eui,
+            };
+            Some(Sha1::digest([ntp.to_be_bytes(), eui].concat()))
+        },
+    };
+    let global_id = match digest {
+        None => random_global_id(),
+        Some(digest) => u64::from_be_bytes(digest[12..].try_into().unwrap()) & 0xff_ffff_ffff,
+    };
+
+    let mut text = TextBuf::new();
+    write!(text, "{}", ula_prefix(global_id)).unwrap();
+    Ok(Some(SqlText(text)))
+}
+
+/// Converts Unix seconds into a 64-bit NTP timestamp: seconds since 1900 in the upper 32 bits, and the fraction of a
+/// second in the lower 32. Times outside the current NTP era wrap, as NTP's own timestamps do.
+fn ntp_timestamp(unix: f64) -> Option<u64> {
+    const NTP_UNIX_OFFSET: f64 = 2_208_988_800.0;
+    let ntp = unix + NTP_UNIX_OFFSET;
+    if !ntp.is_finite() {
+        return None;
+    }
+    let seconds = ntp.floor();
+    let fraction = ((ntp - seconds) * 2f64.powi(32)) as u64;
+    Some(((seconds as i64 as u64) << 32) | fraction.min(u32::MAX.into()))
+}
+
+/// Pulls a single IP address (not a network) out of the provided argument, as text or a blob.
+pub(crate) fn address_arg(ctx: &rusqlite::functions::Context<'_>, idx: usize) -> rusqlite::Result<Option<IpAddr>> {
+    match UserNetAddr::from_ctx(ctx, idx, None)? {
+        None => Ok(None),
+        Some(UserNetAddr::Address(addr)) => Ok(Some(addr)),
+        Some(net @ UserNetAddr::Network(_)) => Err(ArgError::invalid(ctx, idx, InetError::ExpectedAddress(net))),
+    }
+}
+
+/// A 64-bit FNV-1a hash. Used where hashes are persisted, as its output is stable across builds and platforms.
+fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    bytes.into_iter().fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+        (hash ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+fn ip_octets(addr: IpAddr) -> Vec<u8> {
+    match addr {
+        IpAddr::V4(ipv4) => ipv4.octets().to_vec(),
+        IpAddr::V6(ipv6) => ipv6.octets().to_vec(),
+    }
+}
+
+/// FLOW_HASH(src_ip, dst_ip, src_port, dst_port, proto) -> NULL|int
+///
+/// Computes a direction-independent hash of a flow's 5-tuple, so both directions of a conversation hash to the same value.
+///
+/// Addresses may be text or blobs (as produced by [IP_BLOBIFY](crate::exports::inet::blobify)). The protocol may be a protocol
+/// number, or one of `tcp`, `udp`, `icmp`, `icmpv6`, `sctp`. The hash is stable across builds, so it is suitable for storing.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`FLOW_HASH('10.0.0.1', '10.0.0.2', 49152, 443, 6) = FLOW_HASH('10.0.0.2', '10.0.0.1', 443, 49152, 'tcp')`|`TRUE`|
+/// |`FLOW_HASH('10.0.0.1', '10.0.0.2', 49152, 443, 6) = FLOW_HASH('10.0.0.1', '10.0.0.2', 49152, 443, 17)`|`FALSE`|
+/// |`FLOW_HASH('10.0.0.1', NULL, 49152, 443, 6)`|`NULL`|
+pub fn flow_hash(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<i64>> {
+    let Some(src) = address_arg(ctx, 0)? else { return Ok(None); };
+    let Some(dst) = address_arg(ctx, 1)? else { return Ok(None); };
+    let Some(src_port) = port_arg(ctx, 2)? else { return Ok(None); };
+    let Some(dst_port) = port_arg(ctx, 3)? else { return Ok(None); };
+    let Some(proto) = protocol_arg(ctx, 4)? else { return Ok(None); };
+
+    // canonicalize the direction, so that the lower endpoint is always hashed first
+    let (lo, hi) = if (src, src_port) <= (dst, dst_port) {
+        ((src, src_port), (dst, dst_port))
+    } else {
+        ((dst, dst_port), (src, src_port))
+    };
+
+    let bytes = std::iter::once(proto)
+        .chain(ip_octets(lo.0)).chain(lo.1.to_be_bytes())
+        .chain(ip_octets(hi.0)).chain(hi.1.to_be_bytes());
+
+    Ok(Some(fnv1a(bytes) as i64))
+}
+
+#[test]
+fn fnv1a_reference() {
+    // reference values from the FNV specification test suite
+    assert_eq!(fnv1a(*b""), 0xcbf29ce484222325);
+    assert_eq!(fnv1a(*b"a"), 0xaf63dc4c8601ec8c);
+    assert_eq!(fnv1a(*b"foobar"), 0x85944171f73967e8);
+}
+
+#[test]
+fn blob_addresses() {
+    let net = |dat: &[u8]| UserNetAddr::from_blob(dat).map(|una| una.to_string());
+    assert_eq!(net(&[10, 1, 2, 3]).unwrap(), "10.1.2.3");
+    assert_eq!(net(&[10, 1, 2, 3, 8]).unwrap(), "10.1.2.3/8");
+    let v6: [u8; 16] = "2001:db8::1".parse::<std::net::Ipv6Addr>().unwrap().octets();
+    assert_eq!(net(&v6).unwrap(), "2001:db8::1");
+    assert_eq!(net(&[&v6[..], &[32]].concat()).unwrap(), "2001:db8::1/32");
+
+    assert!(matches!(net(&[10, 1, 2, 3, 33]), Err(InetError::BlobPrefixLength(33, _))));
+    assert!(matches!(net(&[&v6[..], &[129]].concat()), Err(InetError::BlobPrefixLength(129, _))));
+    assert!(matches!(net(&[]), Err(InetError::UnrecognizedBlobLength(_))));
+}
+
+#[test]
+fn blob_functions() {
+    let conn = conn();
+    let truth = |sql: &str| query_row::<Option<bool>>(&conn, sql);
+
+    assert_eq!(truth("SELECT IPB_CONTAINS(IP_BLOBIFY('128.231.61.3'), IP_BLOBIFY('128.231.60.0/22'))"), Some(true));
+    assert_eq!(truth("SELECT IPB_CONTAINS(X'80E73B07', X'80E73C0016')"), Some(false));
+    assert_eq!(truth("SELECT IPB_CONTAINS(X'80E73B07', X'80E73B07')"), Some(true));
+    assert_eq!(truth("SELECT IPB_CONTAINS(NULL, X'80E73C0016')"), None);
+
+    assert_eq!(query_text(&conn, "SELECT IPB_FORMAT(IPB_BUCKET(IP_BLOBIFY('10.1.2.3'), 24))").as_deref(), Some("10.1.2.0/24"));
+    assert_eq!(query_text(&conn, "SELECT IPB_FORMAT(IPB_BUCKET(IP_BLOBIFY('2001:db8:85a3::8a2e:370:7334'), 48))").as_deref(), Some("2001:db8:85a3::/48"));
+    assert_eq!(query_text(&conn, "SELECT IPB_FORMAT(IPB_BUCKET(IP_BLOBIFY('10.1.0.0/16'), 24))").as_deref(), Some("10.1.0.0/16"));
+    assert_eq!(query_text(&conn, "SELECT IPB_FORMAT(IP_BLOBIFY('fe80::2:3:0:aabb/10'))").as_deref(), Some("fe80::2:3:0:aabb/10"));
+
+    assert!(query_err(&conn, "SELECT IPB_CONTAINS('128.231.61.3', X'80E73C0016')").starts_with("argument 1 ('128.231.61.3')"));
+    assert!(query_err(&conn, "SELECT IPB_BUCKET(X'0A010203', 33)").starts_with("argument 2 (33)"));
+}
+
+#[test]
+fn buckets() {
+    let conn = conn();
+
+    assert_eq!(query_text(&conn, "SELECT IP_BUCKET('10.1.2.3', 24)").as_deref(), Some("10.1.2.0/24"));
+    assert_eq!(query_text(&conn, "SELECT IP_BUCKET('2001:db8:85a3::8a2e:370:7334', 48)").as_deref(), Some("2001:db8:85a3::/48"));
+    assert_eq!(query_text(&conn, "SELECT IP_BUCKET('10.1.0.0/16', 24)").as_deref(), Some("10.1.0.0/16"));
+    assert_eq!(query_text(&conn, "SELECT IP_BUCKET(IP_BLOBIFY('10.1.2.3'), 0)").as_deref(), Some("0.0.0.0/0"));
+    assert_eq!(query_text(&conn, "SELECT IP_BUCKET(NULL, 24)"), None);
+    assert_eq!(query_text(&conn, "SELECT IP_BUCKET('10.1.2.3', NULL)"), None);
+
+    assert!(query_err(&conn, "SELECT IP_BUCKET('10.1.2.3', 33)").starts_with("argument 2 (33)"));
+    assert!(query_err(&conn, "SELECT IP_BUCKET('10.1.2.300', 24)").starts_with("argument 1 ('10.1.2.300')"));
+}
+
+#[test]
+fn ip_problems() {
+    let cases = [
+        ("10.1.2.3", None),
+        ("10.1.2.0/24", None),
+        ("::ffff:10.1.2.3", None),
+        ("2001:db8::/32", None),
+        ("", Some("empty")),
+        (" 10.1.2.3", Some("whitespace")),
+        ("10.1.2.x", Some("bad-character")),
+        ("10.1.2", Some("octet-count")),
+        ("167838211", Some("octet-count")),
+        ("10.1.2.300", Some("bad-octet")),
+        ("10..2.3", Some("bad-octet")),
+        ("10.01.2.3", Some("leading-zero")),
+        ("2001:db8:00001::", Some("bad-group")),
+        ("2001:db8:1:2:3:4:5:6:7", Some("group-count")),
+        ("2001:db8:1", Some("group-count")),
+        ("2001::1::2", Some("multiple-elisions")),
+        (":2001:db8::1", Some("bad-group")),
+        ("::ffff:10.1.2", Some("mixed-notation")),
+        ("10.1.2.3::1", Some("mixed-notation")),
+        ("1:2:3:4:5:6:7::10.1.2.3", Some("group-count")),
+        ("1:2:3:4:5:6:10.1.2.3", None),
+        ("fe80::1%eth0", Some("zone-id")),
+        ("[2001:db8::1]", Some("brackets")),
+        ("[2001:db8::1]:443", Some("has-port")),
+        ("10.1.2.3:443", Some("has-port")),
+        ("10.1.2.0/", Some("bad-prefix")),
+        ("10.1.2.0/255.255.255.0", Some("mask-notation")),
+        ("10.1.2.0/33", Some("prefix-out-of-range")),
+        ("2001:db8::/129", Some("prefix-out-of-range")),
+        ("10.1.2.0/24/8", Some("bad-prefix")),
+    ];
+    for (text, problem) in cases {
+        assert_eq!(ip_problem(text), problem, "{text:?}");
+    }
+}
+
+#[test]
+fn cidr_intersections() {
+    let conn = conn();
+
+    assert_eq!(query_text(&conn, "SELECT CIDR_INTERSECT('10.0.0.0/8', '10.1.2.3/16')").as_deref(), Some("10.1.0.0/16"));
+    assert_eq!(query_text(&conn, "SELECT CIDR_INTERSECT(IP_BLOBIFY('10.1.0.0/16'), '10.0.0.0/8')").as_deref(), Some("10.1.0.0/16"));
+    assert_eq!(query_text(&conn, "SELECT CIDR_INTERSECT('10.0.0.0/16', '10.1.0.0/16')"), None);
+    assert_eq!(query_text(&conn, "SELECT CIDR_INTERSECT('10.0.0.0/8', '::/0')"), None);
+
+    conn.execute_batch("
+        CREATE TABLE policies (device TEXT, scope TEXT);
+        INSERT INTO policies VALUES
+            ('a', '10.0.0.0/8'), ('a', '10.1.0.0/16'), ('a', NULL), ('a', '10.1.2.0/24'),
+            ('b', '10.0.0.0/16'), ('b', '10.1.0.0/16'), ('b', '0.0.0.0/0'),
+            ('c', NULL);
+    ").unwrap();
+    let mut stmt = conn.prepare("SELECT device, CIDR_INTERSECT(scope) FROM policies GROUP BY device ORDER BY device").unwrap();
+    let rows: Vec<(String, Option<String>)> = stmt.query_map([], |r| Ok((r.get(0)?, r.get(1)?))).unwrap()
+        .collect::<Result<_, _>>().unwrap();
+    assert_eq!(rows, [("a".to_owned(), Some("10.1.2.0/24".to_owned())), ("b".to_owned(), None), ("c".to_owned(), None)]);
+}
+
+#[test]
+fn overlap_counts() {
+    let conn = conn();
+    let count = |sql: &str| query_row::<Value>(&conn, sql);
+
+    assert_eq!(count("SELECT IP_OVERLAP_COUNT('10.0.0.0/8', '10.1.0.0/16')"), Value::Integer(65536));
+    assert_eq!(count("SELECT IP_OVERLAP_COUNT('10.0.0.0/24', '10.0.0.200-10.0.1.50')"), Value::Integer(56));
+    assert_eq!(count("SELECT IP_OVERLAP_COUNT('10.0.0.5', IP_BLOBIFY('10.0.0.0/30'))"), Value::Integer(0));
+    assert_eq!(count("SELECT IP_OVERLAP_COUNT('10.0.0.0/8', '::/0')"), Value::Integer(0));
+    assert_eq!(count("SELECT IP_OVERLAP_COUNT('2001:db8::/64', '2001:db8::/48')"), Value::Text("18446744073709551616".to_owned()));
+    assert_eq!(count("SELECT IP_OVERLAP_COUNT('2001:db8::/66', '2001:db8::/48')"), Value::Integer(1 << 62));
+    assert_eq!(count("SELECT IP_OVERLAP_COUNT('::/0', '::/0')"), Value::Text("340282366920938463463374607431768211456".to_owned()));
+    assert_eq!(count("SELECT IP_OVERLAP_COUNT(NULL, '::/0')"), Value::Null);
+    assert!(conn.query_row("SELECT IP_OVERLAP_COUNT('10.0.0.9-10.0.0.1', '10.0.0.0/8')", [], |r| r.get::<_, Value>(0)).is_err());
+}
+
+#[test]
+fn documented_functions() {
+    let conn = conn();
+    let truth = |sql: &str| query_row::<Option<bool>>(&conn, sql);
+    let blob = |sql: &str| query_row::<Option<Vec<u8>>>(&conn, sql);
+
+    // each arity of IP_FORMAT
+    assert_eq!(query_text(&conn, "SELECT IP_FORMAT('192.168.3.2')").as_deref(), Some("192.168.3.2"));
+    assert_eq!(query_text(&conn, "SELECT IP_FORMAT('192.168.3.2/16')").as_deref(), Some("192.168.3.2/16"));
+    assert_eq!(query_text(&conn, "SELECT IP_FORMAT('10.2.3.1', '255.255.255.0')").as_deref(), Some("10.2.3.1/24"));
+    assert_eq!(query_text(&conn, "SELECT IP_FORMAT('10.2.3.1', '255.255.255.0', TRUE)").as_deref(), Some("10.2.3.0/24"));
+    assert_eq!(query_text(&conn, "SELECT IP_FORMAT('fe80:0:0:0:2:03:0:aabb/10')").as_deref(), Some("fe80::2:3:0:aabb/10"));
+    assert_eq!(query_text(&conn, "SELECT IP_FORMAT('fe80:0:0:0:2:03:0:aabb/10', TRUE)").as_deref(), Some("fe80::/10"));
+    assert_eq!(query_text(&conn, "SELECT IP_FORMAT(NULL)"), None);
+    assert_eq!(query_text(&conn, "SELECT IP_FORMAT('10.2.3.1/24', 0)").as_deref(), Some("10.2.3.1/24"));
+    assert_eq!(query_text(&conn, "SELECT IP_FORMAT('10.2.3.1', 24, FALSE)").as_deref(), Some("10.2.3.1/24"));
+    assert!(query_err(&conn, "SELECT IP_FORMAT('10.2.3.1', 24)").contains("Expected TRUE or FALSE for should_truncate"));
+    assert!(query_err(&conn, "SELECT IP_FORMAT('10.2.3.1/24', 2)").contains("received 2"));
+    assert!(conn.query_row("SELECT IP_FORMAT('192.168.003.002')", [], |_| Ok(())).is_err());
+
+    // each arity of IP_CONTAINS
+    assert_eq!(truth("SELECT IP_CONTAINS('128.231.61.3', '128.231.60.0/22')"), Some(true));
+    assert_eq!(truth("SELECT IP_CONTAINS('128.231.59.7', '128.231.60.0', '255.255.252.0')"), Some(false));
+    assert_eq!(truth("SELECT IP_CONTAINS('128.231.59.7', '128.231.60.0', 22)"), Some(false));
+    assert_eq!(truth("SELECT IP_CONTAINS('fe80::82fe:a2', 'fe80::/10')"), Some(true));
+    assert_eq!(truth("SELECT IP_CONTAINS(NULL, 'fe80::/10')"), None);
+    assert_eq!(truth("SELECT IP_CONTAINS('128.231.61.3', '128.231.60.0', 22)"), Some(true));
+    assert!(conn.query_row("SELECT IP_CONTAINS('10.0.0.1', '10.0.0.0', 33)", [], |_| Ok(())).is_err());
+
+    assert_eq!(blob("SELECT IP_BLOBIFY('127.0.0.1')"), Some(vec![127, 0, 0, 1]));
+    assert_eq!(blob("SELECT IP_BLOBIFY(NULL)"), None);
+}
+
+#[test]
+fn contains_all_elements() {
+    let conn = conn();
+
+    assert_eq!(query_text(&conn, r#"SELECT IP_CONTAINS_ALL('["10.1.2.3", "192.0.2.1", null, "10.9.0.0/16"]', '10.0.0.0/8')"#).as_deref(),
+        Some("[true,false,null,true]"));
+    assert_eq!(query_text(&conn, r#"SELECT IP_CONTAINS_ALL('{"hosts": ["fe80::1"]}' -> '$.hosts', 'fe80::/10')"#).as_deref(), Some("[true]"));
+    assert_eq!(query_text(&conn, "SELECT IP_CONTAINS_ALL('[]', '10.0.0.0/8')").as_deref(), Some("[]"));
+    assert_eq!(query_text(&conn, "SELECT IP_CONTAINS_ALL(NULL, '10.0.0.0/8')"), None);
+
+    assert!(query_err(&conn, r#"SELECT IP_CONTAINS_ALL('["10.1.2.3", "10.1.2.300"]', '10.0.0.0/8')"#).contains("element 1: invalid IP address syntax"));
+    assert!(query_err(&conn, r#"SELECT IP_CONTAINS_ALL('[167837955]', '10.0.0.0/8')"#).contains("element 0: expected"));
+    assert!(query_err(&conn, r#"SELECT IP_CONTAINS_ALL('{"ip": "10.1.2.3"}', '10.0.0.0/8')"#).ends_with("expected a JSON array"));
+}
+
+#[test]
+fn in_any_networks() {
+    use std::rc::Rc;
+    use rusqlite::params;
+
+    let conn = conn();
+    assert_eq!(query_row::<Option<bool>>(&conn, r#"SELECT IP_IN_ANY('10.1.2.3', '["192.0.2.0/24", "10.0.0.0/8"]')"#), Some(true));
+    assert_eq!(query_row::<Option<bool>>(&conn, r#"SELECT IP_IN_ANY('10.1.2.3', '["192.0.2.0/24", null, "10.1.2.4"]')"#), Some(false));
+    assert_eq!(query_row::<Option<bool>>(&conn, r#"SELECT IP_IN_ANY('10.1.2.0/25', '["10.1.2.3", "10.1.0.0/16"]')"#), Some(true));
+    assert_eq!(query_row::<Option<bool>>(&conn, "SELECT IP_IN_ANY('10.1.2.3', '[]')"), Some(false));
+    assert_eq!(query_row::<Option<bool>>(&conn, "SELECT IP_IN_ANY(NULL, '[]')"), None);
+    assert_eq!(query_row::<Option<bool>>(&conn, "SELECT IP_IN_ANY('10.1.2.3', NULL)"), None);
+    assert!(query_err(&conn, r#"SELECT IP_IN_ANY('10.1.2.3', '["10.0.0.0/8", "10.0.0.0/33"]')"#).starts_with("argument 2"));
+    assert!(query_err(&conn, r#"SELECT IP_IN_ANY('10.1.2.3', '["10.0.0.0/8", "10.0.0.0/33"]')"#).contains("element 1: "));
+    assert!(query_err(&conn, "SELECT IP_IN_ANY('10.1.2.3', '[167837955]')").contains("element 0: expected a network as text"));
+    assert!(query_err(&conn, "SELECT IP_IN_ANY('10.1.2.300', '[]')").starts_with("argument 1 ('10.1.2.300')"));
+    assert!(query_err(&conn, "SELECT IP_IN_ANY('10.1.2.3', 8)").ends_with("expected a JSON array, or a bound list"));
+
+    // the same list, bound rather than written out
+    let bound = |ip: &str, networks: Vec<Value>| conn
+        .query_row("SELECT IP_IN_ANY(?1, ?2)", params![ip, Rc::new(networks)], |r| r.get::<_, Option<bool>>(0));
+    let watched = || vec![Value::from("192.0.2.0/24".to_owned()), Value::Null, Value::from("10.0.0.0/8".to_owned())];
+    assert_eq!(bound("10.1.2.3", watched()).unwrap(), Some(true));
+    assert_eq!(bound("198.51.100.1", watched()).unwrap(), Some(false));
+    assert_eq!(bound("10.1.2.3", vec![]).unwrap(), Some(false));
+    assert!(bound("10.1.2.3", vec![Value::Integer(1)]).unwrap_err().to_string().contains("element 0: expected a network as text"));
+
+    let mut stmt = conn.prepare("SELECT ip FROM (SELECT '10.1.2.3' AS ip UNION ALL SELECT '192.0.2.7') WHERE IP_IN_ANY(ip, ?1)").unwrap();
+    let mut rows = |networks: Vec<Value>| stmt.query_map([Rc::new(networks)], |r| r.get::<_, String>(0)).unwrap()
+        .collect::<rusqlite::Result<Vec<_>>>().unwrap();
+    assert_eq!(rows(watched()), ["10.1.2.3", "192.0.2.7"]);
+    assert_eq!(rows(vec![Value::from("192.0.2.0/28".to_owned())]), ["192.0.2.7"]);
+}
+
+#[test]
+fn ip_rollups() {
+    let conn = conn();
+    conn.execute_batch("
+        CREATE TABLE flows (src TEXT);
+        INSERT INTO flows VALUES ('10.1.2.3'), ('10.1.2.4'), ('10.1.9.1'), ('10.200.0.1'), ('192.0.2.1'), (NULL), ('2001:db8::1');
+    ").unwrap();
+    let json = |sql: &str| query_row::<Option<String>>(&conn, sql);
+
+    let rows = |sql: &str| {
+        let mut stmt = conn.prepare(sql).unwrap();
+        stmt.query_map([], |r| Ok(format!("{} {} {}", r.get::<_, String>(0)?, r.get::<_, i64>(1)?, r.get::<_, Option<String>>(2)?.unwrap_or_default())))
+            .unwrap().collect::<Result<Vec<_>, _>>().unwrap()
+    };
+    assert_eq!(rows("SELECT value ->> 'prefix', value ->> 'count', value ->> 'parent' FROM json_each((SELECT IP_ROLLUP(src, '8, /16,24') FROM flows WHERE src LIKE '10.%'))"), [
+        "10.0.0.0/8 4 ",
+        "10.1.0.0/16 3 10.0.0.0/8",
+        "10.1.2.0/24 2 10.1.0.0/16",
+        "10.1.9.0/24 1 10.1.0.0/16",
+        "10.200.0.0/16 1 10.0.0.0/8",
+        "10.200.0.0/24 1 10.200.0.0/16",
+    ]);
+    assert_eq!(json("SELECT IP_ROLLUP(src, '16,48') FROM flows WHERE src NOT LIKE '10.%'").as_deref(), Some(concat!(
+        r#"[{"count":1,"length":16,"parent":null,"prefix":"192.0.0.0/16"},"#,
+        r#"{"count":1,"length":16,"parent":null,"prefix":"2001::/16"},"#,
+        r#"{"count":1,"length":48,"parent":"2001::/16","prefix":"2001:db8::/48"}]"#,
+    )));
+    assert_eq!(json("SELECT IP_ROLLUP(src, 8) FROM flows WHERE src IS NULL"), None);
+    assert!(conn.query_row("SELECT IP_ROLLUP(src, '8,129') FROM flows", [], |r| r.get::<_, Option<String>>(0)).is_err());
+}
+
+#[test]
+fn distinct_prefixes() {
+    let conn = conn();
+    conn.execute_batch("
+        CREATE TABLE flows (dst TEXT);
+        INSERT INTO flows VALUES ('10.1.2.3'), ('10.1.2.4'), ('10.1.9.1'), (NULL), ('2001:db8::1'), ('2001:db8::2'), ('2001:db8:0:1::1');
+    ").unwrap();
+    let int = |sql: &str| query_row::<i64>(&conn, sql);
+
+    assert_eq!(int("SELECT COUNT_DISTINCT_PREFIXES(dst, 24) FROM flows"), 4);
+    assert_eq!(int("SELECT COUNT_DISTINCT_PREFIXES(dst, 16, 48) FROM flows"), 2);
+    assert_eq!(int("SELECT COUNT_DISTINCT_PREFIXES(dst, 32, 128) FROM flows"), 6);
+    assert_eq!(int("SELECT COUNT_DISTINCT_PREFIXES(dst, 24) FROM flows WHERE dst IS NULL"), 0);
+    assert!(conn.query_row("SELECT COUNT_DISTINCT_PREFIXES(dst, 33) FROM flows", [], |r| r.get::<_, i64>(0)).is_err());
+}
+
+#[test]
+fn stable_privacy_addresses() {
+    let prefix = [0x20, 0x01, 0x0d, 0xb8, 0, 1, 0, 2];
+    let key = b"an example secret key";
+    let addr = stable_privacy(prefix, b"eth0", key, 0).unwrap();
+
+    // the interface identifier is the low 64 bits of the digest
+    let digest = Sha256::digest([&prefix[..], b"eth0", &[0], key].concat());
+    assert_eq!(addr.octets()[..8], prefix);
+    assert_eq!(addr.octets()[8..], digest[24..]);
+
+    // each input changes the address
+    assert_ne!(stable_privacy(prefix, b"eth1", key, 0), Some(addr));
+    assert_ne!(stable_privacy(prefix, b"eth0", key, 1), Some(addr));
+    assert_ne!(stable_privacy([0x20, 0x01, 0x0d, 0xb8, 0, 1, 0, 3], b"eth0", key, 0).map(|a| a.octets()[8..].to_vec()), Some(addr.octets()[8..].to_vec()));
+
+    assert!(is_reserved_iid(0));
+    assert!(is_reserved_iid(0xfdff_ffff_ffff_ff90));
+    assert!(is_reserved_iid(0x0200_5eff_fe00_5213));
+    assert!(!is_reserved_iid(0x0200_5eff_ff00_0000));
+
+    let conn = conn();
+    assert_eq!(query_text(&conn, "SELECT IP6_STABLE_PRIVACY('2001:db8:1:2::/64', 'eth0', 'an example secret key')"), Some(addr.to_string()));
+    assert_eq!(query_text(&conn, "SELECT IP6_STABLE_PRIVACY('2001:db8:1:2::99', 'eth0', 'an example secret key', 0)"), Some(addr.to_string()));
+    assert_eq!(query_text(&conn, "SELECT IP6_STABLE_PRIVACY('2001:db8:1:2::/64', NULL, 'an example secret key')"), None);
+
+    assert!(query_err(&conn, "SELECT IP6_STABLE_PRIVACY('2001:db8::/48', 'eth0', 'an example secret key')").starts_with("argument 1"));
+    assert!(query_err(&conn, "SELECT IP6_STABLE_PRIVACY('10.0.0.1', 'eth0', 'an example secret key')").starts_with("argument 1"));
+    assert!(query_err(&conn, "SELECT IP6_STABLE_PRIVACY('2001:db8::/64', 'eth0', 'short')").starts_with("argument 3"));
+    assert!(query_err(&conn, "SELECT IP6_STABLE_PRIVACY('2001:db8::/64', 'eth0', 'an example secret key', 256)").starts_with("argument 4"));
+}
+
+#[test]
+fn ula_prefixes() {
+    assert_eq!(ula_prefix(0x12_3456_789a).to_string(), "fd12:3456:789a::/48");
+    assert_eq!(ula_prefix(0).to_string(), "fd00::/48");
+    assert!(random_global_id() <= 0xff_ffff_ffff);
+
+    let conn = conn();
+
+    assert_eq!(query_text(&conn, "SELECT IP6_ULA_GENERATE('lab network')").as_deref(), Some("fdaf:b1dc:7064::/48"));
+    assert_eq!(query_text(&conn, "SELECT IP6_ULA_GENERATE(NULL)"), None);
+
+    // RFC 4193 §3.2.2, from the NTP time and EUI-64, or the same key given as a seed
+    let rfc = "fda5:ad92:d7f::/48";
+    assert_eq!(query_text(&conn, "SELECT IP6_ULA_GENERATE('3c:a6:f6:c4:34:f8', 1700000000.5)").as_deref(), Some(rfc));
+    assert_eq!(query_text(&conn, "SELECT IP6_ULA_GENERATE('3c:a6:f6:ff:fe:c4:34:f8', 1700000000.5)").as_deref(), Some(rfc));
+    assert_eq!(query_text(&conn, "SELECT IP6_ULA_GENERATE(X'E8FE6F8080000000' || X'3CA6F6FFFEC434F8')").as_deref(), Some(rfc));
+    assert_eq!(ntp_timestamp(0.0), Some(2_208_988_800 << 32));
+    assert_eq!(query_text(&conn, "SELECT IP6_ULA_GENERATE(NULL, 1700000000)"), None);
+    assert!(query_err(&conn, "SELECT IP6_ULA_GENERATE('3c:a6:f6:c4:34:f8', 1e999)").contains("finite time"));
+
+    let random: Ipv6Net = query_text(&conn, "SELECT IP6_ULA_GENERATE()").unwrap().parse().unwrap();
+    assert_eq!(random.prefix_len(), 48);
+    assert!("fd00::/8".parse::<Ipv6Net>().unwrap().contains(&random));
+}
+
+#[test]
+fn subnet_utilization() {
+    let conn = conn();
+    conn.execute_batch("
+        CREATE TABLE leases (subnet TEXT, ip TEXT);
+        INSERT INTO leases VALUES
+            ('10.0.0.0/24', '10.0.0.1'), ('10.0.0.0/24', '10.0.0.2'), ('10.0.0.0/24', '10.0.0.2'),
+            ('10.0.0.0/24', '10.0.0.255'), ('10.0.0.0/24', '10.0.1.1'), ('10.0.0.0/24', NULL),
+            ('2001:db8::/32', '2001:db8::1'), ('::/0', '::1');
+    ").unwrap();
+    let json = |sql: &str| query_row::<Option<String>>(&conn, sql);
+
+    assert_eq!(json("SELECT SUBNET_UTILIZATION(subnet, ip) FROM leases WHERE subnet = '10.0.0.0/24'").as_deref(),
+        Some(r#"{"network":"10.0.0.0/24","percent":1.171875,"size":256,"used":3}"#));
+    assert_eq!(json("SELECT SUBNET_UTILIZATION('10.0.0.0/30', ip) FROM leases").as_deref(),
+        Some(r#"{"network":"10.0.0.0/30","percent":50.0,"size":4,"used":2}"#));
+    assert_eq!(json("SELECT SUBNET_UTILIZATION(subnet, ip) ->> 'size' FROM leases WHERE subnet = '2001:db8::/32'").as_deref(),
+        Some("79228162514264337593543950336"));
+    assert_eq!(json("SELECT SUBNET_UTILIZATION(subnet, ip) ->> 'size' FROM leases WHERE subnet = '::/0'").as_deref(),
+        Some("340282366920938463463374607431768211456"));
+    assert_eq!(json("SELECT SUBNET_UTILIZATION('2001:db8::/64', ip) ->> 'size' FROM leases").as_deref(),
+        Some("18446744073709551616"));
+    assert_eq!(json("SELECT json_type(SUBNET_UTILIZATION('2001:db8::/65', ip), '$.size') FROM leases").as_deref(),
+        Some("integer"));
+    assert_eq!(json("SELECT SUBNET_UTILIZATION(subnet, ip) FROM leases WHERE ip IS NULL"), None);
+    assert!(conn.query_row("SELECT SUBNET_UTILIZATION(subnet, ip) FROM leases", [], |r| r.get::<_, Option<String>>(0)).is_err());
+}
+
 // pub fn split(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
 
 // }
@@ -0,0 +1,384 @@
+use crate::{
+    exports::inet::UserNetAddr,
+    mac::{MacAddr, MacStyle},
+    oui::{OuiDb, SharedOuiDb, EMBEDDED_DB, EMBEDDED_WKA_DB},
+    packs,
+    registration::{FunctionGroup, Registration, Resolution, SharedCollisions},
+    settings::SharedSettings,
+    stats::SharedStats,
+};
+#[cfg(test)]
+use crate::test_util::conn;
+
+/// The crate's enabled features, comma separated
+const FEATURES: &str = env!("NETTOOLS_FEATURES");
+
+/// # NETTOOLS_VERSION() -> version
+/// Returns the version of this extension.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`NETTOOLS_VERSION()` | `'0.1.0'` |
+pub fn version(_ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<&'static str> {
+    Ok(env!("CARGO_PKG_VERSION"))
+}
+
+/// # NETTOOLS_BUILD_INFO() -> json
+/// Returns a JSON object describing how this extension was built, for use with `json_extract`. Storing this alongside
+/// derived columns (such as those from [MAC_MANUF](crate::exports::mac::manuf)) records which build, and which
+/// embedded databases, produced them.
+///
+/// The object contains:
+/// * `version` - The extension's version, as returned by [NETTOOLS_VERSION](crate::exports::info::version)
+/// * `git_hash` - The commit it was built from, or `'unknown'` if not built from a git checkout
+/// * `build_date` - The UTC date it was built, as `YYYY-MM-DD`
+/// * `features` - An array of the enabled cargo features
+/// * `oui_db` - The embedded OUI database's `source` and `date`, as returned by
+///   [OUI_DB_SOURCE](crate::exports::oui::db_source) and [OUI_DB_DATE](crate::exports::oui::db_date), and its number
+///   of `entries`
+/// * `wka_db` - The embedded well-known address database's `source` and number of `entries`, which are empty and 0
+///   without the `wka-db` feature
+/// * `packs` - The optional datasets embedded by their features (such as `services-db`), by name, each with its
+///   `source` and number of `entries`
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`json_extract(NETTOOLS_BUILD_INFO(), '$.version')`        | `'0.1.0'` |
+/// |`json_extract(NETTOOLS_BUILD_INFO(), '$.features')`       | `'["loadable-extension"]'` |
+/// |`json_extract(NETTOOLS_BUILD_INFO(), '$.oui_db.date')`    | `'2024-03-14'` |
+pub fn build_info(_ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<String> {
+    Ok(build_info_json().to_string())
+}
+
+fn build_info_json() -> serde_json::Value {
+    let features: Vec<&str> = FEATURES.split(',').filter(|f| !f.is_empty()).collect();
+    let packs: serde_json::Map<String, serde_json::Value> = packs::embedded().into_iter()
+        .map(|p| (p.name.to_owned(), serde_json::json!({ "source": p.source, "entries": p.entries })))
+        .collect();
+    serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "git_hash": env!("NETTOOLS_GIT_HASH"),
+        "build_date": env!("NETTOOLS_BUILD_DATE"),
+        "features": features,
+        "oui_db": {
+            "source": OuiDb::EMBEDDED_DB_SOURCE,
+            "date": OuiDb::EMBEDDED_DB_DATE,
+            "entries": EMBEDDED_DB.len(),
+        },
+        "wka_db": {
+            "source": OuiDb::EMBEDDED_WKA_SOURCE,
+            "entries": EMBEDDED_WKA_DB.len(),
+        },
+        "packs": packs,
+    })
+}
+
+#[test]
+fn build_info_fields() {
+    let info = build_info_json();
+    assert_eq!(info["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(info["oui_db"]["entries"], EMBEDDED_DB.len());
+    assert_eq!(info["features"].as_array().unwrap().iter().any(|f| f == "loadable-extension"), cfg!(feature = "loadable-extension"));
+    assert!(!info["git_hash"].as_str().unwrap().is_empty());
+    assert_eq!(info["packs"].as_object().unwrap().len(), packs::embedded().len());
+}
+
+/// A function from each group, checked for by NETTOOLS_SELFTEST to confirm the group was registered
+const GROUP_SENTINELS: &[(FunctionGroup, &str)] = &[
+    (FunctionGroup::Mac, "MAC_FORMAT"),
+    (FunctionGroup::Inet, "IP_FORMAT"),
+    (FunctionGroup::Packet, "TCP_FLAGS"),
+    (FunctionGroup::Oui, "OUI_DB_SOURCE"),
+    (FunctionGroup::ClickHouse, "IPv4NumToString"),
+    (FunctionGroup::Siem, "CIDRMATCH"),
+];
+
+/// Formats sample addresses in each style, checking each parses back to the same address
+fn check_mac_parsing() -> Result<serde_json::Value, String> {
+    let samples = [[0x3c, 0xa6, 0xf6, 0xc4, 0x34, 0xf8], [0; 6], [0xff; 6], [0x02, 0x00, 0x5e, 0x10, 0x00, 0x01]];
+    let mut checked = 0;
+    for mac in samples.map(MacAddr::new) {
+        // the interface-id and link-local styles give EUI-64 derived addresses, rather than the MAC itself
+        for (name, style) in MacStyle::NAMES.iter().filter(|(_, s)| !matches!(s, MacStyle::InterfaceId | MacStyle::LinkLocal)) {
+            for uppercase in [false, true] {
+                let text = style.format(mac, uppercase);
+                match text.parse::<MacAddr>() {
+                    Ok(parsed) if parsed == mac => checked += 1,
+                    Ok(parsed) => return Err(format!("{mac} formatted as {name} ({:?}) parsed back as {parsed}", text.as_str())),
+                    Err(e) => return Err(format!("{mac} formatted as {name} ({:?}) failed to parse: {e}", text.as_str())),
+                }
+            }
+        }
+    }
+    Ok(serde_json::json!({ "round_trips": checked }))
+}
+
+/// Formats sample addresses and networks, checking each parses back to the same value
+fn check_ip_parsing() -> Result<serde_json::Value, String> {
+    let samples = ["192.0.2.1", "0.0.0.0", "10.0.0.0/8", "::", "::ffff:192.0.2.1", "2001:db8::1", "fe80::/10", "2001:db8:0:1::/64"];
+    for sample in samples {
+        let parsed: UserNetAddr = sample.parse().map_err(|e| format!("{sample:?} failed to parse: {e}"))?;
+        let text = parsed.to_string();
+        if text != sample {
+            return Err(format!("{sample:?} was formatted as {text:?}"));
+        }
+    }
+    Ok(serde_json::json!({ "round_trips": samples.len() }))
+}
+
+/// Checks each layer of the connection's OUI database holds entries, and that its first, middle, and last prefixes are
+/// found by lookups
+fn check_oui_db(oui_db: &SharedOuiDb) -> Result<serde_json::Value, String> {
+    let layers = oui_db.load();
+    let mut report = Vec::new();
+    for layer in layers.layers() {
+        let provenance = layer.provenance();
+        let len = layer.db.len();
+        if len == 0 {
+            return Err(format!("the {provenance} layer is empty"));
+        }
+        for i in [0, len / 2, len - 1] {
+            let (prefix, _) = layer.db.entry(i).expect("index within the layer");
+            // a higher layer may hold a longer (or the same) prefix, which is found instead
+            match layers.search_entry(prefix.first()) {
+                Some((found, _)) if found.contains_mac(prefix.first()) => {},
+                _ => return Err(format!("{prefix} from the {provenance} layer is not found by lookups")),
+            }
+        }
+        report.push(serde_json::json!({ "layer": provenance, "entries": len }));
+    }
+    Ok(serde_json::Value::from(report))
+}
+
+/// Checks every chosen group's functions and collations are registered on the connection, under their chosen names
+fn check_registration(
+    reg: &Registration,
+    collisions: &SharedCollisions,
+    ctx: &rusqlite::functions::Context<'_>,
+) -> Result<serde_json::Value, String> {
+    // SAFETY: the connection is only used to run read-only queries, and is not closed or otherwise reconfigured
+    let conn = unsafe { ctx.get_connection() }.map_err(|e| e.to_string())?;
+    let exists = |sql: &str, name: &str| -> Result<bool, String> {
+        conn.query_row(sql, [name], |r| r.get(0)).map_err(|e| e.to_string())
+    };
+
+    let mut groups = Vec::new();
+    for (group, function) in GROUP_SENTINELS.iter().filter(|(g, _)| reg.includes(*g)) {
+        let function = reg.name(function);
+        if !exists("SELECT EXISTS (SELECT 1 FROM pragma_function_list WHERE name = lower(?1))", &function)? {
+            return Err(format!("{function} (from the {group} group) is not registered"));
+        }
+        groups.push(group.to_string());
+    }
+    let mut collations = Vec::new();
+    if reg.includes(FunctionGroup::Mac) {
+        let collation = reg.name("MACADDR");
+        if !exists("SELECT EXISTS (SELECT 1 FROM pragma_collation_list WHERE name = ?1)", &collation)? {
+            return Err(format!("the {collation} collation is not registered"));
+        }
+        collations.push(collation);
+    }
+    let collisions: Vec<serde_json::Value> = collisions.collisions().into_iter()
+        .map(|c| match c.resolution {
+            Resolution::Overwritten => serde_json::json!({ "name": c.name, "n_arg": c.n_arg, "resolution": "overwritten" }),
+            Resolution::Skipped => serde_json::json!({ "name": c.name, "n_arg": c.n_arg, "resolution": "skipped" }),
+            Resolution::Renamed(registered) => serde_json::json!({
+                "name": c.name, "n_arg": c.n_arg, "resolution": "renamed", "registered_as": registered,
+            }),
+        })
+        .collect();
+    Ok(serde_json::json!({ "groups": groups, "collations": collations, "collisions": collisions }))
+}
+
+/// Checks the features needing more than the extension itself are usable: that embedded packs hold entries, and that
+/// a DNS server is known for lookups
+#[cfg_attr(not(feature = "dns"), allow(unused_variables))]
+fn check_features(settings: &SharedSettings) -> Result<serde_json::Value, String> {
+    let mut report = serde_json::Map::new();
+    for pack in packs::embedded() {
+        if pack.entries == 0 {
+            return Err(format!("the embedded {} pack is empty", pack.name));
+        }
+        report.insert(pack.name.to_owned(), serde_json::json!({ "entries": pack.entries }));
+    }
+    #[cfg(feature = "dns")]
+    {
+        let server = match settings.load().dns_server {
+            Some(server) => server,
+            None => crate::dns::Resolver::system().map_err(|e| format!("no DNS server is available: {e}"))?.server(),
+        };
+        report.insert("dns".to_owned(), serde_json::json!({ "server": server.to_string() }));
+    }
+    Ok(serde_json::Value::Object(report))
+}
+
+/// # NETTOOLS_SELFTEST() -> json
+/// Runs a battery of checks on the extension as loaded on this connection, returning a JSON report. Run this after
+/// loading the extension in a new environment to confirm it is working.
+///
+/// The report contains the extension's `version`, whether every check passed as `ok`, and the `checks` themselves,
+/// each with its `name`, whether it passed as `ok`, and either its `detail` or the `error` it failed with:
+/// * `mac_parsing` - MAC addresses formatted in each style parse back to the same address
+/// * `ip_parsing` - IP addresses and networks formatted as text parse back to the same value
+/// * `oui_db` - Each layer of the connection's OUI database (see [OUI_LOAD](crate::exports::oui::load)) holds entries,
+///   and lookups find them
+/// * `registration` - Each chosen function group, and the `MACADDR` collation, is registered under its chosen name.
+///   Its detail lists the `collisions` with functions already registered under the same name and number of arguments
+///   (`n_arg`), such as by another extension, and their `resolution`: `overwritten`, `skipped`, or `renamed` (along
+///   with the name it was `registered_as`). These are chosen by the `NETTOOLS_ON_CONFLICT` environment variable, or
+///   [Registration::on_conflict].
+/// * `features` - Each embedded data pack holds entries, and with the `dns` feature, a DNS server is known
+///
+/// Failed checks are reported, rather than raised as errors. As this inspects the connection, it is
+/// [direct-only](crate::exports#direct-only-functions).
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`NETTOOLS_SELFTEST() ->> '$.ok'`                 | `1` |
+/// |`NETTOOLS_SELFTEST() ->> '$.checks[0].name'`     | `'mac_parsing'` |
+/// |`NETTOOLS_SELFTEST() ->> '$.checks[3].detail.collisions'` | `'[]'` |
+/// |`SELECT value ->> 'error' FROM json_each(NETTOOLS_SELFTEST(), '$.checks') WHERE NOT value ->> 'ok'` | (no rows) |
+pub fn selftest(
+    oui_db: &SharedOuiDb,
+    settings: &SharedSettings,
+    reg: &Registration,
+    collisions: &SharedCollisions,
+    ctx: &rusqlite::functions::Context<'_>,
+) -> rusqlite::Result<String> {
+    let checks = [
+        ("mac_parsing", check_mac_parsing()),
+        ("ip_parsing", check_ip_parsing()),
+        ("oui_db", check_oui_db(oui_db)),
+        ("registration", check_registration(reg, collisions, ctx)),
+        ("features", check_features(settings)),
+    ];
+
+    let ok = checks.iter().all(|(_, result)| result.is_ok());
+    let checks: Vec<serde_json::Value> = checks.into_iter()
+        .map(|(name, result)| match result {
+            Ok(detail) => serde_json::json!({ "name": name, "ok": true, "detail": detail }),
+            Err(error) => serde_json::json!({ "name": name, "ok": false, "error": error }),
+        })
+        .collect();
+    Ok(serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "ok": ok,
+        "checks": checks,
+    }).to_string())
+}
+
+#[test]
+fn selftest_report() {
+    use crate::RegistrationOptions;
+
+    let conn = rusqlite::Connection::open_in_memory().unwrap();
+    let reg = Registration::builder().group(FunctionGroup::Mac).group(FunctionGroup::Oui).prefix("nt_");
+    crate::register_with(&conn, RegistrationOptions { registration: reg, ..Default::default() }).unwrap();
+    let report: String = conn.query_row("SELECT nt_NETTOOLS_SELFTEST()", [], |r| r.get(0)).unwrap();
+    let report: serde_json::Value = serde_json::from_str(&report).unwrap();
+    // the features check depends on the environment (such as having a DNS server), so only the others are asserted
+    assert!(report["checks"].as_array().unwrap().iter().filter(|c| c["name"] != "features").all(|c| c["ok"] == true), "{report}");
+    assert_eq!(report["checks"][3]["detail"]["groups"], serde_json::json!(["mac", "oui"]));
+    assert_eq!(report["checks"][3]["detail"]["collations"], serde_json::json!(["nt_MACADDR"]));
+    assert_eq!(report["checks"][3]["detail"]["collisions"], serde_json::json!([]));
+
+    // a layer that can't be found by lookups fails its check, without raising an error
+    assert!(check_oui_db(&SharedOuiDb::new(std::sync::Arc::new(OuiDb::from_entries::<&str>(Vec::new())))).is_err());
+}
+
+/// # NETTOOLS_STATS() -> json
+/// Returns a JSON object describing how this connection's functions have been used, to find where a slow query's time
+/// is going. Counts start from when the functions were registered, or from the last
+/// [NETTOOLS_STATS_RESET](crate::exports::info::stats_reset).
+///
+/// The object contains:
+/// * `functions` - An array of each function called at least once, slowest first, with its `name`, number of `calls`,
+///   the number of calls raising `errors` (such as for arguments that couldn't be parsed), and the total `time_us`
+///   spent within it, in microseconds. Each row given to an aggregate function counts as a call. Functions are only
+///   counted after `NETTOOLS_SET('stats', 1)` (see [NETTOOLS_SET](crate::exports::settings::set)), so this is empty
+///   until then.
+/// * `oui_cache` - How many OUI lookups (such as by [MAC_MANUF](crate::exports::mac::manuf)) were answered from the
+///   connection's cache of recent lookups as `hits`, and how many searched the database as `misses`
+/// * `rdap_cache` - With the `rdap` feature, the `hits` and `misses` of [RDAP](crate::exports::rdap::rdap)'s response
+///   cache. As the cache is shared by the whole process, so are these, which are counted from when it started.
+///
+/// Virtual tables and table-valued functions (such as `oui_prefixes` and `netflow_file`) aren't counted. DNS lookups
+/// (such as by `DNSBL_CHECK`) aren't cached, so have no cache counts; their time is counted with their function's.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`NETTOOLS_STATS() ->> '$.functions[0].name'`     | `'MAC_MANUF'` |
+/// |`NETTOOLS_STATS() ->> '$.oui_cache.hits'`        | `48213` |
+/// |`SELECT value ->> 'name', value ->> 'errors' FROM json_each(NETTOOLS_STATS(), '$.functions')` | `'MAC_FORMAT'`, `12` |
+pub fn stats(stats: &SharedStats, oui_db: &SharedOuiDb, _ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<String> {
+    let mut functions = stats.functions();
+    functions.sort_by_key(|f| std::cmp::Reverse(f.time));
+    let functions: Vec<serde_json::Value> = functions.iter()
+        .map(|f| serde_json::json!({ "name": f.name, "calls": f.calls, "errors": f.errors, "time_us": f.time.as_micros() as u64 }))
+        .collect();
+
+    let oui_cache = oui_db.load().cache_stats();
+    #[allow(unused_mut)]
+    let mut report = serde_json::json!({
+        "functions": functions,
+        "oui_cache": { "hits": oui_cache.hits, "misses": oui_cache.misses },
+    });
+    #[cfg(feature = "rdap")]
+    {
+        let (hits, misses) = crate::exports::rdap::cache_stats();
+        report["rdap_cache"] = serde_json::json!({ "hits": hits, "misses": misses });
+    }
+    Ok(report.to_string())
+}
+
+/// # NETTOOLS_STATS_RESET() -> NULL
+/// Zeroes the counts reported by [NETTOOLS_STATS](crate::exports::info::stats) for this connection, such as before
+/// running a query to be measured. The process-wide `rdap_cache` counts are left as they are.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`NETTOOLS_STATS_RESET()` | `NULL` |
+pub fn stats_reset(stats: &SharedStats, oui_db: &SharedOuiDb, _ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<i64>> {
+    stats.reset();
+    oui_db.load().reset_cache_stats();
+    Ok(None)
+}
+
+#[test]
+fn stats_report() {
+    let conn = conn();
+    conn.query_row("SELECT NETTOOLS_SET('stats', 1)", [], |_| Ok(())).unwrap();
+    let json = |sql: &str| -> serde_json::Value {
+        serde_json::from_str(&conn.query_row(sql, [], |r| r.get::<_, String>(0)).unwrap()).unwrap()
+    };
+
+    let run = |sql: &str| conn.prepare(sql).unwrap().query_map([], |_| Ok(())).unwrap().count();
+    run("SELECT MAC_MANUF('3c:a6:f6:c4:34:f8') FROM (VALUES (1), (2), (3))");
+    run("SELECT IP_ROLLUP(column1, 24) FROM (VALUES ('10.0.0.1'), ('10.0.0.2'))");
+    assert!(conn.query_row("SELECT MAC_FORMAT('bogus', 'colon')", [], |_| Ok(())).is_err());
+
+    let stats = json("SELECT NETTOOLS_STATS()");
+    let function = |name: &str| stats["functions"].as_array().unwrap().iter().find(|f| f["name"] == name).cloned().unwrap();
+    assert_eq!(function("MAC_MANUF")["calls"], 3);
+    assert_eq!(function("IP_ROLLUP")["calls"], 2);
+    assert_eq!((function("MAC_FORMAT")["calls"].clone(), function("MAC_FORMAT")["errors"].clone()), (1.into(), 1.into()));
+    assert_eq!((stats["oui_cache"]["hits"].clone(), stats["oui_cache"]["misses"].clone()), (2.into(), 1.into()));
+
+    conn.query_row("SELECT NETTOOLS_STATS_RESET()", [], |_| Ok(())).unwrap();
+    let stats = json("SELECT NETTOOLS_STATS()");
+    // calls are counted as they return, so only the reset itself remains
+    assert_eq!(stats["functions"][0]["name"], "NETTOOLS_STATS_RESET");
+    assert_eq!(stats["functions"].as_array().unwrap().len(), 1);
+    assert_eq!(stats["oui_cache"]["hits"], 0);
+
+    // once disabled, calls are no longer counted
+    conn.query_row("SELECT NETTOOLS_SET('stats', 0)", [], |_| Ok(())).unwrap();
+    run("SELECT MAC_MANUF('3c:a6:f6:c4:34:f8')");
+    let stats = json("SELECT NETTOOLS_STATS()");
+    assert!(stats["functions"].as_array().unwrap().iter().all(|f| f["name"] != "MAC_MANUF"), "{stats}");
+}
@@ -1,36 +1,103 @@
+use std::{cmp::Ordering, fmt::Write};
+
+use rusqlite::types::ValueRef;
 use smallstr::SmallString;
 
 use crate::{
-    mac::MacStyle,
-    oui::{Oui, OuiMeta},
+    exports::error::{arg, json_array_arg, text_arg, ArgError, ElementError},
+    mac::{Eui, FormatSpec, FormatSpecError, FormatStyle, MacAddr, MacStyle},
+    oui::{Oui, OuiLayers, OuiMeta, ParseMacError, SharedOuiDb},
+    settings::SharedSettings,
+    text::{SqlText, TextBuf},
 };
+#[cfg(test)]
+use crate::test_util::{conn, query_err, query_text};
 
 #[derive(thiserror::Error, Debug)]
 enum MacFormatError {
-    #[error("Mixed case format specifier is not allowed. Input case is used to determine output casing.")]
-    MixedCaseFmtSpecifier,
-    #[error("Bad format specifier provided (got {0:?}). Omit format specifier, or provide one of the following: (NULL, `hex`, `hexstring`), `hexadecimal`, `bare`, `dot`, `canonical`, `interface-id`, `link-local`)")]
-    BadFmtSpecifier(String),
+    #[error(transparent)]
+    Spec(#[from] FormatSpecError),
+    #[error("Bad EUI-64 mode provided (got {0:?}). Omit the mode, or provide one of the following: (NULL, `eui64`), `modified`")]
+    BadEui64Mode(String),
+    #[error("Bad overflow mode provided (got {0:?}). Omit the mode, or provide one of the following: (NULL, `error`), `wrap`, `null`")]
+    BadOverflowMode(String),
+    #[error("MAC address arithmetic overflowed: {0} offset by {1}")]
+    ArithmeticOverflow(String, i128),
+    #[error("Cannot compare a 48-bit MAC address with a 64-bit EUI ({0} and {1})")]
+    MixedWidths(String, String),
+    #[error("Wake-on-LAN packets can only be generated for 48-bit MAC addresses (got {0})")]
+    WolEui64(String),
+    #[error("Bad Wake-on-LAN SecureOn password. Expected 4 or 6 bytes, as hexadecimal text (`aa:bb:cc:dd:ee:ff`) or a blob (got {0:?})")]
+    BadWolPassword(String),
+    #[error("EUI-64 addresses cannot be represented as an SQLite integer. Use MAC_BLOBIFY for compact storage instead.")]
+    Eui64Integer,
+    #[error("Bad MAC match pattern (got {0:?}). Expected hex digits, `?` for any single digit, and an optional trailing `*` for any remaining digits, such as `aa:bb:cc:*`")]
+    BadMatchPattern(String),
+    #[error("Bucket prefix length must be between 0 and {1} bits for this address (got {0})")]
+    BucketBits(i64, usize),
+    #[error("Format template {0:?} has {1} digits, which does not fit {2}")]
+    TemplateWidth(String, usize, String),
+    #[error("Bad UUID (got {0:?}). Expected 32 hex digits, such as `c232ab00-9414-11ec-b3c8-9f6bdeced846`, or a 16-byte blob")]
+    BadUuid(String),
 }
 
-fn find_mac(
-    ctx: &rusqlite::functions::Context<'_>,
-) -> rusqlite::Result<Option<(Oui, OuiMeta<&'static str>)>> {
-    let Some(s) = ctx.get_raw(0).as_str_or_null()? else { return Ok(None); };
-    if s.is_empty() {
+/// Converts an SQLite value into a MAC address or EUI-64.
+///
+/// Accepts text in any of the formats supported by [parse_eui](crate::oui::parse_eui), 6-byte MAC blobs,
+/// 8-byte EUI-64 blobs, and 48-bit integers.
+fn mac_value(value: ValueRef<'_>) -> Result<Option<Eui>, ParseMacError> {
+    match value {
+        ValueRef::Null => Ok(None),
+        ValueRef::Text(_) => {
+            let s = value.as_str().map_err(|e| ParseMacError::UnsupportedType(e.to_string()))?;
+            crate::oui::parse_eui(s).map(Some)
+        },
+        ValueRef::Blob(b) => match *b {
+            [a, b, c, d, e, f] => Ok(Some(Eui::Mac48(MacAddr::new([a, b, c, d, e, f])))),
+            [a, b, c, d, e, f, g, h] => Ok(Some(Eui::Eui64([a, b, c, d, e, f, g, h]))),
+            _ => Err(ParseMacError::InvalidBlob(b.to_vec())),
+        },
+        ValueRef::Integer(i) => {
+            let oui = u64::try_from(i).ok().and_then(|u| Oui::from_int(u).ok())
+                .ok_or(ParseMacError::InvalidInteger(i))?;
+            Ok(Some(Eui::Mac48(oui.as_mac())))
+        },
+        ValueRef::Real(r) => Err(ParseMacError::UnsupportedType(r.to_string())),
+    }
+}
+
+/// Pulls a MAC address or EUI-64 out of the argument at `idx`. Empty strings are treated as NULL.
+pub(crate) fn mac_arg(ctx: &rusqlite::functions::Context<'_>, idx: usize) -> rusqlite::Result<Option<Eui>> {
+    let value = ctx.get_raw(idx);
+    if let ValueRef::Text(b"") = value {
         return Ok(None);
     }
-    let mac = crate::oui::parse_mac_addr(s)
-        .map_err(|e| rusqlite::Error::UserFunctionError(Box::new(e)))?;
+    mac_value(value).map_err(|e| ArgError::invalid(ctx, idx, e))
+}
 
-    Ok(crate::oui::EMBEDDED_DB.search_entry(mac))
+/// Looks up the OUI database entry for the MAC address in the first argument, and maps it with `f`.
+fn find_mac<T>(
+    oui_db: &SharedOuiDb,
+    ctx: &rusqlite::functions::Context<'_>,
+    f: impl FnOnce(Oui, OuiMeta<&str>) -> Option<T>,
+) -> rusqlite::Result<Option<T>> {
+    let Some(mac) = mac_arg(ctx, 0)? else { return Ok(None); };
+
+    let db = oui_db.load();
+    Ok(db.search_entry(mac.oui_key()).and_then(|(o, om)| f(o, om)))
 }
 
 /// # MAC_FORMAT(NULL|mac, \[NULL|fmt]) -> NULL|mac'
 /// Formats a MAC address into a normalized form. Uses `hexstring` format by default.
 ///
+/// EUI-64 addresses are formatted with the same styles, extended to 8 bytes. The `interface-id` and `link-local` styles
+/// use an EUI-64 as-is (with the universal/local bit flipped), rather than inserting `ff:fe`.
+///
 /// The casing of the format string determines the casing of the output. Mixed-case output is not supported.
 ///
+/// To use the connection's `mac_style` setting in place of a format, see
+/// [MAC_FORMAT_DEFAULT](crate::exports::mac::format_default).
+///
 /// Note that prefixing the fmt string with a tilde `~` will make the function use the `hex` format
 /// when a format is not otherwise found. This can be used to prevent query errors for an invalid
 /// format type.
@@ -41,6 +108,10 @@ fn find_mac(
 ///
 /// The format and MAC address validation flags `~`/`?` can be intermixed, and they can be repeated. (Additional flags have no effect)
 ///
+/// Besides the named styles, a custom template can be provided, where each `x` is replaced by one hex digit of the address
+/// and all other characters are kept as-is. Templates hold exactly 12 digits for MAC addresses, or 16 digits for EUI-64s.
+/// Formatting an address of the other width raises a query error.
+///
 /// # Usage
 /// |Call|Result|
 /// |-|-|
@@ -57,71 +128,822 @@ fn find_mac(
 /// |`MAC_FORMAT('aa-bb-cc-dd-ee-ff', 'canonical')`    | `'aa-bb-cc-dd-ee-ff'` |
 /// |`MAC_FORMAT('aa-bb-cc-dd-ee-ff', 'interface-id')` | `'a8bb:ccff:fedd:eeff'` |
 /// |`MAC_FORMAT('aa-bb-cc-dd-ee-ff', 'link-local')`   | `'fe80::a8bb:ccff:fedd:eeff'` |
+/// |`MAC_FORMAT('aabb.ccdd.eeff.0011', 'dash')`       | `'aa-bb-cc-dd-ee-ff-00-11'` |
+/// |`MAC_FORMAT('aabb.ccdd.eeff.0011', 'link-local')` | `'fe80::a8bb:ccdd:eeff:0011'` |
+/// |`MAC_FORMAT('aa-bb-cc-dd-ee-ff', 'XXXX-XXXX-XXXX')` | `'AABB-CCDD-EEFF'` |
+/// |`MAC_FORMAT('aa-bb-cc-dd-ee-ff', 'xxx.xxx.xxx.xxx')` | `'aab.bcc.dde.eff'` |
 /// |`MAC_FORMAT('aa-bb-cc-dd-ee-ff', 'de$H')`         | N/A - A query error is raised with an appropriate error message |
 /// |`MAC_FORMAT('aa-bb-cc-dd-ee-ff', '~de$H')`        | `'aa:bb:cc:dd:ee:ff'` |
 /// |`MAC_FORMAT('a!-bbkcc-dd2ee-ff', '?dash')`        | `NULL` |
 /// |`MAC_FORMAT('a!-bbcc-dd2ee-ff', '?~')`            | `NULL` |
-pub fn format(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
-    let mac_str = if let Some(s) = ctx.get_raw(0).as_str_or_null()? {
-        s
-    } else { return Ok(None); };
-
-    let mut raw_fmt = (ctx.len() == 2)
-        .then(|| ctx.get_raw(1).as_str_or_null())
-        .transpose()?
-        .flatten();
-    let mut has_upper = false;
-    let mut use_default_on_bad_fmt = false;
-    let mut ret_null_on_bad_mac = false;
-    if let Some(fmt) = raw_fmt.as_mut() {
-        loop {
-            if fmt.starts_with('~') {
-                *fmt = &fmt[1..];
-                use_default_on_bad_fmt = true;
-            } else if fmt.starts_with('?') {
-                *fmt = &fmt[1..];
-                ret_null_on_bad_mac = true;
-            } else {
-                break;
+pub fn format(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<SqlText<SmallString<[u8; 32]>>>> {
+    if let ValueRef::Null = ctx.get_raw(0) {
+        return Ok(None);
+    }
+    let spec = format_spec(ctx, 1)?;
+    format_mac(ctx, &spec, 1)
+}
+
+/// # MAC_FORMAT_DEFAULT(NULL|mac) -> NULL|mac'
+/// Formats a MAC address as [MAC_FORMAT](crate::exports::mac::format) does, using the connection's `mac_style` setting
+/// (see [NETTOOLS_SET](crate::exports::settings::set)) as the format, or `hex` when unset.
+///
+/// As the result depends on the setting, this isn't deterministic, and can't be used in indexes or generated columns.
+/// Pass the format to `MAC_FORMAT` explicitly there instead.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_FORMAT_DEFAULT('aa-bb-cc-dd-ee-ff')`                                     | `'aa:bb:cc:dd:ee:ff'` |
+/// |`MAC_FORMAT_DEFAULT('aa-bb-cc-dd-ee-ff')`, after `NETTOOLS_SET('mac_style', 'DASH')` | `'AA-BB-CC-DD-EE-FF'` |
+pub fn format_default(settings: &SharedSettings, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<SqlText<SmallString<[u8; 32]>>>> {
+    if let ValueRef::Null = ctx.get_raw(0) {
+        return Ok(None);
+    }
+    let spec = match &settings.load().mac_style {
+        // checked by NETTOOLS_SET
+        Some(style) => style.parse::<FormatSpec>().map_err(|e| ArgError::invalid(ctx, 0, e))?,
+        None => FormatSpec::default(),
+    };
+    format_mac(ctx, &spec, 0)
+}
+
+/// Formats the address at index 0 with a format specifier, reporting a template of the wrong width against `fmt_idx`.
+fn format_mac(ctx: &rusqlite::functions::Context<'_>, spec: &FormatSpec, fmt_idx: usize) -> rusqlite::Result<Option<SqlText<SmallString<[u8; 32]>>>> {
+    let mac = match mac_value(ctx.get_raw(0)) {
+        Ok(m) => m,
+        Err(_) if spec.null_on_bad_mac => return Ok(None),
+        Err(e) => return Err(ArgError::invalid(ctx, 0, e)),
+    };
+    let Some(mac) = mac else { return Ok(None); };
+
+    Ok(Some(SqlText(format_with(spec, mac).map_err(|e| ArgError::invalid(ctx, fmt_idx, e))?)))
+}
+
+/// Formats an address according to a format specifier, raising an error for a template of the wrong width.
+fn format_with(spec: &FormatSpec, mac: Eui) -> Result<SmallString<[u8; 32]>, MacFormatError> {
+    spec.format(mac).ok_or_else(|| match &spec.style {
+        FormatStyle::Template(tmpl) => MacFormatError::TemplateWidth(
+            tmpl.as_str().to_string(),
+            tmpl.digits(),
+            MacStyle::Colon.format_eui(mac, false).to_string(),
+        ),
+        FormatStyle::Named(_) => unreachable!("named styles format addresses of either width"),
+    })
+}
+
+/// Parses the optional format specifier at `idx`, as documented on [MAC_FORMAT](crate::exports::mac::format).
+fn format_spec(ctx: &rusqlite::functions::Context<'_>, idx: usize) -> rusqlite::Result<FormatSpec> {
+    match text_arg(ctx, idx)? {
+        Some(spec) => spec.parse::<FormatSpec>().map_err(|e| ArgError::invalid(ctx, idx, e)),
+        None => Ok(FormatSpec::default()),
+    }
+}
+
+/// Finds why text isn't a MAC address or EUI-64 accepted by the `MAC_` functions, as a short reason listed by
+/// [MAC_VALIDATE](crate::exports::mac::validate), along with the 1-based position of the offending character (when
+/// there is one). Returns None when the text is valid.
+pub fn mac_problem(s: &str) -> Option<(&'static str, Option<usize>)> {
+    if crate::oui::parse_eui(s).is_ok() {
+        return None;
+    }
+    if s.is_empty() {
+        return Some(("empty", None));
+    }
+
+    // mirror the parser: a leading `0x`, then hex digits and separators
+    let skipped = if s.starts_with("0x") { 2 } else { 0 };
+    let mut digits = 0;
+    for (pos, c) in s.chars().enumerate().skip(skipped).map(|(i, c)| (i + 1, c)) {
+        if c.is_ascii_hexdigit() {
+            digits += 1;
+            // the first digit which can't belong to either width
+            if digits == 17 {
+                return Some(("too-many-digits", Some(pos)));
             }
-        }
-        has_upper = fmt.contains(|c: char| c.is_ascii_uppercase());
-        let has_lower = fmt.contains(|c: char| c.is_ascii_lowercase());
-        if has_upper && has_lower && !use_default_on_bad_fmt {
-            return Err(rusqlite::Error::UserFunctionError(Box::new(
-                MacFormatError::MixedCaseFmtSpecifier,
-            )));
+        } else if !matches!(c, '-' | '.' | ':') {
+            return Some(("bad-character", Some(pos)));
         }
     }
 
-    let mut style = MacStyle::Colon;
-    if let Some(fmt) = raw_fmt {
-        let mut fmt = SmallString::<[u8; 16]>::from_str(fmt);
-        fmt.make_ascii_lowercase();
-        style = match fmt.as_str() {
-            "" | "hex" | "hexstring" | "colon" => MacStyle::Colon,
-            "hexadecimal" => MacStyle::Prefixed,
-            "bare" => MacStyle::Plain,
-            "dot" => MacStyle::Dots,
-            "dash" | "canonical" => MacStyle::Dashed,
-            "interface-id" => MacStyle::InterfaceId,
-            "link-local" => MacStyle::LinkLocal,
-            _ if use_default_on_bad_fmt => style, // passthru default
-            _ => {
-                return Err(rusqlite::Error::UserFunctionError(Box::new(
-                    MacFormatError::BadFmtSpecifier(raw_fmt.unwrap().to_string()),
-                )))
+    match digits {
+        ..=11 => Some(("too-few-digits", None)),
+        13..=15 => {
+            let pos = s.chars().enumerate().skip(skipped).filter(|(_, c)| c.is_ascii_hexdigit()).nth(12).map(|(i, _)| i + 1);
+            Some(("too-many-digits", pos))
+        },
+        _ => Some(("invalid", None)),
+    }
+}
+
+/// # MAC_VALIDATE(NULL|mac) -> NULL|text
+/// Checks a MAC address or EUI-64 without raising an error, returning NULL when it is valid, or a short reason why it
+/// isn't. Reasons about a particular character are followed by its position, counting from 1, as in
+/// `'bad-character:9'`. Useful for finding the rows to clean up before converting a column, such as
+/// `SELECT mac, MAC_VALIDATE(mac) FROM devices WHERE MAC_VALIDATE(mac) IS NOT NULL`.
+///
+/// Values are valid if accepted by functions such as [MAC_FORMAT](crate::exports::mac::format): text in any of the
+/// supported formats, 6 or 8 byte blobs, and 48-bit integers.
+///
+/// |Reason|Meaning|
+/// |-|-|
+/// |`empty`|Empty text, which the other functions treat as NULL|
+/// |`bad-character:N`|A character that is neither a hex digit nor a separator (`-`, `.`, `:`)|
+/// |`too-many-digits:N`|More than 12 hex digits (or 16, for an EUI-64), at the first digit too many|
+/// |`too-few-digits`|Fewer than 12 hex digits|
+/// |`bad-blob-length`|A blob that isn't 6 or 8 bytes|
+/// |`out-of-range`|An integer that is negative, or over 48 bits|
+/// |`wrong-type`|A real number|
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_VALIDATE('aa-bb-cc-dd-ee-ff')`   | `NULL` |
+/// |`MAC_VALIDATE('aa-bb-cc-dd-ee-fg')`   | `'bad-character:17'` |
+/// |`MAC_VALIDATE('aa-bb-cc-dd-ee-ff-0')` | `'too-many-digits:19'` |
+/// |`MAC_VALIDATE('aa-bb-cc')`            | `'too-few-digits'` |
+/// |`MAC_VALIDATE(X'AABBCC')`             | `'bad-blob-length'` |
+/// |`MAC_VALIDATE(NULL)`                  | `NULL` |
+pub fn validate(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let problem = match ctx.get_raw(0) {
+        ValueRef::Text(t) => match std::str::from_utf8(t) {
+            Ok(s) => mac_problem(s),
+            Err(e) => Some(("bad-character", Some(String::from_utf8_lossy(&t[..e.valid_up_to()]).chars().count() + 1))),
+        },
+        value => match mac_value(value) {
+            Ok(_) => None,
+            Err(ParseMacError::InvalidBlob(_)) => Some(("bad-blob-length", None)),
+            Err(ParseMacError::InvalidInteger(_)) => Some(("out-of-range", None)),
+            Err(_) => Some(("wrong-type", None)),
+        },
+    };
+
+    Ok(problem.map(|(reason, pos)| match pos {
+        Some(pos) => format!("{reason}:{pos}"),
+        None => reason.to_owned(),
+    }))
+}
+
+/// # MAC_BLOBIFY(NULL|mac) -> NULL|blob
+/// Converts a MAC address into a 6-byte blob, or an EUI-64 into an 8-byte blob.
+///
+/// This has two primary uses:
+/// * Sorting addresses sequentially
+/// * Storing addresses compactly
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_BLOBIFY('aa-bb-cc-dd-ee-ff')` | `X'AABBCCDDEEFF'` |
+/// |`MAC_BLOBIFY('aa-bb-cc-dd-ee-ff-00-11')` | `X'AABBCCDDEEFF0011'` |
+/// |`MAC_BLOBIFY(NULL)`                | `NULL` |
+pub fn blobify(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<Vec<u8>>> {
+    let Some(mac) = mac_arg(ctx, 0)? else { return Ok(None); };
+
+    Ok(Some(mac.as_bytes().to_vec()))
+}
+
+/// # MAC_FROM_BLOB(NULL|blob, \[NULL|fmt]) -> NULL|mac
+/// Converts a 6-byte (or 8-byte EUI-64) blob, as produced by [MAC_BLOBIFY](crate::exports::mac::blobify), back into a formatted address.
+///
+/// The optional format argument accepts the same specifiers and flags as [MAC_FORMAT](crate::exports::mac::format).
+/// With the `?` flag, blobs of the wrong length produce NULL instead of an error.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_FROM_BLOB(X'AABBCCDDEEFF')`         | `'aa:bb:cc:dd:ee:ff'` |
+/// |`MAC_FROM_BLOB(X'AABBCCDDEEFF', 'DASH')` | `'AA-BB-CC-DD-EE-FF'` |
+/// |`MAC_FROM_BLOB(X'AABBCC', '?dash')`      | `NULL` |
+/// |`MAC_FROM_BLOB(X'AABBCC')`               | N/A - A query error is raised with an appropriate error message |
+pub fn from_blob(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<SqlText<SmallString<[u8; 32]>>>> {
+    let Some(blob) = ctx.get_raw(0).as_blob_or_null().map_err(|_| ArgError::invalid(ctx, 0, "expected a blob"))? else {
+        return Ok(None);
+    };
+
+    let spec = format_spec(ctx, 1)?;
+
+    let mac = match mac_value(ValueRef::Blob(blob)) {
+        Ok(Some(mac)) => mac,
+        Ok(None) => return Ok(None),
+        Err(_) if spec.null_on_bad_mac => return Ok(None),
+        Err(e) => return Err(ArgError::invalid(ctx, 0, e)),
+    };
+
+    Ok(Some(SqlText(format_with(&spec, mac).map_err(|e| ArgError::invalid(ctx, 1, e))?)))
+}
+
+/// # MAC_ASINT(NULL|mac) -> NULL|int
+/// Converts a MAC address into its 48-bit integer value.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_ASINT('aa-bb-cc-dd-ee-ff')` | `187723572702975` |
+/// |`MAC_ASINT('00:00:00:00:01:00')` | `256` |
+/// |`MAC_ASINT(NULL)`                | `NULL` |
+pub fn as_int(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<i64>> {
+    let mac = match mac_arg(ctx, 0)? {
+        None => return Ok(None),
+        Some(Eui::Mac48(mac)) => mac,
+        Some(Eui::Eui64(_)) => return Err(ArgError::invalid(ctx, 0, MacFormatError::Eui64Integer)),
+    };
+
+    Ok(Some(Oui::from_addr(mac).as_int() as i64))
+}
+
+/// # MAC_FROMINT(NULL|int, \[NULL|fmt]) -> NULL|mac
+/// Converts a 48-bit integer (as produced by [MAC_ASINT](crate::exports::mac::as_int)) back into a formatted MAC address.
+///
+/// The optional format argument accepts the same specifiers and flags as [MAC_FORMAT](crate::exports::mac::format).
+/// With the `?` flag, integers outside of the 48-bit range produce NULL instead of an error.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_FROMINT(187723572702975)`         | `'aa:bb:cc:dd:ee:ff'` |
+/// |`MAC_FROMINT(256, 'dot')`              | `'0000.0000.0100'` |
+/// |`MAC_FROMINT(-1, '?')`                 | `NULL` |
+/// |`MAC_FROMINT(281474976710656)`         | N/A - A query error is raised with an appropriate error message |
+pub fn from_int(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<SqlText<SmallString<[u8; 32]>>>> {
+    let Some(int): Option<i64> = arg(ctx, 0)? else { return Ok(None); };
+
+    let spec = format_spec(ctx, 1)?;
+
+    let mac = match mac_value(ValueRef::Integer(int)) {
+        Ok(m) => m,
+        Err(_) if spec.null_on_bad_mac => return Ok(None),
+        Err(e) => return Err(ArgError::invalid(ctx, 0, e)),
+    };
+    let Some(mac) = mac else { return Ok(None); };
+
+    Ok(Some(SqlText(format_with(&spec, mac).map_err(|e| ArgError::invalid(ctx, 1, e))?)))
+}
+
+/// Parses the optional EUI-64 conversion mode at `idx`. Returns true for the modified (IPv6 interface identifier) form.
+fn eui64_mode(ctx: &rusqlite::functions::Context<'_>, idx: usize) -> rusqlite::Result<bool> {
+    match text_arg(ctx, idx)? {
+        None => Ok(false),
+        Some(m) if m.is_empty() || m.eq_ignore_ascii_case("eui64") => Ok(false),
+        Some(m) if m.eq_ignore_ascii_case("modified") => Ok(true),
+        Some(m) => Err(ArgError::invalid(ctx, idx, MacFormatError::BadEui64Mode(m.to_owned()))),
+    }
+}
+
+/// # MAC_TO_EUI64(NULL|mac, \[NULL|mode]) -> NULL|eui64
+/// Converts a MAC address into a 64-bit EUI by inserting `ff:fe` between the OUI and device bytes.
+///
+/// With the `modified` mode, the universal/local bit is also flipped, producing the modified EUI-64 used as an IPv6 interface identifier.
+/// EUI-64 input is returned as-is (with the universal/local bit flipped for the `modified` mode).
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_TO_EUI64('aa-bb-cc-dd-ee-ff')`             | `'aa:bb:cc:ff:fe:dd:ee:ff'` |
+/// |`MAC_TO_EUI64('aa-bb-cc-dd-ee-ff', 'modified')` | `'a8:bb:cc:ff:fe:dd:ee:ff'` |
+/// |`MAC_TO_EUI64(NULL)`                            | `NULL` |
+pub fn to_eui64(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let Some(mac) = mac_arg(ctx, 0)? else { return Ok(None); };
+    let modified = eui64_mode(ctx, 1)?;
+
+    let eui = match mac {
+        Eui::Mac48(mac) => crate::mac::eui48_to_eui64(mac.to_array(), modified),
+        Eui::Eui64(mut eui) => {
+            if modified {
+                eui[0] ^= 0x02;
             }
+            eui
+        },
+    };
+
+    Ok(Some(MacStyle::Colon.format_eui64(eui, false).to_string()))
+}
+
+/// # LINKLOCAL_MATCHES_MAC(NULL|ip, NULL|mac) -> NULL|BOOL
+/// Returns true if an IPv6 link-local address was derived from the MAC address, with its interface identifier being the
+/// MAC's modified EUI-64 (with `ff:fe` inserted and the universal/local bit flipped), as in SLAAC.
+///
+/// This allows cross-checking neighbor discovery tables against switch CAM tables, where a mismatch may be a spoofed
+/// entry. 64-bit EUIs are compared as-is, with the universal/local bit flipped. Addresses outside of `fe80::/10` (including
+/// IPv4 addresses) are never a match, nor are interface identifiers chosen another way (such as privacy addresses).
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`LINKLOCAL_MATCHES_MAC('fe80::a8bb:ccff:fedd:eeff', 'aa-bb-cc-dd-ee-ff')` | `TRUE` |
+/// |`LINKLOCAL_MATCHES_MAC('fe80::aabb:ccff:fedd:eeff', 'aa-bb-cc-dd-ee-ff')` | `FALSE` - The u/l bit wasn't flipped |
+/// |`LINKLOCAL_MATCHES_MAC('2001:db8::a8bb:ccff:fedd:eeff', 'aa-bb-cc-dd-ee-ff')` | `FALSE` |
+/// |`LINKLOCAL_MATCHES_MAC('fe80::1', NULL)` | `NULL` |
+pub fn linklocal_matches(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<bool>> {
+    let Some(addr) = crate::exports::inet::address_arg(ctx, 0)? else { return Ok(None); };
+    let Some(mac) = mac_arg(ctx, 1)? else { return Ok(None); };
+
+    let std::net::IpAddr::V6(addr) = addr else { return Ok(Some(false)); };
+    if addr.segments()[0] & 0xffc0 != 0xfe80 {
+        return Ok(Some(false));
+    }
+
+    let interface_id = match mac {
+        Eui::Mac48(mac) => crate::mac::eui48_to_eui64(mac.to_array(), true),
+        Eui::Eui64(mut eui) => {
+            eui[0] ^= 0x02;
+            eui
+        },
+    };
+    Ok(Some(addr.octets()[8..] == interface_id))
+}
+
+/// # MAC_FROM_EUI64(NULL|eui64, \[NULL|mode]) -> NULL|mac
+/// Converts a 64-bit EUI back into the MAC address it was derived from. This is the inverse of [MAC_TO_EUI64](crate::exports::mac::to_eui64).
+///
+/// Returns NULL if the EUI-64 does not contain the `ff:fe` infix, as it was then not derived from a MAC address.
+/// MAC address input is returned as-is.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_FROM_EUI64('aa:bb:cc:ff:fe:dd:ee:ff')`             | `'aa:bb:cc:dd:ee:ff'` |
+/// |`MAC_FROM_EUI64('a8:bb:cc:ff:fe:dd:ee:ff', 'modified')` | `'aa:bb:cc:dd:ee:ff'` |
+/// |`MAC_FROM_EUI64('aa:bb:cc:dd:ee:ff:00:11')`             | `NULL` |
+pub fn from_eui64(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let Some(eui) = mac_arg(ctx, 0)? else { return Ok(None); };
+    let modified = eui64_mode(ctx, 1)?;
+
+    let mac = match eui {
+        Eui::Mac48(mac) => mac,
+        Eui::Eui64(eui) => match crate::mac::eui64_to_eui48(eui, modified) {
+            Some(mac) => MacAddr::new(mac),
+            None => return Ok(None),
+        },
+    };
+
+    Ok(Some(MacStyle::Colon.format(mac, false).to_string()))
+}
+
+/// Parses a UUID's text form: 32 hex digits, either bare or hyphenated as `8-4-4-4-12`, optionally within braces or
+/// after a `urn:uuid:` prefix.
+fn parse_uuid(s: &str) -> Option<[u8; 16]> {
+    let s = s.trim();
+    let s = match s.get(..9) {
+        Some(urn) if urn.eq_ignore_ascii_case("urn:uuid:") => &s[9..],
+        _ => s.strip_prefix('{').and_then(|s| s.strip_suffix('}')).unwrap_or(s),
+    };
+    let hex: SmallString<[u8; 32]> = match s.len() {
+        32 => s.into(),
+        36 if [8, 13, 18, 23].iter().all(|&i| s.as_bytes()[i] == b'-') => s.chars().filter(|&c| c != '-').collect(),
+        _ => return None,
+    };
+    // from_str_radix would also accept a sign
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let mut uuid = [0u8; 16];
+    for (i, byte) in uuid.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(uuid)
+}
+
+/// # UUID_MAC(NULL|uuid, \[NULL|fmt]) -> NULL|mac
+/// Returns the MAC address recorded in the node field of a version 1 (time-based) UUID, as generated from the host's
+/// network interface. This allows attributing devices by their UUIDs with the other MAC functions, such as
+/// [MAC_MANUF](crate::exports::mac::manuf).
+///
+/// UUIDs may be given as text (hyphenated or not, optionally within braces or after `urn:uuid:`), or as 16-byte blobs.
+/// The optional format argument accepts the same specifiers and flags as [MAC_FORMAT](crate::exports::mac::format).
+/// To use the connection's `mac_style` setting instead, pass the result to
+/// [MAC_FORMAT_DEFAULT](crate::exports::mac::format_default).
+///
+/// Returns NULL for other UUID versions and variants, and for version 1 UUIDs whose node was chosen randomly instead
+/// (marked by setting the multicast bit, per RFC 9562).
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`UUID_MAC('c232ab00-9414-11ec-b3c8-3ca6f6c434f8')`                     | `'3c:a6:f6:c4:34:f8'` |
+/// |`MAC_MANUF(UUID_MAC('c232ab00-9414-11ec-b3c8-3ca6f6c434f8'))`          | `'Apple'` |
+/// |`UUID_MAC(X'C232AB00941411ECB3C83CA6F6C434F8')`                        | `'3c:a6:f6:c4:34:f8'` |
+/// |`UUID_MAC('c232ab00-9414-11ec-b3c8-3ca6f6c434f8', 'dot')`              | `'3ca6.f6c4.34f8'` |
+/// |`UUID_MAC('c232ab00-9414-11ec-b3c8-9f6bdeced846')`                     | `NULL` - The node is random |
+/// |`UUID_MAC('f81d4fae-7dec-41d0-a765-00a0c91e6bf6')`                     | `NULL` - A version 4 UUID |
+/// |`UUID_MAC('c232ab00-9414')`                                            | N/A - A query error is raised with an appropriate error message |
+pub fn uuid_mac(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<SqlText<SmallString<[u8; 32]>>>> {
+    let uuid = match ctx.get_raw(0) {
+        ValueRef::Null => return Ok(None),
+        ValueRef::Text(t) => {
+            let t = String::from_utf8_lossy(t);
+            parse_uuid(&t).ok_or_else(|| ArgError::invalid(ctx, 0, MacFormatError::BadUuid(t.into_owned())))?
+        },
+        ValueRef::Blob(b) => <[u8; 16]>::try_from(b)
+            .map_err(|_| ArgError::invalid(ctx, 0, MacFormatError::BadUuid(format!("X'{}'", b.iter().map(|b| format!("{b:02X}")).collect::<String>()))))?,
+        _ => return Err(ArgError::invalid(ctx, 0, "expected UUID text or a blob")),
+    };
+
+    // version 1 of the RFC 9562 (formerly RFC 4122) variant
+    if uuid[6] >> 4 != 1 || uuid[8] & 0xc0 != 0x80 {
+        return Ok(None);
+    }
+    let node: [u8; 6] = uuid[10..].try_into().unwrap();
+    if node[0] & 0x01 != 0 {
+        return Ok(None);
+    }
+
+    let spec = format_spec(ctx, 1)?;
+    let mac = format_with(&spec, Eui::Mac48(MacAddr::new(node))).map_err(|e| ArgError::invalid(ctx, 1, e))?;
+    Ok(Some(SqlText(mac)))
+}
+
+/// How MAC arithmetic should behave when the result leaves the address space.
+#[derive(Clone, Copy)]
+enum Overflow {
+    Error,
+    Wrap,
+    Null,
+}
+
+/// Parses the optional overflow mode at `idx`, defaulting to raising an error.
+fn overflow_mode(ctx: &rusqlite::functions::Context<'_>, idx: usize) -> rusqlite::Result<Overflow> {
+    let mode = text_arg(ctx, idx)?;
+    match mode.map(|m| m.to_ascii_lowercase()).as_deref() {
+        None | Some("") | Some("error") => Ok(Overflow::Error),
+        Some("wrap") => Ok(Overflow::Wrap),
+        Some("null") => Ok(Overflow::Null),
+        Some(_) => Err(ArgError::invalid(ctx, idx, MacFormatError::BadOverflowMode(mode.unwrap().to_owned()))),
+    }
+}
+
+/// Offsets an address by `delta`, staying within the address's own width (48 or 64 bits).
+fn offset_mac(mac: Eui, delta: i128, overflow: Overflow) -> Result<Option<Eui>, MacFormatError> {
+    let (value, bits) = match mac {
+        Eui::Mac48(m) => (Oui::from_addr(m).as_int() as i128, 48),
+        Eui::Eui64(e) => (u64::from_be_bytes(e) as i128, 64),
+    };
+    let modulus = 1i128 << bits;
+
+    let mut result = value + delta;
+    if !(0..modulus).contains(&result) {
+        match overflow {
+            Overflow::Wrap => result = result.rem_euclid(modulus),
+            Overflow::Null => return Ok(None),
+            Overflow::Error => return Err(
+                MacFormatError::ArithmeticOverflow(MacStyle::Colon.format_eui(mac, false).to_string(), delta),
+            ),
+        }
+    }
+
+    Ok(Some(match mac {
+        Eui::Mac48(_) => Eui::Mac48(Oui::from_int(result as u64).unwrap().as_mac()),
+        Eui::Eui64(_) => Eui::Eui64((result as u64).to_be_bytes()),
+    }))
+}
+
+fn offset_body(ctx: &rusqlite::functions::Context<'_>, negate: bool) -> rusqlite::Result<Option<String>> {
+    let Some(mac) = mac_arg(ctx, 0)? else { return Ok(None); };
+    let Some(delta): Option<i64> = arg(ctx, 1)? else { return Ok(None); };
+    let overflow = overflow_mode(ctx, 2)?;
+
+    let delta = if negate { -(delta as i128) } else { delta as i128 };
+
+    Ok(offset_mac(mac, delta, overflow).map_err(|e| ArgError::invalid(ctx, 1, e))?
+        .map(|m| MacStyle::Colon.format_eui(m, false).to_string()))
+}
+
+/// # MAC_ADD(NULL|mac, NULL|offset, \[NULL|overflow]) -> NULL|mac
+/// Adds an integer offset to a MAC address (or EUI-64), returning the result in `hexstring` format.
+///
+/// The optional overflow mode determines what happens when the result leaves the address space:
+/// * `error` (default) - a query error is raised
+/// * `wrap` - the result wraps around, modulo the address width
+/// * `null` - NULL is returned
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_ADD('aa:bb:cc:00:00:10', 5)`               | `'aa:bb:cc:00:00:15'` |
+/// |`MAC_ADD('aa:bb:cc:00:00:10', -0x11)`           | `'aa:bb:cb:ff:ff:ff'` |
+/// |`MAC_ADD('ff:ff:ff:ff:ff:ff', 1, 'wrap')`       | `'00:00:00:00:00:00'` |
+/// |`MAC_ADD('ff:ff:ff:ff:ff:ff', 1, 'null')`       | `NULL` |
+/// |`MAC_ADD('ff:ff:ff:ff:ff:ff', 1)`               | N/A - A query error is raised with an appropriate error message |
+pub fn add(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    offset_body(ctx, false)
+}
+
+/// # MAC_SUB(NULL|mac, NULL|offset, \[NULL|overflow]) -> NULL|mac
+/// Subtracts an integer offset from a MAC address (or EUI-64), returning the result in `hexstring` format.
+///
+/// Accepts the same overflow modes as [MAC_ADD](crate::exports::mac::add).
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_SUB('aa:bb:cc:00:00:10', 5)`               | `'aa:bb:cc:00:00:0b'` |
+/// |`MAC_SUB('00:00:00:00:00:00', 1, 'wrap')`       | `'ff:ff:ff:ff:ff:ff'` |
+pub fn sub(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    offset_body(ctx, true)
+}
+
+/// Ensures two addresses are of the same width, so that they may be compared numerically. `b` is the argument at `idx`.
+fn same_width(ctx: &rusqlite::functions::Context<'_>, idx: usize, a: Eui, b: Eui) -> rusqlite::Result<()> {
+    if a.is_eui64() != b.is_eui64() {
+        return Err(ArgError::invalid(ctx, idx, MacFormatError::MixedWidths(
+            MacStyle::Colon.format_eui(a, false).to_string(),
+            MacStyle::Colon.format_eui(b, false).to_string(),
+        )));
+    }
+    Ok(())
+}
+
+/// # MAC_BETWEEN(NULL|mac, NULL|start, NULL|end) -> NULL|BOOL
+/// Returns true if the MAC address is numerically within the inclusive range `[start, end]`, regardless of the formatting of each argument.
+///
+/// All three addresses must be the same width - either all MAC addresses, or all EUI-64s.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_BETWEEN('aa-bb-cc-00-00-15', 'aa:bb:cc:00:00:10', 'aabb.cc00.001f')` | `TRUE` |
+/// |`MAC_BETWEEN('aa-bb-cc-00-00-20', 'aa:bb:cc:00:00:10', 'aabb.cc00.001f')` | `FALSE` |
+/// |`MAC_BETWEEN('aa-bb-cc-00-00-10', 'aa:bb:cc:00:00:10', 'AABBCC00001F')`   | `TRUE` |
+pub fn between(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<bool>> {
+    let Some(mac) = mac_arg(ctx, 0)? else { return Ok(None); };
+    let Some(start) = mac_arg(ctx, 1)? else { return Ok(None); };
+    let Some(end) = mac_arg(ctx, 2)? else { return Ok(None); };
+    same_width(ctx, 1, mac, start)?;
+    same_width(ctx, 2, mac, end)?;
+
+    Ok(Some((start.as_u64()..=end.as_u64()).contains(&mac.as_u64())))
+}
+
+/// # MAC_EQ(NULL|a, NULL|b) -> NULL|BOOL
+/// Returns true if both arguments are the same address, regardless of the formatting or casing of each argument.
+///
+/// A MAC address is never equal to an EUI-64, even one derived from it.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_EQ('aa-bb-cc-dd-ee-ff', 'AABB.CCDD.EEFF')`    | `TRUE` |
+/// |`MAC_EQ('aa-bb-cc-dd-ee-ff', X'AABBCCDDEEFF')`     | `TRUE` |
+/// |`MAC_EQ('aa-bb-cc-dd-ee-ff', 'aa:bb:cc:dd:ee:00')` | `FALSE` |
+/// |`MAC_EQ('aa-bb-cc-dd-ee-ff', NULL)`                | `NULL` |
+pub fn eq(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<bool>> {
+    let Some(a) = mac_arg(ctx, 0)? else { return Ok(None); };
+    let Some(b) = mac_arg(ctx, 1)? else { return Ok(None); };
+
+    Ok(Some(a == b))
+}
+
+/// # MAC_CMP(NULL|a, NULL|b) -> NULL|int
+/// Compares two addresses numerically, regardless of the formatting or casing of each argument.
+/// Returns `-1`, `0`, or `1` if `a` is less than, equal to, or greater than `b`, respectively.
+///
+/// As with the `MACADDR` collation, MAC addresses sort before EUI-64s.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_CMP('aa-bb-cc-dd-ee-ff', 'AABB.CCDD.EEFF')`          | `0` |
+/// |`MAC_CMP('aa:bb:cc:dd:ee:0f', 'aa:bb:cc:dd:ee:f0')`       | `-1` |
+/// |`MAC_CMP('ff:ff:ff:ff:ff:ff', '00:00:00:00:00:00:00:00')` | `-1` |
+/// |`MAC_CMP(NULL, 'aa:bb:cc:dd:ee:ff')`                      | `NULL` |
+pub fn cmp(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<i64>> {
+    let Some(a) = mac_arg(ctx, 0)? else { return Ok(None); };
+    let Some(b) = mac_arg(ctx, 1)? else { return Ok(None); };
+
+    Ok(Some(a.cmp(&b) as i64))
+}
+
+/// A parsed MAC_MATCH wildcard pattern: each nibble is either a required hex digit, or None for any digit.
+struct MatchPattern {
+    nibbles: Vec<Option<u8>>,
+    open_ended: bool,
+}
+
+impl MatchPattern {
+    /// Parses a pattern such as `aa:bb:c?:*`. Separators (`:`, `-`, `.`) are ignored, and `*` may only be the last character.
+    fn parse(pattern: &str) -> Option<MatchPattern> {
+        let (body, open_ended) = match pattern.strip_suffix('*') {
+            Some(body) => (body, true),
+            None => (pattern, false),
         };
+
+        let mut nibbles = Vec::with_capacity(16);
+        for c in body.chars() {
+            match c {
+                ':' | '-' | '.' => {},
+                '?' => nibbles.push(None),
+                c => nibbles.push(Some(c.to_digit(16)? as u8)),
+            }
+            if nibbles.len() > 16 {
+                return None;
+            }
+        }
+
+        // patterns without a wildcard suffix must describe an entire address
+        if !open_ended && !matches!(nibbles.len(), 12 | 16) {
+            return None;
+        }
+        Some(MatchPattern { nibbles, open_ended })
     }
 
-    let mac = match crate::oui::parse_mac_addr(mac_str) {
-        Ok(m) => m,
-        Err(_) if ret_null_on_bad_mac => return Ok(None),
-        Err(e) => return Err(rusqlite::Error::UserFunctionError(Box::new(e))),
+    fn matches(&self, mac: Eui) -> bool {
+        let bytes = mac.as_bytes();
+        let digits = bytes.len() * 2;
+        if self.nibbles.len() > digits || (!self.open_ended && self.nibbles.len() != digits) {
+            return false;
+        }
+
+        self.nibbles.iter().enumerate().all(|(i, nib)| {
+            let digit = (bytes[i / 2] >> (4 * (1 - i % 2))) & 0xf;
+            nib.is_none_or(|n| n == digit)
+        })
+    }
+}
+
+/// # MAC_MATCH(NULL|mac, NULL|pattern, \[NULL|mask]) -> NULL|BOOL
+/// Matches a MAC address (or EUI-64) against a wildcard pattern, or against an address and bitmask.
+///
+/// With two arguments, the pattern consists of hex digits, `?` to match any single digit, and an optional trailing `*`
+/// to match any remaining digits. Separators (`:`, `-`, `.`) within the pattern are ignored, so patterns match
+/// addresses regardless of their formatting. Patterns without a trailing `*` must cover the entire address.
+///
+/// With three arguments, the pattern is an address, and the MAC matches if all bits set in the mask are equal in
+/// both addresses. All three addresses must be the same width.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_MATCH('aa-bb-cc-dd-ee-ff', 'aa:bb:cc:*')`                                   | `TRUE` |
+/// |`MAC_MATCH('aabb.ccdd.eeff', 'AA:BB:C?:DD:EE:??')`                               | `TRUE` |
+/// |`MAC_MATCH('aa-bb-cc-dd-ee-ff', 'aa:bb:cd:*')`                                   | `FALSE` |
+/// |`MAC_MATCH('aa-bb-cc-dd-ee-ff', 'aa:bb:cc:00:00:00', 'ff:ff:ff:00:00:00')`       | `TRUE` |
+/// |`MAC_MATCH('ab-bb-cc-dd-ee-ff', '01:00:00:00:00:00', '01:00:00:00:00:00')`       | `TRUE` |
+/// |`MAC_MATCH('aa-bb-cc-dd-ee-ff', 'aa:bb:*:cc')`                                   | N/A - A query error is raised with an appropriate error message |
+pub fn match_mac(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<bool>> {
+    let Some(mac) = mac_arg(ctx, 0)? else { return Ok(None); };
+
+    if ctx.len() > 2 {
+        let Some(value) = mac_arg(ctx, 1)? else { return Ok(None); };
+        let Some(mask) = mac_arg(ctx, 2)? else { return Ok(None); };
+        same_width(ctx, 1, mac, value)?;
+        same_width(ctx, 2, mac, mask)?;
+
+        let mask = mask.as_u64();
+        return Ok(Some(mac.as_u64() & mask == value.as_u64() & mask));
+    }
+
+    let Some(pattern) = text_arg(ctx, 1)? else { return Ok(None); };
+    let Some(pattern) = MatchPattern::parse(pattern) else {
+        return Err(ArgError::invalid(ctx, 1, MacFormatError::BadMatchPattern(pattern.to_owned())));
+    };
+
+    Ok(Some(pattern.matches(mac)))
+}
+
+#[test]
+fn match_patterns() {
+    let mac = Eui::Mac48(MacAddr::new([0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff]));
+    let eui = Eui::Eui64([0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff, 0x00, 0x11]);
+    let matches = |pat: &str, m: Eui| MatchPattern::parse(pat).unwrap().matches(m);
+
+    assert!(matches("*", mac));
+    assert!(matches("aa:bb:cc:*", mac));
+    assert!(matches("aa:bb:cc:*", eui));
+    assert!(matches("a?bb.ccdd.ee??", mac));
+    assert!(!matches("a?bb.ccdd.ee??", eui));
+    assert!(matches("aabbccddeeff0011", eui));
+    assert!(!matches("aabbccddeeff0011*", mac));
+
+    assert!(MatchPattern::parse("aa:bb:*:cc").is_none());
+    assert!(MatchPattern::parse("aa:bb:cc").is_none());
+    assert!(MatchPattern::parse("aa:bb:cc:dd:ee:ff:00:11:22*").is_none());
+}
+
+/// Zeroes all bits of `bytes` after the first `bits` bits.
+fn truncate_bits(bytes: &mut [u8], bits: usize) {
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        let keep = bits.saturating_sub(8 * i).min(8);
+        *byte &= !(0xffu16 >> keep) as u8;
+    }
+}
+
+/// # MAC_BUCKET(NULL|mac, NULL|bits) -> NULL|prefix
+/// Truncates a MAC address (or EUI-64) to its first `bits` bits, returning the prefix in CIDR style (`hexstring` format,
+/// with the remaining bits zeroed). Unlike [MAC_PREFIX](crate::exports::mac::prefix), this does not consult the OUI
+/// database, and always uses the same notation, which makes it suitable for `GROUP BY` rollups.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_BUCKET('aa-bb-cc-dd-ee-ff', 24)`                                 | `'aa:bb:cc:00:00:00/24'` |
+/// |`MAC_BUCKET('aa-bb-cc-dd-ee-ff', 36)`                                 | `'aa:bb:cc:dd:e0:00/36'` |
+/// |`MAC_BUCKET('aa-bb-cc-dd-ee-ff', 0)`                                  | `'00:00:00:00:00:00/0'` |
+/// |`MAC_BUCKET('aa-bb-cc-dd-ee-ff-00-11', 52)`                           | `'aa:bb:cc:dd:ee:ff:00:00/52'` |
+/// |`MAC_BUCKET('aa-bb-cc-dd-ee-ff', 49)`                                 | N/A - A query error is raised with an appropriate error message |
+pub fn bucket(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<SqlText<TextBuf<28>>>> {
+    let Some(mac) = mac_arg(ctx, 0)? else { return Ok(None); };
+    let Some(bits): Option<i64> = arg(ctx, 1)? else { return Ok(None); };
+
+    let width = mac.as_bytes().len() * 8;
+    if !(0..=width as i64).contains(&bits) {
+        return Err(ArgError::invalid(ctx, 1, MacFormatError::BucketBits(bits, width)));
+    }
+
+    let truncated = match mac {
+        Eui::Mac48(m) => {
+            let mut bytes: [u8; 6] = m.to_array();
+            truncate_bits(&mut bytes, bits as usize);
+            Eui::Mac48(MacAddr::new(bytes))
+        },
+        Eui::Eui64(mut bytes) => {
+            truncate_bits(&mut bytes, bits as usize);
+            Eui::Eui64(bytes)
+        },
     };
 
-    Ok(Some(style.format(mac, has_upper).to_string()))
+    let mut text = TextBuf::new();
+    write!(text, "{}/{}", MacStyle::Colon.format_eui(truncated, false), bits).unwrap();
+    Ok(Some(SqlText(text)))
+}
+
+#[test]
+fn bit_truncation() {
+    let mut bytes = [0xff; 6];
+    truncate_bits(&mut bytes, 36);
+    assert_eq!(bytes, [0xff, 0xff, 0xff, 0xff, 0xf0, 0x00]);
+
+    let mut bytes = [0xff; 6];
+    truncate_bits(&mut bytes, 0);
+    assert_eq!(bytes, [0x00; 6]);
+
+    let mut bytes = [0xff; 8];
+    truncate_bits(&mut bytes, 63);
+    assert_eq!(bytes, [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xfe]);
+}
+
+/// Pulls a 4 or 6 byte SecureOn password out of the argument at `idx`, as either a blob or hexadecimal text.
+fn wol_password(ctx: &rusqlite::functions::Context<'_>, idx: usize) -> rusqlite::Result<Option<Vec<u8>>> {
+    if ctx.len() <= idx {
+        return Ok(None);
+    }
+    let bad_password = |s: String| ArgError::invalid(ctx, idx, MacFormatError::BadWolPassword(s));
+
+    let bytes = match ctx.get_raw(idx) {
+        ValueRef::Null => return Ok(None),
+        ValueRef::Blob(b) => b.to_vec(),
+        ValueRef::Text(_) => {
+            let s = text_arg(ctx, idx)?.unwrap_or_default();
+            let digits: String = s.chars().filter(|c| !matches!(c, '-' | '.' | ':')).collect();
+            if !digits.len().is_multiple_of(2) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(bad_password(s.to_owned()));
+            }
+            (0..digits.len()).step_by(2)
+                .map(|i| u8::from_str_radix(&digits[i..i+2], 16).unwrap())
+                .collect()
+        },
+        _ => return Err(ArgError::invalid(ctx, idx, "expected text or a blob")),
+    };
+
+    match bytes.len() {
+        4 | 6 => Ok(Some(bytes)),
+        _ => Err(bad_password(format!("{bytes:x?}"))),
+    }
+}
+
+/// # WOL_PACKET(NULL|mac, \[NULL|password]) -> NULL|blob
+/// Generates the Wake-on-LAN magic packet for a MAC address, as a blob.
+///
+/// The packet is 6 bytes of `ff`, followed by the MAC address repeated 16 times, for 102 bytes in total. If a
+/// SecureOn password is provided (4 or 6 bytes, as hexadecimal text or a blob), it is appended to the packet.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`length(WOL_PACKET('aa-bb-cc-dd-ee-ff'))`                      | `102` |
+/// |`length(WOL_PACKET('aa-bb-cc-dd-ee-ff', '11:22:33:44:55:66'))` | `108` |
+/// |`substr(hex(WOL_PACKET('aa-bb-cc-dd-ee-ff')), 1, 24)`          | `'FFFFFFFFFFFFAABBCCDDEEFF'` |
+/// |`WOL_PACKET(NULL)`                                             | `NULL` |
+pub fn wol_packet(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<Vec<u8>>> {
+    let mac = match mac_arg(ctx, 0)? {
+        None => return Ok(None),
+        Some(Eui::Mac48(mac)) => mac,
+        Some(eui @ Eui::Eui64(_)) => return Err(ArgError::invalid(ctx, 0,
+            MacFormatError::WolEui64(MacStyle::Colon.format_eui(eui, false).to_string()),
+        )),
+    };
+    let password = wol_password(ctx, 1)?;
+
+    let mut packet = Vec::with_capacity(108);
+    packet.extend_from_slice(&[0xff; 6]);
+    for _ in 0..16 {
+        packet.extend_from_slice(mac.as_bytes());
+    }
+    if let Some(password) = password {
+        packet.extend_from_slice(&password);
+    }
+
+    Ok(Some(packet))
 }
 
 /// # MAC_PREFIX(NULL|mac) -> NULL|oui
@@ -134,10 +956,14 @@ pub fn format(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option
 /// |-|-|
 /// |`MAC_PREFIX('3c-a6-f6-c4-34-f8')` | `'aa:bb:cc'`|
 /// |`MAC_PREFIX('8c-1c-da-82-4c-2e')` | `'8c:1c:da:80:00:00/28'`|
-/// |`MAC_PREFIX('33-33-00-00-00-01')` | `NULL`  |
-pub fn prefix(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
-    let mac = find_mac(ctx)?;
-    Ok(mac.map(|(oui, _om)| format!("{oui:?}")))
+/// |`MAC_PREFIX('33-33-00-00-00-01')` | `'33:33:00:00:00:00/16'` - A well-known multicast range, with the `wka-db` feature |
+/// |`MAC_PREFIX('02-00-00-00-00-01')` | `NULL`  |
+pub fn prefix(oui_db: &SharedOuiDb, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<SqlText<TextBuf<24>>>> {
+    find_mac(oui_db, ctx, |oui, _om| {
+        let mut text = TextBuf::new();
+        write!(text, "{oui:?}").unwrap();
+        Some(SqlText(text))
+    })
 }
 
 /// # MAC_MANUF(NULL|mac) -> NULL|manuf
@@ -148,10 +974,245 @@ pub fn prefix(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option
 /// |-|-|
 /// |`MAC_MANUF('3c-a6-f6-c4-34-f8')` | `'Apple'`|
 /// |`MAC_MANUF('8c-1c-da-82-4c-2e')` | `'Atol'` |
-/// |`MAC_MANUF('33-33-00-00-00-01')` |  `NULL`  |
-pub fn manuf(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<&'static str>> {
-    let mac = find_mac(ctx)?;
-    Ok(mac.map(|(_o, om)| *om.manuf()))
+/// |`MAC_MANUF('33-33-00-00-00-01')` | `'IPv6mcast'` - With the `wka-db` feature |
+/// |`MAC_MANUF('ff-ff-ff-ff-ff-ff')` | `'Broadcast'` - With the `wka-db` feature |
+/// |`MAC_MANUF('02-00-00-00-00-01')` |  `NULL`  |
+pub fn manuf(oui_db: &SharedOuiDb, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    find_mac(oui_db, ctx, |_o, om| Some(om.manuf().to_string()))
+}
+
+/// # MAC_MANUF_ALL(NULL|json_array) -> NULL|json_array
+/// Looks up the short manufacturer name of each MAC address in a JSON array, as with [MAC_MANUF](crate::exports::mac::manuf),
+/// returning a JSON array of the names in the same order. Elements may be text or 48-bit integers. NULL elements and
+/// unregistered addresses give `null`, while malformed addresses are an error naming their position.
+///
+/// # Usage:
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_MANUF_ALL('["3c-a6-f6-c4-34-f8", "02-00-00-00-00-01", null]')` | `'["Apple",null,null]'`|
+/// |`MAC_MANUF_ALL('[]')` | `'[]'`|
+pub fn manuf_all(oui_db: &SharedOuiDb, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let Some(elems) = json_array_arg(ctx, 0)? else { return Ok(None); };
+
+    let db = oui_db.load();
+    let names = elems.iter().enumerate().map(|(i, elem)| {
+        let mac = match elem {
+            serde_json::Value::Null => Ok(None),
+            serde_json::Value::String(s) if s.is_empty() => Ok(None),
+            serde_json::Value::String(s) => mac_value(ValueRef::Text(s.as_bytes())),
+            serde_json::Value::Number(n) => match n.as_i64() {
+                Some(n) => mac_value(ValueRef::Integer(n)),
+                None => Err(ParseMacError::UnsupportedType(n.to_string())),
+            },
+            other => Err(ParseMacError::UnsupportedType(other.to_string())),
+        };
+        let mac = mac.map_err(|e| ArgError::invalid(ctx, 0, ElementError::new(i, e)))?;
+
+        let manuf = mac.and_then(|mac| db.search_entry(mac.oui_key()));
+        Ok(manuf.map_or(serde_json::Value::Null, |(_o, om)| om.manuf().to_string().into()))
+    }).collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(Some(serde_json::Value::Array(names).to_string()))
+}
+
+/// Legal entity suffixes stripped from manufacturer names, in lowercase. Longer forms come first, so that `co., ltd` is
+/// stripped whole rather than leaving `co.,` behind. Trailing dots are trimmed before matching, so aren't listed.
+const MANUF_SUFFIXES: &[&str] = &[
+    "co., ltd", "co.,ltd", "co ltd", "co.ltd", "pty ltd", "pte ltd", "sdn bhd",
+    "incorporated", "corporation", "company", "limited", "gmbh & co. kg", "gmbh & co kg",
+    "inc", "corp", "co", "ltd", "llc", "l.l.c", "plc", "gmbh", "ag", "kg", "s.a", "sa", "s.p.a", "spa", "s.r.l", "srl",
+    "s.a.s", "sas", "b.v", "bv", "n.v", "nv", "oy", "oyj", "ab", "a/s", "as", "k.k", "kk", "pty", "pte",
+];
+
+/// Canonical names for vendors appearing under differing names across OUI sources, keyed by their lowercase name after
+/// suffixes are stripped.
+const MANUF_ALIASES: &[(&str, &str)] = &[
+    ("apple computer", "Apple"),
+    ("cisco systems", "Cisco"),
+    ("cisco-linksys", "Linksys"),
+    ("dell technologies", "Dell"),
+    ("hewlett packard", "Hewlett Packard"),
+    ("hewlett-packard", "Hewlett Packard"),
+    ("huawei technologies", "Huawei"),
+    ("huawei device", "Huawei"),
+    ("intel corporate", "Intel"),
+    ("juniper networks", "Juniper"),
+    ("microsoft", "Microsoft"),
+    ("samsung electronics", "Samsung"),
+    ("sony", "Sony"),
+    ("tp-link technologies", "TP-Link"),
+    ("tp-link", "TP-Link"),
+    ("zte", "ZTE"),
+];
+
+/// Canonicalizes a manufacturer name, so variants of the same vendor's name compare equal. Legal entity suffixes are
+/// stripped, known aliases are replaced with a canonical name, and names written entirely in capitals are title cased
+/// (keeping words of up to three letters, which are usually acronyms, in capitals).
+fn normalize_manuf(name: &str) -> String {
+    let mut name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+    loop {
+        let trimmed = name.trim_end_matches([' ', ',', '.']);
+        let lower = trimmed.to_ascii_lowercase();
+        let stripped = MANUF_SUFFIXES.iter().find_map(|suffix| {
+            let rest = lower.strip_suffix(suffix)?;
+            // only strip whole words, and never the whole name
+            (rest.ends_with([' ', ',', '.']) && !rest.trim_end_matches([' ', ',', '.']).is_empty())
+                .then(|| trimmed[..rest.len()].to_owned())
+        });
+        match stripped {
+            Some(rest) => name = rest,
+            None => {
+                name.truncate(trimmed.len());
+                break;
+            },
+        }
+    }
+
+    let lower = name.to_ascii_lowercase();
+    if let Some((_, canonical)) = MANUF_ALIASES.iter().find(|(alias, _)| *alias == lower) {
+        return canonical.to_string();
+    }
+
+    if name.chars().any(char::is_alphabetic) && !name.chars().any(char::is_lowercase) {
+        name = name.split(' ')
+            .map(|word| match word.chars().filter(|c| c.is_alphabetic()).count() {
+                0..=3 => word.to_owned(),
+                _ => {
+                    let mut chars = word.chars();
+                    chars.next().into_iter().chain(chars.flat_map(char::to_lowercase)).collect()
+                },
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+    }
+    name
+}
+
+/// # MANUF_NORMALIZE(NULL|manuf) -> NULL|manuf
+/// Canonicalizes a manufacturer name, so that reports grouped by vendor don't split across naming variants, such as
+/// when mixing names from the embedded database with those from IEEE registry files or other tools.
+///
+/// Legal entity suffixes (`Inc.`, `Co., Ltd.`, `GmbH`, and the like) are stripped, names of well-known vendors
+/// appearing under several names are replaced with one, and names written entirely in capitals are title cased, with
+/// words of up to three letters kept as acronyms.
+///
+/// # Usage:
+/// |Call|Result|
+/// |-|-|
+/// |`MANUF_NORMALIZE('Apple, Inc.')` | `'Apple'`|
+/// |`MANUF_NORMALIZE('APPLE')` | `'Apple'`|
+/// |`MANUF_NORMALIZE('Cisco Systems, Inc')` | `'Cisco'`|
+/// |`MANUF_NORMALIZE('HUAWEI TECHNOLOGIES CO.,LTD')` | `'Huawei'`|
+/// |`MANUF_NORMALIZE('NETGEAR')` | `'Netgear'`|
+/// |`MANUF_NORMALIZE(MAC_MANUFLONG('3c-a6-f6-c4-34-f8'))` | `'Apple'`|
+pub fn manuf_normalize(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let Some(name) = text_arg(ctx, 0)? else { return Ok(None); };
+    Ok(Some(normalize_manuf(name)))
+}
+
+#[test]
+fn manuf_normalization() {
+    assert_eq!(normalize_manuf("Apple, Inc."), "Apple");
+    assert_eq!(normalize_manuf("Apple Inc"), "Apple");
+    assert_eq!(normalize_manuf("APPLE"), "Apple");
+    assert_eq!(normalize_manuf("  Apple   Computer Inc. "), "Apple");
+    assert_eq!(normalize_manuf("HUAWEI TECHNOLOGIES CO.,LTD"), "Huawei");
+    assert_eq!(normalize_manuf("Shenzhen Example Electronics Co., Ltd."), "Shenzhen Example Electronics");
+    assert_eq!(normalize_manuf("Siemens AG"), "Siemens");
+    assert_eq!(normalize_manuf("NETGEAR"), "Netgear");
+    assert_eq!(normalize_manuf("ZTE CORPORATION"), "ZTE");
+    assert_eq!(normalize_manuf("IBM CORP"), "IBM");
+    // suffixes are only stripped as whole words, and never leave nothing behind
+    assert_eq!(normalize_manuf("Nasa"), "Nasa");
+    assert_eq!(normalize_manuf("Inc."), "Inc");
+    assert_eq!(normalize_manuf(""), "");
+}
+
+/// ASCII transliterations of U+00C0 to U+00FF, the letters of Latin-1 Supplement
+const FOLD_LATIN_1: [&str; 64] = [
+    "A", "A", "A", "A", "A", "A", "AE", "C", "E", "E", "E", "E", "I", "I", "I", "I",
+    "D", "N", "O", "O", "O", "O", "O", "x", "O", "U", "U", "U", "U", "Y", "Th", "ss",
+    "a", "a", "a", "a", "a", "a", "ae", "c", "e", "e", "e", "e", "i", "i", "i", "i",
+    "d", "n", "o", "o", "o", "o", "o", "/", "o", "u", "u", "u", "u", "y", "th", "y",
+];
+
+/// ASCII transliterations of U+0100 to U+017F, Latin Extended-A
+const FOLD_LATIN_EXTENDED_A: [&str; 128] = [
+    "A", "a", "A", "a", "A", "a", "C", "c", "C", "c", "C", "c", "C", "c", "D", "d",
+    "D", "d", "E", "e", "E", "e", "E", "e", "E", "e", "E", "e", "G", "g", "G", "g",
+    "G", "g", "G", "g", "H", "h", "H", "h", "I", "i", "I", "i", "I", "i", "I", "i",
+    "I", "i", "IJ", "ij", "J", "j", "K", "k", "k", "L", "l", "L", "l", "L", "l", "L",
+    "l", "L", "l", "N", "n", "N", "n", "N", "n", "n", "N", "n", "O", "o", "O", "o",
+    "O", "o", "OE", "oe", "R", "r", "R", "r", "R", "r", "S", "s", "S", "s", "S", "s",
+    "S", "s", "T", "t", "T", "t", "T", "t", "U", "u", "U", "u", "U", "u", "U", "u",
+    "U", "u", "U", "u", "W", "w", "Y", "y", "Y", "Z", "z", "Z", "z", "Z", "z", "s",
+];
+
+/// Transliterates a character to ASCII, or None if it is a combining mark to be dropped. Characters without a
+/// transliteration become `?`.
+fn fold_char(c: char) -> Option<&'static str> {
+    Some(match c {
+        '\u{c0}'..='\u{ff}' => FOLD_LATIN_1[c as usize - 0xc0],
+        '\u{100}'..='\u{17f}' => FOLD_LATIN_EXTENDED_A[c as usize - 0x100],
+        // combining diacritical marks, left over from decomposed text
+        '\u{300}'..='\u{36f}' => return None,
+        // Romanian's comma-below letters, which are often written with cedillas instead
+        '\u{218}' => "S", '\u{219}' => "s", '\u{21a}' => "T", '\u{21b}' => "t",
+        '\u{a0}' | '\u{2002}'..='\u{200a}' => " ",
+        '\u{2018}' | '\u{2019}' | '\u{b4}' => "'",
+        '\u{201c}' | '\u{201d}' => "\"",
+        '\u{2010}'..='\u{2015}' => "-",
+        '\u{a9}' => "(C)",
+        '\u{ae}' => "(R)",
+        '\u{2122}' => "TM",
+        _ => "?",
+    })
+}
+
+/// Folds text to ASCII, stripping diacritics (`Soluções` to `Solucoes`) and transliterating letters without them
+/// (`ß` to `ss`, `Ø` to `O`). Other characters are replaced with `?`.
+fn fold_ascii(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c.is_ascii() {
+            true => folded.push(c),
+            false => folded.extend(fold_char(c)),
+        }
+    }
+    folded
+}
+
+/// # MANUF_ASCII(NULL|manuf) -> NULL|manuf
+/// Folds a manufacturer name to ASCII, so that `LIKE` filters (which only ignore the case of ASCII letters) match the
+/// names in the Wireshark database written with accented letters.
+///
+/// Diacritics are stripped, and letters without them are transliterated (`ß` as `ss`, `Æ` as `AE`, `Ł` as `L`).
+/// This covers the Latin letters used by European languages. Other characters, such as CJK, are replaced with `?`.
+///
+/// # Usage:
+/// |Call|Result|
+/// |-|-|
+/// |`MANUF_ASCII('Soluções Tecnológicas')` | `'Solucoes Tecnologicas'`|
+/// |`MANUF_ASCII('Müller & Søn')` | `'Muller & Son'`|
+/// |`MANUF_ASCII('Straße')` | `'Strasse'`|
+/// |`MAC_MANUFLONG(mac) LIKE '%electronique%'` | `FALSE`, for `'Société Électronique'`|
+/// |`MANUF_ASCII(MAC_MANUFLONG(mac)) LIKE '%electronique%'` | `TRUE`, for `'Société Électronique'`|
+pub fn manuf_ascii(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let Some(name) = text_arg(ctx, 0)? else { return Ok(None); };
+    Ok(Some(fold_ascii(name)))
+}
+
+#[test]
+fn ascii_folding() {
+    assert_eq!(fold_ascii("Soluções"), "Solucoes");
+    assert_eq!(fold_ascii("Société Électronique"), "Societe Electronique");
+    assert_eq!(fold_ascii("Straße Œuvre Łódź"), "Strasse OEuvre Lodz");
+    assert_eq!(fold_ascii("Ştiinţă Științifică"), "Stiinta Stiintifica");
+    // decomposed text, with its diacritics as combining marks
+    assert_eq!(fold_ascii("Solu\u{63}\u{327}o\u{303}es"), "Solucoes");
+    assert_eq!(fold_ascii("Acme\u{2122} \u{201c}Pro\u{201d}"), "AcmeTM \"Pro\"");
+    assert_eq!(fold_ascii("华为"), "??");
+    assert_eq!(fold_ascii("Apple, Inc."), "Apple, Inc.");
 }
 
 /// # MAC_MANUFLONG(NULL|mac) -> NULL|manuf_long
@@ -162,12 +1223,12 @@ pub fn manuf(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<
 /// |-|-|
 /// |`MAC_MANUFLONG('3c-a6-f6-c4-34-f8')` | `'Apple, Inc.'`|
 /// |`MAC_MANUFLONG('8c-1c-da-82-4c-2e')` | `'Atol Llc'` |
-/// |`MAC_MANUFLONG('33-33-00-00-00-01')` |  `NULL`  |
+/// |`MAC_MANUFLONG('02-00-00-00-00-01')` |  `NULL`  |
 pub fn manuf_long(
+    oui_db: &SharedOuiDb,
     ctx: &rusqlite::functions::Context<'_>,
-) -> rusqlite::Result<Option<&'static str>> {
-    let mac = find_mac(ctx)?;
-    Ok(mac.and_then(|(_o, om)| om.manuf_long().copied()))
+) -> rusqlite::Result<Option<String>> {
+    find_mac(oui_db, ctx, |_o, om| om.manuf_long().map(|s| s.to_string()))
 }
 
 /// # MAC_COMMENT(NULL|mac) -> NULL|comment
@@ -178,24 +1239,357 @@ pub fn manuf_long(
 /// |-|-|
 /// |`MAC_COMMENT('3c-a6-f6-c4-34-f8')` | `NULL`|
 /// |`MAC_COMMENT('08-00-87-aa-bb-cc')` | `'terminal servers'`|
-/// |`MAC_COMMENT('33-33-00-00-00-01')` |  `NULL`  |
+/// |`MAC_COMMENT('02-00-00-00-00-01')` |  `NULL`  |
 pub fn comment(
+    oui_db: &SharedOuiDb,
     ctx: &rusqlite::functions::Context<'_>,
-) -> rusqlite::Result<Option<&'static str>> {
-    let mac = find_mac(ctx)?;
-    Ok(mac.and_then(|(_o, om)| om.comment().copied()))
+) -> rusqlite::Result<Option<String>> {
+    find_mac(oui_db, ctx, |_o, om| om.comment().map(|s| s.to_string()))
+}
+
+/// # MAC_MANUF_COUNTRY(NULL|mac) -> NULL|country
+/// Returns the two-letter country code of the organization registered to this MAC's OUI.
+///
+/// Only the IEEE registries publish registrant addresses, so this requires a database built from the IEEE CSVs (the
+/// `ieee-oui-db` feature, or a CSV loaded with [OUI_LOAD](crate::exports::oui::load)). NULL is returned otherwise.
+///
+/// # Usage:
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_MANUF_COUNTRY('3c-a6-f6-c4-34-f8')` | `'US'`|
+/// |`MAC_MANUF_COUNTRY('8c-1c-da-82-4c-2e')` | `'RU'` |
+/// |`MAC_MANUF_COUNTRY('02-00-00-00-00-01')` |  `NULL`  |
+pub fn manuf_country(oui_db: &SharedOuiDb, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    find_mac(oui_db, ctx, |_o, om| om.country().map(|s| s.to_string()))
+}
+
+/// # MAC_MANUF_ADDRESS(NULL|mac) -> NULL|address
+/// Returns the postal address of the organization registered to this MAC's OUI, as published by the IEEE.
+///
+/// As with [MAC_MANUF_COUNTRY](crate::exports::mac::manuf_country), this is NULL unless the IEEE registries are used.
+///
+/// # Usage:
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_MANUF_ADDRESS('3c-a6-f6-c4-34-f8')` | `'1 Infinite Loop Cupertino CA US 95014'`|
+/// |`MAC_MANUF_ADDRESS('02-00-00-00-00-01')` |  `NULL`  |
+pub fn manuf_address(oui_db: &SharedOuiDb, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    find_mac(oui_db, ctx, |_o, om| om.address().map(|s| s.to_string()))
+}
+
+/// # MAC_MANUF_SOURCE(NULL|mac) -> NULL|source
+/// Returns which OUI database source resolved this MAC's prefix, for attributing vendor information in reports.
+///
+/// One of:
+/// * `registered` - Registered with [OUI_REGISTER](crate::exports::oui::register)
+/// * `table:<name>` - Loaded from a table with [OUI_LOAD_TABLE](crate::exports::oui::load_table)
+/// * `file:<path>` - Loaded from a file with [OUI_LOAD](crate::exports::oui::load), or a URL with `OUI_UPDATE`
+/// * `embedded` - The database embedded at build time
+/// * `embedded:custom` - The database an application started the connection with, in place of the embedded one (see
+///   [register_with](crate::register_with))
+/// * `wka` - Wireshark's well-known address database, embedded at build time with the `wka-db` feature
+///
+/// # Usage:
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_MANUF_SOURCE('3c-a6-f6-c4-34-f8')` | `'embedded'`|
+/// |`MAC_MANUF_SOURCE('33-33-00-00-00-01')` | `'wka'` - With the `wka-db` feature |
+/// |`MAC_MANUF_SOURCE('02-00-00-00-00-01')` |  `NULL`  |
+pub fn manuf_source(oui_db: &SharedOuiDb, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let Some(mac) = mac_arg(ctx, 0)? else { return Ok(None); };
+
+    let db = oui_db.load();
+    Ok(db.search_layered(mac.oui_key()).map(|(_o, _om, layer)| layer.provenance()))
+}
+
+/// Locally administered prefixes that are assigned deterministically by well-known software, rather than randomly.
+///
+/// Each entry is the prefix value (in the upper bits of a 48-bit MAC) and the prefix length.
+const KNOWN_LOCAL_PREFIXES: &[(u64, u8)] = &[
+    (0x0242_0000_0000, 16), // Docker
+    (0x5254_0000_0000, 24), // QEMU/KVM
+    (0x0A00_2700_0000, 24), // VirtualBox
+    (0x0200_4C4F_4F50, 48), // Microsoft Loopback Adapter
+];
+
+/// Determines if a MAC address is likely randomized, being locally administered without a known owner.
+fn is_randomized_mac(db: &OuiLayers, mac: Eui) -> bool {
+    if !mac.is_unicast() || !mac.is_local() {
+        return false;
+    }
+
+    // IEEE assigned CIDs use the local bit, but are registered to a company
+    let key = mac.oui_key();
+    if db.search_entry(key).is_some() {
+        return false;
+    }
+
+    let value = Oui::from_addr(key).as_int();
+    !KNOWN_LOCAL_PREFIXES.iter().any(|&(prefix, len)| {
+        let mask = !((1u64 << (48 - len)) - 1) & 0x0000_FFFF_FFFF_FFFF;
+        value & mask == prefix
+    })
+}
+
+/// # MAC_ISRANDOMIZED(NULL|mac) -> NULL|BOOL
+///
+/// Returns true if the MAC address appears to be randomized, as Wi-Fi clients do for privacy.
+///
+/// This is a heuristic: the address must be a locally administered unicast address, must not fall within a company ID
+/// (CID) registered in the OUI database, and must not match a prefix used deterministically by well-known software
+/// (Docker, QEMU/KVM, VirtualBox, and the Microsoft Loopback Adapter's `02:00:4c:4f:4f:50`). iOS, Android, and Windows all choose every other bit at
+/// random, so randomized addresses carry no mark of the system that chose them, and any other locally administered
+/// address (such as one set by hand) is reported as randomized too.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_ISRANDOMIZED('3e:a6:f6:c4:34:f8')` | `TRUE` |
+/// |`MAC_ISRANDOMIZED('3c:a6:f6:c4:34:f8')` | `FALSE` |
+/// |`MAC_ISRANDOMIZED('02:42:ac:11:00:02')` | `FALSE` |
+/// |`MAC_ISRANDOMIZED('02:00:4c:4f:4f:50')` | `FALSE` |
+/// |`MAC_ISRANDOMIZED('33:33:00:00:00:01')` | `FALSE` |
+pub fn is_randomized(oui_db: &SharedOuiDb, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<bool>> {
+    let Some(mac) = mac_arg(ctx, 0)? else { return Ok(None); };
+
+    Ok(Some(is_randomized_mac(&oui_db.load(), mac)))
+}
+
+/// # MAC_BLOCKTYPE(NULL|mac) -> NULL|block_type
+/// Returns the IEEE registry assignment class of the prefix this MAC belongs to, or NULL if it is not registered.
+///
+/// One of:
+/// * `MA-L` - MAC Address Block Large (24-bit prefix, formerly "OUI")
+/// * `MA-M` - MAC Address Block Medium (28-bit prefix)
+/// * `MA-S` - MAC Address Block Small (36-bit prefix, formerly "OUI-36")
+/// * `IAB` - Individual Address Block (legacy 36-bit prefix)
+/// * `CID` - Company ID (24-bit prefix with the local bit set)
+///
+/// Databases read from the IEEE registry CSVs (such as with the `ieee-oui-db` feature) record each prefix's registry.
+/// Other databases, such as Wireshark's manuf file, don't, so it is derived from the prefix's length and the blocks
+/// IEEE set aside for IABs.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_BLOCKTYPE('3c-a6-f6-c4-34-f8')` | `'MA-L'` |
+/// |`MAC_BLOCKTYPE('8c-1c-da-82-4c-2e')` | `'MA-M'` |
+/// |`MAC_BLOCKTYPE('8c-1f-64-cb-2b-cc')` | `'MA-S'` |
+/// |`MAC_BLOCKTYPE('33-33-00-00-00-01')` | `NULL` |
+pub fn block_type(oui_db: &SharedOuiDb, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<&'static str>> {
+    find_mac(oui_db, ctx, |o, om| om.block_type().or_else(|| o.block_type()).map(|bt| bt.as_str()))
+}
+
+/// # MAC_INFO(NULL|mac) -> NULL|json
+/// Returns a JSON object summarizing everything this extension knows about a MAC address, for use with `json_extract`.
+///
+/// The object contains:
+/// * `colon`, `dash`, `dot`, `bare` - The address in each canonical format
+/// * `eui64` - Whether the address is a 64-bit EUI
+/// * `prefix`, `manuf`, `manuf_long`, `comment`, `country`, `block_type` - OUI database fields, or null if not registered
+/// * `source` - The OUI database source, as returned by [MAC_MANUF_SOURCE](crate::exports::mac::manuf_source)
+/// * `unicast`, `multicast`, `universal`, `local`, `randomized` - Address flags
+/// * `blob` - The hexadecimal blob encoding, as produced by [MAC_BLOBIFY](crate::exports::mac::blobify)
+/// * `int` - The integer encoding, as produced by [MAC_ASINT](crate::exports::mac::as_int), or null for EUI-64s
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`json_extract(MAC_INFO('3c-a6-f6-c4-34-f8'), '$.manuf')` | `'Apple'` |
+/// |`json_extract(MAC_INFO('3c-a6-f6-c4-34-f8'), '$.dot')`   | `'3ca6.f6c4.34f8'` |
+/// |`json_extract(MAC_INFO('3c-a6-f6-c4-34-f8'), '$.int')`   | `66687802291448` |
+/// |`MAC_INFO(NULL)`                                          | `NULL` |
+pub fn info(oui_db: &SharedOuiDb, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let Some(mac) = mac_arg(ctx, 0)? else { return Ok(None); };
+    let db = oui_db.load();
+    let entry = db.search_layered(mac.oui_key());
+
+    let blob: String = mac.as_bytes().iter().map(|b| format!("{b:02X}")).collect();
+    let info = serde_json::json!({
+        "colon": MacStyle::Colon.format_eui(mac, false).as_str(),
+        "dash": MacStyle::Dashed.format_eui(mac, false).as_str(),
+        "dot": MacStyle::Dots.format_eui(mac, false).as_str(),
+        "bare": MacStyle::Plain.format_eui(mac, false).as_str(),
+        "eui64": mac.is_eui64(),
+        "prefix": entry.as_ref().map(|(o, _om, _l)| format!("{o:?}")),
+        "manuf": entry.as_ref().map(|(_o, om, _l)| *om.manuf()),
+        "manuf_long": entry.as_ref().and_then(|(_o, om, _l)| om.manuf_long().copied()),
+        "comment": entry.as_ref().and_then(|(_o, om, _l)| om.comment().copied()),
+        "country": entry.as_ref().and_then(|(_o, om, _l)| om.country().copied()),
+        "block_type": entry.as_ref().and_then(|(o, om, _l)| om.block_type().or_else(|| o.block_type())).map(|bt| bt.as_str()),
+        "source": entry.as_ref().map(|(_o, _om, layer)| layer.provenance()),
+        "unicast": mac.is_unicast(),
+        "multicast": mac.is_multicast(),
+        "universal": mac.is_universal(),
+        "local": mac.is_local(),
+        "randomized": is_randomized_mac(&db, mac),
+        "blob": blob,
+        "int": (!mac.is_eui64()).then(|| mac.as_u64() as i64),
+    });
+
+    Ok(Some(info.to_string()))
+}
+
+/// Identifies the virtual router protocol, group, and (for GLBP) forwarder number encoded in a well-known virtual MAC address.
+fn vrouter_mac(mac: [u8; 6]) -> Option<(&'static str, u16, Option<u8>)> {
+    match mac {
+        // CARP shares the VRRP IPv4 range, and can't be distinguished from it by MAC alone
+        [0x00, 0x00, 0x5e, 0x00, 0x01, vrid] => Some(("VRRP", vrid as u16, None)),
+        [0x00, 0x00, 0x5e, 0x00, 0x02, vrid] => Some(("VRRPv3-IPv6", vrid as u16, None)),
+        [0x00, 0x00, 0x0c, 0x07, 0xac, group] => Some(("HSRPv1", group as u16, None)),
+        [0x00, 0x00, 0x0c, 0x9f, hi, lo] if hi & 0xf0 == 0xf0 => Some(("HSRPv2", u16::from_be_bytes([hi & 0x0f, lo]), None)),
+        [0x00, 0x07, 0xb4, hi, lo, fwd] if hi & 0xfc == 0 => Some(("GLBP", u16::from_be_bytes([hi, lo]), Some(fwd))),
+        _ => None,
+    }
+}
+
+/// # MAC_VROUTER(NULL|mac) -> NULL|json
+/// Identifies gateway virtual MAC addresses, returning a JSON object with the first-hop redundancy `protocol` and `group`
+/// (VRID/group number), or NULL for addresses outside of the well-known virtual MAC ranges.
+///
+/// Recognized ranges:
+/// * `VRRP` - `00:00:5e:00:01:xx` (also used by CARP, which is indistinguishable by MAC)
+/// * `VRRPv3-IPv6` - `00:00:5e:00:02:xx`
+/// * `HSRPv1` - `00:00:0c:07:ac:xx`
+/// * `HSRPv2` - `00:00:0c:9f:fx:xx`
+/// * `GLBP` - `00:07:b4:0x:xx:yy`, which additionally includes the `forwarder` number `yy`
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_VROUTER('00:00:5e:00:01:0a')`                          | `'{"group":10,"protocol":"VRRP"}'` |
+/// |`json_extract(MAC_VROUTER('0000.0c9f.f123'), '$.group')`    | `291` |
+/// |`MAC_VROUTER('00:07:b4:00:01:02')`                          | `'{"forwarder":2,"group":1,"protocol":"GLBP"}'` |
+/// |`MAC_VROUTER('3c-a6-f6-c4-34-f8')`                          | `NULL` |
+pub fn vrouter(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let Some(Eui::Mac48(mac)) = mac_arg(ctx, 0)? else { return Ok(None); };
+
+    let Some((protocol, group, forwarder)) = vrouter_mac(mac.to_array()) else { return Ok(None); };
+
+    let mut info = serde_json::json!({
+        "protocol": protocol,
+        "group": group,
+    });
+    if let Some(fwd) = forwarder {
+        info["forwarder"] = fwd.into();
+    }
+
+    Ok(Some(info.to_string()))
+}
+
+#[test]
+fn vrouter_patterns() {
+    assert_eq!(vrouter_mac([0x00, 0x00, 0x5e, 0x00, 0x01, 0x0a]), Some(("VRRP", 10, None)));
+    assert_eq!(vrouter_mac([0x00, 0x00, 0x0c, 0x9f, 0xf1, 0x23]), Some(("HSRPv2", 0x123, None)));
+    assert_eq!(vrouter_mac([0x00, 0x07, 0xb4, 0x03, 0xff, 0x04]), Some(("GLBP", 1023, Some(4))));
+    assert_eq!(vrouter_mac([0x00, 0x07, 0xb4, 0x04, 0x00, 0x04]), None);
+    assert_eq!(vrouter_mac([0x00, 0x00, 0x0c, 0x9f, 0xe1, 0x23]), None);
+}
+
+/// Identifies the protocol a reserved destination MAC address belongs to.
+fn protocol_mac(mac: [u8; 6]) -> Option<&'static str> {
+    Some(match mac {
+        [0xff, 0xff, 0xff, 0xff, 0xff, 0xff] => "Broadcast",
+
+        // IEEE 802.1 reserved group addresses
+        [0x01, 0x80, 0xc2, 0x00, 0x00, 0x00] => "STP",
+        [0x01, 0x80, 0xc2, 0x00, 0x00, 0x01] => "Pause",
+        [0x01, 0x80, 0xc2, 0x00, 0x00, 0x02] => "LACP",
+        [0x01, 0x80, 0xc2, 0x00, 0x00, 0x03] => "802.1X",
+        [0x01, 0x80, 0xc2, 0x00, 0x00, 0x08] => "Provider-STP",
+        [0x01, 0x80, 0xc2, 0x00, 0x00, 0x0e] => "LLDP",
+        [0x01, 0x80, 0xc2, 0x00, 0x00, 0x04..=0x0f] => "802.1-Reserved",
+        [0x01, 0x80, 0xc2, 0x00, 0x00, 0x10] => "Bridge-Management",
+        [0x01, 0x80, 0xc2, 0x00, 0x00, 0x14 | 0x15] => "IS-IS",
+        [0x01, 0x80, 0xc2, 0x00, 0x00, 0x20] => "MMRP",
+        [0x01, 0x80, 0xc2, 0x00, 0x00, 0x21] => "MVRP",
+        [0x01, 0x80, 0xc2, 0x00, 0x00, 0x30..=0x3f] => "CFM",
+        [0x01, 0x1b, 0x19, 0x00, 0x00, 0x00] => "PTP",
+
+        // Cisco
+        [0x01, 0x00, 0x0c, 0xcc, 0xcc, 0xcc] => "CDP/VTP/DTP/PAgP/UDLD",
+        [0x01, 0x00, 0x0c, 0xcc, 0xcc, 0xcd] => "PVST+",
+        [0x01, 0x00, 0x0c, 0xcd, 0xcd, 0xcd] => "STP-UplinkFast",
+        [0x01, 0x00, 0x0c, 0x00, 0x00, 0x00] => "ISL",
+
+        // OSI
+        [0x09, 0x00, 0x2b, 0x00, 0x00, 0x04 | 0x05] => "ES-IS",
+        [0x09, 0x00, 0x2b, 0x00, 0x00, 0x14 | 0x15] => "IS-IS",
+
+        // IEC 61850
+        [0x01, 0x0c, 0xcd, 0x01, _, _] => "GOOSE",
+        [0x01, 0x0c, 0xcd, 0x04, _, _] => "SV",
+
+        // IPv4 multicast, mapped from the low 23 bits of the group address
+        [0x01, 0x00, 0x5e, 0x00, 0x00, 0x01] => "IPv4-AllHosts",
+        [0x01, 0x00, 0x5e, 0x00, 0x00, 0x02] => "IPv4-AllRouters",
+        [0x01, 0x00, 0x5e, 0x00, 0x00, 0x05 | 0x06] => "OSPF",
+        [0x01, 0x00, 0x5e, 0x00, 0x00, 0x09] => "RIPv2",
+        [0x01, 0x00, 0x5e, 0x00, 0x00, 0x0d] => "PIM",
+        [0x01, 0x00, 0x5e, 0x00, 0x00, 0x12] => "VRRP",
+        [0x01, 0x00, 0x5e, 0x00, 0x00, 0x16] => "IGMPv3",
+        [0x01, 0x00, 0x5e, 0x00, 0x00, 0x66] => "HSRPv2",
+        [0x01, 0x00, 0x5e, 0x00, 0x00, 0xfb] => "mDNS",
+        [0x01, 0x00, 0x5e, 0x00, 0x00, 0xfc] => "LLMNR",
+        [0x01, 0x00, 0x5e, 0x7f, 0xff, 0xfa] => "SSDP",
+        [0x01, 0x00, 0x5e, 0x00..=0x7f, _, _] => "IPv4-Multicast",
+
+        // IPv6 multicast, mapped from the low 32 bits of the group address
+        [0x33, 0x33, 0x00, 0x00, 0x00, 0x01] => "IPv6-AllNodes",
+        [0x33, 0x33, 0x00, 0x00, 0x00, 0x02] => "IPv6-AllRouters",
+        [0x33, 0x33, 0x00, 0x00, 0x00, 0x05 | 0x06] => "OSPFv3",
+        [0x33, 0x33, 0x00, 0x00, 0x00, 0x09] => "RIPng",
+        [0x33, 0x33, 0x00, 0x00, 0x00, 0x0d] => "PIM",
+        [0x33, 0x33, 0x00, 0x00, 0x00, 0x12] => "VRRP",
+        [0x33, 0x33, 0x00, 0x00, 0x00, 0x16] => "MLDv2",
+        [0x33, 0x33, 0x00, 0x00, 0x00, 0xfb] => "mDNS",
+        [0x33, 0x33, 0x00, 0x01, 0x00, 0x02] => "DHCPv6",
+        [0x33, 0x33, 0x00, 0x01, 0x00, 0x03] => "LLMNR",
+        [0x33, 0x33, 0x00, 0x00, 0x00, 0x0c] => "SSDP",
+        [0x33, 0x33, 0xff, _, _, _] => "IPv6-SolicitedNode",
+        [0x33, 0x33, _, _, _, _] => "IPv6-Multicast",
+
+        _ => return None,
+    })
+}
+
+/// # MAC_PROTOCOL(NULL|mac) -> NULL|protocol
+/// Identifies protocol-reserved destination MAC addresses, returning the name of the protocol that uses it, or NULL for
+/// other addresses.
+///
+/// Unlike the vendor lookups (such as [MAC_MANUF](crate::exports::mac::manuf)), this classifies the traffic sent to an
+/// address: link-layer control protocols (STP, LACP, LLDP, CDP, ...), routing protocol multicast groups, and IPv4/IPv6
+/// multicast in general. Some addresses are shared by several protocols, such as Cisco's `CDP/VTP/DTP/PAgP/UDLD`.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`MAC_PROTOCOL('01:80:c2:00:00:0e')` | `'LLDP'` |
+/// |`MAC_PROTOCOL('01:00:0c:cc:cc:cc')` | `'CDP/VTP/DTP/PAgP/UDLD'` |
+/// |`MAC_PROTOCOL('01:00:5e:00:00:05')` | `'OSPF'` |
+/// |`MAC_PROTOCOL('33:33:ff:12:34:56')` | `'IPv6-SolicitedNode'` |
+/// |`MAC_PROTOCOL('3c-a6-f6-c4-34-f8')` | `NULL` |
+pub fn protocol(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<&'static str>> {
+    let Some(Eui::Mac48(mac)) = mac_arg(ctx, 0)? else { return Ok(None); };
+
+    Ok(protocol_mac(mac.to_array()))
+}
+
+#[test]
+fn protocol_patterns() {
+    assert_eq!(protocol_mac([0x01, 0x80, 0xc2, 0x00, 0x00, 0x0e]), Some("LLDP"));
+    assert_eq!(protocol_mac([0x01, 0x80, 0xc2, 0x00, 0x00, 0x0b]), Some("802.1-Reserved"));
+    assert_eq!(protocol_mac([0x01, 0x00, 0x0c, 0xcc, 0xcc, 0xcd]), Some("PVST+"));
+    assert_eq!(protocol_mac([0x01, 0x00, 0x5e, 0x7f, 0xff, 0xfa]), Some("SSDP"));
+    assert_eq!(protocol_mac([0x01, 0x00, 0x5e, 0x01, 0x02, 0x03]), Some("IPv4-Multicast"));
+    assert_eq!(protocol_mac([0x01, 0x00, 0x5e, 0x80, 0x02, 0x03]), None);
+    assert_eq!(protocol_mac([0x33, 0x33, 0x00, 0x01, 0x00, 0x02]), Some("DHCPv6"));
+    assert_eq!(protocol_mac([0x3c, 0xa6, 0xf6, 0xc4, 0x34, 0xf8]), None);
 }
 
 macro_rules! gen_passthrough_body {
     ($fname: ident, $ctx: ident) => {{
-        let raw_str: Option<String> = $ctx.get(0)?;
-        let mac_str = match raw_str.as_deref() {
-            None | Some("") => return Ok(None),
-            Some(s) => s,
-        };
-
-        let mac = crate::oui::parse_mac_addr(&mac_str)
-            .map_err(|e| rusqlite::Error::UserFunctionError(Box::new(e)))?;
+        let Some(mac) = mac_arg($ctx, 0)? else { return Ok(None); };
         Ok(Some(mac.$fname()))
     }};
 }
@@ -226,4 +1620,138 @@ pub fn is_universal(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<
 /// Returns true if bit 2 of Y is 1 in address `xY:xx:xx:xx:xx:xx`
 pub fn is_local(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<bool>> {
     gen_passthrough_body!(is_local, ctx)
-}
\ No newline at end of file
+}
+
+/// # COLLATE MACADDR
+/// A collation sequence ordering MAC addresses numerically, regardless of their textual format or casing.
+///
+/// MAC addresses sort before EUI-64s. Text that cannot be parsed as either sorts last, in binary order.
+///
+/// # Usage
+/// ```sql
+/// SELECT mac FROM devices ORDER BY mac COLLATE MACADDR;
+/// CREATE INDEX devices_mac ON devices (mac COLLATE MACADDR);
+/// SELECT * FROM devices WHERE mac = 'AABB.CCDD.EEFF' COLLATE MACADDR;
+/// ```
+pub fn collate(a: &str, b: &str) -> Ordering {
+    fn key(s: &str) -> Option<(bool, u64)> {
+        crate::oui::parse_eui(s).ok().map(|e| (e.is_eui64(), e.as_u64()))
+    }
+
+    match (key(a), key(b)) {
+        (Some(ka), Some(kb)) => ka.cmp(&kb),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => a.cmp(b),
+    }
+}
+
+#[test]
+fn mac_problems() {
+    let cases = [
+        ("aa-bb-cc-dd-ee-ff", None),
+        ("0xaabbccddeeff0011", None),
+        ("", Some(("empty", None))),
+        ("aa-bb-cc-dd-ee-fg", Some(("bad-character", Some(17)))),
+        (" aa-bb-cc-dd-ee-ff", Some(("bad-character", Some(1)))),
+        ("aa–bb-cc-dd-ee-ff", Some(("bad-character", Some(3)))),
+        ("aa-bb-cc-dd-ee-ff-0", Some(("too-many-digits", Some(19)))),
+        ("aabbccddeeff00112", Some(("too-many-digits", Some(17)))),
+        ("0xaabbccddeeff00112", Some(("too-many-digits", Some(19)))),
+        ("aa-bb-cc", Some(("too-few-digits", None))),
+    ];
+    for (text, problem) in cases {
+        assert_eq!(mac_problem(text), problem, "{text:?}");
+    }
+}
+
+#[test]
+fn collation_order() {
+    assert_eq!(collate("aa-bb-cc-dd-ee-ff", "AABB.CCDD.EEFF"), Ordering::Equal);
+    assert_eq!(collate("00:00:00:00:00:02", "00-00-00-00-00-10"), Ordering::Less);
+    assert_eq!(collate("ff:ff:ff:ff:ff:ff", "00:00:00:00:00:00:00:00"), Ordering::Less);
+    assert_eq!(collate("not a mac", "ff:ff:ff:ff:ff:ff"), Ordering::Greater);
+    assert_eq!(collate("abc", "abd"), Ordering::Less);
+}
+
+#[test]
+fn linklocal_matching() {
+    let conn = conn();
+    let matches = |ip: &str, mac: &str| conn.query_row("SELECT LINKLOCAL_MATCHES_MAC(?1, ?2)", [ip, mac], |r| r.get::<_, Option<bool>>(0)).unwrap();
+
+    assert_eq!(matches("fe80::a8bb:ccff:fedd:eeff", "aa-bb-cc-dd-ee-ff"), Some(true));
+    assert_eq!(matches("FEBF::A8BB:CCFF:FEDD:EEFF", "aabb.ccdd.eeff"), Some(true));
+    assert_eq!(matches("fe80::aabb:ccff:fedd:eeff", "aa-bb-cc-dd-ee-ff"), Some(false));
+    assert_eq!(matches("fe80::a8bb:ccff:fedd:eefe", "aa-bb-cc-dd-ee-ff"), Some(false));
+    assert_eq!(matches("2001:db8::a8bb:ccff:fedd:eeff", "aa-bb-cc-dd-ee-ff"), Some(false));
+    assert_eq!(matches("169.254.0.1", "aa-bb-cc-dd-ee-ff"), Some(false));
+    assert_eq!(matches("fe80::a8bb:ccdd:eeff:11", "aa:bb:cc:dd:ee:ff:00:11"), Some(true));
+    assert!(conn.query_row("SELECT LINKLOCAL_MATCHES_MAC('fe80::/64', 'aa-bb-cc-dd-ee-ff')", [], |r| r.get::<_, Option<bool>>(0)).is_err());
+}
+
+#[test]
+fn manuf_all_elements() {
+    let conn = conn();
+
+    assert_eq!(query_text(&conn, r#"SELECT MAC_MANUF_ALL('["3c-a6-f6-c4-34-f8", "02-00-00-00-00-01", null, ""]')"#).as_deref(),
+        Some(r#"["Apple",null,null,null]"#));
+    assert_eq!(query_text(&conn, "SELECT MAC_MANUF_ALL(json_array(MAC_ASINT('3c-a6-f6-c4-34-f8')))").as_deref(), Some(r#"["Apple"]"#));
+    assert_eq!(query_text(&conn, "SELECT MAC_MANUF_ALL('[]')").as_deref(), Some("[]"));
+    assert_eq!(query_text(&conn, "SELECT MAC_MANUF_ALL(NULL)"), None);
+
+    assert!(query_err(&conn, r#"SELECT MAC_MANUF_ALL('["3c-a6-f6-c4-34-f8", "3c-a6-f6"]')"#).starts_with("argument 1 ("));
+    assert!(query_err(&conn, r#"SELECT MAC_MANUF_ALL('["3c-a6-f6-c4-34-f8", "3c-a6-f6"]')"#).contains("element 1: "));
+    assert!(query_err(&conn, "SELECT MAC_MANUF_ALL('[1.5]')").contains("element 0: "));
+}
+
+#[test]
+fn uuid_macs() {
+    let conn = conn();
+    let mac = |sql: &str| conn.query_row(sql, [], |r| r.get::<_, Option<String>>(0));
+
+    for uuid in [
+        "'c232ab00-9414-11ec-b3c8-3ca6f6c434f8'",
+        "'C232AB00941411ECB3C83CA6F6C434F8'",
+        "'{c232ab00-9414-11ec-b3c8-3ca6f6c434f8}'",
+        "'urn:uuid:c232ab00-9414-11ec-b3c8-3ca6f6c434f8'",
+        "X'C232AB00941411ECB3C83CA6F6C434F8'",
+    ] {
+        assert_eq!(mac(&format!("SELECT UUID_MAC({uuid})")).unwrap().as_deref(), Some("3c:a6:f6:c4:34:f8"), "{uuid}");
+    }
+    assert_eq!(mac("SELECT MAC_MANUF(UUID_MAC('c232ab00-9414-11ec-b3c8-3ca6f6c434f8'))").unwrap().as_deref(), Some("Apple"));
+
+    // random nodes, other versions, and other variants
+    assert_eq!(mac("SELECT UUID_MAC('c232ab00-9414-11ec-b3c8-9f6bdeced846')").unwrap(), None);
+    assert_eq!(mac("SELECT UUID_MAC('f81d4fae-7dec-41d0-a765-00a0c91e6bf6')").unwrap(), None);
+    assert_eq!(mac("SELECT UUID_MAC('c232ab00-9414-11ec-c3c8-3ca6f6c434f8')").unwrap(), None);
+    assert_eq!(mac("SELECT UUID_MAC(NULL)").unwrap(), None);
+
+    assert!(mac("SELECT UUID_MAC('c232ab00-9414')").unwrap_err().to_string().contains("Bad UUID"));
+    assert!(mac("SELECT UUID_MAC('c232ab0-09414-11ec-b3c8-3ca6f6c434f8')").is_err());
+    assert!(mac("SELECT UUID_MAC(X'C232AB00')").unwrap_err().to_string().contains("X'C232AB00'"));
+    assert!(mac("SELECT UUID_MAC(1)").is_err());
+    assert!(mac("SELECT UUID_MAC('+232ab00-9414-11ec-b3c8-3ca6f6c434f8')").is_err());
+    assert!(mac("SELECT UUID_MAC('c232ab00-9414-11ec-b3c8-3ca6f6c434+8')").is_err());
+
+    let formatted = "SELECT UUID_MAC('c232ab00-9414-11ec-b3c8-3ca6f6c434f8', 'dot')";
+    assert_eq!(mac(formatted).unwrap().as_deref(), Some("3ca6.f6c4.34f8"));
+    conn.execute_batch("SELECT NETTOOLS_SET('mac_style', 'DASH')").unwrap();
+    let styled = "SELECT MAC_FORMAT_DEFAULT(UUID_MAC('c232ab00-9414-11ec-b3c8-3ca6f6c434f8'))";
+    assert_eq!(mac(styled).unwrap().as_deref(), Some("3C-A6-F6-C4-34-F8"));
+}
+
+#[test]
+fn randomized_macs() {
+    let conn = conn();
+    let randomized = |mac: &str| conn.query_row("SELECT MAC_ISRANDOMIZED(?1)", [mac], |r| r.get::<_, Option<bool>>(0)).unwrap();
+
+    assert_eq!(randomized("3e:a6:f6:c4:34:f8"), Some(true));
+    assert_eq!(randomized("3c:a6:f6:c4:34:f8"), Some(false));
+    assert_eq!(randomized("33:33:00:00:00:01"), Some(false));
+    // software assigning local addresses deterministically
+    assert_eq!(randomized("02:42:ac:11:00:02"), Some(false));
+    assert_eq!(randomized("52:54:00:12:34:56"), Some(false));
+    assert_eq!(randomized("0a:00:27:00:00:05"), Some(false));
+    assert_eq!(randomized("02:00:4c:4f:4f:50"), Some(false));
+    assert_eq!(randomized("02:00:4c:4f:4f:51"), Some(true));
+}
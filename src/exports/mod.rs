@@ -8,10 +8,69 @@
 /// * `aabbccddeeff`
 /// * `0xaabbccddeeff`
 ///
+/// MAC addresses may also be provided as 6-byte blobs (see [MAC_BLOBIFY](crate::exports::mac::blobify)), or as 48-bit integers.
+///
+/// 64-bit EUIs (`aa:bb:cc:dd:ee:ff:00:11`, and the equivalent of each format above) are accepted wherever a MAC address is,
+/// as text or 8-byte blobs. OUI lookups on an EUI-64 use its leading bytes.
+///
 /// See the [MAC_FORMAT](crate::exports::mac::format) function to convert MAC addresses between known formats.
 pub mod mac;
 
 /// some documentation
 pub mod inet;
 
+/// Functions for managing the OUI database used by a connection's MAC address lookups.
+///
+/// Lookups resolve through a chain of sources, using the first with a matching prefix:
+/// 1. Prefixes registered with [OUI_REGISTER](crate::exports::oui::register)
+/// 2. Entries read from a table, with [OUI_LOAD_TABLE](crate::exports::oui::load_table)
+/// 3. A file loaded with [OUI_LOAD](crate::exports::oui::load) (or downloaded with `OUI_UPDATE`)
+/// 4. The OUI database embedded at build time
+/// 5. With the `wka-db` feature, Wireshark's well-known address database, naming broadcast and protocol multicast
+///    addresses
+///
+/// The source used for a particular MAC address can be found with [MAC_MANUF_SOURCE](crate::exports::mac::manuf_source).
+///
+/// As the database may change at runtime, functions looking up MAC addresses in it (such as
+/// [MAC_MANUF](crate::exports::mac::manuf)) are not marked deterministic, and can't be used in indexes or generated
+/// columns. Management functions may only be called directly, and not from views, triggers, or the schema.
+pub mod oui;
+
+/// Naming hosts by MAC address from ethers files (such as `/etc/ethers`), loaded separately from the OUI database.
+pub mod ethers;
+
+/// Virtual tables and table-valued functions.
+pub mod tables;
+
+/// Decoding helpers for packet header fields, such as TCP flags and ethertypes.
+pub mod packet;
+
+/// Errors raised by the functions, naming the argument at fault.
+pub mod error;
+
+/// Reading function arguments SQLite passes as pointers, such as lists bound with rusqlite's `array` feature.
+pub mod bound;
+
+/// Functions describing the extension build itself, such as its version.
+pub mod info;
+
+/// Changing the defaults used by a connection's functions.
+pub mod settings;
+
+/// ClickHouse's IP functions, under their ClickHouse names and with ClickHouse's semantics, so queries may be ported
+/// between the two. Registered only when the `clickhouse` group is chosen.
+pub mod clickhouse;
+
+/// Lookups made through DNS, such as blocklist checks. Only available with the `dns` feature.
+#[cfg(feature = "dns")]
+pub mod dns;
+
+/// Network registration lookups through RDAP, the successor to WHOIS. Only available with the `rdap` feature.
+#[cfg(feature = "rdap")]
+pub mod rdap;
+
+/// Aliases matching the names and argument orders of SIEM and database functions, so translated searches run
+/// unmodified. Registered only when the `siem` group is chosen.
+pub mod siem;
+
 // figure out a way to generate SQL tests in build.rs from rustdoc examples, and include! them here?
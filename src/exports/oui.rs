@@ -0,0 +1,468 @@
+#[cfg(not(feature = "wasm"))]
+use std::io::{Read, Write};
+use std::sync::Arc;
+
+use crate::exports::error::{text_arg, ArgError};
+
+#[cfg(not(feature = "wasm"))]
+use crate::oui::{binary::{self, ReadOuiBinError}, write_manuf_line, ParseOuiDbError};
+use crate::oui::{MergePrecedence, Oui, OuiDb, OuiLayerKind, OuiMeta, ParseOuiError, SharedOuiDb};
+#[cfg(test)]
+use crate::test_util::{conn, query_row, query_text, TempPath};
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum OuiLoadError {
+    #[cfg(not(feature = "wasm"))]
+    #[error("OUI_LOAD requires at least one path")]
+    NoPaths,
+    #[cfg(not(feature = "wasm"))]
+    #[error("Unable to read OUI database file {0:?}: {1}")]
+    Io(String, #[source] std::io::Error),
+    #[cfg(not(feature = "wasm"))]
+    #[error("Unable to parse OUI database file {0:?}: {1}")]
+    Parse(String, #[source] ParseOuiDbError),
+    #[cfg(not(feature = "wasm"))]
+    #[error("Unable to write OUI database dump {0:?}: {1}")]
+    Dump(String, #[source] std::io::Error),
+    #[cfg(not(feature = "wasm"))]
+    #[error("Unable to read binary OUI database file {0:?}: {1}")]
+    Binary(String, #[source] ReadOuiBinError),
+    #[error("OUI table {0:?} must have between 2 and 4 columns: (prefix, short, [long], [comment]). Found {1} columns")]
+    TableColumns(String, usize),
+    #[error("OUI table {0:?} has a NULL prefix or short name in row {1}")]
+    TableMissingField(String, usize),
+    #[error("Unable to parse OUI prefix in table {0:?}, row {1}: {2}")]
+    TablePrefix(String, usize, #[source] ParseOuiError),
+    #[cfg(feature = "oui-update")]
+    #[error("Unable to download OUI database from any source: {}", .0.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("; "))]
+    Download(Vec<ureq::Error>),
+}
+
+/// # OUI_LOAD(NULL|path, [path...]) -> NULL|count
+/// Loads one or more OUI database files, layering them over the embedded OUI database used by this connection's lookup
+/// functions (such as [MAC_MANUF](crate::exports::mac::manuf)). Returns the number of prefixes loaded.
+///
+/// Each file may be a Wireshark-format `manuf` file, one of the IEEE registry CSV downloads (`oui.csv`, `mam.csv`,
+/// `oui36.csv`, `iab.csv`, `cid.csv`), or a compact binary database (see [OuiDb::to_binary]), detected by its header.
+/// Binary databases are already parsed, and load considerably faster than the text formats. Multiple files are merged into a single
+/// database, such as to combine each of the IEEE registries. If files share a prefix, the earliest listed file wins.
+///
+/// The embedded database is a snapshot taken when the extension was built. This allows a fresher copy to be used
+/// without rebuilding the extension. Prefixes missing from the files still resolve through the embedded database.
+/// Loading replaces any file previously loaded (or downloaded with `OUI_UPDATE`). The files are read in full
+/// before being swapped in, so a failed load leaves the current database in place.
+///
+/// Note that indexes and generated columns built on OUI lookups are not recomputed when the database changes.
+///
+/// As this reads from the filesystem, it is [direct-only](crate::exports#direct-only-functions).
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`OUI_LOAD('/usr/share/wireshark/manuf')`             | `52719` |
+/// |`OUI_LOAD('oui.csv', 'mam.csv', 'oui36.csv')`        | `50243` |
+/// |`OUI_LOAD(NULL)`                                     | `NULL` |
+/// |`OUI_LOAD('/does/not/exist')`                        | N/A - A query error is raised with an appropriate error message |
+#[cfg(not(feature = "wasm"))]
+pub fn load(oui_db: &SharedOuiDb, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<i64>> {
+    if ctx.is_empty() {
+        return Err(rusqlite::Error::UserFunctionError(Box::new(OuiLoadError::NoPaths)));
+    }
+    let mut paths = Vec::with_capacity(ctx.len());
+    for i in 0..ctx.len() {
+        let Some(path) = text_arg(ctx, i)? else { return Ok(None); };
+        paths.push(path);
+    }
+
+    let _span = span!(INFO, "oui_load", paths = ?paths);
+    let mut db = OuiDb::default();
+    for (i, path) in paths.iter().enumerate() {
+        let file_db = read_db_file(path).map_err(|e| ArgError::invalid(ctx, i, e))?;
+        event!(DEBUG, path, entries = file_db.len(), "read oui database file");
+        db = db.merge(&file_db, MergePrecedence::Ours);
+    }
+
+    let count = db.len();
+    oui_db.set_layer(OuiLayerKind::File, Some((paths.join(", "), Arc::new(db))));
+    event!(INFO, entries = count, "loaded oui database files");
+
+    Ok(Some(count as i64))
+}
+
+/// Reads an OUI database file in any supported format: binary, IEEE registry CSV, or Wireshark's `manuf`.
+#[cfg(not(feature = "wasm"))]
+pub(crate) fn read_db_file(path: &str) -> Result<OuiDb, OuiLoadError> {
+    let mut magic = [0u8; binary::MAGIC.len()];
+    let is_binary = std::fs::File::open(path)
+        .and_then(|mut f| f.read_exact(&mut magic))
+        .is_ok_and(|()| OuiDb::is_binary(&magic));
+    if is_binary {
+        return OuiDb::load_binary_file(path).map_err(|e| OuiLoadError::Binary(path.to_owned(), e));
+    }
+
+    let txt = std::fs::read_to_string(path).map_err(|e| OuiLoadError::Io(path.to_owned(), e))?;
+    OuiDb::parse_any(&txt).map_err(|e| OuiLoadError::Parse(path.to_owned(), e))
+}
+
+/// # OUI_REGISTER(NULL|prefix, NULL|short, [long], [comment]) -> NULL|count
+/// Registers a single OUI prefix for this connection's lookup functions, taking precedence over every other source.
+/// Returns the number of prefixes now registered.
+///
+/// This allows prefixes missing from the OUI database, such as locally-administered addresses used within a lab, to be
+/// attributed without loading a whole file. Registering a prefix again replaces its previous entry. Registered
+/// prefixes are reported by [MAC_MANUF_SOURCE](crate::exports::mac::manuf_source) as `registered`.
+///
+/// As this changes the results of other functions, it is [direct-only](crate::exports#direct-only-functions).
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`OUI_REGISTER('02:11:22', 'MyLab')`                               | `1` |
+/// |`OUI_REGISTER('02:11:22:30:00:00/28', 'MyLab', 'My Lab Devices')` | `2` |
+/// |`OUI_REGISTER('02:11:22', NULL)`                                  | `NULL` |
+/// |`OUI_REGISTER('02:11:22/20', 'MyLab')`                            | N/A - A query error is raised with an appropriate error message |
+pub fn register(oui_db: &SharedOuiDb, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<i64>> {
+    let (Some(prefix), Some(short)) = (text_arg(ctx, 0)?, text_arg(ctx, 1)?) else {
+        return Ok(None);
+    };
+    let (long, comment) = (text_arg(ctx, 2)?, text_arg(ctx, 3)?);
+
+    let prefix: Oui = prefix.trim().parse()
+        .map_err(|e: ParseOuiError| ArgError::invalid(ctx, 0, e))?;
+    let entry = OuiDb::from_entries(vec![(prefix, OuiMeta::new(short, long, comment))]);
+
+    let mut count = 0;
+    oui_db.update_layer(OuiLayerKind::Registered, |prev| {
+        let db = match prev {
+            Some(prev) => entry.merge(&prev.db, MergePrecedence::Ours),
+            None => entry,
+        };
+        count = db.len();
+        Some((String::new(), Arc::new(db)))
+    });
+
+    Ok(Some(count as i64))
+}
+
+/// # OUI_UNREGISTER(NULL|prefix) -> NULL|bool
+/// Removes a prefix registered with [OUI_REGISTER](crate::exports::oui::register), returning whether it was registered.
+///
+/// The prefix must be given with the same length it was registered with.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`OUI_UNREGISTER('02:11:22')`   | `1` |
+/// |`OUI_UNREGISTER('02:11:22')`   | `0` - if it was not registered |
+/// |`OUI_UNREGISTER(NULL)`         | `NULL` |
+pub fn unregister(oui_db: &SharedOuiDb, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<bool>> {
+    let Some(prefix) = text_arg(ctx, 0)? else { return Ok(None); };
+    let prefix: Oui = prefix.trim().parse()
+        .map_err(|e: ParseOuiError| ArgError::invalid(ctx, 0, e))?;
+
+    let mut removed = false;
+    oui_db.update_layer(OuiLayerKind::Registered, |prev| {
+        let prev = prev?;
+        let db = OuiDb::from_entries(prev.db.raw_prefixes().filter(|(o, _om)| *o != prefix).collect());
+        removed = db.len() != prev.db.len();
+        match db.is_empty() {
+            true => None,
+            false => Some((String::new(), Arc::new(db))),
+        }
+    });
+
+    Ok(Some(removed))
+}
+
+/// # OUI_DUMP(NULL|path) -> NULL|count
+/// Writes this connection's OUI database to a file as a Wireshark `manuf` file, returning the number of entries written.
+///
+/// Each layer of the lookup chain (see [MAC_MANUF_SOURCE](crate::exports::mac::manuf_source)) is listed in order of
+/// precedence, beginning with a `# <source>` comment, so the dump can be read back with `OUI_LOAD` or `manuf_file`.
+/// Entries overridden by an earlier layer are written commented out, as are well-known address prefixes, which may be
+/// shorter than an OUI. Registrant addresses and countries aren't part of the format, and are left out. An existing
+/// file is overwritten.
+///
+/// As this writes to the filesystem, it is [direct-only](crate::exports#direct-only-functions).
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`OUI_DUMP('oui_db_dump.txt')` | `52719` |
+/// |`OUI_DUMP(NULL)`              | `NULL` |
+#[cfg(not(feature = "wasm"))]
+pub fn dump(oui_db: &SharedOuiDb, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<i64>> {
+    let Some(path) = text_arg(ctx, 0)? else { return Ok(None); };
+
+    let layers = oui_db.load();
+    let write = || -> std::io::Result<usize> {
+        let mut w = std::io::BufWriter::new(std::fs::File::create(path)?);
+        let mut written = std::collections::BTreeSet::new();
+        for layer in layers.layers() {
+            writeln!(w, "# {}", layer.provenance())?;
+            // well-known prefixes may be shorter than an OUI, and a file can't list a prefix twice
+            for (o, om) in layer.db.raw_prefixes() {
+                if layer.kind == OuiLayerKind::WellKnown || !written.insert(o) {
+                    w.write_all(b"# ")?;
+                }
+                write_manuf_line(&mut w, o, &om)?;
+            }
+        }
+        w.flush()?;
+        Ok(layers.layers().iter().map(|layer| layer.db.len()).sum())
+    };
+
+    let count = write().map_err(|e| ArgError::invalid(ctx, 0, OuiLoadError::Dump(path.to_owned(), e)))?;
+    Ok(Some(count as i64))
+}
+
+/// # OUI_DB_DATE() -> date
+/// Returns the UTC date (`YYYY-MM-DD`) the embedded OUI database was fetched, when the extension was built.
+///
+/// When the build fell back to a copy cached by an earlier build, this is the date that copy was fetched. For a local
+/// copy (`NETTOOLS_MANUF_PATH`), this is the date the file was last modified.
+///
+/// This describes the embedded database only. Use [MAC_MANUF_SOURCE](crate::exports::mac::manuf_source) to check
+/// whether a particular lookup was resolved by it.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`OUI_DB_DATE()` | `'2024-03-14'` |
+pub fn db_date(_ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<&'static str> {
+    Ok(OuiDb::EMBEDDED_DB_DATE)
+}
+
+/// # OUI_DB_SOURCE() -> url
+/// Returns the URL the embedded OUI database was fetched from, when the extension was built.
+///
+/// When built with the `ieee-oui-db` feature, this lists the URL of each IEEE registry, separated by spaces.
+/// When built from a local copy (with `NETTOOLS_MANUF_PATH`), this is the path of that file instead.
+/// When a download at build time was incomplete, and the copy cached by an earlier build was embedded in its place, the
+/// source is suffixed with `(cached)`.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`OUI_DB_SOURCE()` | `'https://gitlab.com/wireshark/wireshark/-/raw/master/manuf'` |
+pub fn db_source(_ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<&'static str> {
+    Ok(OuiDb::EMBEDDED_DB_SOURCE)
+}
+
+/// # OUI_DB_SIZE() -> count
+/// Returns the number of prefixes in this connection's OUI database, summed across each layer of the lookup chain (see
+/// [MAC_MANUF_SOURCE](crate::exports::mac::manuf_source)). This grows as databases are loaded, and counts a prefix
+/// again for each layer listing it, as [OUI_DUMP](crate::exports::oui::dump) does.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`OUI_DB_SIZE()` | `52719` |
+/// |`OUI_DB_SIZE()`, after `OUI_REGISTER('02:00:00', 'Lab')` | `52720` |
+pub fn db_size(oui_db: &SharedOuiDb, _ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<i64> {
+    Ok(oui_db.load().layers().iter().map(|layer| layer.db.len() as i64).sum())
+}
+
+/// # OUI_LOAD_TABLE(NULL|table) -> NULL|count
+/// Reads OUI entries from a table in this database, and layers them over the OUI database used by this connection's
+/// lookup functions. Returns the number of prefixes loaded.
+///
+/// The table's first four columns are used positionally as `(prefix, short, long, comment)`, with the last two being
+/// optional. Prefixes use the same notation as Wireshark's `manuf` file: either `aa:bb:cc` for a 24-bit prefix, or
+/// `aa:bb:cc:d0:00:00/28` for longer prefixes. Table entries take precedence over the underlying database for any MAC
+/// address they match, including those from a file loaded with [OUI_LOAD](crate::exports::oui::load), which allows
+/// individual entries to be overridden.
+///
+/// Loading a table replaces any entries previously loaded from a table. The table is read once, so later changes
+/// to it require it to be loaded again.
+///
+/// As this changes the results of other functions, it is [direct-only](crate::exports#direct-only-functions).
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`OUI_LOAD_TABLE('my_ouis')`     | `12` |
+/// |`OUI_LOAD_TABLE(NULL)`          | `NULL` |
+/// |`OUI_LOAD_TABLE('not_a_table')` | N/A - A query error is raised with an appropriate error message |
+pub fn load_table(oui_db: &SharedOuiDb, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<i64>> {
+    let Some(table) = text_arg(ctx, 0)? else { return Ok(None); };
+    let table_err = |e: OuiLoadError| ArgError::invalid(ctx, 0, e);
+    let _span = span!(INFO, "oui_load_table", table);
+
+    // SAFETY: the connection is only used to run a read-only query, and is not closed or otherwise reconfigured
+    let conn = unsafe { ctx.get_connection()? };
+    let mut stmt = conn.prepare(&format!("SELECT * FROM \"{}\"", table.replace('"', "\"\"")))?;
+    let columns = stmt.column_count();
+    if !(2..=4).contains(&columns) {
+        return Err(table_err(OuiLoadError::TableColumns(table.to_owned(), columns)));
+    }
+
+    let mut entries = Vec::new();
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let rownum = entries.len() + 1;
+        let field = |i: usize| -> rusqlite::Result<Option<String>> {
+            match i < columns {
+                true => row.get(i),
+                false => Ok(None),
+            }
+        };
+        let (Some(prefix), Some(short)) = (field(0)?, field(1)?) else {
+            return Err(table_err(OuiLoadError::TableMissingField(table.to_owned(), rownum)));
+        };
+
+        let prefix: Oui = prefix.trim().parse()
+            .map_err(|e| table_err(OuiLoadError::TablePrefix(table.to_owned(), rownum, e)))?;
+        entries.push((prefix, OuiMeta::new(short, field(2)?, field(3)?)));
+    }
+
+    let db = OuiDb::from_entries(entries);
+    let count = db.len();
+    oui_db.set_layer(OuiLayerKind::Table, Some((table.to_owned(), Arc::new(db))));
+    event!(INFO, entries = count, "loaded oui table");
+
+    Ok(Some(count as i64))
+}
+
+/// Sources for Wireshark's OUI database, in order of preference. These match those used to embed the database at build time.
+#[cfg(feature = "oui-update")]
+const WIRESHARK_MANUF_URLS: &[&str] = &[
+    "https://gitlab.com/wireshark/wireshark/-/raw/master/manuf",
+    "https://www.wireshark.org/download/automated/data/manuf",
+];
+
+/// # OUI_UPDATE(\[NULL|url]) -> count
+/// Downloads a Wireshark-format `manuf` file, layering it over the embedded OUI database used by this connection's
+/// lookup functions, in the same manner as [OUI_LOAD](crate::exports::oui::load). Returns the number of prefixes loaded.
+///
+/// Without a URL (or with a NULL URL), the current Wireshark database is fetched, falling back to a mirror if
+/// the primary source is unavailable. The database is only swapped in once it has been downloaded and parsed in full,
+/// so a failed update leaves the current database in place.
+///
+/// As this makes network requests, it is [direct-only](crate::exports#direct-only-functions). This function is only
+/// available when the extension is built with the `oui-update` feature.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`OUI_UPDATE()`                                      | `52719` |
+/// |`OUI_UPDATE('https://example.com/mirror/manuf')`    | `52719` |
+/// |`OUI_UPDATE('https://example.com/does-not-exist')`  | N/A - A query error is raised with an appropriate error message |
+#[cfg(feature = "oui-update")]
+pub fn update(oui_db: &SharedOuiDb, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<i64> {
+    let url = text_arg(ctx, 0)?;
+    let urls = match url {
+        Some(url) => vec![url],
+        None => WIRESHARK_MANUF_URLS.to_vec(),
+    };
+
+    let _span = span!(INFO, "oui_update");
+    let mut failures = Vec::new();
+    for url in urls {
+        let txt = match ureq::get(url).call().map(|resp| resp.into_string()) {
+            Ok(Ok(txt)) => txt,
+            Ok(Err(e)) => {
+                event!(WARN, url, error = %e, "unable to read oui database download");
+                failures.push(ureq::Error::from(e));
+                continue;
+            },
+            Err(e) => {
+                event!(WARN, url, error = %e, "unable to download oui database");
+                failures.push(e);
+                continue;
+            },
+        };
+
+        let db = OuiDb::parse_any(&txt)
+            .map_err(|e| rusqlite::Error::UserFunctionError(Box::new(OuiLoadError::Parse(url.to_owned(), e))))?;
+
+        let count = db.len();
+        oui_db.set_layer(OuiLayerKind::File, Some((url.to_owned(), Arc::new(db))));
+        event!(INFO, url, entries = count, "loaded downloaded oui database");
+        return Ok(count as i64);
+    }
+
+    Err(rusqlite::Error::UserFunctionError(Box::new(OuiLoadError::Download(failures))))
+}
+
+#[test]
+fn embedded_db_metadata() {
+    use crate::oui::{EMBEDDED_DB, EMBEDDED_WKA_DB};
+
+    let conn = conn();
+    let date = query_text(&conn, "SELECT OUI_DB_DATE()").unwrap();
+    assert_eq!(date, OuiDb::EMBEDDED_DB_DATE);
+    assert!(date.is_empty() || (date.len() == 10 && date.bytes().enumerate().all(|(i, b)| match i {
+        4 | 7 => b == b'-',
+        _ => b.is_ascii_digit(),
+    })), "{date:?}");
+    assert_eq!(query_text(&conn, "SELECT OUI_DB_SOURCE()").as_deref(), Some(OuiDb::EMBEDDED_DB_SOURCE));
+
+    // the embedded database, and the well-known addresses searched after it
+    let size = (EMBEDDED_DB.len() + EMBEDDED_WKA_DB.len()) as i64;
+    assert_eq!(query_row::<i64>(&conn, "SELECT OUI_DB_SIZE()"), size);
+    conn.query_row("SELECT OUI_REGISTER('02:00:00', 'Lab')", [], |_| Ok(())).unwrap();
+    assert_eq!(query_row::<i64>(&conn, "SELECT OUI_DB_SIZE()"), size + 1);
+}
+
+#[cfg(not(feature = "wasm"))]
+#[test]
+fn dump_loads_back() {
+    use crate::oui::EMBEDDED_DB;
+
+    let dumped = conn();
+    dumped.execute_batch("
+        SELECT OUI_REGISTER('02:00:00:10:00:00/28', 'Lab', 'Lab Devices', 'bench rack');
+        SELECT OUI_REGISTER('02:00:01', 'Spare', NULL, 'unused');
+        SELECT OUI_REGISTER('00:00:0c', 'NotCisco');
+    ").unwrap();
+    let path = TempPath::new("oui-dump.txt");
+    let written: i64 = dumped.query_row("SELECT OUI_DUMP(?1)", [path.as_str()], |r| r.get(0)).unwrap();
+    assert_eq!(written, query_row::<i64>(&dumped, "SELECT OUI_DB_SIZE()"));
+
+    let loaded = conn();
+    let count: i64 = loaded.query_row("SELECT OUI_LOAD(?1)", [path.as_str()], |r| r.get(0)).unwrap();
+    // the registered prefixes, and the embedded ones less the one overridden
+    assert_eq!(count, EMBEDDED_DB.len() as i64 + 2);
+
+    let lookups = "SELECT MAC_MANUF(m) || '|' || ifnull(MAC_MANUFLONG(m), '') || '|' || ifnull(MAC_COMMENT(m), '')
+        FROM (SELECT ?1 AS m)";
+    for mac in ["02:00:00:12:34:56", "00:00:0c:00:00:01", "00:00:17:00:00:01"] {
+        let lookup = |conn: &rusqlite::Connection| conn.query_row(lookups, [mac], |r| r.get::<_, Option<String>>(0)).unwrap();
+        assert_eq!(lookup(&loaded), lookup(&dumped), "{mac}");
+    }
+    // without a long name, the short name is written in its place ahead of the comment
+    let spare = |conn: &rusqlite::Connection| {
+        query_text(conn, "SELECT MAC_MANUFLONG('02:00:01:00:00:01') || '|' || MAC_COMMENT('02:00:01:00:00:01')")
+    };
+    assert_eq!(spare(&dumped), None);
+    assert_eq!(spare(&loaded).as_deref(), Some("Spare|unused"));
+    assert_eq!(query_text(&loaded, "SELECT MAC_MANUF('00:00:0c:00:00:01')").as_deref(), Some("NotCisco"));
+}
+
+#[test]
+fn unregister_falls_through() {
+    let conn = conn();
+    let lookup = || {
+        query_text(&conn, "SELECT MAC_MANUF('00:00:0c:12:34:56') || ' ' || MAC_MANUF_SOURCE('00:00:0c:12:34:56')")
+    };
+    let embedded = lookup().unwrap();
+    assert!(embedded.ends_with(" embedded"), "{embedded}");
+
+    conn.execute_batch("
+        SELECT OUI_REGISTER('00:00:0c', 'NotCisco');
+        SELECT OUI_REGISTER('00:00:0c:10:00:00/28', 'NotCisco28');
+    ").unwrap();
+    assert_eq!(lookup().as_deref(), Some("NotCisco28 registered"));
+
+    // only the given length is removed, leaving the shorter registration
+    assert!(query_row::<bool>(&conn, "SELECT OUI_UNREGISTER('00:00:0c:10:00:00/28')"));
+    assert_eq!(lookup().as_deref(), Some("NotCisco registered"));
+    assert!(!query_row::<bool>(&conn, "SELECT OUI_UNREGISTER('00:00:0c:10:00:00/28')"));
+
+    // once nothing is registered, lookups fall through to the embedded database again
+    assert!(query_row::<bool>(&conn, "SELECT OUI_UNREGISTER('00:00:0c')"));
+    assert_eq!(lookup(), Some(embedded));
+    assert_eq!(query_row::<i64>(&conn, "SELECT count(*) FROM oui WHERE source = 'registered'"), 0);
+    assert_eq!(query_text(&conn, "SELECT OUI_UNREGISTER(NULL)"), None);
+}
@@ -0,0 +1,495 @@
+use rusqlite::types::ValueRef;
+
+use crate::exports::error::{text_arg, ArgError};
+#[cfg(test)]
+use crate::test_util::{conn, query_err, query_row};
+
+#[derive(thiserror::Error, Debug)]
+enum TcpFlagsError {
+    #[error("TCP flags bitmask out of range (got {0}). Expected an integer within [0, 0x1ff]")]
+    BitmaskRange(i64),
+    #[error("Unable to parse TCP flags from {0:?}. Provide an integer bitmask, comma separated flag names (`SYN,ACK`), or compact letters (`.A..S.`)")]
+    BadFlags(String),
+}
+
+/// TCP flags, in bit order. Each entry is the flag's bit, name, and the letter used by compact notations.
+const TCP_FLAGS_TABLE: [(u16, &str, char); 9] = [
+    (0x001, "FIN", 'F'),
+    (0x002, "SYN", 'S'),
+    (0x004, "RST", 'R'),
+    (0x008, "PSH", 'P'),
+    (0x010, "ACK", 'A'),
+    (0x020, "URG", 'U'),
+    (0x040, "ECE", 'E'),
+    (0x080, "CWR", 'C'),
+    (0x100, "NS",  'N'),
+];
+
+/// Parses TCP flags in any of the supported textual notations into a bitmask.
+///
+/// Integers may be decimal or `0x` prefixed hexadecimal. Named flags may be separated by commas, pipes, or whitespace.
+/// Compact letter notation uses the placeholders `.` and `-` for unset flags, as emitted by nfdump and similar tools.
+///
+/// Text that reads both as flag names and as letters is read as names: `NS` is the NS flag rather than N and S, and
+/// `ECE` the ECE flag rather than E, C, and E. Letters can be told apart with a placeholder (`N.S`) or another order.
+fn parse_tcp_flags(s: &str) -> Option<u16> {
+    let s = s.trim();
+    let as_int = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => s.parse::<u16>().ok(),
+    };
+    if let Some(mask) = as_int {
+        return (mask <= 0x1ff).then_some(mask);
+    }
+
+    let by_name = s
+        .split(|c: char| c == ',' || c == '|' || c.is_ascii_whitespace())
+        .filter(|name| !name.is_empty())
+        .try_fold(0u16, |mask, name| {
+            TCP_FLAGS_TABLE.iter()
+                .find(|(_, n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(bit, _, _)| mask | bit)
+        });
+    if let Some(mask) = by_name {
+        return Some(mask);
+    }
+
+    s.chars().try_fold(0u16, |mask, c| match c {
+        '.' | '-' => Some(mask),
+        c => TCP_FLAGS_TABLE.iter()
+            .find(|(_, _, l)| l.eq_ignore_ascii_case(&c))
+            .map(|(bit, _, _)| mask | bit),
+    })
+}
+
+/// Pulls a TCP flags bitmask out of the provided argument, accepting either integers or text.
+fn flags_arg(ctx: &rusqlite::functions::Context<'_>, idx: usize) -> rusqlite::Result<Option<u16>> {
+    match ctx.get_raw(idx) {
+        ValueRef::Null => Ok(None),
+        ValueRef::Integer(i) => match u16::try_from(i) {
+            Ok(mask) if mask <= 0x1ff => Ok(Some(mask)),
+            _ => Err(ArgError::invalid(ctx, idx, TcpFlagsError::BitmaskRange(i))),
+        },
+        ValueRef::Text(_) => {
+            let s = text_arg(ctx, idx)?.unwrap_or_default();
+            parse_tcp_flags(s)
+                .map(Some)
+                .ok_or_else(|| ArgError::invalid(ctx, idx, TcpFlagsError::BadFlags(s.to_owned())))
+        },
+        ValueRef::Real(_) | ValueRef::Blob(_) => Err(ArgError::invalid(ctx, idx, "expected an integer or text")),
+    }
+}
+
+/// # TCP_FLAGS(NULL|flags) -> NULL|names
+/// Decodes a TCP flags bitmask into a comma separated list of flag names, in bit order.
+///
+/// The flags may be provided as an integer bitmask, as comma separated flag names, or in the compact letter notation
+/// emitted by some flow collectors (`F`, `S`, `R`, `P`, `A`, `U`, `E`, `C`, `N`, with `.` or `-` for unset flags).
+/// Text that reads both ways is read as flag names, so `'NS'` is the NS flag alone, while `'N.S'` is NS and SYN.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`TCP_FLAGS(0x12)`      | `'SYN,ACK'` |
+/// |`TCP_FLAGS(18)`        | `'SYN,ACK'` |
+/// |`TCP_FLAGS('0x11')`    | `'FIN,ACK'` |
+/// |`TCP_FLAGS('.A..SF')`  | `'FIN,SYN,ACK'` |
+/// |`TCP_FLAGS('ack syn')` | `'SYN,ACK'` |
+/// |`TCP_FLAGS('NS')`      | `'NS'` |
+/// |`TCP_FLAGS('N.S')`     | `'SYN,NS'` |
+/// |`TCP_FLAGS(0)`         | `''` |
+/// |`TCP_FLAGS('XYZ')`     | N/A - A query error is raised with an appropriate error message |
+pub fn flags(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let Some(mask) = flags_arg(ctx, 0)? else { return Ok(None); };
+
+    let names: Vec<&str> = TCP_FLAGS_TABLE.iter()
+        .filter(|(bit, _, _)| mask & bit != 0)
+        .map(|(_, name, _)| *name)
+        .collect();
+
+    Ok(Some(names.join(",")))
+}
+
+/// # TCP_FLAG_SET(NULL|flags, NULL|flag) -> NULL|BOOL
+/// Returns true if every flag in the second argument is set within the first.
+///
+/// Both arguments accept the same notations as [TCP_FLAGS](crate::exports::packet::flags).
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`TCP_FLAG_SET(0x12, 'SYN')`     | `TRUE` |
+/// |`TCP_FLAG_SET(0x12, 'RST')`     | `FALSE` |
+/// |`TCP_FLAG_SET('.A..S.', 'A')`   | `TRUE` |
+/// |`TCP_FLAG_SET(0x12, 'SYN,ACK')` | `TRUE` |
+/// |`TCP_FLAG_SET(0x12, 0x04)`      | `FALSE` |
+pub fn flag_set(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<bool>> {
+    let Some(mask) = flags_arg(ctx, 0)? else { return Ok(None); };
+    let Some(test) = flags_arg(ctx, 1)? else { return Ok(None); };
+
+    Ok(Some(mask & test == test))
+}
+
+#[test]
+fn tcp_flag_notations() {
+    assert_eq!(parse_tcp_flags("0x12"), Some(0x12));
+    assert_eq!(parse_tcp_flags("18"), Some(0x12));
+    assert_eq!(parse_tcp_flags("SYN,ACK"), Some(0x12));
+    assert_eq!(parse_tcp_flags("syn | ack"), Some(0x12));
+    assert_eq!(parse_tcp_flags(".A..S."), Some(0x12));
+    assert_eq!(parse_tcp_flags("UAPRSF"), Some(0x3f));
+    assert_eq!(parse_tcp_flags("NS"), Some(0x100));
+    assert_eq!(parse_tcp_flags("N.S"), Some(0x102));
+    assert_eq!(parse_tcp_flags("ECE"), Some(0x40));
+    assert_eq!(parse_tcp_flags("E.CE"), Some(0xc0));
+    assert_eq!(parse_tcp_flags("0x200"), None);
+    assert_eq!(parse_tcp_flags("SYNACK"), None);
+}
+
+#[derive(thiserror::Error, Debug)]
+enum EthertypeError {
+    #[error("Ethertype out of range (got {0}). Expected an integer within [0, 0xffff]")]
+    OutOfRange(i64),
+    #[error("Unable to parse ethertype from {0:?}. Provide an integer, decimal or `0x` prefixed hexadecimal text, or a 2-byte blob")]
+    BadEthertype(String),
+}
+
+lazy_static::lazy_static! {
+    /// Commonly seen ethertypes, sorted by value, as listed by `ethertypes.tsv`.
+    static ref ETHERTYPES: Vec<(u16, &'static str)> = include_str!("ethertypes.tsv")
+        .lines()
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .map(|l| {
+            let (value, name) = l.split_once('\t').expect("malformed ethertypes.tsv");
+            let value = value.strip_prefix("0x").and_then(|v| u16::from_str_radix(v, 16).ok());
+            (value.expect("malformed ethertypes.tsv"), name)
+        })
+        .collect();
+}
+
+/// Pulls an ethertype out of the provided argument, accepting integers, decimal or `0x` prefixed hexadecimal text, or
+/// 2-byte blobs.
+fn ethertype_arg(ctx: &rusqlite::functions::Context<'_>, idx: usize) -> rusqlite::Result<Option<u16>> {
+    match ctx.get_raw(idx) {
+        ValueRef::Null => Ok(None),
+        ValueRef::Integer(i) => u16::try_from(i)
+            .map(Some)
+            .map_err(|_| ArgError::invalid(ctx, idx, EthertypeError::OutOfRange(i))),
+        ValueRef::Blob(&[hi, lo]) => Ok(Some(u16::from_be_bytes([hi, lo]))),
+        ValueRef::Text(_) => {
+            let s = text_arg(ctx, idx)?.unwrap_or_default();
+            let t = s.trim();
+            let parsed = match t.strip_prefix("0x").or_else(|| t.strip_prefix("0X")) {
+                Some(hex) => u16::from_str_radix(hex, 16).ok(),
+                None => t.parse().ok(),
+            };
+            // from_str_radix accepts a leading '+', which isn't a digit of either form
+            parsed
+                .filter(|_| !t.contains('+'))
+                .map(Some)
+                .ok_or(())
+                .map_err(|_| ArgError::invalid(ctx, idx, EthertypeError::BadEthertype(s.to_owned())))
+        },
+        ValueRef::Blob(b) => Err(ArgError::invalid(ctx, idx, EthertypeError::BadEthertype(format!("{b:x?}")))),
+        ValueRef::Real(_) => Err(ArgError::invalid(ctx, idx, "expected an integer, text, or a 2-byte blob")),
+    }
+}
+
+/// # ETHERTYPE_NAME(NULL|ethertype) -> NULL|name
+/// Returns the name of a commonly used ethertype, or NULL if it is not known.
+///
+/// The ethertype may be an integer, text, or a 2-byte big-endian blob. Text is read as decimal, unless prefixed by `0x`
+/// for hexadecimal, as with SQL integer literals.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`ETHERTYPE_NAME(0x0800)`   | `'IPv4'` |
+/// |`ETHERTYPE_NAME(34525)`    | `'IPv6'` |
+/// |`ETHERTYPE_NAME('0x88cc')` | `'LLDP'` |
+/// |`ETHERTYPE_NAME('2048')`   | `'IPv4'` |
+/// |`ETHERTYPE_NAME('88cc')`   | N/A - A query error is raised, as text without `0x` is read as decimal |
+/// |`ETHERTYPE_NAME(X'8100')`  | `'802.1Q'` |
+/// |`ETHERTYPE_NAME(0x1234)`   | `NULL` |
+pub fn ethertype_name(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<&'static str>> {
+    let Some(ethertype) = ethertype_arg(ctx, 0)? else { return Ok(None); };
+
+    Ok(ETHERTYPES.binary_search_by_key(&ethertype, |(v, _)| *v)
+        .ok()
+        .map(|i| ETHERTYPES[i].1))
+}
+
+/// # ETHERTYPE_VALUE(NULL|name) -> NULL|ethertype
+/// Returns the integer ethertype for a name known by [ETHERTYPE_NAME](crate::exports::packet::ethertype_name), or NULL if it is not known.
+///
+/// Names are matched case-insensitively.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`ETHERTYPE_VALUE('IPv4')`   | `2048` |
+/// |`ETHERTYPE_VALUE('eapol')`  | `34958` |
+/// |`ETHERTYPE_VALUE('Bogus')`  | `NULL` |
+pub fn ethertype_value(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<u16>> {
+    let Some(name) = ctx.get_raw(0).as_str_or_null()? else { return Ok(None); };
+
+    Ok(ETHERTYPES.iter()
+        .find(|(_, n)| n.eq_ignore_ascii_case(name.trim()))
+        .map(|(v, _)| *v))
+}
+
+#[test]
+fn ethertypes_sorted_and_unique() {
+    assert!(ETHERTYPES.windows(2).all(|w| w[0].0 < w[1].0));
+    for (i, (_, name)) in ETHERTYPES.iter().enumerate() {
+        assert!(ETHERTYPES[i+1..].iter().all(|(_, n)| !n.eq_ignore_ascii_case(name)));
+    }
+
+    let conn = conn();
+    let name = |sql: &str| query_row::<Option<String>>(&conn, sql);
+    assert_eq!(name("SELECT ETHERTYPE_NAME('0x0800')").as_deref(), Some("IPv4"));
+    assert_eq!(name("SELECT ETHERTYPE_NAME('2048')").as_deref(), Some("IPv4"));
+    assert_eq!(name("SELECT ETHERTYPE_NAME('0800')"), None);
+    assert!(query_err(&conn, "SELECT ETHERTYPE_NAME('0x+800')").contains("Unable to parse ethertype"));
+    assert!(query_err(&conn, "SELECT ETHERTYPE_NAME('88cc')").contains("Unable to parse ethertype"));
+}
+
+#[derive(thiserror::Error, Debug)]
+enum VlanError {
+    #[error("VLAN TCI out of range (got {0}). Expected an integer within [0, 0xffff]")]
+    TciRange(i64),
+    #[error("Expected a 2-byte TCI, or a 4-byte VLAN tag, but received {0} bytes")]
+    BlobLength(usize),
+    #[error("VLAN tag has ethertype {0:#06x}, which isn't an 802.1Q or QinQ tag")]
+    NotVlanTag(u16),
+}
+
+/// Ethertypes used as the tag protocol identifier of VLAN tags: 802.1Q, 802.1ad, and the pre-standard QinQ values.
+const VLAN_TPIDS: [u16; 5] = [0x8100, 0x88A8, 0x9100, 0x9200, 0x9300];
+
+/// Pulls a VLAN tag control information field out of the provided argument. This may be an integer, a 2-byte blob of
+/// the TCI, or a 4-byte blob of a whole tag (starting with its 802.1Q or QinQ ethertype).
+fn tci_arg(ctx: &rusqlite::functions::Context<'_>, idx: usize) -> rusqlite::Result<Option<u16>> {
+    match ctx.get_raw(idx) {
+        ValueRef::Null => Ok(None),
+        ValueRef::Integer(i) => u16::try_from(i)
+            .map(Some)
+            .map_err(|_| ArgError::invalid(ctx, idx, VlanError::TciRange(i))),
+        ValueRef::Blob(&[hi, lo]) => Ok(Some(u16::from_be_bytes([hi, lo]))),
+        ValueRef::Blob(&[tpid_hi, tpid_lo, hi, lo]) => match u16::from_be_bytes([tpid_hi, tpid_lo]) {
+            tpid if VLAN_TPIDS.contains(&tpid) => Ok(Some(u16::from_be_bytes([hi, lo]))),
+            tpid => Err(ArgError::invalid(ctx, idx, VlanError::NotVlanTag(tpid))),
+        },
+        ValueRef::Blob(b) => Err(ArgError::invalid(ctx, idx, VlanError::BlobLength(b.len()))),
+        ValueRef::Text(_) | ValueRef::Real(_) => Err(ArgError::invalid(ctx, idx, "expected an integer, or a 2 or 4-byte blob")),
+    }
+}
+
+/// # VLAN_ID(NULL|tci) -> NULL|id
+/// Returns the VLAN identifier (the low 12 bits) of an 802.1Q tag's control information.
+///
+/// The TCI may be an integer, a 2-byte big-endian blob, or a 4-byte blob of the whole tag, whose ethertype must be one
+/// of 802.1Q (`0x8100`), 802.1ad (`0x88a8`), or the pre-standard QinQ values (`0x9100`, `0x9200`, `0x9300`).
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`VLAN_ID(0xA064)`      | `100` |
+/// |`VLAN_ID(X'A064')`     | `100` |
+/// |`VLAN_ID(X'88A8A064')` | `100` |
+/// |`VLAN_ID(X'0800A064')` | N/A - A query error is raised, as `0x0800` isn't a VLAN tag |
+pub fn vlan_id(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<u16>> {
+    Ok(tci_arg(ctx, 0)?.map(|tci| tci & 0x0fff))
+}
+
+/// # VLAN_PCP(NULL|tci) -> NULL|priority
+/// Returns the priority code point (the high 3 bits) of an 802.1Q tag's control information, as used for class of
+/// service.
+///
+/// The TCI is accepted in the same forms as [VLAN_ID](crate::exports::packet::vlan_id).
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`VLAN_PCP(0xA064)`  | `5` |
+/// |`VLAN_PCP(X'0064')` | `0` |
+pub fn vlan_pcp(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<u8>> {
+    Ok(tci_arg(ctx, 0)?.map(|tci| (tci >> 13) as u8))
+}
+
+/// # VLAN_DEI(NULL|tci) -> NULL|BOOL
+/// Returns the drop eligible indicator (bit 12) of an 802.1Q tag's control information, marking frames that may be
+/// dropped under congestion.
+///
+/// The TCI is accepted in the same forms as [VLAN_ID](crate::exports::packet::vlan_id).
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`VLAN_DEI(0xB064)` | `TRUE` |
+/// |`VLAN_DEI(0xA064)` | `FALSE` |
+pub fn vlan_dei(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<bool>> {
+    Ok(tci_arg(ctx, 0)?.map(|tci| tci & 0x1000 != 0))
+}
+
+#[test]
+fn vlan_fields() {
+    let conn = conn();
+    let int = |sql: &str| query_row::<Option<i64>>(&conn, sql);
+
+    assert_eq!(int("SELECT VLAN_ID(0xA064)"), Some(100));
+    assert_eq!(int("SELECT VLAN_ID(X'88A8A064')"), Some(100));
+    assert_eq!(int("SELECT VLAN_ID(X'9300A064')"), Some(100));
+    assert_eq!(int("SELECT VLAN_PCP(X'A064')"), Some(5));
+    assert_eq!(int("SELECT VLAN_DEI(0xB064)"), Some(1));
+    assert_eq!(int("SELECT VLAN_DEI(NULL)"), None);
+    assert_eq!(conn.query_row("SELECT ETHERTYPE_NAME(0x9200)", [], |r| r.get::<_, String>(0)).unwrap(), "QinQ-9200");
+
+    assert!(query_err(&conn, "SELECT VLAN_ID(X'0800A064')").contains("isn't an 802.1Q or QinQ tag"));
+    assert!(query_err(&conn, "SELECT VLAN_ID(65536)").starts_with("argument 1 (65536)"));
+    assert!(query_err(&conn, "SELECT VLAN_ID(X'A0')").contains("received 1 bytes"));
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+enum MplsError {
+    #[error("MPLS label stack is truncated, with {0} bytes left over where a 4-byte entry was expected")]
+    Truncated(usize),
+    #[error("MPLS label stack ends without an entry marking the bottom of the stack")]
+    NoBottom,
+}
+
+/// Decodes an MPLS label stack into each entry's label, traffic class, bottom of stack flag, and TTL. Decoding stops
+/// at the entry marking the bottom of the stack, so any payload following it is ignored.
+fn parse_mpls_stack(stack: &[u8]) -> Result<Vec<(u32, u8, bool, u8)>, MplsError> {
+    let mut entries = Vec::new();
+    let mut rest = stack;
+    loop {
+        let Some((entry, next)) = rest.split_first_chunk::<4>() else {
+            return Err(match rest.len() {
+                0 => MplsError::NoBottom,
+                n => MplsError::Truncated(n),
+            });
+        };
+        let entry = u32::from_be_bytes(*entry);
+        let bottom = entry & 0x100 != 0;
+        entries.push((entry >> 12, ((entry >> 9) & 0x7) as u8, bottom, entry as u8));
+        if bottom {
+            return Ok(entries);
+        }
+        rest = next;
+    }
+}
+
+/// # MPLS_LABELS(NULL|stack) -> NULL|json
+/// Decodes a raw MPLS label stack into a JSON array with an object for each entry, from the top of the stack down.
+/// Each has its `label`, traffic class (`tc`), bottom of stack flag (`s`), and `ttl`.
+///
+/// Decoding stops at the bottom of the stack, so a blob continuing on into the labelled packet may be passed as is.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`MPLS_LABELS(X'00064140')`                 | `'[{"label":100,"s":true,"tc":0,"ttl":64}]'` |
+/// |`MPLS_LABELS(X'0003E4FF00064140')`         | `'[{"label":62,"s":false,"tc":2,"ttl":255},{"label":100,"s":true,"tc":0,"ttl":64}]'` |
+/// |`MPLS_LABELS(X'00064140') ->> '$[0].label'` | `100` |
+/// |`MPLS_LABELS(X'0003E4FF')`                 | N/A - A query error is raised, as the stack has no bottom |
+pub fn mpls_labels(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let stack = match ctx.get_raw(0) {
+        ValueRef::Null => return Ok(None),
+        ValueRef::Blob(b) => b,
+        _ => return Err(ArgError::invalid(ctx, 0, "expected a blob")),
+    };
+    let entries = parse_mpls_stack(stack).map_err(|e| ArgError::invalid(ctx, 0, e))?;
+
+    let json: Vec<serde_json::Value> = entries.into_iter()
+        .map(|(label, tc, s, ttl)| serde_json::json!({ "label": label, "tc": tc, "s": s, "ttl": ttl }))
+        .collect();
+    Ok(Some(serde_json::Value::from(json).to_string()))
+}
+
+#[test]
+fn mpls_stacks() {
+    assert_eq!(parse_mpls_stack(&[0x00, 0x06, 0x41, 0x40]), Ok(vec![(100, 0, true, 64)]));
+    assert_eq!(parse_mpls_stack(&[0x00, 0x03, 0xE4, 0xFF, 0x00, 0x06, 0x41, 0x40, 0x45]), Ok(vec![(62, 2, false, 255), (100, 0, true, 64)]));
+    assert_eq!(parse_mpls_stack(&[0xFF, 0xFF, 0xFF, 0xFF]), Ok(vec![(0xFFFFF, 7, true, 255)]));
+    assert_eq!(parse_mpls_stack(&[0x00, 0x03, 0xE4, 0xFF]), Err(MplsError::NoBottom));
+    assert_eq!(parse_mpls_stack(&[0x00, 0x03, 0xE4, 0xFF, 0x00]), Err(MplsError::Truncated(1)));
+    assert_eq!(parse_mpls_stack(&[]), Err(MplsError::NoBottom));
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+enum PortSpecError {
+    #[error("Port out of range (got {0}). Expected an integer within [0, 65535]")]
+    PortRange(i64),
+    #[error("Unable to parse port {0:?} in port specification. Expected a port, or a range such as `8000-8100`")]
+    BadPort(String),
+    #[error("Port range {0:?} ends before it starts")]
+    BackwardsRange(String),
+    #[error("Empty port specification")]
+    Empty,
+}
+
+/// Parses a port specification of comma separated ports and inclusive ranges (`80,443,8000-8100`), as written in
+/// firewall rules and scanner configurations, into the ranges it covers. Ranges may also be written with a colon
+/// (`8000:8100`), as iptables does, and `*` or `any` matches every port.
+fn parse_port_spec(spec: &str) -> Result<Vec<(u16, u16)>, PortSpecError> {
+    let mut ranges = Vec::new();
+    for item in spec.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+        if item == "*" || item.eq_ignore_ascii_case("any") {
+            ranges.push((0, u16::MAX));
+            continue;
+        }
+        let port = |s: &str| s.trim().parse::<u16>().map_err(|_| PortSpecError::BadPort(item.to_owned()));
+        let range = match item.split_once(['-', ':']) {
+            Some((lo, hi)) => (port(lo)?, port(hi)?),
+            None => (port(item)?, port(item)?),
+        };
+        if range.0 > range.1 {
+            return Err(PortSpecError::BackwardsRange(item.to_owned()));
+        }
+        ranges.push(range);
+    }
+    match ranges.is_empty() {
+        true => Err(PortSpecError::Empty),
+        false => Ok(ranges),
+    }
+}
+
+/// # PORTRANGE_CONTAINS(NULL|spec, NULL|port) -> NULL|BOOL
+/// Returns true if a port is listed in a port specification, such as those found in firewall rules and scanner
+/// configurations.
+///
+/// Specifications are comma separated ports and inclusive ranges, such as `80,443,8000-8100`. Ranges may also be
+/// written with a colon (`8000:8100`), and `*` or `any` matches every port.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`PORTRANGE_CONTAINS('80,443,8000-8100', 8080)` | `TRUE` |
+/// |`PORTRANGE_CONTAINS('80,443,8000-8100', 22)`   | `FALSE` |
+/// |`PORTRANGE_CONTAINS('1024:65535', 51000)`      | `TRUE` |
+/// |`PORTRANGE_CONTAINS('any', 22)`                | `TRUE` |
+/// |`PORTRANGE_CONTAINS('8100-8000', 8080)`        | N/A - A query error is raised with an appropriate error message |
+/// |`PORTRANGE_CONTAINS('80', 70000)`              | N/A - A query error is raised with an appropriate error message |
+pub fn portrange_contains(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<bool>> {
+    let Some(spec) = text_arg(ctx, 0)? else { return Ok(None); };
+    let port = match ctx.get_raw(1) {
+        ValueRef::Null => return Ok(None),
+        ValueRef::Integer(i) => u16::try_from(i).map_err(|_| ArgError::invalid(ctx, 1, PortSpecError::PortRange(i)))?,
+        _ => return Err(ArgError::invalid(ctx, 1, "expected an integer")),
+    };
+    let ranges = parse_port_spec(spec).map_err(|e| ArgError::invalid(ctx, 0, e))?;
+
+    Ok(Some(ranges.iter().any(|(lo, hi)| (*lo..=*hi).contains(&port))))
+}
+
+#[test]
+fn port_specs() {
+    assert_eq!(parse_port_spec("80,443,8000-8100"), Ok(vec![(80, 80), (443, 443), (8000, 8100)]));
+    assert_eq!(parse_port_spec(" 22 , 1024:65535,"), Ok(vec![(22, 22), (1024, 65535)]));
+    assert_eq!(parse_port_spec("ANY"), Ok(vec![(0, 65535)]));
+    assert_eq!(parse_port_spec("8100-8000"), Err(PortSpecError::BackwardsRange("8100-8000".into())));
+    assert_eq!(parse_port_spec("80,http"), Err(PortSpecError::BadPort("http".into())));
+    assert_eq!(parse_port_spec("65536"), Err(PortSpecError::BadPort("65536".into())));
+    assert_eq!(parse_port_spec(" , "), Err(PortSpecError::Empty));
+}
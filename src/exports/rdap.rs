@@ -0,0 +1,275 @@
+use std::{net::IpAddr, sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex, PoisonError}};
+
+use ipnet::IpNet;
+use serde_json::Value as Json;
+
+use crate::exports::{error::{text_arg, ArgError}, inet::address_arg};
+#[cfg(test)]
+use crate::test_util::conn;
+
+/// IANA's bootstrap registries, naming the RDAP service responsible for each block of addresses (RFC 9224).
+const BOOTSTRAP_V4_URL: &str = "https://data.iana.org/rdap/ipv4.json";
+const BOOTSTRAP_V6_URL: &str = "https://data.iana.org/rdap/ipv6.json";
+
+/// The number of responses kept, after which the oldest is dropped.
+const RESPONSE_CACHE_LEN: usize = 256;
+
+/// Networks and the base URL of the RDAP service responsible for them.
+type Bootstrap = Vec<(IpNet, String)>;
+
+/// The bootstrap registries, fetched on first use and kept for the life of the process.
+static BOOTSTRAP_V4: Mutex<Option<Arc<Bootstrap>>> = Mutex::new(None);
+static BOOTSTRAP_V6: Mutex<Option<Arc<Bootstrap>>> = Mutex::new(None);
+
+/// Responses by the first and last addresses of the network they describe, so later lookups anywhere within that
+/// network are answered without a request.
+static RESPONSES: Mutex<Vec<(IpAddr, IpAddr, Arc<str>)>> = Mutex::new(Vec::new());
+
+/// How many lookups were answered from [RESPONSES], and how many weren't, since the process started.
+static CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+static CACHE_MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// The number of lookups answered from the response cache, and the number needing a request (or with no registry
+/// responsible), since the process started. Reported by `NETTOOLS_STATS`.
+pub fn cache_stats() -> (u64, u64) {
+    (CACHE_HITS.load(Ordering::Relaxed), CACHE_MISSES.load(Ordering::Relaxed))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum RdapError {
+    #[error("Unable to query {0}: {1}")]
+    Request(String, Box<ureq::Error>),
+    #[error("Unable to read the response from {0}: {1}")]
+    Response(String, std::io::Error),
+    #[error("The response from {0} isn't valid JSON: {1}")]
+    Json(String, serde_json::Error),
+    #[error("The bootstrap registry from {0} doesn't list any services")]
+    Bootstrap(String),
+}
+
+/// Fetches a URL as JSON, returning its text and parsed forms, or None when the server has no such resource.
+fn get_json(url: &str) -> Result<Option<(String, Json)>, RdapError> {
+    let _span = span!(DEBUG, "rdap_request", url);
+    let resp = match ureq::get(url).set("Accept", "application/rdap+json, application/json").call() {
+        Ok(resp) => resp,
+        Err(ureq::Error::Status(404, _)) => return Ok(None),
+        Err(e) => {
+            event!(WARN, error = %e, "rdap request failed");
+            return Err(RdapError::Request(url.to_owned(), Box::new(e)));
+        },
+    };
+    let body = resp.into_string().map_err(|e| RdapError::Response(url.to_owned(), e))?;
+    let json = serde_json::from_str(&body).map_err(|e| RdapError::Json(url.to_owned(), e))?;
+    Ok(Some((body, json)))
+}
+
+/// Reads a bootstrap registry, pairing each network with the first of its services' URLs (preferring HTTPS).
+fn parse_bootstrap(json: &Json) -> Bootstrap {
+    let Some(services) = json.get("services").and_then(Json::as_array) else { return Vec::new(); };
+    let mut bootstrap = Vec::new();
+    for service in services {
+        let [Json::Array(nets), Json::Array(urls)] = service.as_array().map(Vec::as_slice).unwrap_or_default() else { continue; };
+        let urls: Vec<&str> = urls.iter().filter_map(Json::as_str).collect();
+        let Some(url) = urls.iter().find(|u| u.starts_with("https:")).or(urls.first()) else { continue; };
+        for net in nets.iter().filter_map(Json::as_str).filter_map(|n| n.parse::<IpNet>().ok()) {
+            bootstrap.push((net, url.to_string()));
+        }
+    }
+    bootstrap
+}
+
+/// Finds the service responsible for an address, by its most specific network.
+fn service_for<'a>(bootstrap: &'a Bootstrap, addr: &IpAddr) -> Option<&'a str> {
+    bootstrap.iter()
+        .filter(|(net, _)| net.contains(addr))
+        .max_by_key(|(net, _)| net.prefix_len())
+        .map(|(_, url)| url.as_str())
+}
+
+/// Returns the bootstrap registry for an address's family, fetching it if this is its first use.
+fn bootstrap(addr: &IpAddr) -> Result<Arc<Bootstrap>, RdapError> {
+    let (cache, url) = match addr {
+        IpAddr::V4(_) => (&BOOTSTRAP_V4, BOOTSTRAP_V4_URL),
+        IpAddr::V6(_) => (&BOOTSTRAP_V6, BOOTSTRAP_V6_URL),
+    };
+    let mut cache = cache.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(bootstrap) = cache.as_ref() {
+        return Ok(Arc::clone(bootstrap));
+    }
+
+    let (_, json) = get_json(url)?.ok_or_else(|| RdapError::Bootstrap(url.to_owned()))?;
+    let bootstrap = parse_bootstrap(&json);
+    if bootstrap.is_empty() {
+        return Err(RdapError::Bootstrap(url.to_owned()));
+    }
+    Ok(Arc::clone(cache.insert(Arc::new(bootstrap))))
+}
+
+/// Reads the first and last addresses of the network described by an IP network response.
+fn network_range(json: &Json) -> Option<(IpAddr, IpAddr)> {
+    let addr = |key: &str| json.get(key)?.as_str()?.parse::<IpAddr>().ok();
+    Some((addr("startAddress")?, addr("endAddress")?))
+}
+
+/// Returns the entities of an object, and those nested within them, such as an abuse contact for a registrant.
+fn entities(json: &Json) -> Vec<&Json> {
+    let mut found = Vec::new();
+    let mut pending: Vec<&Json> = vec![json];
+    while let Some(obj) = pending.pop() {
+        for entity in obj.get("entities").and_then(Json::as_array).into_iter().flatten() {
+            found.push(entity);
+            pending.push(entity);
+        }
+    }
+    found
+}
+
+/// Returns the text of a vCard property of an entity, such as its `fn` (name) or `email`.
+fn vcard_property<'a>(entity: &'a Json, name: &str) -> Option<&'a str> {
+    entity.get("vcardArray")?.get(1)?.as_array()?.iter()
+        .find(|prop| prop.get(0).and_then(Json::as_str) == Some(name))?
+        .get(3)?.as_str()
+}
+
+/// Finds the first entity with a role, including those nested within other entities.
+fn entity_with_role<'a>(json: &'a Json, role: &str) -> Option<&'a Json> {
+    entities(json).into_iter().find(|e| {
+        e.get("roles").and_then(Json::as_array).is_some_and(|roles| roles.iter().any(|r| r.as_str() == Some(role)))
+    })
+}
+
+/// Reads an RDAP response argument as JSON.
+fn response_arg(ctx: &rusqlite::functions::Context<'_>, idx: usize) -> rusqlite::Result<Option<Json>> {
+    let Some(s) = text_arg(ctx, idx)? else { return Ok(None); };
+    serde_json::from_str(s).map(Some).map_err(|e| ArgError::invalid(ctx, idx, e))
+}
+
+/// # RDAP(NULL|ip) -> NULL|json
+/// Queries the regional internet registry responsible for an address through RDAP (the successor to WHOIS), returning
+/// its response describing the registered network, as JSON. The registry is found through IANA's bootstrap registries.
+/// Use [RDAP_NETNAME](crate::exports::rdap::netname), [RDAP_ORG](crate::exports::rdap::org), and
+/// [RDAP_ABUSE](crate::exports::rdap::abuse) to extract common fields.
+///
+/// Responses are cached for the life of the process, by the network they describe, so addresses within a network
+/// already looked up are answered without another request. Addresses no registry is responsible for (such as private
+/// addresses) return NULL.
+///
+/// As this makes network requests, it is [direct-only](crate::exports#direct-only-functions). This function is only
+/// available when the extension is built with the `rdap` feature.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`RDAP('8.8.8.8') ->> '$.name'`       | `'GOGL'` |
+/// |`RDAP('10.0.0.1')`                   | `NULL` |
+/// |`RDAP('10.0.0.0/8')`                 | N/A - A query error is raised, as only addresses are accepted |
+pub fn rdap(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let Some(addr) = address_arg(ctx, 0)? else { return Ok(None); };
+    let to_err = |e: RdapError| rusqlite::Error::UserFunctionError(Box::new(e));
+
+    let cached = RESPONSES.lock().unwrap_or_else(PoisonError::into_inner).iter()
+        .find(|(first, last, _)| (first..=last).contains(&&addr))
+        .map(|(_, _, resp)| Arc::clone(resp));
+    if let Some(resp) = cached {
+        event!(TRACE, %addr, "rdap response cached");
+        CACHE_HITS.fetch_add(1, Ordering::Relaxed);
+        return Ok(Some(resp.to_string()));
+    }
+    CACHE_MISSES.fetch_add(1, Ordering::Relaxed);
+
+    let bootstrap = bootstrap(&addr).map_err(to_err)?;
+    let Some(base) = service_for(&bootstrap, &addr) else { return Ok(None); };
+    let url = format!("{}/ip/{addr}", base.trim_end_matches('/'));
+    let Some((body, json)) = get_json(&url).map_err(to_err)? else { return Ok(None); };
+    if let Some((first, last)) = network_range(&json) {
+        let mut responses = RESPONSES.lock().unwrap_or_else(PoisonError::into_inner);
+        if responses.len() >= RESPONSE_CACHE_LEN {
+            responses.remove(0);
+        }
+        responses.push((first, last, Arc::from(body.as_str())));
+    }
+    Ok(Some(body))
+}
+
+/// # RDAP_NETNAME(NULL|json) -> NULL|text
+/// Returns the name of the network in an [RDAP](crate::exports::rdap::rdap) response.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`RDAP_NETNAME(RDAP('8.8.8.8'))`     | `'GOGL'` |
+/// |`RDAP_NETNAME('{}')`                | `NULL` |
+pub fn netname(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let Some(json) = response_arg(ctx, 0)? else { return Ok(None); };
+    Ok(json.get("name").and_then(Json::as_str).map(str::to_owned))
+}
+
+/// # RDAP_ORG(NULL|json) -> NULL|text
+/// Returns the name of the organization registered as holding the network in an [RDAP](crate::exports::rdap::rdap)
+/// response.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`RDAP_ORG(RDAP('8.8.8.8'))`     | `'Google LLC'` |
+/// |`RDAP_ORG('{}')`                | `NULL` |
+pub fn org(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let Some(json) = response_arg(ctx, 0)? else { return Ok(None); };
+    Ok(entity_with_role(&json, "registrant").and_then(|e| vcard_property(e, "fn")).map(str::to_owned))
+}
+
+/// # RDAP_ABUSE(NULL|json) -> NULL|text
+/// Returns the email address of the abuse contact in an [RDAP](crate::exports::rdap::rdap) response, which may be
+/// nested within the registrant's entity.
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`RDAP_ABUSE(RDAP('8.8.8.8'))`     | `'network-abuse@google.com'` |
+/// |`RDAP_ABUSE('{}')`                | `NULL` |
+pub fn abuse(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let Some(json) = response_arg(ctx, 0)? else { return Ok(None); };
+    Ok(entity_with_role(&json, "abuse").and_then(|e| vcard_property(e, "email")).map(str::to_owned))
+}
+
+#[test]
+fn bootstrap_services() {
+    let json = serde_json::json!({
+        "services": [
+            [["41.0.0.0/8", "102.0.0.0/8"], ["https://rdap.afrinic.net/rdap/", "http://rdap.afrinic.net/rdap/"]],
+            [["8.0.0.0/8"], ["http://rdap.arin.net/registry/"]],
+            [["8.8.0.0/16"], ["https://rdap.example.net/"]],
+            ["malformed"],
+        ]
+    });
+    let bootstrap = parse_bootstrap(&json);
+    assert_eq!(bootstrap.len(), 4);
+    assert_eq!(service_for(&bootstrap, &"102.1.2.3".parse().unwrap()), Some("https://rdap.afrinic.net/rdap/"));
+    assert_eq!(service_for(&bootstrap, &"8.1.2.3".parse().unwrap()), Some("http://rdap.arin.net/registry/"));
+    assert_eq!(service_for(&bootstrap, &"8.8.8.8".parse().unwrap()), Some("https://rdap.example.net/"));
+    assert_eq!(service_for(&bootstrap, &"10.0.0.1".parse().unwrap()), None);
+}
+
+#[test]
+fn rdap_fields() {
+    let resp = r#"{
+        "objectClassName": "ip network", "name": "GOGL", "startAddress": "8.8.8.0", "endAddress": "8.8.8.255",
+        "entities": [{
+            "roles": ["registrant"],
+            "vcardArray": ["vcard", [["version", {}, "text", "4.0"], ["fn", {}, "text", "Google LLC"]]],
+            "entities": [{
+                "roles": ["abuse"],
+                "vcardArray": ["vcard", [["fn", {}, "text", "Abuse"], ["email", {}, "text", "network-abuse@google.com"]]]
+            }]
+        }]
+    }"#;
+    assert_eq!(network_range(&serde_json::from_str(resp).unwrap()), Some(("8.8.8.0".parse().unwrap(), "8.8.8.255".parse().unwrap())));
+
+    let conn = conn();
+    let text = |sql: &str| conn.query_row(sql, [resp], |r| r.get::<_, Option<String>>(0)).unwrap();
+    assert_eq!(text("SELECT RDAP_NETNAME(?1)").as_deref(), Some("GOGL"));
+    assert_eq!(text("SELECT RDAP_ORG(?1)").as_deref(), Some("Google LLC"));
+    assert_eq!(text("SELECT RDAP_ABUSE(?1)").as_deref(), Some("network-abuse@google.com"));
+    assert_eq!(text("SELECT RDAP_ABUSE(json_remove(?1, '$.entities'))"), None);
+    assert!(conn.query_row("SELECT RDAP_ORG('not json')", [], |r| r.get::<_, Option<String>>(0)).is_err());
+}
@@ -0,0 +1,86 @@
+use rusqlite::types::ValueRef;
+
+use crate::{
+    exports::error::{text_arg, ArgError},
+    mac::FormatSpec,
+    settings::SharedSettings,
+    stats::SharedStats,
+};
+#[cfg(test)]
+use crate::test_util::conn;
+
+/// # NETTOOLS_SET(name, NULL|value) -> NULL|previous
+/// Changes a default used by this connection's functions when an argument is omitted, returning the setting's previous
+/// value (or NULL if it was unset). Setting NULL restores the built-in default.
+///
+/// Settings:
+/// * `mac_style` - The format specifier used by [MAC_FORMAT_DEFAULT](crate::exports::mac::format_default). Accepts any
+///   specifier (and flags) that the second argument of `MAC_FORMAT` does. Defaults to `hex`.
+/// * `ipv6_style` - How [IP_FORMAT_DEFAULT](crate::exports::inet::format_default) writes IPv6 addresses:
+///   `compressed` (`fe80::2:3:0:aabb`) or `expanded` (`fe80:0000:0000:0000:0002:0003:0000:aabb`). Defaults to
+///   `compressed`.
+/// * `dns_server` - The DNS server queried by DNS lookups such as `DNSBL_CHECK`, as an IP address with an optional
+///   port. Defaults to the first `nameserver` in `/etc/resolv.conf`. Other platforms have no default, so on Windows
+///   this must be set before any lookup.
+/// * `stats` - `1` to count each function's calls, errors, and time for [NETTOOLS_STATS](crate::exports::info::stats),
+///   or `0` to stop. Defaults to `0`, as timing every call slows queries down.
+///
+/// Settings last as long as the connection, and are not shared with other connections. As the `_DEFAULT` functions
+/// depend on them, they aren't deterministic, and can't be used in indexes or generated columns. Pass the style to
+/// `MAC_FORMAT` explicitly there instead.
+///
+/// This is [direct-only](crate::exports#direct-only-functions).
+///
+/// # Usage
+/// |Call|Result|
+/// |-|-|
+/// |`NETTOOLS_SET('mac_style', 'DASH')`       | `NULL` |
+/// |`MAC_FORMAT_DEFAULT('aa:bb:cc:dd:ee:ff')` | `'AA-BB-CC-DD-EE-FF'` |
+/// |`NETTOOLS_SET('mac_style', NULL)`         | `'DASH'` |
+/// |`NETTOOLS_SET('ipv6_style', 'expanded')`  | `NULL` |
+/// |`NETTOOLS_SET('stats', 1)`                | `NULL` |
+/// |`NETTOOLS_SET('ipv6_style', 'shortest')`  | N/A - A query error is raised with an appropriate error message |
+/// |`NETTOOLS_SET('colour', 'blue')`          | N/A - A query error is raised with an appropriate error message |
+pub fn set(settings: &SharedSettings, stats: &SharedStats, ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<String>> {
+    let Some(name) = text_arg(ctx, 0)? else { return Ok(None); };
+    // switches such as `stats` are usually given as integers
+    let value = match ctx.get_raw(1) {
+        ValueRef::Integer(i) => Some(i.to_string()),
+        _ => text_arg(ctx, 1)?.map(str::to_owned),
+    };
+    let value = value.as_deref();
+
+    // check the MAC style as MAC_FORMAT would, so a bad one is reported here rather than by every later call
+    if let (true, Some(value)) = (name.trim().eq_ignore_ascii_case("mac_style"), value) {
+        value.parse::<FormatSpec>().map_err(|e| ArgError::invalid(ctx, 1, e))?;
+    }
+
+    let mut previous = None;
+    settings.update(|s| {
+        previous = s.get(name).map_err(|e| ArgError::invalid(ctx, 0, e))?;
+        s.set(name, value).map_err(|e| ArgError::invalid(ctx, 1, e))?;
+        stats.set_enabled(s.stats);
+        Ok::<_, rusqlite::Error>(())
+    })?;
+    Ok(previous)
+}
+
+#[test]
+fn connection_defaults() {
+    let conn = conn();
+    let query = |sql: &str| conn.query_row(sql, [], |r| r.get::<_, Option<String>>(0));
+
+    assert_eq!(query("SELECT NETTOOLS_SET('mac_style', 'DASH')").unwrap(), None);
+    assert_eq!(query("SELECT MAC_FORMAT_DEFAULT('aa:bb:cc:dd:ee:ff')").unwrap().as_deref(), Some("AA-BB-CC-DD-EE-FF"));
+    assert_eq!(query("SELECT MAC_FORMAT('aa:bb:cc:dd:ee:ff')").unwrap().as_deref(), Some("aa:bb:cc:dd:ee:ff"));
+    assert_eq!(query("SELECT MAC_FORMAT('aa:bb:cc:dd:ee:ff', 'dot')").unwrap().as_deref(), Some("aabb.ccdd.eeff"));
+    assert!(query("SELECT NETTOOLS_SET('mac_style', 'DaSh')").is_err());
+    assert_eq!(query("SELECT NETTOOLS_SET('mac_style', NULL)").unwrap().as_deref(), Some("DASH"));
+    assert_eq!(query("SELECT MAC_FORMAT_DEFAULT('aa-bb-cc-dd-ee-ff')").unwrap().as_deref(), Some("aa:bb:cc:dd:ee:ff"));
+
+    query("SELECT NETTOOLS_SET('ipv6_style', 'expanded')").unwrap();
+    assert_eq!(query("SELECT IP_FORMAT_DEFAULT('fe80::2:3:0:aabb/10')").unwrap().as_deref(), Some("fe80:0000:0000:0000:0002:0003:0000:aabb/10"));
+    assert_eq!(query("SELECT IP_FORMAT('fe80::2:3:0:aabb/10')").unwrap().as_deref(), Some("fe80::2:3:0:aabb/10"));
+    assert_eq!(query("SELECT IP_FORMAT_DEFAULT('10.0.0.1')").unwrap().as_deref(), Some("10.0.0.1"));
+    assert!(query("SELECT NETTOOLS_SET('colour', 'blue')").is_err());
+}
@@ -0,0 +1,195 @@
+use std::{fmt::Write, net::{IpAddr, Ipv4Addr, Ipv6Addr}};
+
+use ipnet::IpNet;
+use rusqlite::types::ValueRef;
+
+use crate::{exports::{error::text_arg, inet::UserNetAddr}, text::{SqlText, TextBuf}};
+#[cfg(test)]
+use crate::test_util::{query_row, query_text};
+
+/// Reads an IPv4 address as MySQL's `INET_ATON` does, allowing the short forms `a`, `a.b`, and `a.b.c`, where the
+/// last part is the low byte and any missing bytes are zero (`127.1` is `127.0.0.1`).
+fn mysql_inet_aton(s: &str) -> Option<u32> {
+    let mut parts = [0u8; 4];
+    let mut len = 0;
+    for part in s.split('.') {
+        if len == 4 || part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        parts[len] = part.parse().ok()?;
+        len += 1;
+    }
+
+    let (high, low) = parts[..len].split_at(len - 1);
+    let high = high.iter().fold(0u32, |acc, b| (acc << 8) | *b as u32);
+    // the high bytes fill from the top, so a lone part has none (and a shift of 32 would overflow)
+    Some(high.checked_shl(8 * (5 - len) as u32).unwrap_or(0) | low[0] as u32)
+}
+
+/// Reads the argument as text, treating any other type as unusable (as MySQL's functions do, by returning NULL).
+fn lenient_text<'a>(ctx: &'a rusqlite::functions::Context<'_>, idx: usize) -> Option<&'a str> {
+    text_arg(ctx, idx).ok().flatten()
+}
+
+/// CIDRMATCH(NULL|subnet, NULL|ip) -> NULL|bool
+///
+/// Tests if an address is within a subnet given in CIDR notation, as with Splunk's `cidrmatch`. Note that the subnet
+/// comes first, unlike [IP_CONTAINS](crate::exports::inet::contains). As in Splunk, malformed values never match,
+/// rather than being an error.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`CIDRMATCH('192.0.2.0/24', '192.0.2.5')`|`TRUE`|
+/// |`CIDRMATCH('192.0.2.0/24', '198.51.100.1')`|`FALSE`|
+/// |`CIDRMATCH('2001:db8::/32', '2001:db8::1')`|`TRUE`|
+/// |`CIDRMATCH('192.0.2.0/24', 'not an ip')`|`FALSE`|
+pub fn cidrmatch(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<bool>> {
+    if ctx.get_raw(0) == ValueRef::Null || ctx.get_raw(1) == ValueRef::Null {
+        return Ok(None);
+    }
+    let subnet = lenient_text(ctx, 0).and_then(|s| s.parse::<IpNet>().ok());
+    let addr = lenient_text(ctx, 1).and_then(|s| s.parse::<UserNetAddr>().ok());
+
+    Ok(Some(match (subnet, addr) {
+        (Some(subnet), Some(addr)) => addr.within(subnet),
+        _ => false,
+    }))
+}
+
+/// inet_aton(NULL|ipv4) -> NULL|int
+///
+/// Reads an IPv4 address into its integer form, as with MySQL's `INET_ATON`. Short forms are accepted, with the last
+/// part as the low byte (`127.1` is `127.0.0.1`). Malformed addresses return NULL.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`inet_aton('10.0.5.9')`|`167773449`|
+/// |`inet_aton('127.1')`|`2130706433`|
+/// |`inet_aton('10.0.5.256')`|`NULL`|
+pub fn inet_aton(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<i64>> {
+    Ok(lenient_text(ctx, 0).and_then(mysql_inet_aton).map(i64::from))
+}
+
+/// inet_ntoa(NULL|int) -> NULL|text
+///
+/// Writes an IPv4 address from its integer form, as with MySQL's `INET_NTOA`. Integers out of range return NULL.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`inet_ntoa(167773449)`|`'10.0.5.9'`|
+/// |`inet_ntoa(-1)`|`NULL`|
+pub fn inet_ntoa(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<SqlText<TextBuf<16>>>> {
+    let ValueRef::Integer(num) = ctx.get_raw(0) else { return Ok(None); };
+    let Ok(num) = u32::try_from(num) else { return Ok(None); };
+
+    let mut text = TextBuf::new();
+    write!(text, "{}", Ipv4Addr::from(num)).unwrap();
+    Ok(Some(SqlText(text)))
+}
+
+/// inet6_aton(NULL|ip) -> NULL|blob
+///
+/// Converts an IPv4 or IPv6 address into its 4 or 16 byte binary form, as with MySQL's `INET6_ATON`. This is the same
+/// encoding as [IP_BLOBIFY](crate::exports::inet::blobify) uses for addresses. Malformed addresses (including
+/// networks) return NULL.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`inet6_aton('10.0.5.9')`|`X'0A000509'`|
+/// |`inet6_aton('fdfe::5a55:caff:fefa:9089')`|`X'FDFE0000000000005A55CAFFFEFA9089'`|
+/// |`inet6_aton('10.0.0.0/8')`|`NULL`|
+pub fn inet6_aton(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<Vec<u8>>> {
+    let addr = lenient_text(ctx, 0).and_then(|s| s.parse::<IpAddr>().ok());
+    Ok(addr.map(|addr| UserNetAddr::Address(addr).to_blob()))
+}
+
+/// inet6_ntoa(NULL|blob) -> NULL|text
+///
+/// Writes an IPv4 or IPv6 address from its 4 or 16 byte binary form, as with MySQL's `INET6_NTOA`. Other values
+/// return NULL.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`inet6_ntoa(X'0A000509')`|`'10.0.5.9'`|
+/// |`inet6_ntoa(inet6_aton('fdfe::5a55:caff:fefa:9089'))`|`'fdfe::5a55:caff:fefa:9089'`|
+/// |`inet6_ntoa('10.0.5.9')`|`NULL`|
+pub fn inet6_ntoa(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<SqlText<TextBuf<48>>>> {
+    let ValueRef::Blob(dat) = ctx.get_raw(0) else { return Ok(None); };
+    let Ok(UserNetAddr::Address(addr)) = UserNetAddr::from_blob(dat) else { return Ok(None); };
+
+    let mut text = TextBuf::new();
+    write!(text, "{addr}").unwrap();
+    Ok(Some(SqlText(text)))
+}
+
+/// is_ipv4(NULL|text) -> NULL|bool
+///
+/// Tests if text is an IPv4 address in dotted quad form, as with MySQL's `IS_IPV4`.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`is_ipv4('10.0.5.9')`|`TRUE`|
+/// |`is_ipv4('10.0.5.256')`|`FALSE`|
+pub fn is_ipv4(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<bool>> {
+    if ctx.get_raw(0) == ValueRef::Null {
+        return Ok(None);
+    }
+    Ok(Some(lenient_text(ctx, 0).is_some_and(|s| s.parse::<Ipv4Addr>().is_ok())))
+}
+
+/// is_ipv6(NULL|text) -> NULL|bool
+///
+/// Tests if text is an IPv6 address, as with MySQL's `IS_IPV6`.
+///
+/// # Examples
+/// |Call|Result|
+/// |-|-|
+/// |`is_ipv6('10.0.5.9')`|`FALSE`|
+/// |`is_ipv6('::1')`|`TRUE`|
+pub fn is_ipv6(ctx: &rusqlite::functions::Context<'_>) -> rusqlite::Result<Option<bool>> {
+    if ctx.get_raw(0) == ValueRef::Null {
+        return Ok(None);
+    }
+    Ok(Some(lenient_text(ctx, 0).is_some_and(|s| s.parse::<Ipv6Addr>().is_ok())))
+}
+
+#[test]
+fn mysql_short_forms() {
+    assert_eq!(mysql_inet_aton("10.0.5.9"), Some(0x0A000509));
+    assert_eq!(mysql_inet_aton("127"), Some(127));
+    assert_eq!(mysql_inet_aton("127.255"), Some(0x7F0000FF));
+    assert_eq!(mysql_inet_aton("127.2.1"), Some(0x7F020001));
+    assert_eq!(mysql_inet_aton("127.256"), None);
+    assert_eq!(mysql_inet_aton("1.2.3.4.5"), None);
+    assert_eq!(mysql_inet_aton("1..2"), None);
+    assert_eq!(mysql_inet_aton(""), None);
+    assert_eq!(mysql_inet_aton("+1.2.3.4"), None);
+}
+
+#[test]
+fn siem_functions() {
+    use crate::{FunctionGroup, Registration};
+
+    let conn = rusqlite::Connection::open_in_memory().unwrap();
+    Registration::builder().group(FunctionGroup::Siem).register(&conn).unwrap();
+    let int = |sql: &str| query_row::<Option<i64>>(&conn, sql);
+
+    assert_eq!(int("SELECT CIDRMATCH('192.0.2.0/24', '192.0.2.5')"), Some(1));
+    assert_eq!(int("SELECT CIDRMATCH('192.0.2.0/24', '198.51.100.1')"), Some(0));
+    assert_eq!(int("SELECT CIDRMATCH('192.0.2.0/24', 'not an ip')"), Some(0));
+    assert_eq!(int("SELECT CIDRMATCH('192.0.2.0/24', NULL)"), None);
+
+    assert_eq!(int("SELECT inet_aton('127.1')"), Some(2130706433));
+    assert_eq!(query_text(&conn, "SELECT inet_ntoa(inet_aton('10.0.5.9'))").as_deref(), Some("10.0.5.9"));
+    assert_eq!(query_text(&conn, "SELECT inet_ntoa(4294967296)"), None);
+    assert_eq!(query_text(&conn, "SELECT hex(inet6_aton('10.0.5.9'))").as_deref(), Some("0A000509"));
+    assert_eq!(query_text(&conn, "SELECT inet6_ntoa(inet6_aton('fdfe::5a55:caff:fefa:9089'))").as_deref(), Some("fdfe::5a55:caff:fefa:9089"));
+    assert_eq!(query_text(&conn, "SELECT inet6_ntoa(X'0A00050918')"), None);
+    assert_eq!(int("SELECT is_ipv4('10.0.5.9') + is_ipv6('::1') * 2 + is_ipv6('10.0.5.9') * 4"), Some(3));
+}
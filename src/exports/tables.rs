@@ -0,0 +1,1350 @@
+use std::{marker::PhantomData, os::raw::c_int, sync::Arc};
+
+use rusqlite::{
+    types::Value,
+    vtab::{
+        eponymous_only_module, sqlite3_vtab, sqlite3_vtab_cursor, Context, IndexConstraintOp, IndexInfo, Module,
+        VTab, VTabConfig, VTabConnection, VTabCursor, Values,
+    },
+};
+#[cfg(all(feature = "nmap", not(feature = "wasm")))]
+use rusqlite::vtab::{read_only_module, CreateVTab, VTabKind};
+
+#[cfg(all(feature = "nmap", not(feature = "wasm")))]
+use crate::mac::{Eui, FormatSpec};
+use crate::oui::{OuiLayers, SharedOuiDb};
+#[cfg(not(feature = "wasm"))]
+use crate::oui::{DiffEntry, OuiDb, OuiLayerKind, OuiMeta, ParseOuiDbError};
+#[cfg(test)]
+use crate::test_util::{conn, query_row, query_text, TempPath};
+
+/// A table-valued function whose result rows are computed up-front from its arguments.
+///
+/// Implementors are exposed to SQLite through [TableFunctionTab]. Arguments are passed through `HIDDEN` columns,
+/// which must be the last `ARGS` columns declared in `SCHEMA`.
+pub trait TableFunction: Sized + 'static {
+    /// Shared state passed in at registration, such as the connection's OUI database.
+    type Aux: Clone + Default;
+
+    /// The `CREATE TABLE` statement declaring the function's columns, ending with its hidden argument columns.
+    const SCHEMA: &'static str;
+    /// The number of result (non-argument) columns.
+    const COLUMNS: usize;
+    /// The number of hidden argument columns.
+    const ARGS: usize;
+    /// Whether the function has side effects (such as reading files), so is
+    /// [direct-only](crate::exports#direct-only-functions).
+    const DIRECT: bool = false;
+
+    /// Computes every result row. Arguments that were not provided are passed as NULL.
+    fn rows(aux: &Self::Aux, args: &[Value]) -> rusqlite::Result<Vec<Vec<Value>>>;
+}
+
+#[repr(C)]
+pub struct TableFunctionTab<T: TableFunction> {
+    /// Base class. Must be first
+    base: sqlite3_vtab,
+    aux: T::Aux,
+}
+
+impl<T: TableFunction> TableFunctionTab<T> {
+    pub fn module() -> &'static Module<'static, TableFunctionTab<T>> {
+        eponymous_only_module::<TableFunctionTab<T>>()
+    }
+}
+
+unsafe impl<'vtab, T: TableFunction> VTab<'vtab> for TableFunctionTab<T> {
+    type Aux = T::Aux;
+    type Cursor = TableFunctionCursor<'vtab, T>;
+
+    fn connect(db: &mut VTabConnection, aux: Option<&T::Aux>, _args: &[&[u8]]) -> rusqlite::Result<(String, Self)> {
+        db.config(if T::DIRECT { VTabConfig::DirectOnly } else { VTabConfig::Innocuous })?;
+        let vtab = TableFunctionTab {
+            base: sqlite3_vtab::default(),
+            aux: aux.cloned().unwrap_or_default(),
+        };
+        Ok((T::SCHEMA.to_owned(), vtab))
+    }
+
+    fn best_index(&self, info: &mut IndexInfo) -> rusqlite::Result<()> {
+        // pass each argument through as an equality constraint, with idx_num recording which were provided
+        let mut args = vec![None; T::ARGS];
+        for (i, constraint) in info.constraints().enumerate() {
+            let Some(arg) = (constraint.column() as usize).checked_sub(T::COLUMNS) else { continue; };
+            if constraint.is_usable() && constraint.operator() == IndexConstraintOp::SQLITE_INDEX_CONSTRAINT_EQ {
+                args[arg] = Some(i);
+            }
+        }
+
+        let mut idx_num = 0;
+        let mut argv_index = 0;
+        for (arg, constraint) in args.iter().enumerate() {
+            let Some(constraint) = constraint else { continue; };
+            argv_index += 1;
+            idx_num |= 1 << arg;
+            let mut usage = info.constraint_usage(*constraint);
+            usage.set_argv_index(argv_index);
+            usage.set_omit(true);
+        }
+
+        info.set_idx_num(idx_num);
+        info.set_estimated_cost(1000.0 / f64::from(argv_index + 1));
+        Ok(())
+    }
+
+    fn open(&'vtab mut self) -> rusqlite::Result<TableFunctionCursor<'vtab, T>> {
+        Ok(TableFunctionCursor {
+            base: sqlite3_vtab_cursor::default(),
+            aux: self.aux.clone(),
+            args: Vec::new(),
+            rows: Vec::new(),
+            row: 0,
+            phantom: PhantomData,
+        })
+    }
+}
+
+#[repr(C)]
+pub struct TableFunctionCursor<'vtab, T: TableFunction> {
+    /// Base class. Must be first
+    base: sqlite3_vtab_cursor,
+    aux: T::Aux,
+    args: Vec<Value>,
+    rows: Vec<Vec<Value>>,
+    row: usize,
+    phantom: PhantomData<&'vtab TableFunctionTab<T>>,
+}
+
+unsafe impl<T: TableFunction> VTabCursor for TableFunctionCursor<'_, T> {
+    fn filter(&mut self, idx_num: c_int, _idx_str: Option<&str>, args: &Values<'_>) -> rusqlite::Result<()> {
+        let mut provided = args.iter().map(Value::from);
+        self.args = (0..T::ARGS)
+            .map(|arg| match idx_num & (1 << arg) {
+                0 => Value::Null,
+                _ => provided.next().unwrap_or(Value::Null),
+            })
+            .collect();
+        self.rows = T::rows(&self.aux, &self.args)?;
+        self.row = 0;
+        Ok(())
+    }
+
+    fn next(&mut self) -> rusqlite::Result<()> {
+        self.row += 1;
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.row >= self.rows.len()
+    }
+
+    fn column(&self, ctx: &mut Context, i: c_int) -> rusqlite::Result<()> {
+        let i = i as usize;
+        match i.checked_sub(T::COLUMNS) {
+            None => ctx.set_result(&self.rows[self.row][i]),
+            Some(arg) => ctx.set_result(&self.args[arg]),
+        }
+    }
+
+    fn rowid(&self) -> rusqlite::Result<i64> {
+        Ok(self.row as i64 + 1)
+    }
+}
+
+/// # oui
+/// A read-only, eponymous virtual table listing every entry in this connection's OUI database.
+///
+/// Entries from every source are listed, in order of lookup precedence (see [oui](crate::exports::oui)),
+/// then in prefix order. A prefix overridden by a higher precedence source is listed once for each source.
+///
+/// Columns:
+/// * `prefix` - The prefix, in the same notation as [MAC_PREFIX](crate::exports::mac::prefix)
+/// * `length` - The prefix length, in bits
+/// * `manuf` - The short manufacturer name
+/// * `manuf_long` - The long manufacturer name, if any
+/// * `comment` - The entry's comment, if any
+/// * `source` - The database source, as returned by [MAC_MANUF_SOURCE](crate::exports::mac::manuf_source)
+///
+/// # Usage
+/// |Query|Result|
+/// |-|-|
+/// |`SELECT prefix, manuf FROM oui WHERE manuf_long LIKE 'Cisco%' LIMIT 1` | `'00:00:0c', 'Cisco'` |
+/// |`SELECT manuf, source FROM oui WHERE prefix = '3c:a6:f6'`               | `'Apple', 'embedded'` |
+#[repr(C)]
+pub struct OuiTab {
+    /// Base class. Must be first
+    base: sqlite3_vtab,
+    oui_db: SharedOuiDb,
+}
+
+impl OuiTab {
+    pub fn module() -> &'static Module<'static, OuiTab> {
+        eponymous_only_module::<OuiTab>()
+    }
+}
+
+unsafe impl<'vtab> VTab<'vtab> for OuiTab {
+    type Aux = SharedOuiDb;
+    type Cursor = OuiTabCursor<'vtab>;
+
+    fn connect(db: &mut VTabConnection, aux: Option<&SharedOuiDb>, _args: &[&[u8]]) -> rusqlite::Result<(String, OuiTab)> {
+        db.config(VTabConfig::Innocuous)?;
+        let vtab = OuiTab {
+            base: sqlite3_vtab::default(),
+            oui_db: aux.cloned().unwrap_or_default(),
+        };
+        Ok((
+            "CREATE TABLE x(prefix TEXT, length INTEGER, manuf TEXT, manuf_long TEXT, comment TEXT, source TEXT)".to_owned(),
+            vtab,
+        ))
+    }
+
+    fn best_index(&self, info: &mut IndexInfo) -> rusqlite::Result<()> {
+        // always a full scan
+        info.set_estimated_cost(self.oui_db.load().layers().iter().map(|l| l.db.len()).sum::<usize>() as f64);
+        Ok(())
+    }
+
+    fn open(&'vtab mut self) -> rusqlite::Result<OuiTabCursor<'vtab>> {
+        Ok(OuiTabCursor {
+            base: sqlite3_vtab_cursor::default(),
+            layers: self.oui_db.load(),
+            layer: 0,
+            entry: 0,
+            rowid: 0,
+            phantom: PhantomData,
+        })
+    }
+}
+
+/// A cursor over a snapshot of the OUI database layers, taken when the cursor is opened.
+#[repr(C)]
+pub struct OuiTabCursor<'vtab> {
+    /// Base class. Must be first
+    base: sqlite3_vtab_cursor,
+    layers: Arc<OuiLayers>,
+    layer: usize,
+    entry: usize,
+    rowid: i64,
+    phantom: PhantomData<&'vtab OuiTab>,
+}
+
+impl OuiTabCursor<'_> {
+    /// Moves to the next layer while the current one is exhausted.
+    fn skip_exhausted(&mut self) {
+        while let Some(layer) = self.layers.layers().get(self.layer) {
+            if self.entry < layer.db.len() {
+                break;
+            }
+            self.layer += 1;
+            self.entry = 0;
+        }
+    }
+}
+
+unsafe impl VTabCursor for OuiTabCursor<'_> {
+    fn filter(&mut self, _idx_num: c_int, _idx_str: Option<&str>, _args: &Values<'_>) -> rusqlite::Result<()> {
+        self.layer = 0;
+        self.entry = 0;
+        self.rowid = 1;
+        self.skip_exhausted();
+        Ok(())
+    }
+
+    fn next(&mut self) -> rusqlite::Result<()> {
+        self.entry += 1;
+        self.rowid += 1;
+        self.skip_exhausted();
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.layer >= self.layers.layers().len()
+    }
+
+    fn column(&self, ctx: &mut Context, i: c_int) -> rusqlite::Result<()> {
+        let layer = &self.layers.layers()[self.layer];
+        let (oui, meta) = layer.db.entry(self.entry).expect("cursor positioned past the end of an OUI layer");
+        match i {
+            0 => ctx.set_result(&format!("{oui:?}")),
+            1 => ctx.set_result(&oui.length()),
+            2 => ctx.set_result(meta.manuf()),
+            3 => ctx.set_result(&meta.manuf_long()),
+            4 => ctx.set_result(&meta.comment()),
+            5 => ctx.set_result(&layer.provenance()),
+            _ => Err(rusqlite::Error::InvalidColumnIndex(i as usize)),
+        }
+    }
+
+    fn rowid(&self) -> rusqlite::Result<i64> {
+        Ok(self.rowid)
+    }
+}
+
+/// # oui_prefixes(vendor)
+/// A table-valued function listing every prefix registered to a vendor's short manufacturer name (case-insensitive),
+/// such as for building capture filters or switch ACLs from vendor names.
+///
+/// Prefixes are resolved through the same chain of sources as [MAC_MANUF](crate::exports::mac::manuf), so a prefix
+/// overridden to another vendor (for example, with [OUI_LOAD_TABLE](crate::exports::oui::load_table)) is not listed.
+///
+/// Columns:
+/// * `prefix` - The prefix, in the same notation as [MAC_PREFIX](crate::exports::mac::prefix)
+/// * `length` - The prefix length, in bits
+/// * `manuf` - The short manufacturer name
+/// * `manuf_long` - The long manufacturer name, if any
+/// * `comment` - The entry's comment, if any
+/// * `source` - The database source, as returned by [MAC_MANUF_SOURCE](crate::exports::mac::manuf_source)
+///
+/// # Usage
+/// |Query|Result|
+/// |-|-|
+/// |`SELECT prefix FROM oui_prefixes('Xyplex')`           | `'00:a0:9c'` |
+/// |`SELECT count(*) > 100 FROM oui_prefixes('apple')`    | `TRUE` |
+/// |`SELECT count(*) FROM oui_prefixes(NULL)`             | `0` |
+pub struct OuiPrefixes;
+
+impl TableFunction for OuiPrefixes {
+    type Aux = SharedOuiDb;
+
+    const SCHEMA: &'static str = "CREATE TABLE x(prefix TEXT, length INTEGER, manuf TEXT, manuf_long TEXT, comment TEXT, source TEXT, vendor HIDDEN)";
+    const COLUMNS: usize = 6;
+    const ARGS: usize = 1;
+
+    fn rows(oui_db: &SharedOuiDb, args: &[Value]) -> rusqlite::Result<Vec<Vec<Value>>> {
+        let Value::Text(vendor) = &args[0] else { return Ok(Vec::new()); };
+
+        let layers = oui_db.load();
+        let mut rows = Vec::new();
+        for (layer_idx, layer) in layers.layers().iter().enumerate() {
+            for (oui, meta) in layer.db.raw_prefixes() {
+                if !meta.manuf().eq_ignore_ascii_case(vendor) {
+                    continue;
+                }
+
+                // skip prefixes overridden by a higher precedence source
+                let resolved = layers.search_layered(oui.as_mac())
+                    .map(|(o, _om, l)| (o, std::ptr::eq(l, &layers.layers()[layer_idx])));
+                if resolved != Some((oui, true)) {
+                    continue;
+                }
+
+                rows.push(vec![
+                    Value::Text(format!("{oui:?}")),
+                    Value::Integer(oui.length().into()),
+                    Value::Text(meta.manuf().to_string()),
+                    meta.manuf_long().map_or(Value::Null, |s| Value::Text(s.to_string())),
+                    meta.comment().map_or(Value::Null, |s| Value::Text(s.to_string())),
+                    Value::Text(layer.provenance()),
+                ]);
+            }
+        }
+
+        Ok(rows)
+    }
+}
+
+#[test]
+fn oui_prefixes_rows() {
+    let conn = conn();
+    conn.execute_batch("
+        CREATE TABLE lab_ouis (prefix TEXT, short TEXT, long TEXT);
+        INSERT INTO lab_ouis VALUES
+            ('02:00:00', 'Lab', 'Example Lab'),
+            ('02:00:01:50:00:00/28', 'LAB', NULL),
+            ('02:00:02:33:40:00/36', 'lab', NULL),
+            ('02:00:00:a0:00:00/28', 'Other', NULL),
+            ('02:00:03', 'Other', NULL);
+        SELECT OUI_LOAD_TABLE('lab_ouis');
+    ").unwrap();
+    // a prefix overridden to another vendor by a higher precedence source isn't listed
+    conn.query_row("SELECT OUI_REGISTER('02:00:02:33:40:00/36', 'Elsewhere')", [], |_| Ok(())).unwrap();
+
+    let prefixes = |vendor: &str| query_text(&conn, &format!(
+        "SELECT group_concat(prefix || ' ' || length || ' ' || source, ', ') FROM oui_prefixes('{vendor}')"
+    ));
+    assert_eq!(prefixes("lab").as_deref(), Some("02:00:00 24 table:lab_ouis, 02:00:01:50:00:00/28 28 table:lab_ouis"));
+    assert_eq!(prefixes("other").as_deref(), Some("02:00:00:a0:00:00/28 28 table:lab_ouis, 02:00:03 24 table:lab_ouis"));
+    assert_eq!(prefixes("elsewhere").as_deref(), Some("02:00:02:33:40:00/36 36 registered"));
+    assert_eq!(prefixes("nobody"), None);
+}
+
+/// Tests if text matches an SQL `LIKE` pattern, where `%` matches any run of characters and `_` any single character.
+/// As with SQLite's `LIKE`, only ASCII letters are compared case-insensitively.
+fn like_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().map(|c| c.to_ascii_lowercase()).collect();
+    let text: Vec<char> = text.chars().map(|c| c.to_ascii_lowercase()).collect();
+
+    // on a mismatch, backtrack to the last `%` and let it match one more character
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack = None;
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('%') => {
+                backtrack = Some((p, t));
+                p += 1;
+            },
+            Some(&c) if c == '_' || c == text[t] => {
+                p += 1;
+                t += 1;
+            },
+            _ => match backtrack {
+                Some((bp, bt)) => {
+                    p = bp + 1;
+                    t = bt + 1;
+                    backtrack = Some((bp, bt + 1));
+                },
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '%')
+}
+
+/// How an [OuiSearch] pattern is matched against vendor names.
+enum SearchPattern {
+    Like(String),
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl SearchPattern {
+    fn new(pattern: &str, mode: Option<&str>) -> rusqlite::Result<SearchPattern> {
+        match mode.map(|m| m.trim().to_ascii_lowercase()).as_deref() {
+            None | Some("like") => Ok(SearchPattern::Like(pattern.to_owned())),
+            #[cfg(feature = "regex")]
+            Some("regex") => regex::RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .build()
+                .map(SearchPattern::Regex)
+                .map_err(|e| rusqlite::Error::ModuleError(format!("Bad oui_search regex {pattern:?}: {e}"))),
+            #[cfg(not(feature = "regex"))]
+            Some("regex") => Err(rusqlite::Error::ModuleError("oui_search regex patterns require the `regex` feature".to_owned())),
+            Some(_) => Err(rusqlite::Error::ModuleError(format!("Bad oui_search mode {:?}. Omit the mode, or provide one of `like`, `regex`", mode.unwrap_or_default()))),
+        }
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        match self {
+            SearchPattern::Like(pattern) => like_match(pattern, text),
+            #[cfg(feature = "regex")]
+            SearchPattern::Regex(re) => re.is_match(text),
+        }
+    }
+}
+
+/// # oui_search(pattern, [mode])
+/// A table-valued function listing every prefix whose short name, long name, or comment matches a pattern, for finding
+/// a vendor from part of its name. Unlike [oui_prefixes](OuiPrefixes), the name doesn't need to be exact.
+///
+/// Patterns are matched as with SQL's `LIKE` by default, case-insensitively, where `%` matches any run of characters
+/// and `_` any single character. With the `regex` feature, a mode of `regex` matches a case-insensitive regular
+/// expression anywhere within the names instead.
+///
+/// Prefixes are resolved through the same chain of sources as [MAC_MANUF](crate::exports::mac::manuf), so a prefix
+/// overridden by a higher precedence source is listed only with its current entry.
+///
+/// Columns:
+/// * `prefix` - The prefix, in the same notation as [MAC_PREFIX](crate::exports::mac::prefix)
+/// * `length` - The prefix length, in bits
+/// * `manuf` - The short manufacturer name
+/// * `manuf_long` - The long manufacturer name, if any
+/// * `comment` - The entry's comment, if any
+/// * `source` - The database source, as returned by [MAC_MANUF_SOURCE](crate::exports::mac::manuf_source)
+///
+/// # Usage
+/// |Query|Result|
+/// |-|-|
+/// |`SELECT prefix, manuf_long FROM oui_search('%satellite%') LIMIT 1`   | `'00:03:ca', 'MTS Systems Corp. Satellite ...'` |
+/// |`SELECT count(*) > 100 FROM oui_search('apple%')`                    | `TRUE` |
+/// |`SELECT DISTINCT manuf FROM oui_search('^cisco.*systems$', 'regex')` | `'Cisco'`, ... |
+/// |`SELECT count(*) FROM oui_search(NULL)`                              | `0` |
+pub struct OuiSearch;
+
+impl TableFunction for OuiSearch {
+    type Aux = SharedOuiDb;
+
+    const SCHEMA: &'static str = "CREATE TABLE x(prefix TEXT, length INTEGER, manuf TEXT, manuf_long TEXT, comment TEXT, source TEXT, pattern HIDDEN, mode HIDDEN)";
+    const COLUMNS: usize = 6;
+    const ARGS: usize = 2;
+
+    fn rows(oui_db: &SharedOuiDb, args: &[Value]) -> rusqlite::Result<Vec<Vec<Value>>> {
+        let Value::Text(pattern) = &args[0] else { return Ok(Vec::new()); };
+        let mode = match &args[1] {
+            Value::Text(mode) => Some(mode.as_str()),
+            _ => None,
+        };
+        let pattern = SearchPattern::new(pattern, mode)?;
+
+        let layers = oui_db.load();
+        let mut rows = Vec::new();
+        for (layer_idx, layer) in layers.layers().iter().enumerate() {
+            for (oui, meta) in layer.db.raw_prefixes() {
+                let names = [Some(*meta.manuf()), meta.manuf_long().copied(), meta.comment().copied()];
+                if !names.iter().flatten().any(|name| pattern.is_match(name)) {
+                    continue;
+                }
+
+                // skip prefixes overridden by a higher precedence source
+                let resolved = layers.search_layered(oui.as_mac())
+                    .map(|(o, _om, l)| (o, std::ptr::eq(l, &layers.layers()[layer_idx])));
+                if resolved != Some((oui, true)) {
+                    continue;
+                }
+
+                rows.push(vec![
+                    Value::Text(format!("{oui:?}")),
+                    Value::Integer(oui.length().into()),
+                    Value::Text(meta.manuf().to_string()),
+                    meta.manuf_long().map_or(Value::Null, |s| Value::Text(s.to_string())),
+                    meta.comment().map_or(Value::Null, |s| Value::Text(s.to_string())),
+                    Value::Text(layer.provenance()),
+                ]);
+            }
+        }
+
+        Ok(rows)
+    }
+}
+
+#[test]
+fn like_patterns() {
+    assert!(like_match("%satellite%", "Hughes Satellite Systems"));
+    assert!(like_match("apple%", "Apple, Inc."));
+    assert!(like_match("a_ple", "APPLE"));
+    assert!(like_match("%", ""));
+    assert!(like_match("%a%b%", "xxaxxbxx"));
+    assert!(!like_match("apple", "Apple, Inc."));
+    assert!(!like_match("%satellite", "Satellites"));
+    assert!(!like_match("_", ""));
+    assert!(like_match("_ü", "Xü"));
+}
+
+#[test]
+fn oui_search_rows() {
+    let conn = conn();
+    conn.query_row("SELECT OUI_REGISTER('02:00:00', 'Sat', 'Example Satellite Labs')", [], |_| Ok(())).unwrap();
+    let int = |sql: &str| query_row::<i64>(&conn, sql);
+
+    assert_eq!(query_text(&conn, "SELECT group_concat(source) FROM oui_search('%SATELLITE LABS')").as_deref(), Some("registered"));
+    assert_eq!(int("SELECT count(*) > 0 FROM oui_search('apple%')"), 1);
+    assert_eq!(int("SELECT count(*) FROM oui_search(NULL)"), 0);
+    assert!(conn.query_row("SELECT count(*) FROM oui_search('x', 'glob')", [], |r| r.get::<_, i64>(0)).is_err());
+
+    #[cfg(feature = "regex")]
+    assert_eq!(query_text(&conn, "SELECT group_concat(manuf) FROM oui_search('^example sat', 'regex')").as_deref(), Some("Sat"));
+}
+
+#[test]
+#[cfg_attr(feature = "ieee-oui-db", ignore = "expects Wireshark's manuf naming")]
+fn oui_rows() {
+    let conn = conn();
+    conn.query_row("SELECT OUI_REGISTER('02:00:00', 'Lab', 'Example Lab')", [], |_| Ok(())).unwrap();
+    let int = |sql: &str| query_row::<i64>(&conn, sql);
+
+    // without a constraint, every entry of every layer is listed, beginning with the highest precedence layer
+    assert_eq!(int("SELECT count(*) FROM oui"), int("SELECT OUI_DB_SIZE()"));
+    assert_eq!(query_text(&conn, "SELECT prefix || ' ' || source FROM oui LIMIT 1").as_deref(), Some("02:00:00 registered"));
+    assert_eq!(int("SELECT count(*) FROM oui WHERE source = 'embedded'"), crate::oui::EMBEDDED_DB.len() as i64);
+
+    // a prefix constraint is checked by SQLite against each row
+    let row = |prefix: &str| query_text(&conn, &format!(
+        "SELECT length || ' ' || manuf || ' ' || ifnull(manuf_long, '-') || ' ' || source FROM oui WHERE prefix = '{prefix}'"
+    ));
+    assert_eq!(row("02:00:00").as_deref(), Some("24 Lab Example Lab registered"));
+    assert_eq!(row("00:00:0c").as_deref(), Some("24 Cisco Cisco Systems, Inc embedded"));
+    assert_eq!(row("00:00:17").as_deref(), Some("24 Oracle - embedded"));
+    assert_eq!(int("SELECT count(*) FROM oui WHERE prefix = '02:00:01'"), 0);
+}
+
+/// # manuf_file(path)
+/// A table-valued function parsing a Wireshark-format `manuf` file into one row per entry, without loading it as a
+/// source for lookups. This allows a custom database to be checked before loading it with
+/// [OUI_LOAD](crate::exports::oui::load).
+///
+/// Unlike `OUI_LOAD`, a bad line doesn't stop the file from being read. It's listed with the reason in `error`, and
+/// NULL for any fields that couldn't be parsed. Lines repeating an earlier line's prefix are also reported, as only
+/// the first is kept when loaded.
+///
+/// As this reads from the filesystem, it is [direct-only](crate::exports#direct-only-functions).
+///
+/// Columns:
+/// * `prefix` - The prefix, in the same notation as [MAC_PREFIX](crate::exports::mac::prefix)
+/// * `length` - The prefix length, in bits
+/// * `manuf` - The short manufacturer name
+/// * `manuf_long` - The long manufacturer name, if any
+/// * `comment` - The entry's comment, if any
+/// * `line` - The line number of the entry, counting from 1
+/// * `error` - Why the line can't be loaded, or NULL if it can
+///
+/// # Usage
+/// |Query|Result|
+/// |-|-|
+/// |`SELECT prefix, manuf FROM manuf_file('/usr/share/wireshark/manuf') LIMIT 1` | `'00:00:00', '00:00:00'` |
+/// |`SELECT line, error FROM manuf_file('lab.manuf') WHERE error IS NOT NULL`    | `3, 'expected 2 to 4 tab separated fields, found 1'` |
+/// |`SELECT count(*) FROM manuf_file(NULL)`                                      | `0` |
+#[cfg(not(feature = "wasm"))]
+pub struct ManufFile;
+
+#[cfg(not(feature = "wasm"))]
+impl TableFunction for ManufFile {
+    type Aux = ();
+
+    const SCHEMA: &'static str = "CREATE TABLE x(prefix TEXT, length INTEGER, manuf TEXT, manuf_long TEXT, comment TEXT, line INTEGER, error TEXT, path HIDDEN)";
+    const COLUMNS: usize = 7;
+    const ARGS: usize = 1;
+    const DIRECT: bool = true;
+
+    fn rows(_aux: &(), args: &[Value]) -> rusqlite::Result<Vec<Vec<Value>>> {
+        let Value::Text(path) = &args[0] else { return Ok(Vec::new()); };
+        let txt = std::fs::read_to_string(path)
+            .map_err(|e| rusqlite::Error::ModuleError(format!("Unable to read OUI database file {path:?}: {e}")))?;
+
+        let mut first_lines = std::collections::BTreeMap::new();
+        let mut rows = Vec::new();
+        for (lnum, l) in txt.split('\n').enumerate().map(|(i, l)| (i + 1, l)) {
+            let row = match OuiDb::parse_manuf_line(lnum, l) {
+                Ok(None) => continue,
+                Ok(Some((oui, meta))) => {
+                    let first = *first_lines.entry(oui).or_insert(lnum);
+                    vec![
+                        Value::Text(format!("{oui:?}")),
+                        Value::Integer(oui.length().into()),
+                        Value::Text(meta.manuf().to_string()),
+                        meta.manuf_long().map_or(Value::Null, |s| Value::Text(s.to_string())),
+                        meta.comment().map_or(Value::Null, |s| Value::Text(s.to_string())),
+                        Value::Integer(lnum as i64),
+                        match first == lnum {
+                            true => Value::Null,
+                            false => Value::Text(format!("duplicate of the prefix on line {first}")),
+                        },
+                    ]
+                },
+                Err(e) => {
+                    let error = match e {
+                        ParseOuiDbError::OuiParsing(_, e, _) => e.to_string(),
+                        ParseOuiDbError::BadFieldCount(_, n, _) => format!("expected 2 to 4 tab separated fields, found {n}"),
+                        e => e.to_string(),
+                    };
+                    let mut row = vec![Value::Null; Self::COLUMNS];
+                    row[5] = Value::Integer(lnum as i64);
+                    row[6] = Value::Text(error);
+                    row
+                },
+            };
+            rows.push(row);
+        }
+
+        Ok(rows)
+    }
+}
+
+/// # oui_diff(older, [newer])
+/// A table-valued function comparing two OUI database files, listing each prefix added, removed, or renamed between
+/// them. With one file, it's compared against the database this connection started with (usually the embedded
+/// database), as the older of the two. This allows reviewing how vendor attribution changes before loading a new file.
+///
+/// Files may be in any format read by [OUI_LOAD](crate::exports::oui::load). As this reads from the filesystem, it is
+/// [direct-only](crate::exports#direct-only-functions).
+///
+/// Columns:
+/// * `change` - One of `added`, `removed`, or `changed`
+/// * `prefix` - The prefix, in the same notation as [MAC_PREFIX](crate::exports::mac::prefix)
+/// * `length` - The prefix length, in bits
+/// * `old_manuf`, `old_manuf_long`, `old_comment` - The older database's entry, or NULL when added
+/// * `new_manuf`, `new_manuf_long`, `new_comment` - The newer database's entry, or NULL when removed
+///
+/// # Usage
+/// |Query|Result|
+/// |-|-|
+/// |`SELECT change, count(*) FROM oui_diff('manuf-4.2', 'manuf-4.4') GROUP BY change` | `'added', 1204`, ... |
+/// |`SELECT prefix, old_manuf, new_manuf FROM oui_diff('manuf') WHERE change = 'changed'` | `'00:1b:63', 'Apple', 'AppleInc'`, ... |
+/// |`SELECT count(*) FROM oui_diff(NULL)` | `0` |
+#[cfg(not(feature = "wasm"))]
+pub struct OuiDiff;
+
+#[cfg(not(feature = "wasm"))]
+impl TableFunction for OuiDiff {
+    type Aux = SharedOuiDb;
+
+    const SCHEMA: &'static str = "CREATE TABLE x(change TEXT, prefix TEXT, length INTEGER, old_manuf TEXT, old_manuf_long TEXT, old_comment TEXT, new_manuf TEXT, new_manuf_long TEXT, new_comment TEXT, older HIDDEN, newer HIDDEN)";
+    const COLUMNS: usize = 9;
+    const ARGS: usize = 2;
+    const DIRECT: bool = true;
+
+    fn rows(oui_db: &SharedOuiDb, args: &[Value]) -> rusqlite::Result<Vec<Vec<Value>>> {
+        let read = |path: &str| crate::exports::oui::read_db_file(path)
+            .map(std::sync::Arc::new)
+            .map_err(|e| rusqlite::Error::ModuleError(e.to_string()));
+        let (older, newer) = match args {
+            [Value::Text(older), Value::Text(newer)] => (read(older)?, read(newer)?),
+            [Value::Text(newer), Value::Null] => {
+                let layers = oui_db.load();
+                let started = layers.layers().iter().find(|l| l.kind == OuiLayerKind::Embedded)
+                    .map_or_else(Default::default, |l| l.db.clone());
+                (started, read(newer)?)
+            },
+            _ => return Ok(Vec::new()),
+        };
+
+        let meta = |om: Option<OuiMeta<&str>>| match om {
+            None => [Value::Null, Value::Null, Value::Null],
+            Some(om) => [
+                Value::Text(om.manuf().to_string()),
+                om.manuf_long().map_or(Value::Null, |s| Value::Text(s.to_string())),
+                om.comment().map_or(Value::Null, |s| Value::Text(s.to_string())),
+            ],
+        };
+        let rows = older.diff(&newer).map(|entry| {
+            let (change, oui, old, new) = match entry {
+                DiffEntry::Added(oui, om) => ("added", oui, None, Some(om)),
+                DiffEntry::Removed(oui, om) => ("removed", oui, Some(om), None),
+                DiffEntry::Changed(oui, old, new) => ("changed", oui, Some(old), Some(new)),
+            };
+            let mut row = vec![
+                Value::Text(change.to_owned()),
+                Value::Text(format!("{oui:?}")),
+                Value::Integer(oui.length().into()),
+            ];
+            row.extend(meta(old));
+            row.extend(meta(new));
+            row
+        }).collect();
+
+        Ok(rows)
+    }
+}
+
+#[cfg(not(feature = "wasm"))]
+#[test]
+fn oui_diff_rows() {
+    let older = TempPath::file("oui-diff-old.txt", "02:00:00\tLab\n02:00:01\tOld\n02:00:02\tSame\n");
+    let newer = TempPath::file("oui-diff-new.txt", "02:00:00\tLabs\tLab Devices\n02:00:02\tSame\n02:00:03\tNew\n");
+
+    let conn = conn();
+    let mut stmt = conn.prepare("SELECT change || ' ' || prefix, old_manuf, new_manuf FROM oui_diff(?1, ?2)").unwrap();
+    let rows: Vec<(String, Option<String>, Option<String>)> = stmt
+        .query_map([older.as_str(), newer.as_str()], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(rows, vec![
+        ("changed 02:00:00".to_owned(), Some("Lab".to_owned()), Some("Labs".to_owned())),
+        ("removed 02:00:01".to_owned(), Some("Old".to_owned()), None),
+        ("added 02:00:03".to_owned(), None, Some("New".to_owned())),
+    ]);
+
+    // against the embedded database, nearly every prefix is removed
+    let (added, removed): (i64, i64) = conn.query_row(
+        "SELECT count(*) FILTER (WHERE change = 'added'), count(*) FILTER (WHERE change = 'removed') FROM oui_diff(?1)",
+        [newer.as_str()],
+        |r| Ok((r.get(0)?, r.get(1)?)),
+    ).unwrap();
+    assert_eq!(added, 3);
+    assert!(removed > 1000);
+}
+
+#[cfg(not(feature = "wasm"))]
+#[test]
+fn manuf_file_rows() {
+    let path = TempPath::file("manuf-file.txt", "# lab prefixes\n\n02:00:00\tLab\tLab Devices\n02:00:01\n02:00:00\tLabAgain\nzz:00:00\tBad\n");
+
+    let conn = conn();
+    let mut stmt = conn.prepare("SELECT line, prefix || ' ' || manuf || ' ' || manuf_long, error FROM manuf_file(?1)").unwrap();
+    let rows: Vec<(i64, Option<String>, Option<String>)> = stmt
+        .query_map([path.as_str()], |r| Ok((r.get(0)?, r.get(1)?, r.get(2)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(rows.len(), 4);
+    assert_eq!(rows[0], (3, Some("02:00:00 Lab Lab Devices".into()), None));
+    assert_eq!(rows[1], (4, None, Some("expected 2 to 4 tab separated fields, found 1".into())));
+    assert_eq!(rows[2].2.as_deref(), Some("duplicate of the prefix on line 3"));
+    assert_eq!((rows[3].0, rows[3].2.is_some()), (6, true));
+
+    // the file isn't loaded as a lookup source
+    let manuf: Option<String> = conn.query_row("SELECT MAC_MANUF('02:00:00:00:00:01')", [], |r| r.get(0)).unwrap();
+    assert_eq!(manuf, None);
+    assert!(conn.query_row("SELECT count(*) FROM manuf_file('/does/not/exist')", [], |r| r.get::<_, i64>(0)).is_err());
+}
+
+/// # netflow_file(path)
+/// A table-valued function reading a file of NetFlow v5 or IPFIX export packets, as captured back to back by a
+/// collector (including IPFIX files, per RFC 5655), into one row per flow. Collector-specific formats (such as
+/// nfdump's) aren't read, so export them as IPFIX first.
+///
+/// IPFIX data is read using the templates sent before it in the file. Data for a template not yet seen is skipped.
+///
+/// As this reads from the filesystem, it is [direct-only](crate::exports#direct-only-functions).
+///
+/// Columns:
+/// * `version` - The export version the flow was read from: `5` for NetFlow v5, or `10` for IPFIX
+/// * `start`, `end` - When the flow started and ended, in milliseconds since the Unix epoch. IPFIX flows without an
+///   end time use the export time of their message
+/// * `src`, `dst` - The source and destination addresses
+/// * `src_port`, `dst_port` - The source and destination ports
+/// * `protocol` - The IP protocol number, such as `6` for TCP
+/// * `tcp_flags` - The TCP flags seen across the flow
+/// * `bytes`, `packets` - The number of bytes and packets in the flow
+///
+/// Columns not sent in an IPFIX flow's template are NULL.
+///
+/// # Usage
+/// |Query|Result|
+/// |-|-|
+/// |`SELECT src, dst, bytes FROM netflow_file('flows.bin') ORDER BY bytes DESC LIMIT 1` | `'10.0.0.5', '192.0.2.9', 1048576` |
+/// |`SELECT datetime(start / 1000, 'unixepoch') FROM netflow_file('flows.ipfix') LIMIT 1` | `'2024-03-14 12:00:00'` |
+/// |`SELECT count(*) FROM netflow_file(NULL)` | `0` |
+#[cfg(all(feature = "netflow", not(feature = "wasm")))]
+pub struct NetflowFile;
+
+#[cfg(all(feature = "netflow", not(feature = "wasm")))]
+impl TableFunction for NetflowFile {
+    type Aux = ();
+
+    const SCHEMA: &'static str = "CREATE TABLE x(version INTEGER, start INTEGER, end INTEGER, src TEXT, dst TEXT, src_port INTEGER, dst_port INTEGER, protocol INTEGER, tcp_flags INTEGER, bytes INTEGER, packets INTEGER, path HIDDEN)";
+    const COLUMNS: usize = 11;
+    const ARGS: usize = 1;
+    const DIRECT: bool = true;
+
+    fn rows(_aux: &(), args: &[Value]) -> rusqlite::Result<Vec<Vec<Value>>> {
+        let Value::Text(path) = &args[0] else { return Ok(Vec::new()); };
+        let data = std::fs::read(path)
+            .map_err(|e| rusqlite::Error::ModuleError(format!("Unable to read flow file {path:?}: {e}")))?;
+        let flows = crate::flow::parse_flows(&data)
+            .map_err(|e| rusqlite::Error::ModuleError(format!("Unable to parse flow file {path:?}: {e}")))?;
+
+        let int = |v: Option<i64>| v.map_or(Value::Null, Value::Integer);
+        let rows = flows.into_iter().map(|f| vec![
+            Value::Integer(f.version.into()),
+            int(f.start),
+            int(f.end),
+            f.src.map_or(Value::Null, |a| Value::Text(a.to_string())),
+            f.dst.map_or(Value::Null, |a| Value::Text(a.to_string())),
+            int(f.src_port.map(i64::from)),
+            int(f.dst_port.map(i64::from)),
+            int(f.protocol.map(i64::from)),
+            int(f.tcp_flags.map(i64::from)),
+            // counters beyond i64 are only possible from corrupt data
+            int(f.bytes.map(|n| n.min(i64::MAX as u64) as i64)),
+            int(f.packets.map(|n| n.min(i64::MAX as u64) as i64)),
+        ]).collect();
+
+        Ok(rows)
+    }
+}
+
+#[cfg(all(feature = "netflow", not(feature = "wasm")))]
+#[test]
+fn netflow_file_rows() {
+    // a NetFlow v5 header and one record, for a 1500 byte TCP flow ending at the export time
+    let mut data = vec![0, 5, 0, 1, 0, 0, 0x27, 0x10, 0x65, 0x53, 0xf1, 0x00, 0, 0, 0, 0];
+    data.extend_from_slice(&[0; 8]);
+    let mut record = [0u8; 48];
+    record[0..8].copy_from_slice(&[10, 0, 0, 1, 192, 0, 2, 9]);
+    record[16..24].copy_from_slice(&[0, 0, 0, 3, 0, 0, 0x05, 0xdc]);
+    record[24..32].copy_from_slice(&[0, 0, 0x23, 0x28, 0, 0, 0x27, 0x10]);
+    record[32..36].copy_from_slice(&[0xc7, 0x38, 0x01, 0xbb]);
+    record[38] = 6;
+    data.extend_from_slice(&record);
+
+    let path = TempPath::file("netflow-file.bin", &data);
+    let conn = conn();
+    let row = conn.query_row(
+        "SELECT src || ' ' || dst || ' ' || dst_port, bytes, end - start, datetime(end / 1000, 'unixepoch') FROM netflow_file(?1)",
+        [path.as_str()],
+        |r| Ok((r.get::<_, String>(0)?, r.get::<_, i64>(1)?, r.get::<_, i64>(2)?, r.get::<_, String>(3)?)),
+    ).unwrap();
+    assert_eq!(row, ("10.0.0.1 192.0.2.9 443".to_owned(), 1500, 1000, "2023-11-14 22:13:20".to_owned()));
+}
+
+/// How a field of a Zeek log is typed, as named in its `#types` header.
+#[cfg(not(feature = "wasm"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ZeekType {
+    Time,
+    Text,
+    Addr,
+    Port,
+    Count,
+    Interval,
+    Bool,
+    Set,
+}
+
+/// The standard fields of Zeek's `conn.log`, in column order. Columns are named as the fields, without the `id.` prefix.
+#[cfg(not(feature = "wasm"))]
+const ZEEK_CONN_FIELDS: &[(&str, ZeekType)] = &[
+    ("ts", ZeekType::Time),
+    ("uid", ZeekType::Text),
+    ("id.orig_h", ZeekType::Addr),
+    ("id.orig_p", ZeekType::Port),
+    ("id.resp_h", ZeekType::Addr),
+    ("id.resp_p", ZeekType::Port),
+    ("proto", ZeekType::Text),
+    ("service", ZeekType::Text),
+    ("duration", ZeekType::Interval),
+    ("orig_bytes", ZeekType::Count),
+    ("resp_bytes", ZeekType::Count),
+    ("conn_state", ZeekType::Text),
+    ("local_orig", ZeekType::Bool),
+    ("local_resp", ZeekType::Bool),
+    ("missed_bytes", ZeekType::Count),
+    ("history", ZeekType::Text),
+    ("orig_pkts", ZeekType::Count),
+    ("orig_ip_bytes", ZeekType::Count),
+    ("resp_pkts", ZeekType::Count),
+    ("resp_ip_bytes", ZeekType::Count),
+    ("tunnel_parents", ZeekType::Set),
+];
+
+/// Converts a field of a Zeek TSV log. Unset fields (`-` by default) are NULL, and sets become JSON arrays.
+#[cfg(not(feature = "wasm"))]
+fn zeek_tsv_value(ty: ZeekType, field: &str, unset: &str, empty: &str, set_separator: &str) -> Result<Value, String> {
+    if field == unset {
+        return Ok(Value::Null);
+    }
+    let bad = |what: &str| format!("expected {what}, found {field:?}");
+    Ok(match ty {
+        ZeekType::Time | ZeekType::Interval => Value::Real(field.parse().map_err(|_| bad("a number"))?),
+        ZeekType::Port | ZeekType::Count => Value::Integer(field.parse().map_err(|_| bad("an integer"))?),
+        ZeekType::Bool => match field {
+            "T" => Value::Integer(1),
+            "F" => Value::Integer(0),
+            _ => return Err(bad("T or F")),
+        },
+        ZeekType::Addr => Value::Text(field.parse::<std::net::IpAddr>().map_err(|_| bad("an IP address"))?.to_string()),
+        ZeekType::Text if field == empty => Value::Text(String::new()),
+        ZeekType::Text => Value::Text(field.to_owned()),
+        ZeekType::Set => {
+            let items: Vec<&str> = match field == empty {
+                true => Vec::new(),
+                false => field.split(set_separator).collect(),
+            };
+            Value::Text(serde_json::Value::from(items).to_string())
+        },
+    })
+}
+
+/// Converts a field of a Zeek JSON log. Times may be numbers, or text when Zeek is configured to write ISO 8601.
+#[cfg(not(feature = "wasm"))]
+fn zeek_json_value(ty: ZeekType, field: &serde_json::Value) -> Result<Value, String> {
+    use serde_json::Value as Json;
+
+    let bad = |what: &str| format!("expected {what}, found {field}");
+    Ok(match (ty, field) {
+        (_, Json::Null) => Value::Null,
+        (ZeekType::Time, Json::String(s)) => Value::Text(s.clone()),
+        (ZeekType::Time | ZeekType::Interval, Json::Number(n)) => Value::Real(n.as_f64().ok_or_else(|| bad("a number"))?),
+        (ZeekType::Port | ZeekType::Count, Json::Number(n)) => Value::Integer(n.as_i64().ok_or_else(|| bad("an integer"))?),
+        (ZeekType::Bool, Json::Bool(b)) => Value::Integer(i64::from(*b)),
+        (ZeekType::Addr, Json::String(s)) => Value::Text(s.parse::<std::net::IpAddr>().map_err(|_| bad("an IP address"))?.to_string()),
+        (ZeekType::Text, Json::String(s)) => Value::Text(s.clone()),
+        (ZeekType::Set, Json::Array(_)) => Value::Text(field.to_string()),
+        (ZeekType::Time | ZeekType::Interval, _) => return Err(bad("a number")),
+        (ZeekType::Port | ZeekType::Count, _) => return Err(bad("an integer")),
+        (ZeekType::Bool, _) => return Err(bad("a boolean")),
+        (ZeekType::Addr | ZeekType::Text, _) => return Err(bad("a string")),
+        (ZeekType::Set, _) => return Err(bad("an array")),
+    })
+}
+
+/// Unescapes the `\xHH` escapes used by the separators declared in a Zeek TSV log's header.
+#[cfg(not(feature = "wasm"))]
+fn zeek_unescape(s: &str) -> String {
+    let mut out = String::new();
+    let mut rest = s;
+    while let Some(i) = rest.find("\\x") {
+        out.push_str(&rest[..i]);
+        match rest.get(i + 2..i + 4).and_then(|h| u8::from_str_radix(h, 16).ok()) {
+            Some(b) => {
+                out.push(char::from(b));
+                rest = &rest[i + 4..];
+            },
+            None => {
+                out.push_str("\\x");
+                rest = &rest[i + 2..];
+            },
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// # zeek_conn(path)
+/// A table-valued function reading a Zeek `conn.log` into one row per connection, with typed columns for the standard
+/// fields. Logs may be in Zeek's default TSV form (with its `#fields` header), or its JSON form of one object per line.
+/// Compressed logs must be decompressed first.
+///
+/// Columns are named as Zeek's fields, with `id.orig_h` and the like shortened to `orig_h`:
+/// * `ts` - The connection's start time, in seconds since the Unix epoch. JSON logs written with ISO 8601 timestamps
+///   keep them as text
+/// * `uid` - The connection's unique ID
+/// * `orig_h`, `orig_p`, `resp_h`, `resp_p` - The originator and responder addresses and ports
+/// * `proto`, `service` - The transport protocol, and the application protocols detected
+/// * `duration` - The connection's length, in seconds
+/// * `orig_bytes`, `resp_bytes` - The payload bytes sent by each side
+/// * `conn_state`, `history` - How the connection ended, and the history of its packets
+/// * `local_orig`, `local_resp` - Whether each side is in the local networks, as `1` or `0`
+/// * `missed_bytes` - The bytes missed in content gaps
+/// * `orig_pkts`, `orig_ip_bytes`, `resp_pkts`, `resp_ip_bytes` - The packets and IP level bytes sent by each side
+/// * `tunnel_parents` - The UIDs of any encapsulating connections, as a JSON array
+///
+/// Unset fields, and standard fields missing from the log, are NULL. Other fields are ignored. Addresses are written as
+/// [IP_FORMAT](crate::exports::inet::format) does with its defaults, so they compare equal to other columns formatted
+/// by it. A malformed line stops the file from being read, with an error naming the line.
+///
+/// As this reads from the filesystem, it is [direct-only](crate::exports#direct-only-functions).
+///
+/// # Usage
+/// |Query|Result|
+/// |-|-|
+/// |`SELECT resp_h, sum(orig_bytes) FROM zeek_conn('conn.log') GROUP BY resp_h ORDER BY 2 DESC LIMIT 1` | `'192.0.2.9', 1048576` |
+/// |`SELECT count(*) FROM zeek_conn('conn.log') WHERE IP_CONTAINS('10.0.0.0/8', orig_h)` | `1204` |
+/// |`SELECT count(*) FROM zeek_conn(NULL)` | `0` |
+#[cfg(not(feature = "wasm"))]
+pub struct ZeekConn;
+
+#[cfg(not(feature = "wasm"))]
+impl TableFunction for ZeekConn {
+    type Aux = ();
+
+    const SCHEMA: &'static str = "CREATE TABLE x(ts REAL, uid TEXT, orig_h TEXT, orig_p INTEGER, resp_h TEXT, resp_p INTEGER, proto TEXT, service TEXT, duration REAL, orig_bytes INTEGER, resp_bytes INTEGER, conn_state TEXT, local_orig INTEGER, local_resp INTEGER, missed_bytes INTEGER, history TEXT, orig_pkts INTEGER, orig_ip_bytes INTEGER, resp_pkts INTEGER, resp_ip_bytes INTEGER, tunnel_parents TEXT, path HIDDEN)";
+    const COLUMNS: usize = 21;
+    const ARGS: usize = 1;
+    const DIRECT: bool = true;
+
+    fn rows(_aux: &(), args: &[Value]) -> rusqlite::Result<Vec<Vec<Value>>> {
+        let Value::Text(path) = &args[0] else { return Ok(Vec::new()); };
+        let txt = std::fs::read_to_string(path)
+            .map_err(|e| rusqlite::Error::ModuleError(format!("Unable to read Zeek log {path:?}: {e}")))?;
+        let line_err = |lnum: usize, e: String| rusqlite::Error::ModuleError(format!("Unable to read Zeek log {path:?}, line {lnum}: {e}"));
+
+        // the TSV header's separators, and which column each field fills
+        let mut separator = "\t".to_owned();
+        let mut set_separator = ",".to_owned();
+        let mut empty = "(empty)".to_owned();
+        let mut unset = "-".to_owned();
+        let mut columns: Option<Vec<Option<usize>>> = None;
+
+        let mut rows = Vec::new();
+        for (lnum, l) in txt.lines().enumerate().map(|(i, l)| (i + 1, l)) {
+            if l.trim().is_empty() {
+                continue;
+            }
+            if l.starts_with('{') {
+                let obj: serde_json::Map<String, serde_json::Value> = serde_json::from_str(l)
+                    .map_err(|e| line_err(lnum, e.to_string()))?;
+                let row = ZEEK_CONN_FIELDS.iter()
+                    .map(|(name, ty)| obj.get(*name).map_or(Ok(Value::Null), |v| zeek_json_value(*ty, v)))
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| line_err(lnum, e))?;
+                rows.push(row);
+            } else if let Some(header) = l.strip_prefix('#') {
+                // the separator is declared with a space, as the tab hasn't been declared yet
+                if let Some(sep) = header.strip_prefix("separator ") {
+                    separator = zeek_unescape(sep);
+                    continue;
+                }
+                let mut parts = header.split(separator.as_str());
+                match parts.next() {
+                    Some("set_separator") => set_separator = parts.next().unwrap_or(",").to_owned(),
+                    Some("empty_field") => empty = parts.next().unwrap_or("(empty)").to_owned(),
+                    Some("unset_field") => unset = parts.next().unwrap_or("-").to_owned(),
+                    Some("fields") => columns = Some(parts
+                        .map(|f| ZEEK_CONN_FIELDS.iter().position(|(name, _)| *name == f))
+                        .collect()),
+                    _ => {},
+                }
+            } else {
+                let columns = columns.as_ref()
+                    .ok_or_else(|| line_err(lnum, "expected a #fields header before the first entry".to_owned()))?;
+                let mut row = vec![Value::Null; Self::COLUMNS];
+                for (field, col) in l.split(separator.as_str()).zip(columns) {
+                    let Some(col) = *col else { continue; };
+                    row[col] = zeek_tsv_value(ZEEK_CONN_FIELDS[col].1, field, &unset, &empty, &set_separator)
+                        .map_err(|e| line_err(lnum, format!("{}: {e}", ZEEK_CONN_FIELDS[col].0)))?;
+                }
+                rows.push(row);
+            }
+        }
+
+        Ok(rows)
+    }
+}
+
+#[cfg(not(feature = "wasm"))]
+#[test]
+fn zeek_conn_rows() {
+    let tsv = concat!(
+        "#separator \\x09\n",
+        "#set_separator\t,\n",
+        "#empty_field\t(empty)\n",
+        "#unset_field\t-\n",
+        "#path\tconn\n",
+        "#fields\tts\tuid\tid.orig_h\tid.orig_p\tid.resp_h\tid.resp_p\tproto\tservice\tduration\torig_bytes\tlocal_orig\ttunnel_parents\n",
+        "#types\ttime\tstring\taddr\tport\taddr\tport\tenum\tstring\tinterval\tcount\tbool\tset[string]\n",
+        "1700000000.123456\tCabc\t10.0.0.1\t51000\t2001:db8:0::9\t443\ttcp\tssl\t1.5\t1500\tT\tCx,Cy\n",
+        "1700000001.000000\tCdef\t10.0.0.2\t53\t192.0.2.53\t53\tudp\t-\t-\t-\tF\t(empty)\n",
+        "#close\t2023-11-14-22-13-21\n",
+    );
+    let json = concat!(
+        r#"{"ts":1700000002.5,"uid":"Cghi","id.orig_h":"10.0.0.3","id.orig_p":5353,"id.resp_h":"224.0.0.251","id.resp_p":5353,"proto":"udp","local_orig":true,"tunnel_parents":[]}"#,
+        "\n",
+    );
+
+    let tsv_path = TempPath::file("zeek-conn.log", tsv);
+    let json_path = TempPath::file("zeek-conn.json", json);
+
+    let conn = conn();
+    let query = |path: &std::path::Path| {
+        let mut stmt = conn.prepare("SELECT uid, resp_h, resp_p, service, duration, local_orig, tunnel_parents FROM zeek_conn(?1)").unwrap();
+        stmt.query_map([path.to_str().unwrap()], |r| Ok(format!(
+            "{} {} {} {:?} {:?} {:?} {:?}",
+            r.get::<_, String>(0)?, r.get::<_, String>(1)?, r.get::<_, i64>(2)?, r.get::<_, Option<String>>(3)?,
+            r.get::<_, Option<f64>>(4)?, r.get::<_, Option<i64>>(5)?, r.get::<_, Option<String>>(6)?,
+        ))).unwrap().collect::<Result<Vec<_>, _>>()
+    };
+    let tsv_rows = query(&tsv_path);
+    let json_rows = query(&json_path);
+
+    std::fs::write(&tsv_path, "#fields\tts\tid.orig_p\n1700000000.0\thttp\n").unwrap();
+    let bad = query(&tsv_path);
+
+    assert_eq!(tsv_rows.unwrap(), vec![
+        r#"Cabc 2001:db8::9 443 Some("ssl") Some(1.5) Some(1) Some("[\"Cx\",\"Cy\"]")"#,
+        r#"Cdef 192.0.2.53 53 None None Some(0) Some("[]")"#,
+    ]);
+    assert_eq!(json_rows.unwrap(), vec![r#"Cghi 224.0.0.251 5353 None None Some(1) Some("[]")"#]);
+    assert!(bad.unwrap_err().to_string().contains("line 2: id.orig_p: expected an integer"));
+}
+
+/// # nmap_xml(path)
+/// A read-only virtual table over an nmap XML scan (`nmap -oX`), with one row for each port scanned on each host, so
+/// scan results can be queried in place. Create one for each scan with
+/// `CREATE VIRTUAL TABLE scan USING nmap_xml('scan.xml')`. Relative paths are resolved against the working directory
+/// of the process using SQLite.
+///
+/// The file is read each time the table is queried, so it reflects the latest scan written to it. Hosts finished
+/// before an interrupted scan stopped are still listed.
+///
+/// As this reads from the filesystem, it is [direct-only](crate::exports#direct-only-functions).
+///
+/// Columns:
+/// * `address` - The host's IPv4 or IPv6 address
+/// * `mac` - The host's MAC address, only known for hosts on the local network. Written as
+///   [MAC_FORMAT](crate::exports::mac::format) does by default
+/// * `mac_vendor` - The vendor nmap guessed from the MAC address, for comparison with
+///   [MAC_MANUF](crate::exports::mac::manuf)
+/// * `hostname` - The host's first hostname, either given on the command line or found through reverse DNS
+/// * `status`, `status_reason` - Whether the host was up or down, and why nmap thought so
+/// * `protocol`, `port` - The port scanned, such as `'tcp'` and `22`
+/// * `state`, `reason` - The port's state (such as `open` or `filtered`), and why nmap gave it that state
+/// * `service`, `product`, `version`, `extra_info` - The service detected on the port
+/// * `method`, `confidence` - How the service was detected (`probed` by version detection, or guessed from the port
+///   `table`), and nmap's confidence in it, from 0 to 10
+///
+/// Hosts without any ports listed (such as those found down, or from a ping scan) have a single row, with NULL port
+/// and service columns. Ports summarized by nmap as "not shown" aren't listed.
+///
+/// # Usage
+/// |Query|Result|
+/// |-|-|
+/// |`CREATE VIRTUAL TABLE scan USING nmap_xml('scan.xml')` | |
+/// |`SELECT address, port, product, version FROM scan WHERE state = 'open' AND service = 'ssh'` | `'192.168.1.1', 22, 'OpenSSH', '9.6'` |
+/// |`SELECT DISTINCT mac, mac_vendor, MAC_MANUF(mac) FROM scan WHERE mac_vendor IS NOT MAC_MANUF(mac)` | `'00:00:0c:12:34:56', 'Cisco Systems', 'Cisco'` |
+/// |`CREATE VIRTUAL TABLE scan USING nmap_xml` | N/A - A query error is raised, as a path is required |
+#[cfg(all(feature = "nmap", not(feature = "wasm")))]
+#[repr(C)]
+pub struct NmapXmlTab {
+    /// Base class. Must be first
+    base: sqlite3_vtab,
+    path: String,
+}
+
+#[cfg(all(feature = "nmap", not(feature = "wasm")))]
+impl NmapXmlTab {
+    pub fn module() -> &'static Module<'static, NmapXmlTab> {
+        read_only_module::<NmapXmlTab>()
+    }
+
+    /// Reads the scan into rows, in column order.
+    fn rows(&self) -> rusqlite::Result<Vec<Vec<Value>>> {
+        let path = &self.path;
+        let xml = std::fs::read_to_string(path)
+            .map_err(|e| rusqlite::Error::ModuleError(format!("Unable to read nmap scan {path:?}: {e}")))?;
+        let hosts = crate::nmap::parse_hosts(&xml)
+            .map_err(|e| rusqlite::Error::ModuleError(format!("Unable to parse nmap scan {path:?}: {e}")))?;
+
+        let text = |v: Option<String>| v.map_or(Value::Null, Value::Text);
+        let mut rows = Vec::new();
+        for host in hosts {
+            let host_columns = [
+                host.addr.map_or(Value::Null, |a| Value::Text(a.to_string())),
+                host.mac
+                    .and_then(|m| FormatSpec::default().format(Eui::Mac48(m)))
+                    .map_or(Value::Null, |m| Value::Text(m.to_string())),
+                text(host.mac_vendor),
+                text(host.hostname),
+                text(host.status),
+                text(host.reason),
+            ];
+            if host.ports.is_empty() {
+                let mut row = host_columns.to_vec();
+                row.resize(NMAP_XML_COLUMNS, Value::Null);
+                rows.push(row);
+            }
+            for port in host.ports {
+                let service = port.service.unwrap_or_default();
+                let mut row = host_columns.to_vec();
+                row.extend([
+                    Value::Text(port.protocol),
+                    Value::Integer(port.port.into()),
+                    text(port.state),
+                    text(port.reason),
+                    text(service.name),
+                    text(service.product),
+                    text(service.version),
+                    text(service.extra_info),
+                    text(service.method),
+                    service.conf.map_or(Value::Null, |c| Value::Integer(c.into())),
+                ]);
+                rows.push(row);
+            }
+        }
+
+        Ok(rows)
+    }
+}
+
+#[cfg(all(feature = "nmap", not(feature = "wasm")))]
+const NMAP_XML_COLUMNS: usize = 16;
+
+#[cfg(all(feature = "nmap", not(feature = "wasm")))]
+unsafe impl<'vtab> VTab<'vtab> for NmapXmlTab {
+    type Aux = ();
+    type Cursor = NmapXmlCursor<'vtab>;
+
+    fn connect(db: &mut VTabConnection, _aux: Option<&()>, args: &[&[u8]]) -> rusqlite::Result<(String, NmapXmlTab)> {
+        db.config(VTabConfig::DirectOnly)?;
+        // the module, database, and table names come before the arguments given to the module
+        let [path] = args.get(3..).unwrap_or_default() else {
+            return Err(rusqlite::Error::ModuleError("nmap_xml requires a single argument: the path of the scan".to_owned()));
+        };
+        let path = std::str::from_utf8(path)
+            .map_err(|_| rusqlite::Error::ModuleError("nmap_xml requires a UTF-8 path".to_owned()))?
+            .trim();
+        // arguments are passed as written, so a quoted path keeps its quotes
+        let path = match path.chars().next() {
+            Some(q @ ('\'' | '"')) if path.len() >= 2 && path.ends_with(q) => path[1..path.len() - 1].replace(&format!("{q}{q}"), &q.to_string()),
+            _ => path.to_owned(),
+        };
+
+        let vtab = NmapXmlTab {
+            base: sqlite3_vtab::default(),
+            path,
+        };
+        Ok((
+            "CREATE TABLE x(address TEXT, mac TEXT, mac_vendor TEXT, hostname TEXT, status TEXT, status_reason TEXT, protocol TEXT, port INTEGER, state TEXT, reason TEXT, service TEXT, product TEXT, version TEXT, extra_info TEXT, method TEXT, confidence INTEGER)".to_owned(),
+            vtab,
+        ))
+    }
+
+    fn best_index(&self, info: &mut IndexInfo) -> rusqlite::Result<()> {
+        // always a full scan
+        info.set_estimated_cost(1_000_000.0);
+        Ok(())
+    }
+
+    fn open(&'vtab mut self) -> rusqlite::Result<NmapXmlCursor<'vtab>> {
+        Ok(NmapXmlCursor {
+            base: sqlite3_vtab_cursor::default(),
+            vtab: self,
+            rows: Vec::new(),
+            row: 0,
+        })
+    }
+}
+
+#[cfg(all(feature = "nmap", not(feature = "wasm")))]
+impl CreateVTab<'_> for NmapXmlTab {
+    const KIND: VTabKind = VTabKind::Default;
+}
+
+/// A cursor over the rows of a scan, read when the cursor is filtered.
+#[cfg(all(feature = "nmap", not(feature = "wasm")))]
+#[repr(C)]
+pub struct NmapXmlCursor<'vtab> {
+    /// Base class. Must be first
+    base: sqlite3_vtab_cursor,
+    vtab: &'vtab NmapXmlTab,
+    rows: Vec<Vec<Value>>,
+    row: usize,
+}
+
+#[cfg(all(feature = "nmap", not(feature = "wasm")))]
+unsafe impl VTabCursor for NmapXmlCursor<'_> {
+    fn filter(&mut self, _idx_num: c_int, _idx_str: Option<&str>, _args: &Values<'_>) -> rusqlite::Result<()> {
+        self.rows = self.vtab.rows()?;
+        self.row = 0;
+        Ok(())
+    }
+
+    fn next(&mut self) -> rusqlite::Result<()> {
+        self.row += 1;
+        Ok(())
+    }
+
+    fn eof(&self) -> bool {
+        self.row >= self.rows.len()
+    }
+
+    fn column(&self, ctx: &mut Context, i: c_int) -> rusqlite::Result<()> {
+        match self.rows[self.row].get(i as usize) {
+            Some(value) => ctx.set_result(value),
+            None => Err(rusqlite::Error::InvalidColumnIndex(i as usize)),
+        }
+    }
+
+    fn rowid(&self) -> rusqlite::Result<i64> {
+        Ok(self.row as i64 + 1)
+    }
+}
+
+#[cfg(all(feature = "nmap", not(feature = "wasm")))]
+#[test]
+fn nmap_xml_rows() {
+    let xml = concat!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>"#, "\n",
+        r#"<nmaprun scanner="nmap" args="nmap -sV -oX scan.xml 192.168.1.0/30">"#, "\n",
+        r#"<host><status state="up" reason="arp-response"/><address addr="192.168.1.1" addrtype="ipv4"/>"#,
+        r#"<address addr="3C:A6:F6:C4:34:F8" addrtype="mac" vendor="Apple"/><hostnames><hostname name="router.lan" type="PTR"/></hostnames>"#,
+        r#"<ports><port protocol="tcp" portid="22"><state state="open" reason="syn-ack"/><service name="ssh" product="OpenSSH" version="9.6" method="probed" conf="10"/></port>"#,
+        r#"<port protocol="tcp" portid="80"><state state="closed" reason="reset"/><service name="http" method="table" conf="3"/></port></ports></host>"#, "\n",
+        r#"<host><status state="down" reason="no-response"/><address addr="192.168.1.2" addrtype="ipv4"/></host>"#, "\n",
+        "</nmaprun>\n",
+    );
+    let path = TempPath::file("nmap-xml.xml", xml);
+
+    let conn = conn();
+    conn.execute_batch(&format!("CREATE VIRTUAL TABLE scan USING nmap_xml('{}')", path.as_str().replace('\'', "''"))).unwrap();
+    let mut stmt = conn.prepare("SELECT address, mac, mac_vendor IS MAC_MANUF(mac), status, port, state, product, confidence FROM scan ORDER BY rowid").unwrap();
+    let rows = stmt.query_map([], |r| Ok(format!(
+        "{} {:?} {} {} {:?} {:?} {:?} {:?}",
+        r.get::<_, String>(0)?, r.get::<_, Option<String>>(1)?, r.get::<_, bool>(2)?, r.get::<_, String>(3)?,
+        r.get::<_, Option<i64>>(4)?, r.get::<_, Option<String>>(5)?, r.get::<_, Option<String>>(6)?, r.get::<_, Option<i64>>(7)?,
+    ))).unwrap().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(rows, vec![
+        r#"192.168.1.1 Some("3c:a6:f6:c4:34:f8") true up Some(22) Some("open") Some("OpenSSH") Some(10)"#,
+        r#"192.168.1.1 Some("3c:a6:f6:c4:34:f8") true up Some(80) Some("closed") None Some(3)"#,
+        "192.168.1.2 None true down None None None None",
+    ]);
+
+    // the file is read on each query, and the table can still be dropped once it's gone
+    std::fs::remove_file(&path).unwrap();
+    let err = conn.query_row("SELECT count(*) FROM scan", [], |r| r.get::<_, i64>(0)).unwrap_err();
+    assert!(err.to_string().contains("Unable to read nmap scan"));
+    conn.execute_batch("DROP TABLE scan").unwrap();
+    assert!(conn.execute_batch("CREATE VIRTUAL TABLE scan USING nmap_xml").is_err());
+}
@@ -0,0 +1,395 @@
+use std::{collections::HashMap, net::{IpAddr, Ipv4Addr, Ipv6Addr}};
+
+/// A single flow read from a NetFlow v5 or IPFIX export.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlowRecord {
+    /// The export protocol version the flow was read from: 5 for NetFlow v5, or 10 for IPFIX
+    pub version: u16,
+    /// When the flow started, in milliseconds since the Unix epoch
+    pub start: Option<i64>,
+    /// When the flow ended, in milliseconds since the Unix epoch
+    pub end: Option<i64>,
+    pub src: Option<IpAddr>,
+    pub dst: Option<IpAddr>,
+    pub src_port: Option<u16>,
+    pub dst_port: Option<u16>,
+    pub protocol: Option<u8>,
+    pub tcp_flags: Option<u16>,
+    pub bytes: Option<u64>,
+    pub packets: Option<u64>,
+}
+
+impl FlowRecord {
+    fn empty(version: u16) -> FlowRecord {
+        FlowRecord {
+            version,
+            start: None,
+            end: None,
+            src: None,
+            dst: None,
+            src_port: None,
+            dst_port: None,
+            protocol: None,
+            tcp_flags: None,
+            bytes: None,
+            packets: None,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum ParseFlowError {
+    #[error("unsupported flow export version {1} at byte {0}. Expected NetFlow v5 (5) or IPFIX (10)")]
+    UnsupportedVersion(usize, u16),
+    #[error("truncated flow export at byte {0}, expected {1} more bytes")]
+    Truncated(usize, usize),
+    #[error("malformed IPFIX message at byte {0}: {1}")]
+    Malformed(usize, &'static str),
+}
+
+/// The size of a NetFlow v5 export header, and of each flow record following it
+const V5_HEADER_LEN: usize = 24;
+const V5_RECORD_LEN: usize = 48;
+
+/// The size of an IPFIX message header, and of each set header within it
+const IPFIX_HEADER_LEN: usize = 16;
+const IPFIX_SET_HEADER_LEN: usize = 4;
+
+/// A field of an IPFIX template: its information element, length (65535 for variable length), and whether it belongs
+/// to an enterprise (whose elements aren't read).
+#[derive(Debug, Clone, Copy)]
+struct TemplateField {
+    element: u16,
+    length: u16,
+    enterprise: bool,
+}
+
+/// Reads a big-endian unsigned integer of up to 8 bytes, as IPFIX allows integers to be sent in fewer bytes than
+/// their type (reduced-size encoding).
+fn be_uint(b: &[u8]) -> Option<u64> {
+    (b.len() <= 8).then(|| b.iter().fold(0u64, |acc, b| (acc << 8) | u64::from(*b)))
+}
+
+fn be_u16(data: &[u8], at: usize) -> u16 {
+    u16::from_be_bytes([data[at], data[at + 1]])
+}
+
+fn be_u32(data: &[u8], at: usize) -> u32 {
+    u32::from_be_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+/// Checks that `len` bytes are available at `at`.
+fn need(data: &[u8], at: usize, len: usize) -> Result<(), ParseFlowError> {
+    match data.len().checked_sub(at) {
+        Some(left) if left >= len => Ok(()),
+        left => Err(ParseFlowError::Truncated(at, len - left.unwrap_or(0))),
+    }
+}
+
+/// Parses a file of NetFlow v5 or IPFIX export packets, as written back to back by a collector (such as IPFIX files
+/// described by RFC 5655). Each packet's version is read from its header, so the two may be mixed.
+pub fn parse_flows(data: &[u8]) -> Result<Vec<FlowRecord>, ParseFlowError> {
+    let mut flows = Vec::new();
+    let mut templates = HashMap::new();
+    let mut at = 0;
+    while at < data.len() {
+        need(data, at, 2)?;
+        at = match be_u16(data, at) {
+            5 => parse_v5(data, at, &mut flows)?,
+            10 => parse_ipfix(data, at, &mut templates, &mut flows)?,
+            version => return Err(ParseFlowError::UnsupportedVersion(at, version)),
+        };
+    }
+    Ok(flows)
+}
+
+/// Parses a NetFlow v5 export packet starting at `at`, returning the offset following it.
+fn parse_v5(data: &[u8], at: usize, flows: &mut Vec<FlowRecord>) -> Result<usize, ParseFlowError> {
+    need(data, at, V5_HEADER_LEN)?;
+    let count = be_u16(data, at + 2) as usize;
+    let uptime = i64::from(be_u32(data, at + 4));
+    let export_ms = i64::from(be_u32(data, at + 8)) * 1000 + i64::from(be_u32(data, at + 12)) / 1_000_000;
+    need(data, at + V5_HEADER_LEN, count * V5_RECORD_LEN)?;
+
+    // flow times are the router's uptime in milliseconds, which the header relates to the wall clock
+    let wall_clock = |sys_uptime: u32| export_ms - (uptime - i64::from(sys_uptime));
+    for i in 0..count {
+        let r = &data[at + V5_HEADER_LEN + i * V5_RECORD_LEN..][..V5_RECORD_LEN];
+        flows.push(FlowRecord {
+            version: 5,
+            start: Some(wall_clock(be_u32(r, 24))),
+            end: Some(wall_clock(be_u32(r, 28))),
+            src: Some(IpAddr::V4(Ipv4Addr::from(be_u32(r, 0)))),
+            dst: Some(IpAddr::V4(Ipv4Addr::from(be_u32(r, 4)))),
+            src_port: Some(be_u16(r, 32)),
+            dst_port: Some(be_u16(r, 34)),
+            protocol: Some(r[38]),
+            tcp_flags: Some(u16::from(r[37])),
+            bytes: Some(u64::from(be_u32(r, 20))),
+            packets: Some(u64::from(be_u32(r, 16))),
+        });
+    }
+    Ok(at + V5_HEADER_LEN + count * V5_RECORD_LEN)
+}
+
+/// Parses an IPFIX message starting at `at`, returning the offset following it. Templates are kept by observation
+/// domain and template ID, for reading data sets in later messages.
+fn parse_ipfix(
+    data: &[u8],
+    at: usize,
+    templates: &mut HashMap<(u32, u16), Vec<TemplateField>>,
+    flows: &mut Vec<FlowRecord>,
+) -> Result<usize, ParseFlowError> {
+    need(data, at, IPFIX_HEADER_LEN)?;
+    let len = be_u16(data, at + 2) as usize;
+    if len < IPFIX_HEADER_LEN {
+        return Err(ParseFlowError::Malformed(at, "message length is shorter than its header"));
+    }
+    need(data, at, len)?;
+    let export_ms = i64::from(be_u32(data, at + 4)) * 1000;
+    let domain = be_u32(data, at + 12);
+
+    let end = at + len;
+    let mut set = at + IPFIX_HEADER_LEN;
+    while set + IPFIX_SET_HEADER_LEN <= end {
+        let set_id = be_u16(data, set);
+        let set_len = be_u16(data, set + 2) as usize;
+        if set_len < IPFIX_SET_HEADER_LEN || set + set_len > end {
+            return Err(ParseFlowError::Malformed(set, "set length exceeds its message"));
+        }
+        let body = &data[set + IPFIX_SET_HEADER_LEN..set + set_len];
+        match set_id {
+            2 => parse_templates(body, set, domain, templates)?,
+            // options templates describe the exporter rather than flows
+            3 => {},
+            256.. => {
+                // data for an unknown template can't be read, such as when the file starts mid-stream
+                if let Some(template) = templates.get(&(domain, set_id)) {
+                    parse_data_set(body, set, template, export_ms, flows)?;
+                }
+            },
+            _ => return Err(ParseFlowError::Malformed(set, "reserved set ID")),
+        }
+        set += set_len;
+    }
+    Ok(end)
+}
+
+fn parse_templates(
+    body: &[u8],
+    set: usize,
+    domain: u32,
+    templates: &mut HashMap<(u32, u16), Vec<TemplateField>>,
+) -> Result<(), ParseFlowError> {
+    let truncated = ParseFlowError::Malformed(set, "truncated template record");
+    let mut at = 0;
+    // anything shorter than a template header is padding
+    while at + 4 <= body.len() {
+        let id = be_u16(body, at);
+        let count = be_u16(body, at + 2);
+        at += 4;
+
+        let mut fields = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            if at + 4 > body.len() {
+                return Err(truncated);
+            }
+            let element = be_u16(body, at);
+            let length = be_u16(body, at + 2);
+            let enterprise = element & 0x8000 != 0;
+            at += if enterprise { 8 } else { 4 };
+            fields.push(TemplateField { element: element & 0x7fff, length, enterprise });
+        }
+        if at > body.len() {
+            return Err(truncated);
+        }
+        templates.insert((domain, id), fields);
+    }
+    Ok(())
+}
+
+fn parse_data_set(
+    body: &[u8],
+    set: usize,
+    template: &[TemplateField],
+    export_ms: i64,
+    flows: &mut Vec<FlowRecord>,
+) -> Result<(), ParseFlowError> {
+    // the shortest possible record, to tell records apart from trailing padding
+    let min_len: usize = template.iter().map(|f| if f.length == u16::MAX { 1 } else { f.length as usize }).sum();
+    if min_len == 0 {
+        return Ok(());
+    }
+
+    let mut at = 0;
+    while at + min_len <= body.len() {
+        let mut flow = FlowRecord::empty(10);
+        let mut start_secs = None;
+        let mut end_secs = None;
+        for field in template {
+            let len = match field.length {
+                u16::MAX => {
+                    let short = *body.get(at).ok_or(ParseFlowError::Malformed(set, "truncated variable length field"))?;
+                    at += 1;
+                    match short {
+                        255 if at + 2 <= body.len() => {
+                            at += 2;
+                            be_u16(body, at - 2) as usize
+                        },
+                        255 => return Err(ParseFlowError::Malformed(set, "truncated variable length field")),
+                        short => short as usize,
+                    }
+                },
+                len => len as usize,
+            };
+            let value = body.get(at..at + len).ok_or(ParseFlowError::Malformed(set, "truncated data record"))?;
+            at += len;
+            if !field.enterprise {
+                read_element(&mut flow, &mut start_secs, &mut end_secs, field.element, value);
+            }
+        }
+
+        // prefer millisecond timestamps, then seconds, then the message's export time. Seconds too large to convert
+        // are left NULL, rather than falling back to the export time.
+        let ms = |secs: Option<i64>| secs.map(|s| s.checked_mul(1000));
+        flow.start = flow.start.or(ms(start_secs).flatten());
+        flow.end = flow.end.or_else(|| ms(end_secs).unwrap_or(Some(export_ms)));
+        flows.push(flow);
+    }
+    Ok(())
+}
+
+/// Stores the value of an IPFIX information element in the flow, if it's one of the flow's fields.
+fn read_element(flow: &mut FlowRecord, start_secs: &mut Option<i64>, end_secs: &mut Option<i64>, element: u16, value: &[u8]) {
+    let int = be_uint(value);
+    let signed = int.and_then(|i| i64::try_from(i).ok());
+    match element {
+        // octetDeltaCount, then octetTotalCount
+        1 => flow.bytes = int,
+        85 => flow.bytes = flow.bytes.or(int),
+        // packetDeltaCount, then packetTotalCount
+        2 => flow.packets = int,
+        86 => flow.packets = flow.packets.or(int),
+        4 => flow.protocol = int.and_then(|i| u8::try_from(i).ok()),
+        6 => flow.tcp_flags = int.and_then(|i| u16::try_from(i).ok()),
+        7 => flow.src_port = int.and_then(|i| u16::try_from(i).ok()),
+        11 => flow.dst_port = int.and_then(|i| u16::try_from(i).ok()),
+        8 => flow.src = <[u8; 4]>::try_from(value).ok().map(|b| IpAddr::V4(Ipv4Addr::from(b))),
+        12 => flow.dst = <[u8; 4]>::try_from(value).ok().map(|b| IpAddr::V4(Ipv4Addr::from(b))),
+        27 => flow.src = <[u8; 16]>::try_from(value).ok().map(|b| IpAddr::V6(Ipv6Addr::from(b))),
+        28 => flow.dst = <[u8; 16]>::try_from(value).ok().map(|b| IpAddr::V6(Ipv6Addr::from(b))),
+        // flowStartSeconds and flowEndSeconds
+        150 => *start_secs = signed,
+        151 => *end_secs = signed,
+        // flowStartMilliseconds and flowEndMilliseconds
+        152 => flow.start = signed,
+        153 => flow.end = signed,
+        _ => {},
+    }
+}
+
+#[cfg(test)]
+fn v5_packet(unix_secs: u32, uptime: u32, records: &[[u8; V5_RECORD_LEN]]) -> Vec<u8> {
+    let mut packet = Vec::new();
+    packet.extend_from_slice(&5u16.to_be_bytes());
+    packet.extend_from_slice(&(records.len() as u16).to_be_bytes());
+    packet.extend_from_slice(&uptime.to_be_bytes());
+    packet.extend_from_slice(&unix_secs.to_be_bytes());
+    packet.extend_from_slice(&500_000_000u32.to_be_bytes());
+    packet.extend_from_slice(&[0; 8]);
+    records.iter().for_each(|r| packet.extend_from_slice(r));
+    packet
+}
+
+#[test]
+fn netflow_v5() {
+    let mut record = [0u8; V5_RECORD_LEN];
+    record[0..4].copy_from_slice(&[10, 0, 0, 1]);
+    record[4..8].copy_from_slice(&[192, 0, 2, 9]);
+    record[16..20].copy_from_slice(&3u32.to_be_bytes());
+    record[20..24].copy_from_slice(&1500u32.to_be_bytes());
+    record[24..28].copy_from_slice(&9_000u32.to_be_bytes());
+    record[28..32].copy_from_slice(&9_750u32.to_be_bytes());
+    record[32..34].copy_from_slice(&51000u16.to_be_bytes());
+    record[34..36].copy_from_slice(&443u16.to_be_bytes());
+    record[37] = 0x12;
+    record[38] = 6;
+
+    let data = [v5_packet(1_700_000_000, 10_000, &[record]), v5_packet(1_700_000_001, 11_000, &[record, record])].concat();
+    let flows = parse_flows(&data).unwrap();
+    assert_eq!(flows.len(), 3);
+    assert_eq!(flows[0], FlowRecord {
+        version: 5,
+        start: Some(1_700_000_000_500 - 1_000),
+        end: Some(1_700_000_000_500 - 250),
+        src: Some("10.0.0.1".parse().unwrap()),
+        dst: Some("192.0.2.9".parse().unwrap()),
+        src_port: Some(51000),
+        dst_port: Some(443),
+        protocol: Some(6),
+        tcp_flags: Some(0x12),
+        bytes: Some(1500),
+        packets: Some(3),
+    });
+
+    assert_eq!(parse_flows(&data[..data.len() - 1]), Err(ParseFlowError::Truncated(96, 1)));
+    assert_eq!(parse_flows(&[0, 9, 0, 0]), Err(ParseFlowError::UnsupportedVersion(0, 9)));
+}
+
+#[test]
+fn ipfix() {
+    let message = |sets: &[u8]| {
+        let mut msg = Vec::new();
+        msg.extend_from_slice(&10u16.to_be_bytes());
+        msg.extend_from_slice(&((IPFIX_HEADER_LEN + sets.len()) as u16).to_be_bytes());
+        msg.extend_from_slice(&1_700_000_000u32.to_be_bytes());
+        msg.extend_from_slice(&[0, 0, 0, 1, 0, 0, 0, 7]);
+        msg.extend_from_slice(sets);
+        msg
+    };
+    let set = |id: u16, body: &[u8]| [&id.to_be_bytes()[..], &((body.len() + 4) as u16).to_be_bytes(), body].concat();
+
+    // sourceIPv6Address, destinationIPv6Address, protocolIdentifier, octetDeltaCount (reduced to 4 bytes),
+    // flowStartMilliseconds, an enterprise element, and a variable length element
+    let be16s = |vals: &[u16]| vals.iter().flat_map(|v| v.to_be_bytes()).collect::<Vec<u8>>();
+    let template = [
+        be16s(&[300, 7, 27, 16, 28, 16, 4, 1, 1, 4, 152, 8, 0x8001, 2]),
+        vec![0, 0, 0x73, 0x37],
+        be16s(&[82, 0xffff]),
+    ].concat();
+
+    let src: Ipv6Addr = "2001:db8::1".parse().unwrap();
+    let dst: Ipv6Addr = "2001:db8::2".parse().unwrap();
+    let record = [
+        &src.octets()[..], &dst.octets(), &[17], &4096u32.to_be_bytes(), &1_700_000_000_250u64.to_be_bytes(),
+        &[0xaa, 0xbb], &[4], b"eth0",
+    ].concat();
+
+    let data = [
+        // data before its template is skipped
+        message(&set(300, &record)),
+        message(&[set(2, &template), set(300, &[&record[..], &record, &[0, 0]].concat())].concat()),
+    ].concat();
+    let flows = parse_flows(&data).unwrap();
+    assert_eq!(flows.len(), 2);
+    assert_eq!(flows[0], FlowRecord {
+        version: 10,
+        start: Some(1_700_000_000_250),
+        end: Some(1_700_000_000_000),
+        src: Some(IpAddr::V6(src)),
+        dst: Some(IpAddr::V6(dst)),
+        protocol: Some(17),
+        bytes: Some(4096),
+        ..FlowRecord::empty(10)
+    });
+
+    // flowStartSeconds and flowEndSeconds, the latter too large to hold in milliseconds
+    let template = be16s(&[301, 2, 150, 8, 151, 8]);
+    let record = [i64::MAX / 2000, i64::MAX / 500].map(|s| s.to_be_bytes()).concat();
+    let flows = parse_flows(&message(&[set(2, &template), set(301, &record)].concat())).unwrap();
+    assert_eq!((flows[0].start, flows[0].end), (Some(i64::MAX / 2000 * 1000), None));
+
+    let mut bad = message(&set(2, &template));
+    bad[2..4].copy_from_slice(&8u16.to_be_bytes());
+    assert!(matches!(parse_flows(&bad), Err(ParseFlowError::Malformed(0, _))));
+}
@@ -1,18 +1,86 @@
 
-use rusqlite::{ffi, functions::FunctionFlags, Connection};
-use std::ffi::CString;
+use rusqlite::{functions::FunctionFlags, Connection};
+#[cfg(feature = "loadable-extension")]
+use rusqlite::ffi;
+
+use std::{panic::{AssertUnwindSafe, RefUnwindSafe, UnwindSafe}, sync::{Arc, PoisonError, RwLock}, time::Instant};
+
+#[cfg(all(feature = "wasm", feature = "loadable-extension"))]
+compile_error!("the `wasm` feature replaces the loadable extension entry point, build with `--no-default-features --features wasm`");
+#[cfg(all(feature = "wasm", feature = "oui-update"))]
+compile_error!("`oui-update` downloads over the network, which is unavailable with the `wasm` feature");
+#[cfg(all(feature = "wasm", feature = "dns"))]
+compile_error!("`dns` queries over the network, which is unavailable with the `wasm` feature");
+#[cfg(all(feature = "wasm", feature = "rdap"))]
+compile_error!("`rdap` queries over the network, which is unavailable with the `wasm` feature");
+
+use rusqlite::{functions::{Aggregate, Context}, types::ToSql};
+
+use crate::{
+    ethers::SharedEthers,
+    exports::bound::{RawArgs, RawFunction},
+    oui::{OuiLayerKind, SharedOuiDb, EMBEDDED_DB, EMBEDDED_WKA_DB},
+    registration::{Collision, Resolution, SharedCollisions},
+    settings::SharedSettings,
+    stats::{FunctionCounts, SharedStats},
+};
+
+/// Optional instrumentation through the `tracing` crate
+#[macro_use]
+mod trace;
 
 /// Main collection of functions exported to SQLite. Also acts as documentation for those functions.
 ///
 /// Functions will short-circuit on to return NULL if any non-optional arguments are NULL.
+///
+/// # Direct-only functions
+/// Functions with side effects, such as reading or writing files, making network requests, or changing the results of
+/// other functions, are registered with `SQLITE_DIRECTONLY` (as are table-valued functions reading files). They may
+/// only be called from top-level SQL, and not from views, triggers, or the schema, so a database can't run them on
+/// behalf of whoever opens it.
 pub mod exports;
 
+/// Hostnames for MAC addresses, loaded from ethers files
+pub mod ethers;
+
 /// Non-alloc MAC address formatting
 pub mod mac;
 
 /// OUI database and lookup
 pub mod oui;
 
+/// Optional datasets embedded at build time, such as service names and IANA registries
+pub mod packs;
+
+/// Choosing which functions to register, and under what names
+pub mod registration;
+
+/// Per-connection defaults, such as output styles
+pub mod settings;
+pub use registration::{Casing, FunctionGroup, OnConflict, Registration, RegistrationOptions};
+
+/// Per-connection usage counts, such as calls to each function
+pub mod stats;
+
+/// Text results formatted without heap allocation
+pub mod text;
+
+/// A minimal DNS stub resolver, for lookups made by functions
+#[cfg(feature = "dns")]
+pub mod dns;
+
+/// NetFlow v5 and IPFIX export parsing
+#[cfg(feature = "netflow")]
+pub mod flow;
+
+/// Reading nmap XML scan results
+#[cfg(feature = "nmap")]
+pub mod nmap;
+
+/// Helpers shared by the tests of the SQL functions
+#[cfg(test)]
+mod test_util;
+
 // const FUNCS: &[FunctionDescription] = &[
 //     FunctionDescription::new(cstr!("ip2int"),        1, 0, true, ip2intFunc),
 //     FunctionDescription::new(cstr!("int2ip"),        1, 0, true, int2ipFunc),
@@ -28,39 +96,630 @@ pub mod oui;
 //     FunctionDescription::new(cstr!("issamenet"),     3, 0, true, issamenet3Func),
 // ];
 
-fn register_scalar_funcs(dbconn: &Connection) -> rusqlite::Result<()> {
-    // All of our functions use UTF8 strings, are deterministic, and without side-effects.
-    let flags = FunctionFlags::SQLITE_UTF8
-        | FunctionFlags::SQLITE_DETERMINISTIC
-        | FunctionFlags::SQLITE_INNOCUOUS;
+/// Binds a function taking the connection's OUI database as its first argument into a closure usable as a scalar function.
+macro_rules! with_oui_db {
+    ($db: ident, $func: path) => {{
+        let $db = $db.clone();
+        move |ctx: &rusqlite::functions::Context<'_>| $func(&$db, ctx)
+    }};
+}
+
+/// Binds a function taking the connection's ethers names as its first argument into a closure usable as a scalar function.
+macro_rules! with_ethers {
+    ($ethers: ident, $func: path) => {{
+        let $ethers = $ethers.clone();
+        move |ctx: &rusqlite::functions::Context<'_>| $func(&$ethers, ctx)
+    }};
+}
+
+/// Binds a function taking the connection's settings as its first argument into a closure usable as a scalar function.
+macro_rules! with_settings {
+    ($settings: ident, $func: path) => {{
+        let $settings = $settings.clone();
+        move |ctx: &rusqlite::functions::Context<'_>| $func(&$settings, ctx)
+    }};
+}
+
+// Every function is registered with one of these, according to how it behaves.
+
+/// For pure functions: deterministic, and without side-effects. These may be used anywhere, including in indexes,
+/// generated columns, and CHECK constraints.
+const PURE: FunctionFlags = FunctionFlags::SQLITE_UTF8
+    .union(FunctionFlags::SQLITE_DETERMINISTIC)
+    .union(FunctionFlags::SQLITE_INNOCUOUS);
+
+/// For functions reading the connection's OUI database or settings. These are without side-effects, but aren't
+/// deterministic, as either may be changed at runtime (such as by `OUI_LOAD` or `NETTOOLS_SET`), which would silently
+/// invalidate any index built on them.
+const LOOKUP: FunctionFlags = FunctionFlags::SQLITE_UTF8
+    .union(FunctionFlags::SQLITE_INNOCUOUS);
+
+/// For functions with side effects (such as changing the OUI database, or accessing files and the network). These
+/// can't be called from views, triggers, or the schema, where they could be invoked without the user's knowledge.
+const DIRECT: FunctionFlags = FunctionFlags::SQLITE_UTF8
+    .union(FunctionFlags::SQLITE_DIRECTONLY);
+
+/// Creates functions as [Connection] does, wrapping each to count its calls, errors, and time in the connection's stats
+/// while they're enabled.
+/// Functions colliding with those already registered are handled as chosen by the registration's [OnConflict].
+struct Registrar<'c> {
+    conn: &'c Connection,
+    reg: &'c Registration,
+    stats: &'c SharedStats,
+    collisions: &'c SharedCollisions,
+}
+
+impl Registrar<'_> {
+    /// Whether a function is already registered under the name (case insensitively) and number of arguments.
+    fn exists(&self, fn_name: &str, n_arg: std::ffi::c_int) -> rusqlite::Result<bool> {
+        self.conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM pragma_function_list WHERE name = lower(?1) AND narg = ?2)",
+            rusqlite::params![fn_name, n_arg],
+            |r| r.get(0),
+        )
+    }
+
+    /// Returns the name to register a function under, or None if it is to be skipped, recording any collision.
+    fn resolve(&self, fn_name: &str, n_arg: std::ffi::c_int) -> rusqlite::Result<Option<String>> {
+        let overwrite = self.reg.conflict_handling() == &OnConflict::Overwrite;
+        let taken = match self.exists(fn_name, n_arg) {
+            Ok(taken) => taken,
+            // SQLite may be built without its introspection pragmas, leaving collisions undetectable. That only matters
+            // when they aren't to be overwritten.
+            Err(_) if overwrite => false,
+            Err(e) => return Err(e),
+        };
+        if !taken {
+            return Ok(Some(fn_name.to_owned()));
+        }
+
+        let resolution = if overwrite {
+            Resolution::Overwritten
+        } else {
+            match self.reg.alternate_name(fn_name) {
+                Some(alternate) if !self.exists(&alternate, n_arg)? => Resolution::Renamed(alternate),
+                _ => Resolution::Skipped,
+            }
+        };
+        event!(WARN, function = fn_name, n_arg, resolution = ?resolution, "function is already registered");
+        let registered = match &resolution {
+            Resolution::Overwritten => Some(fn_name.to_owned()),
+            Resolution::Skipped => None,
+            Resolution::Renamed(alternate) => Some(alternate.clone()),
+        };
+        self.collisions.push(Collision { name: fn_name.to_owned(), n_arg, resolution });
+        Ok(registered)
+    }
+
+    fn create_scalar_function<F, T>(&self, fn_name: &str, n_arg: std::ffi::c_int, flags: FunctionFlags, mut x_func: F) -> rusqlite::Result<()>
+    where
+        F: FnMut(&Context<'_>) -> rusqlite::Result<T> + Send + UnwindSafe + 'static,
+        T: ToSql,
+    {
+        let Some(fn_name) = self.resolve(fn_name, n_arg)? else { return Ok(()); };
+        let (stats, counts) = (self.stats.clone(), self.stats.function(&fn_name));
+        self.conn.create_scalar_function(&fn_name, n_arg, flags, move |ctx: &Context<'_>| {
+            if !stats.enabled() {
+                return x_func(ctx);
+            }
+            let started = Instant::now();
+            let result = x_func(ctx);
+            counts.record(started, result.is_err());
+            result
+        })
+    }
+
+    fn create_aggregate_function<A, D, T>(&self, fn_name: &str, n_arg: std::ffi::c_int, flags: FunctionFlags, aggr: D) -> rusqlite::Result<()>
+    where
+        A: RefUnwindSafe + UnwindSafe,
+        D: Aggregate<A, T> + 'static,
+        T: ToSql,
+    {
+        let Some(fn_name) = self.resolve(fn_name, n_arg)? else { return Ok(()); };
+        let (stats, counts) = (self.stats.clone(), self.stats.function(&fn_name));
+        self.conn.create_aggregate_function(&fn_name, n_arg, flags, Counted { aggr, stats, counts })
+    }
+
+    /// Creates a function through SQLite directly, for functions reading arguments that rusqlite's [Context] can't, such
+    /// as bound lists. It is counted and named as with [create_scalar_function](Registrar::create_scalar_function).
+    fn create_raw_scalar_function(&self, fn_name: &str, n_arg: std::ffi::c_int, flags: FunctionFlags, x_func: RawFunction) -> rusqlite::Result<()> {
+        let Some(fn_name) = self.resolve(fn_name, n_arg)? else { return Ok(()); };
+        let counted = RawCounted { func: x_func, stats: self.stats.clone(), counts: self.stats.function(&fn_name) };
+        let c_name = std::ffi::CString::new(fn_name)?;
+        // SAFETY: the handle is only used to create the function, and SQLite passes `counted` back to `call_raw`, until
+        // dropping it with `drop_raw` (including when creating the function fails)
+        let rc = unsafe {
+            rusqlite::ffi::sqlite3_create_function_v2(
+                self.conn.handle(),
+                c_name.as_ptr(),
+                n_arg,
+                flags.bits(),
+                Box::into_raw(Box::new(counted)).cast(),
+                Some(call_raw),
+                None,
+                None,
+                Some(drop_raw),
+            )
+        };
+        match rc {
+            rusqlite::ffi::SQLITE_OK => Ok(()),
+            rc => Err(rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(rc), None)),
+        }
+    }
+}
+
+/// A function created by [Registrar::create_raw_scalar_function], with the counts it records while stats are enabled.
+struct RawCounted {
+    func: RawFunction,
+    stats: SharedStats,
+    counts: Arc<FunctionCounts>,
+}
+
+unsafe extern "C" fn call_raw(ctx: *mut rusqlite::ffi::sqlite3_context, argc: std::ffi::c_int, argv: *mut *mut rusqlite::ffi::sqlite3_value) {
+    let raw = &*rusqlite::ffi::sqlite3_user_data(ctx).cast::<RawCounted>();
+    let args = match usize::try_from(argc) {
+        Ok(argc) if !argv.is_null() => std::slice::from_raw_parts(argv, argc),
+        _ => &[],
+    };
+    let args = RawArgs::new(args);
+
+    let started = raw.stats.enabled().then(Instant::now);
+    let result = std::panic::catch_unwind(AssertUnwindSafe(|| (raw.func)(&args)))
+        .unwrap_or_else(|_| Err(rusqlite::Error::UnwindingPanic));
+    if let Some(started) = started {
+        raw.counts.record(started, result.is_err());
+    }
+    exports::bound::set_result(ctx, result);
+}
+
+unsafe extern "C" fn drop_raw(p: *mut std::ffi::c_void) {
+    drop(Box::from_raw(p.cast::<RawCounted>()));
+}
+
+/// An aggregate function counting each row it is given as a call, while stats are enabled.
+struct Counted<D> {
+    aggr: D,
+    stats: SharedStats,
+    counts: Arc<FunctionCounts>,
+}
+
+impl<A, T, D> Aggregate<A, T> for Counted<D>
+where
+    A: RefUnwindSafe + UnwindSafe,
+    D: Aggregate<A, T>,
+    T: ToSql,
+{
+    fn init(&self, ctx: &mut Context<'_>) -> rusqlite::Result<A> {
+        self.aggr.init(ctx)
+    }
+
+    fn step(&self, ctx: &mut Context<'_>, acc: &mut A) -> rusqlite::Result<()> {
+        if !self.stats.enabled() {
+            return self.aggr.step(ctx, acc);
+        }
+        let started = Instant::now();
+        let result = self.aggr.step(ctx, acc);
+        self.counts.record(started, result.is_err());
+        result
+    }
+
+    fn finalize(&self, ctx: &mut Context<'_>, acc: Option<A>) -> rusqlite::Result<T> {
+        self.aggr.finalize(ctx, acc)
+    }
+}
+
+fn register_scalar_funcs(
+    dbconn: &Connection,
+    reg: &Registration,
+    oui_db: &SharedOuiDb,
+    ethers: &SharedEthers,
+    settings: &SharedSettings,
+    stats: &SharedStats,
+    collisions: &SharedCollisions,
+) -> rusqlite::Result<()> {
+    let dbconn = &Registrar { conn: dbconn, reg, stats, collisions };
+    let name = |n| reg.name(n);
     // dbconn.create_scalar_function("INSUBNET", 2, flags, exports::in_subnet)?;
     // dbconn.create_scalar_function("INSUBNET", 3, flags, exports::in_subnet)?;
 
-    dbconn.create_scalar_function("MAC_FORMAT",      1, flags, exports::mac::format)?;
-    dbconn.create_scalar_function("MAC_FORMAT",      2, flags, exports::mac::format)?;
-    dbconn.create_scalar_function("MAC_PREFIX",      1, flags, exports::mac::prefix)?;
-    dbconn.create_scalar_function("MAC_MANUF",       1, flags, exports::mac::manuf)?;
-    dbconn.create_scalar_function("MAC_MANUFLONG",   1, flags, exports::mac::manuf_long)?;
-    dbconn.create_scalar_function("MAC_COMMENT",     1, flags, exports::mac::comment)?;
-    dbconn.create_scalar_function("MAC_ISUNICAST",   1, flags, exports::mac::is_unicast)?;
-    dbconn.create_scalar_function("MAC_ISMULTICAST", 1, flags, exports::mac::is_multicast)?;
-    dbconn.create_scalar_function("MAC_ISUNIVERSAL", 1, flags, exports::mac::is_universal)?;
-    dbconn.create_scalar_function("MAC_ISLOCAL",     1, flags, exports::mac::is_local)?;
-
-    dbconn.create_scalar_function("IP_FORMAT",     1, flags, exports::inet::format)?;
-    dbconn.create_scalar_function("IP_FORMAT",     2, flags, exports::inet::format)?;
-    dbconn.create_scalar_function("IP_CONTAINS",   2, flags, exports::inet::contains)?;
-    dbconn.create_scalar_function("IP_CONTAINS",   3, flags, exports::inet::contains)?;
-    dbconn.create_scalar_function("IP_BLOBIFY",      1, flags, exports::inet::blobify)?;
+    // describing the build is always registered, regardless of the chosen groups
+    dbconn.create_scalar_function(&name("NETTOOLS_VERSION"),    0, PURE,   exports::info::version)?;
+    dbconn.create_scalar_function(&name("NETTOOLS_BUILD_INFO"), 0, PURE,   exports::info::build_info)?;
+    dbconn.create_scalar_function(&name("NETTOOLS_SET"),        2, DIRECT, {
+        let (settings, stats) = (settings.clone(), stats.clone());
+        move |ctx: &rusqlite::functions::Context<'_>| exports::settings::set(&settings, &stats, ctx)
+    })?;
+    dbconn.create_scalar_function(&name("NETTOOLS_SELFTEST"),   0, DIRECT, {
+        let (oui_db, settings, reg, collisions) = (oui_db.clone(), settings.clone(), reg.clone(), collisions.clone());
+        move |ctx: &rusqlite::functions::Context<'_>| exports::info::selftest(&oui_db, &settings, &reg, &collisions, ctx)
+    })?;
+    dbconn.create_scalar_function(&name("NETTOOLS_STATS"),     0, LOOKUP, {
+        let (stats, oui_db) = (stats.clone(), oui_db.clone());
+        move |ctx: &rusqlite::functions::Context<'_>| exports::info::stats(&stats, &oui_db, ctx)
+    })?;
+    dbconn.create_scalar_function(&name("NETTOOLS_STATS_RESET"), 0, DIRECT, {
+        let (stats, oui_db) = (stats.clone(), oui_db.clone());
+        move |ctx: &rusqlite::functions::Context<'_>| exports::info::stats_reset(&stats, &oui_db, ctx)
+    })?;
+
+    if reg.includes(FunctionGroup::Mac) {
+        dbconn.create_scalar_function(&name("MAC_FORMAT"),      1, PURE,   exports::mac::format)?;
+        dbconn.create_scalar_function(&name("MAC_FORMAT"),      2, PURE,   exports::mac::format)?;
+        dbconn.create_scalar_function(&name("MAC_FORMAT_DEFAULT"), 1, LOOKUP, with_settings!(settings, exports::mac::format_default))?;
+        dbconn.create_scalar_function(&name("MAC_PREFIX"),      1, LOOKUP, with_oui_db!(oui_db, exports::mac::prefix))?;
+        dbconn.create_scalar_function(&name("MAC_MANUF"),       1, LOOKUP, with_oui_db!(oui_db, exports::mac::manuf))?;
+        dbconn.create_scalar_function(&name("MAC_MANUF_ALL"),   1, LOOKUP, with_oui_db!(oui_db, exports::mac::manuf_all))?;
+        dbconn.create_scalar_function(&name("MANUF_NORMALIZE"), 1, PURE,   exports::mac::manuf_normalize)?;
+        dbconn.create_scalar_function(&name("MANUF_ASCII"),     1, PURE,   exports::mac::manuf_ascii)?;
+        dbconn.create_scalar_function(&name("MAC_MANUFLONG"),   1, LOOKUP, with_oui_db!(oui_db, exports::mac::manuf_long))?;
+        dbconn.create_scalar_function(&name("MAC_COMMENT"),     1, LOOKUP, with_oui_db!(oui_db, exports::mac::comment))?;
+        dbconn.create_scalar_function(&name("MAC_MANUF_COUNTRY"), 1, LOOKUP, with_oui_db!(oui_db, exports::mac::manuf_country))?;
+        dbconn.create_scalar_function(&name("MAC_MANUF_ADDRESS"), 1, LOOKUP, with_oui_db!(oui_db, exports::mac::manuf_address))?;
+        dbconn.create_scalar_function(&name("MAC_MANUF_SOURCE"), 1, LOOKUP, with_oui_db!(oui_db, exports::mac::manuf_source))?;
+        dbconn.create_scalar_function(&name("MAC_BLOCKTYPE"),   1, LOOKUP, with_oui_db!(oui_db, exports::mac::block_type))?;
+        dbconn.create_scalar_function(&name("MAC_INFO"),        1, LOOKUP, with_oui_db!(oui_db, exports::mac::info))?;
+        dbconn.create_scalar_function(&name("MAC_VROUTER"),     1, PURE,   exports::mac::vrouter)?;
+        dbconn.create_scalar_function(&name("MAC_PROTOCOL"),    1, PURE,   exports::mac::protocol)?;
+        dbconn.create_scalar_function(&name("WOL_PACKET"),      1, PURE,   exports::mac::wol_packet)?;
+        dbconn.create_scalar_function(&name("WOL_PACKET"),      2, PURE,   exports::mac::wol_packet)?;
+        dbconn.create_scalar_function(&name("MAC_ISUNICAST"),   1, PURE,   exports::mac::is_unicast)?;
+        dbconn.create_scalar_function(&name("MAC_ISMULTICAST"), 1, PURE,   exports::mac::is_multicast)?;
+        dbconn.create_scalar_function(&name("MAC_ISUNIVERSAL"), 1, PURE,   exports::mac::is_universal)?;
+        dbconn.create_scalar_function(&name("MAC_ISLOCAL"),     1, PURE,   exports::mac::is_local)?;
+        dbconn.create_scalar_function(&name("MAC_ISRANDOMIZED"), 1, LOOKUP, with_oui_db!(oui_db, exports::mac::is_randomized))?;
+        dbconn.create_scalar_function(&name("MAC_VALIDATE"),    1, PURE,   exports::mac::validate)?;
+        dbconn.create_scalar_function(&name("MAC_BLOBIFY"),     1, PURE,   exports::mac::blobify)?;
+        dbconn.create_scalar_function(&name("MAC_FROM_BLOB"),   1, PURE,   exports::mac::from_blob)?;
+        dbconn.create_scalar_function(&name("MAC_FROM_BLOB"),   2, PURE,   exports::mac::from_blob)?;
+        dbconn.create_scalar_function(&name("MAC_ASINT"),       1, PURE,   exports::mac::as_int)?;
+        dbconn.create_scalar_function(&name("MAC_FROMINT"),     1, PURE,   exports::mac::from_int)?;
+        dbconn.create_scalar_function(&name("MAC_FROMINT"),     2, PURE,   exports::mac::from_int)?;
+        dbconn.create_scalar_function(&name("MAC_TO_EUI64"),    1, PURE,   exports::mac::to_eui64)?;
+        dbconn.create_scalar_function(&name("MAC_TO_EUI64"),    2, PURE,   exports::mac::to_eui64)?;
+        dbconn.create_scalar_function(&name("MAC_FROM_EUI64"),  1, PURE,   exports::mac::from_eui64)?;
+        dbconn.create_scalar_function(&name("MAC_FROM_EUI64"),  2, PURE,   exports::mac::from_eui64)?;
+        dbconn.create_scalar_function(&name("LINKLOCAL_MATCHES_MAC"), 2, PURE, exports::mac::linklocal_matches)?;
+        dbconn.create_scalar_function(&name("UUID_MAC"),        1, PURE,   exports::mac::uuid_mac)?;
+        dbconn.create_scalar_function(&name("UUID_MAC"),        2, PURE,   exports::mac::uuid_mac)?;
+        dbconn.create_scalar_function(&name("MAC_ADD"),         2, PURE,   exports::mac::add)?;
+        dbconn.create_scalar_function(&name("MAC_ADD"),         3, PURE,   exports::mac::add)?;
+        dbconn.create_scalar_function(&name("MAC_SUB"),         2, PURE,   exports::mac::sub)?;
+        dbconn.create_scalar_function(&name("MAC_SUB"),         3, PURE,   exports::mac::sub)?;
+        dbconn.create_scalar_function(&name("MAC_BETWEEN"),     3, PURE,   exports::mac::between)?;
+        dbconn.create_scalar_function(&name("MAC_EQ"),          2, PURE,   exports::mac::eq)?;
+        dbconn.create_scalar_function(&name("MAC_CMP"),         2, PURE,   exports::mac::cmp)?;
+        dbconn.create_scalar_function(&name("MAC_MATCH"),       2, PURE,   exports::mac::match_mac)?;
+        dbconn.create_scalar_function(&name("MAC_MATCH"),       3, PURE,   exports::mac::match_mac)?;
+        dbconn.create_scalar_function(&name("MAC_BUCKET"),      2, PURE,   exports::mac::bucket)?;
+    }
+
+    if reg.includes(FunctionGroup::Inet) {
+        dbconn.create_scalar_function(&name("IP_FORMAT"),     1, PURE,   exports::inet::format)?;
+        dbconn.create_scalar_function(&name("IP_FORMAT"),     2, PURE,   exports::inet::format)?;
+        dbconn.create_scalar_function(&name("IP_FORMAT"),     3, PURE,   exports::inet::format)?;
+        dbconn.create_scalar_function(&name("IP_FORMAT_DEFAULT"), 1, LOOKUP, with_settings!(settings, exports::inet::format_default))?;
+        dbconn.create_scalar_function(&name("IP_CONTAINS"),   2, PURE,   exports::inet::contains)?;
+        dbconn.create_scalar_function(&name("IP_CONTAINS"),   3, PURE,   exports::inet::contains)?;
+        dbconn.create_scalar_function(&name("IP_CONTAINS_ALL"), 2, PURE, exports::inet::contains_all)?;
+        dbconn.create_scalar_function(&name("IP_CONTAINS_ALL"), 3, PURE, exports::inet::contains_all)?;
+        // reads lists bound as pointers, which rusqlite's Context can't
+        dbconn.create_raw_scalar_function(&name("IP_IN_ANY"), 2, PURE, exports::inet::in_any)?;
+        dbconn.create_scalar_function(&name("IP_BLOBIFY"),      1, PURE,   exports::inet::blobify)?;
+        dbconn.create_scalar_function(&name("IP_BUCKET"),     2, PURE,   exports::inet::bucket)?;
+        dbconn.create_scalar_function(&name("IP_VALIDATE"),   1, PURE,   exports::inet::validate)?;
+        dbconn.create_scalar_function(&name("FLOW_HASH"),     5, PURE,   exports::inet::flow_hash)?;
+        dbconn.create_scalar_function(&name("CIDR_INTERSECT"), 2, PURE,   exports::inet::cidr_intersect)?;
+        dbconn.create_aggregate_function(&name("CIDR_INTERSECT"), 1, PURE, exports::inet::CidrIntersect)?;
+        dbconn.create_scalar_function(&name("IP_OVERLAP_COUNT"), 2, PURE, exports::inet::overlap_count)?;
+        dbconn.create_aggregate_function(&name("SUBNET_UTILIZATION"), 2, PURE, exports::inet::SubnetUtilization)?;
+        dbconn.create_aggregate_function(&name("IP_ROLLUP"), 2, PURE, exports::inet::IpRollup)?;
+        dbconn.create_aggregate_function(&name("COUNT_DISTINCT_PREFIXES"), 2, PURE, exports::inet::CountDistinctPrefixes)?;
+        dbconn.create_aggregate_function(&name("COUNT_DISTINCT_PREFIXES"), 3, PURE, exports::inet::CountDistinctPrefixes)?;
+        dbconn.create_scalar_function(&name("IP6_STABLE_PRIVACY"), 3, PURE, exports::inet::stable_privacy_address)?;
+        dbconn.create_scalar_function(&name("IP6_STABLE_PRIVACY"), 4, PURE, exports::inet::stable_privacy_address)?;
+        // the zero argument form is random
+        dbconn.create_scalar_function(&name("IP6_ULA_GENERATE"), 0, LOOKUP, exports::inet::ula_generate)?;
+        dbconn.create_scalar_function(&name("IP6_ULA_GENERATE"), 1, PURE, exports::inet::ula_generate)?;
+        dbconn.create_scalar_function(&name("IP6_ULA_GENERATE"), 2, PURE, exports::inet::ula_generate)?;
+        dbconn.create_scalar_function(&name("IPB_CONTAINS"),  2, PURE,   exports::inet::blob_contains)?;
+        dbconn.create_scalar_function(&name("IPB_BUCKET"),    2, PURE,   exports::inet::blob_bucket)?;
+        dbconn.create_scalar_function(&name("IPB_FORMAT"),    1, PURE,   exports::inet::blob_format)?;
+        #[cfg(feature = "dns")]
+        {
+            dbconn.create_scalar_function(&name("DNSBL_CHECK"), 2, DIRECT, with_settings!(settings, exports::dns::dnsbl_check))?;
+            dbconn.create_scalar_function(&name("FCRDNS"),      1, DIRECT, with_settings!(settings, exports::dns::fcrdns))?;
+        }
+        #[cfg(feature = "rdap")]
+        {
+            dbconn.create_scalar_function(&name("RDAP"),          1, DIRECT, exports::rdap::rdap)?;
+            dbconn.create_scalar_function(&name("RDAP_NETNAME"),  1, PURE,   exports::rdap::netname)?;
+            dbconn.create_scalar_function(&name("RDAP_ORG"),      1, PURE,   exports::rdap::org)?;
+            dbconn.create_scalar_function(&name("RDAP_ABUSE"),    1, PURE,   exports::rdap::abuse)?;
+        }
+    }
+
+    if reg.includes(FunctionGroup::ClickHouse) {
+        dbconn.create_scalar_function(&name("IPv4NumToString"),    1, PURE, exports::clickhouse::ipv4_num_to_string)?;
+        dbconn.create_scalar_function(&name("IPv4StringToNum"),    1, PURE, exports::clickhouse::ipv4_string_to_num)?;
+        dbconn.create_scalar_function(&name("IPv6NumToString"),    1, PURE, exports::clickhouse::ipv6_num_to_string)?;
+        dbconn.create_scalar_function(&name("IPv4CIDRToRange"),    2, PURE, exports::clickhouse::ipv4_cidr_to_range)?;
+        dbconn.create_scalar_function(&name("IPv6CIDRToRange"),    2, PURE, exports::clickhouse::ipv6_cidr_to_range)?;
+        dbconn.create_scalar_function(&name("isIPAddressInRange"), 2, PURE, exports::clickhouse::is_ip_address_in_range)?;
+    }
+
+    if reg.includes(FunctionGroup::Siem) {
+        dbconn.create_scalar_function(&name("CIDRMATCH"),  2, PURE, exports::siem::cidrmatch)?;
+        dbconn.create_scalar_function(&name("inet_aton"),  1, PURE, exports::siem::inet_aton)?;
+        dbconn.create_scalar_function(&name("inet_ntoa"),  1, PURE, exports::siem::inet_ntoa)?;
+        dbconn.create_scalar_function(&name("inet6_aton"), 1, PURE, exports::siem::inet6_aton)?;
+        dbconn.create_scalar_function(&name("inet6_ntoa"), 1, PURE, exports::siem::inet6_ntoa)?;
+        dbconn.create_scalar_function(&name("is_ipv4"),    1, PURE, exports::siem::is_ipv4)?;
+        dbconn.create_scalar_function(&name("is_ipv6"),    1, PURE, exports::siem::is_ipv6)?;
+    }
+
+    if reg.includes(FunctionGroup::Packet) {
+        dbconn.create_scalar_function(&name("TCP_FLAGS"),    1, PURE,   exports::packet::flags)?;
+        dbconn.create_scalar_function(&name("TCP_FLAG_SET"), 2, PURE,   exports::packet::flag_set)?;
+        dbconn.create_scalar_function(&name("ETHERTYPE_NAME"),  1, PURE,   exports::packet::ethertype_name)?;
+        dbconn.create_scalar_function(&name("ETHERTYPE_VALUE"), 1, PURE,   exports::packet::ethertype_value)?;
+        dbconn.create_scalar_function(&name("VLAN_ID"),         1, PURE,   exports::packet::vlan_id)?;
+        dbconn.create_scalar_function(&name("VLAN_PCP"),        1, PURE,   exports::packet::vlan_pcp)?;
+        dbconn.create_scalar_function(&name("VLAN_DEI"),        1, PURE,   exports::packet::vlan_dei)?;
+        dbconn.create_scalar_function(&name("MPLS_LABELS"),     1, PURE,   exports::packet::mpls_labels)?;
+        dbconn.create_scalar_function(&name("PORTRANGE_CONTAINS"), 2, PURE, exports::packet::portrange_contains)?;
+    }
+
+    if reg.includes(FunctionGroup::Oui) {
+        dbconn.create_scalar_function(&name("OUI_DB_DATE"),   0, PURE,   exports::oui::db_date)?;
+        dbconn.create_scalar_function(&name("OUI_DB_SOURCE"), 0, PURE,   exports::oui::db_source)?;
+        dbconn.create_scalar_function(&name("OUI_DB_SIZE"),   0, LOOKUP, with_oui_db!(oui_db, exports::oui::db_size))?;
+
+        #[cfg(not(feature = "wasm"))]
+        dbconn.create_scalar_function(&name("OUI_LOAD"),      -1, DIRECT, with_oui_db!(oui_db, exports::oui::load))?;
+        dbconn.create_scalar_function(&name("OUI_LOAD_TABLE"), 1, DIRECT, with_oui_db!(oui_db, exports::oui::load_table))?;
+        dbconn.create_scalar_function(&name("OUI_REGISTER"),   2, DIRECT, with_oui_db!(oui_db, exports::oui::register))?;
+        dbconn.create_scalar_function(&name("OUI_REGISTER"),   3, DIRECT, with_oui_db!(oui_db, exports::oui::register))?;
+        dbconn.create_scalar_function(&name("OUI_REGISTER"),   4, DIRECT, with_oui_db!(oui_db, exports::oui::register))?;
+        dbconn.create_scalar_function(&name("OUI_UNREGISTER"), 1, DIRECT, with_oui_db!(oui_db, exports::oui::unregister))?;
+        #[cfg(not(feature = "wasm"))]
+        dbconn.create_scalar_function(&name("OUI_DUMP"),       1, DIRECT, with_oui_db!(oui_db, exports::oui::dump))?;
+        #[cfg(not(feature = "wasm"))]
+        dbconn.create_scalar_function(&name("ETHERS_LOAD"),   -1, DIRECT, with_ethers!(ethers, exports::ethers::load))?;
+        dbconn.create_scalar_function(&name("ETHERS_NAME"),    1, LOOKUP, with_ethers!(ethers, exports::ethers::name))?;
+        #[cfg(feature = "oui-update")]
+        {
+            dbconn.create_scalar_function(&name("OUI_UPDATE"), 0, DIRECT, with_oui_db!(oui_db, exports::oui::update))?;
+            dbconn.create_scalar_function(&name("OUI_UPDATE"), 1, DIRECT, with_oui_db!(oui_db, exports::oui::update))?;
+        }
+    }
 
     // supernet-address
     // - takes many IP addresses, and returns the address above them
-    // dbconn.create_aggregate_function(fn_name, n_arg, flags, aggr)
 
     // eprintln!("scalar funcs: done");
     Ok(())
 }
 
+fn register_collations(dbconn: &Connection, reg: &Registration) -> rusqlite::Result<()> {
+    if reg.includes(FunctionGroup::Mac) {
+        dbconn.create_collation(&reg.name("MACADDR"), exports::mac::collate)?;
+    }
+    Ok(())
+}
+
+fn register_modules(dbconn: &Connection, reg: &Registration, oui_db: &SharedOuiDb) -> rusqlite::Result<()> {
+    if reg.includes(FunctionGroup::Oui) {
+        dbconn.create_module(&reg.name("oui"),          exports::tables::OuiTab::module(), Some(oui_db.clone()))?;
+        dbconn.create_module(&reg.name("oui_prefixes"), exports::tables::TableFunctionTab::<exports::tables::OuiPrefixes>::module(), Some(oui_db.clone()))?;
+        dbconn.create_module(&reg.name("oui_search"),   exports::tables::TableFunctionTab::<exports::tables::OuiSearch>::module(), Some(oui_db.clone()))?;
+        #[cfg(not(feature = "wasm"))]
+        dbconn.create_module(&reg.name("manuf_file"),   exports::tables::TableFunctionTab::<exports::tables::ManufFile>::module(), None)?;
+        #[cfg(not(feature = "wasm"))]
+        dbconn.create_module(&reg.name("oui_diff"),     exports::tables::TableFunctionTab::<exports::tables::OuiDiff>::module(), Some(oui_db.clone()))?;
+    }
+    #[cfg(not(feature = "wasm"))]
+    if reg.includes(FunctionGroup::Packet) {
+        dbconn.create_module(&reg.name("zeek_conn"),    exports::tables::TableFunctionTab::<exports::tables::ZeekConn>::module(), None)?;
+        #[cfg(feature = "netflow")]
+        dbconn.create_module(&reg.name("netflow_file"), exports::tables::TableFunctionTab::<exports::tables::NetflowFile>::module(), None)?;
+        #[cfg(feature = "nmap")]
+        dbconn.create_module(&reg.name("nmap_xml"),     exports::tables::NmapXmlTab::module(), None)?;
+    }
+    Ok(())
+}
+
+/// Registers every function, collation, and virtual table chosen by the options on a connection.
+///
+/// Unlike [register], this allows starting the connection's OUI lookups from an application's own database, such as
+/// one that is pre-built, filtered, or loaded from elsewhere, in place of [EMBEDDED_DB](crate::oui::EMBEDDED_DB).
+///
+/// ```
+/// use std::sync::Arc;
+/// use sqlite3_nettools::{oui::{OuiDb, OuiMeta}, RegistrationOptions};
+///
+/// let db = OuiDb::from_entries(vec![("3c:a6:f6".parse().unwrap(), OuiMeta::new("Lab", None, None))]);
+/// let conn = rusqlite::Connection::open_in_memory()?;
+/// sqlite3_nettools::register_with(&conn, RegistrationOptions { oui_db: Arc::new(db), ..Default::default() })?;
+/// let manuf: Option<String> = conn.query_row("SELECT MAC_MANUF('3c-a6-f6-c4-34-f8')", [], |r| r.get(0))?;
+/// assert_eq!(manuf.as_deref(), Some("Lab"));
+/// let source: Option<String> = conn.query_row("SELECT MAC_MANUF_SOURCE('3c-a6-f6-c4-34-f8')", [], |r| r.get(0))?;
+/// assert_eq!(source.as_deref(), Some("embedded:custom"));
+/// # Ok::<(), rusqlite::Error>(())
+/// ```
+pub fn register_with(dbconn: &Connection, options: RegistrationOptions) -> rusqlite::Result<()> {
+    let _span = span!(DEBUG, "register", registration = ?options.registration);
+    // Each connection starts with its own layers over the base OUI database, which may later be layered over
+    let custom = !Arc::ptr_eq(&options.oui_db, &EMBEDDED_DB);
+    let oui_db = SharedOuiDb::new(options.oui_db);
+    if custom {
+        oui_db.update_layer(OuiLayerKind::Embedded, |base| base.map(|l| ("custom".to_owned(), Arc::clone(&l.db))));
+    }
+    if options.well_known && !EMBEDDED_WKA_DB.is_empty() {
+        oui_db.set_layer(OuiLayerKind::WellKnown, Some((String::new(), Arc::clone(&EMBEDDED_WKA_DB))));
+    }
+
+    let ethers = SharedEthers::default();
+
+    let stats = SharedStats::new();
+    stats.set_enabled(options.settings.stats);
+    let settings = SharedSettings::new(options.settings);
+    let collisions = SharedCollisions::new();
+
+    let reg = &options.registration;
+    register_scalar_funcs(dbconn, reg, &oui_db, &ethers, &settings, &stats, &collisions)?;
+    register_collations(dbconn, reg)?;
+    register_modules(dbconn, reg, &oui_db)?;
+    event!(DEBUG, "registered functions");
+    Ok(())
+}
+
+/// Registers every function, collation, and virtual table of this extension on a connection.
+///
+/// This allows using them from a Rust application's own connections, without loading the extension as a shared
+/// library. Build with `default-features = false` to leave out the loadable extension entry point. Use
+/// [Registration] to register only some of them, or under other names.
+///
+/// ```
+/// let conn = rusqlite::Connection::open_in_memory()?;
+/// sqlite3_nettools::register(&conn)?;
+/// let manuf: Option<String> = conn.query_row("SELECT MAC_MANUF('3c-a6-f6-c4-34-f8')", [], |r| r.get(0))?;
+/// # Ok::<(), rusqlite::Error>(())
+/// ```
+pub fn register(dbconn: &Connection) -> rusqlite::Result<()> {
+    register_with(dbconn, RegistrationOptions::default())
+}
+
+/// The options used to register each connection opened after [auto_register] is called.
+static AUTO_REGISTRATION: RwLock<Option<RegistrationOptions>> = RwLock::new(None);
+
+/// Initializes each connection opened after [auto_register] is called.
+unsafe extern "C" fn auto_register_init(
+    db: *mut rusqlite::ffi::sqlite3,
+    errmsg: *mut *mut std::ffi::c_char,
+    _p_api: *const rusqlite::ffi::sqlite3_api_routines,
+) -> std::ffi::c_int {
+    let options = AUTO_REGISTRATION.read().unwrap_or_else(PoisonError::into_inner).clone().unwrap_or_default();
+    let result = unsafe { Connection::from_handle(db) }.and_then(|dbconn| register_with(&dbconn, options));
+    match result {
+        Ok(()) => rusqlite::ffi::SQLITE_OK,
+        Err(e) => {
+            unsafe { set_errmsg(errmsg, &e) };
+            rusqlite::ffi::SQLITE_ERROR
+        }
+    }
+}
+
+/// Reports an initialization error back to SQLite, through an entry point's error message pointer.
+unsafe fn set_errmsg(errmsg: *mut *mut std::ffi::c_char, e: &dyn std::fmt::Display) {
+    // SQLite frees the message with sqlite3_free, so it must be allocated by sqlite3_malloc
+    let msg = format!("Unable to register extension functions for sqlite3-nettools: {e}\0");
+    if !errmsg.is_null() {
+        let buf = unsafe { rusqlite::ffi::sqlite3_malloc(msg.len() as std::ffi::c_int) } as *mut std::ffi::c_char;
+        if !buf.is_null() {
+            unsafe { std::ptr::copy_nonoverlapping(msg.as_ptr().cast(), buf, msg.len()) };
+            unsafe { *errmsg = buf };
+        }
+    }
+}
+
+/// Registers this extension's functions on every connection opened from now on, using `sqlite3_auto_extension`.
+///
+/// This covers connections opened by code that doesn't expose them for [register], such as ORMs and connection pools.
+/// Connections that are already open are unaffected. Calling this more than once has no further effect. Use
+/// [Registration::auto_register] to register only some of the functions, or under other names.
+///
+/// ```
+/// sqlite3_nettools::auto_register()?;
+/// let conn = rusqlite::Connection::open_in_memory()?;
+/// let mac: String = conn.query_row("SELECT MAC_FORMAT('3ca6f6c434f8', 'colon')", [], |r| r.get(0))?;
+/// assert_eq!(mac, "3c:a6:f6:c4:34:f8");
+/// # Ok::<(), rusqlite::Error>(())
+/// ```
+pub fn auto_register() -> rusqlite::Result<()> {
+    auto_register_with(RegistrationOptions::default())
+}
+
+/// Registers this extension's functions on every connection opened from now on, as with [register_with].
+///
+/// Replaces the options given to any earlier call.
+pub fn auto_register_with(options: RegistrationOptions) -> rusqlite::Result<()> {
+    *AUTO_REGISTRATION.write().unwrap_or_else(PoisonError::into_inner) = Some(options);
+
+    // rusqlite's bindings declare the entry point as a bare `fn()` when built for loadable extensions
+    #[allow(clippy::useless_transmute, clippy::missing_transmute_annotations)]
+    let rc = unsafe { rusqlite::ffi::sqlite3_auto_extension(Some(std::mem::transmute(auto_register_init as AutoExtensionInit))) };
+    match rc {
+        rusqlite::ffi::SQLITE_OK => Ok(()),
+        rc => Err(rusqlite::Error::SqliteFailure(rusqlite::ffi::Error::new(rc), None)),
+    }
+}
+
+/// Stops registering this extension's functions on new connections, undoing [auto_register].
+///
+/// Returns whether [auto_register] had been called.
+pub fn cancel_auto_register() -> bool {
+    *AUTO_REGISTRATION.write().unwrap_or_else(PoisonError::into_inner) = None;
+    #[allow(clippy::useless_transmute, clippy::missing_transmute_annotations)]
+    let rc = unsafe { rusqlite::ffi::sqlite3_cancel_auto_extension(Some(std::mem::transmute(auto_register_init as AutoExtensionInit))) };
+    rc == 1
+}
+
+#[test]
+fn auto_registered_connections() {
+    let _lock = test_util::AUTO_REGISTER.lock().unwrap_or_else(PoisonError::into_inner);
+    let format = |conn: &Connection| {
+        conn.query_row("SELECT auto_MAC_FORMAT('3ca6f6c434f8', 'colon')", [], |r| r.get::<_, String>(0))
+    };
+
+    let registration = Registration::builder().prefix("auto_");
+    auto_register_with(RegistrationOptions { registration, ..Default::default() }).unwrap();
+    let first = Connection::open_in_memory().unwrap();
+    let second = Connection::open_in_memory().unwrap();
+    assert_eq!(format(&second).unwrap(), "3c:a6:f6:c4:34:f8");
+    // each connection keeps its own OUI database
+    first.query_row("SELECT auto_OUI_REGISTER('02:00:00', 'Lab')", [], |_| Ok(())).unwrap();
+    assert_eq!(test_util::query_text(&first, "SELECT auto_MAC_MANUF('02:00:00:00:00:01')").as_deref(), Some("Lab"));
+    assert_eq!(test_util::query_text(&second, "SELECT auto_MAC_MANUF('02:00:00:00:00:01')"), None);
+
+    // connections already open keep their functions
+    assert!(cancel_auto_register());
+    assert!(!cancel_auto_register());
+    assert!(format(&Connection::open_in_memory().unwrap()).is_err());
+    assert_eq!(format(&first).unwrap(), "3c:a6:f6:c4:34:f8");
+}
+
+/// Reads the registration options from the environment when loaded as an extension, see [Registration::from_env]
+#[cfg(feature = "loadable-extension")]
+fn env_registration() -> rusqlite::Result<Registration> {
+    Registration::from_env().map_err(|e| rusqlite::Error::UserFunctionError(Box::new(e)))
+}
+
+type AutoExtensionInit = unsafe extern "C" fn(
+    *mut rusqlite::ffi::sqlite3,
+    *mut *mut std::ffi::c_char,
+    *const rusqlite::ffi::sqlite3_api_routines,
+) -> std::ffi::c_int;
+
+/// Registers this extension's functions in WebAssembly builds, where SQLite is linked in with this crate (such as a
+/// custom sqlite-wasm or sql.js build) and extensions can't be loaded. The host may call this on an open connection, or
+/// pass it to `sqlite3_auto_extension` to cover every connection.
+///
+/// Registers with the options given to [auto_register_with], if called, and otherwise as [register] does.
+///
+/// # Safety
+/// `db` must be an open connection, and `errmsg` either null or valid to write an error message to, which SQLite
+/// ensures when this is called as an auto extension. `p_api` is unused, and may be null.
+#[cfg(feature = "wasm")]
+#[no_mangle]
+pub unsafe extern "C" fn sqlite3_nettools_register(
+    db: *mut rusqlite::ffi::sqlite3,
+    errmsg: *mut *mut std::ffi::c_char,
+    p_api: *const rusqlite::ffi::sqlite3_api_routines,
+) -> std::ffi::c_int {
+    unsafe { auto_register_init(db, errmsg, p_api) }
+}
+
+#[cfg(feature = "loadable-extension")]
 #[no_mangle]
 unsafe extern "C" fn sqlite3_extension_init(
     db: *mut ffi::sqlite3,
@@ -70,12 +729,14 @@ unsafe extern "C" fn sqlite3_extension_init(
     rusqlite::ffi::loadable_extension_init(p_api as *mut ffi::sqlite3_api_routines);
     let dbconn = unsafe { rusqlite::Connection::from_handle(db).unwrap() };
 
-    match register_scalar_funcs(&dbconn) {
+    match env_registration().and_then(|reg| reg.register(&dbconn)) {
         Ok(()) => ffi::SQLITE_OK,
         Err(e) => {
+            event!(ERROR, error = %e, "unable to register extension functions");
+            #[cfg(not(feature = "tracing"))]
             eprintln!("Unable to register extension functions for sqlite3-inet: {e}");
 
-            let upper_err = CString::new(e.to_string()).unwrap();
+            let upper_err = std::ffi::CString::new(e.to_string()).unwrap();
 
             // This "hack" is due to the custom bindgen for the rust-wrappers for the sqlite3_api_routines not propogating varargs style argument passing
             let api_routine_raw_ptr = core::ptr::addr_of!((*p_api).mprintf);
@@ -94,3 +755,58 @@ unsafe extern "C" fn sqlite3_extension_init(
         }
     }
 }
+
+// SQLite derives a default entry point from the library's filename when none is given to `.load`: the directory,
+// any `lib` prefix, the extension, and any non-letters are dropped. Both `libsqlite3_nettools.so` and
+// `sqlite3_nettools.dll` look for `sqlite3_sqlitenettools_init`, while a library renamed to `nettools.so` looks for
+// `sqlite3_nettools_init`.
+
+#[cfg(feature = "loadable-extension")]
+#[no_mangle]
+unsafe extern "C" fn sqlite3_sqlitenettools_init(
+    db: *mut ffi::sqlite3,
+    errmsg: *mut *mut std::ffi::c_char,
+    p_api: *const ffi::sqlite3_api_routines,
+) -> std::ffi::c_int {
+    unsafe { sqlite3_extension_init(db, errmsg, p_api) }
+}
+
+#[cfg(feature = "loadable-extension")]
+#[no_mangle]
+unsafe extern "C" fn sqlite3_nettools_init(
+    db: *mut ffi::sqlite3,
+    errmsg: *mut *mut std::ffi::c_char,
+    p_api: *const ffi::sqlite3_api_routines,
+) -> std::ffi::c_int {
+    unsafe { sqlite3_extension_init(db, errmsg, p_api) }
+}
+
+/// Returned by an entry point to keep the extension loaded after its connection closes (`SQLITE_OK | (1 << 8)`).
+#[cfg(feature = "loadable-extension")]
+const SQLITE_OK_LOAD_PERMANENTLY: std::ffi::c_int = 256;
+
+/// Loads the extension permanently, for use as `.load ./libsqlite3_nettools sqlite3_nettools_permanent_init`.
+///
+/// The library stays loaded for the life of the process, rather than being unloaded (and its embedded OUI database
+/// discarded) along with the connection. Every connection opened afterwards is registered automatically, as with
+/// [auto_register], so it only needs to be loaded once.
+#[cfg(feature = "loadable-extension")]
+#[no_mangle]
+unsafe extern "C" fn sqlite3_nettools_permanent_init(
+    db: *mut ffi::sqlite3,
+    errmsg: *mut *mut std::ffi::c_char,
+    p_api: *const ffi::sqlite3_api_routines,
+) -> std::ffi::c_int {
+    let rc = unsafe { sqlite3_extension_init(db, errmsg, p_api) };
+    if rc != ffi::SQLITE_OK {
+        return rc;
+    }
+
+    match env_registration().and_then(|reg| reg.auto_register()) {
+        Ok(()) => SQLITE_OK_LOAD_PERMANENTLY,
+        Err(e) => {
+            unsafe { set_errmsg(errmsg, &e) };
+            ffi::SQLITE_ERROR
+        }
+    }
+}
@@ -1,180 +1,349 @@
-use eui48::MacAddress;
+use std::{fmt, str::FromStr};
+
 use smallstr::SmallString;
 
-use crate::oui::Oui;
+use crate::oui::{Oui, ParseMacError};
+
+pub use sqlite3_nettools_core::{MacStyle, MacText};
+use sqlite3_nettools_core::EuiBytes;
 
-#[derive(Clone, Copy, Debug)]
-pub enum MacStyle {
-    Plain,
-    Dashed,
-    Colon,
-    Dots,
-    Prefixed,
-    InterfaceId,
-    LinkLocal,
+/// A parsed format specifier, as accepted by `MAC_FORMAT`: a style name (see [MacStyle::from_str]) or [MacTemplate],
+/// whose casing sets the output's casing, optionally prefixed with the `?` and `~` flags. Parsed with [str::parse].
+///
+/// ```
+/// use sqlite3_nettools::mac::{Eui, FormatSpec, FormatStyle, MacAddr, MacStyle};
+///
+/// let spec: FormatSpec = "?DASH".parse().unwrap();
+/// assert!(matches!(spec.style, FormatStyle::Named(MacStyle::Dashed)));
+/// assert!(spec.uppercase && spec.null_on_bad_mac);
+///
+/// let mac = Eui::from(MacAddr::new([0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]));
+/// assert_eq!(spec.format(mac).unwrap(), "AA-BB-CC-DD-EE-FF");
+/// assert_eq!("xxxx_xxxx_xxxx".parse::<FormatSpec>().unwrap().format(mac).unwrap(), "aabb_ccdd_eeff");
+/// assert!("Dash".parse::<FormatSpec>().is_err());
+/// ```
+#[derive(Clone, Debug)]
+pub struct FormatSpec {
+    pub style: FormatStyle,
+    /// Whether the output is uppercase, as determined by the specifier's casing
+    pub uppercase: bool,
+    /// The `?` flag: whether a bad address should produce NULL, rather than an error
+    pub null_on_bad_mac: bool,
+    /// The `~` flag: whether an unknown style falls back to the default, rather than being an error
+    pub default_on_bad_fmt: bool,
 }
 
-struct StyleDescription {
-    base: [u8; 25],
-    length: usize,
-    offsets: [usize; 12],
+/// Either one of the named styles, or a custom format template.
+#[derive(Clone, Debug)]
+pub enum FormatStyle {
+    Named(MacStyle),
+    Template(MacTemplate),
 }
 
-macro_rules! style_desc {
-    ($style: ident, $base: ident, $len: expr, $offset: ident) => {
-        (MacStyle::$style, StyleDescription {
-            base: MacStyle::$base,
-            length: $len,
-            offsets: MacStyle::$offset,
-        })
+impl FormatSpec {
+    /// Formats an address according to this specifier. Returns None if the address does not have as many digits as
+    /// a template.
+    pub fn format(&self, eui: Eui) -> Option<SmallString<[u8; 32]>> {
+        match &self.style {
+            FormatStyle::Named(style) => Some(SmallString::from_str(&style.format_eui(eui, self.uppercase))),
+            FormatStyle::Template(tmpl) => tmpl.format_eui(eui, self.uppercase),
+        }
     }
 }
 
-impl MacStyle {
-    const NIBBLE_IDXS: [usize; 12] = [0x2c, 0x28, 0x24, 0x20, 0x1c, 0x18, 0x14, 0x10, 0x0c, 0x08, 0x04, 0x00];
-
-    const BASE_PLAIN:      [u8; 25] = *b"############@@@@@@@@@@@@@";
-    const BASE_DASHED:     [u8; 25] = *b"##-##-##-##-##-##@@@@@@@@";
-    const BASE_COLON:      [u8; 25] = *b"##:##:##:##:##:##@@@@@@@@";
-    const BASE_DOTS:       [u8; 25] = *b"####.####.####@@@@@@@@@@@";
-    const BASE_PREFIXED:   [u8; 25] = *b"0x############@@@@@@@@@@@";
-    const BASE_INTF_ID:    [u8; 25] = *b"####:##ff:fe##:####@@@@@@";
-    const BASE_LINK_LOCAL: [u8; 25] = *b"fe80::####:##ff:fe##:####";
-
-    const OFFSETS_NONE: [usize; 2*6] = [0,1,2,3,4,5,6,7,8,9,10,11];
-    const OFFSETS_NONE_PREFIXED: [usize; 2*6] = [2,3,4,5,6,7,8,9,10,11,12,13];
-    const OFFSETS_BYTE: [usize; 2*6] = [0,1,3,4,6,7,9,10,12,13,15,16];
-    const OFFSETS_SHORT: [usize; 2*6] = [0,1,2,3,5,6,7,8,10,11,12,13];
-    const OFFSETS_INTF_ID: [usize; 2*6] = [0,1,2,3,5,6,12,13,15,16,17,18];
-    const OFFSETS_LINK_LOCAL: [usize; 2*6] = [6,7,8,9,11,12,18,19,21,22,23,24];
-
-    const FMT_TABLE: &'static [(MacStyle, StyleDescription)] = &[
-        style_desc!(Plain, BASE_PLAIN, 12, OFFSETS_NONE),
-        style_desc!(Dashed, BASE_DASHED, 17, OFFSETS_BYTE),
-        style_desc!(Colon, BASE_COLON, 17, OFFSETS_BYTE),
-        style_desc!(Dots, BASE_DOTS, 14, OFFSETS_SHORT),
-        style_desc!(Prefixed, BASE_PREFIXED, 14, OFFSETS_NONE_PREFIXED),
-        style_desc!(InterfaceId, BASE_INTF_ID, 19, OFFSETS_INTF_ID),
-        style_desc!(LinkLocal, BASE_LINK_LOCAL, 25, OFFSETS_LINK_LOCAL),
-    ];
-
-    #[inline(always)]
-    const fn fmt_desc(&self) -> &'static StyleDescription {
-        match self {
-            MacStyle::Plain => &MacStyle::FMT_TABLE[0].1,
-            MacStyle::Dashed => &MacStyle::FMT_TABLE[1].1,
-            MacStyle::Colon => &MacStyle::FMT_TABLE[2].1,
-            MacStyle::Dots => &MacStyle::FMT_TABLE[3].1,
-            MacStyle::Prefixed => &MacStyle::FMT_TABLE[4].1,
-            MacStyle::InterfaceId => &MacStyle::FMT_TABLE[5].1,
-            MacStyle::LinkLocal => &MacStyle::FMT_TABLE[6].1,
+/// The format used when none is given: lowercase, colon separated
+impl Default for FormatSpec {
+    fn default() -> FormatSpec {
+        FormatSpec {
+            style: FormatStyle::Named(MacStyle::Colon),
+            uppercase: false,
+            null_on_bad_mac: false,
+            default_on_bad_fmt: false,
+        }
+    }
+}
+
+impl FromStr for FormatSpec {
+    type Err = FormatSpecError;
+    fn from_str(mut spec: &str) -> Result<FormatSpec, FormatSpecError> {
+        let mut default_on_bad_fmt = false;
+        let mut null_on_bad_mac = false;
+        loop {
+            if let Some(rest) = spec.strip_prefix('~') {
+                spec = rest;
+                default_on_bad_fmt = true;
+            } else if let Some(rest) = spec.strip_prefix('?') {
+                spec = rest;
+                null_on_bad_mac = true;
+            } else {
+                break;
+            }
         }
+
+        let uppercase = spec.contains(|c: char| c.is_ascii_uppercase());
+        let has_lower = spec.contains(|c: char| c.is_ascii_lowercase());
+        if uppercase && has_lower && !default_on_bad_fmt {
+            return Err(FormatSpecError::MixedCase);
+        }
+
+        let named = match spec {
+            "" => Some(MacStyle::Colon),
+            spec => spec.parse::<MacStyle>().ok(),
+        };
+        let style = match (named, MacTemplate::parse(spec)) {
+            (Some(named), _) => FormatStyle::Named(named),
+            (None, Some(tmpl)) => FormatStyle::Template(tmpl),
+            (None, None) if default_on_bad_fmt => FormatSpec::default().style, // passthru default
+            (None, None) => return Err(FormatSpecError::UnknownStyle(spec.to_owned())),
+        };
+
+        Ok(FormatSpec { style, uppercase, null_on_bad_mac, default_on_bad_fmt })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum FormatSpecError {
+    #[error("Mixed case format specifier is not allowed. Input case is used to determine output casing.")]
+    MixedCase,
+    #[error("Bad format specifier provided (got {0:?}). Omit format specifier, or provide one of the following: (NULL, `hex`, `hexstring`), `hexadecimal`, `bare`, `dot`, `canonical`, `interface-id`, `link-local`, or a template of 12 or 16 `x` digits such as `xxxx-xxxx-xxxx`)")]
+    UnknownStyle(String),
+}
+
+/// A custom format template, where each `x` or `X` is replaced with one hex digit of the address, and all
+/// other characters are copied through as-is. For example, `xxxx-xxxx-xxxx` or `XX.XX.XX.XX.XX.XX`.
+///
+/// Templates contain exactly 12 placeholders for MAC addresses, or 16 placeholders for EUI-64s.
+#[derive(Clone, Debug)]
+pub struct MacTemplate {
+    template: SmallString<[u8; 32]>,
+    digits: usize,
+}
+impl MacTemplate {
+    /// Parses a format template, returning None if it does not have 12 or 16 digit placeholders.
+    pub fn parse(template: &str) -> Option<MacTemplate> {
+        let digits = template.bytes().filter(|b| matches!(b, b'x' | b'X')).count();
+        matches!(digits, 12 | 16).then(|| MacTemplate {
+            template: SmallString::from_str(template),
+            digits,
+        })
     }
 
-    /// The length of a MAC address when serialized into a string
-    #[inline(always)]
-    pub const fn length(&self) -> usize {
-        self.fmt_desc().length
+    /// The template string, as provided.
+    pub fn as_str(&self) -> &str {
+        self.template.as_str()
     }
 
-    /// A template string of a MAC address. Only the first `MacStyle::length()` bytes will be used, the rest is padding.
-    #[inline(always)]
-    pub const fn base(&self) -> [u8; 25] {
-        self.fmt_desc().base
+    /// The number of hex digits this template holds. Either 12 (for MAC addresses) or 16 (for EUI-64s).
+    pub fn digits(&self) -> usize {
+        self.digits
     }
 
-    #[inline(always)]
-    pub(crate) const fn _format_mac<const UPPERCASE: bool>(
-        eui64: u64,
-        offsets: [usize; 12],
-        mut arr: [u8; 25],
-    ) -> [u8; 25] {
-        let nibbles: [u8; 16] = if UPPERCASE {
-            *b"0123456789ABCDEF"
-        } else {
-            *b"0123456789abcdef"
-        };
-        let eui = eui64 as usize;
-        let mut i = 0;
-        while i < offsets.len() {
-            let ind = offsets[i];
-            let off = MacStyle::NIBBLE_IDXS[i];
-            arr[ind] = nibbles[(eui >> off) & 0xf];
-            i += 1;
+    /// Formats an address into this template. Returns None if the address does not have as many digits as the template.
+    ///
+    /// Like the named styles, templates of up to 32 bytes are formatted without allocating.
+    pub fn format_eui(&self, eui: Eui, uppercase: bool) -> Option<SmallString<[u8; 32]>> {
+        let bytes = eui.as_bytes();
+        if bytes.len() * 2 != self.digits {
+            return None;
         }
-        arr
+
+        let nibbles: &[u8; 16] = if uppercase { b"0123456789ABCDEF" } else { b"0123456789abcdef" };
+        let mut digits = bytes.iter().flat_map(|b| [b >> 4, b & 0xf]);
+        Some(self.template.chars()
+            .map(|c| match c {
+                'x' | 'X' => nibbles[digits.next().unwrap() as usize] as char,
+                c => c,
+            })
+            .collect())
     }
+}
 
-    /// Formats a MAC address into a small string of at most 25 bytes.
-    pub fn format(&self, mac: MacAddress, uppercase: bool) -> SmallString<[u8; 25]> {
-        let (fmtd, len) = self.format_internal(mac.as_bytes().try_into().unwrap(), uppercase);
+/// Converts a MAC address into a 64-bit EUI, by inserting `ff:fe` between the OUI and device bytes.
+///
+/// When `modified` is set, the universal/local bit is flipped to produce the modified EUI-64 used in IPv6 interface identifiers.
+///
+/// # Example
+/// ```
+/// # use sqlite3_nettools::mac::eui48_to_eui64;
+/// let mac = [0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF];
+/// assert_eq!(eui48_to_eui64(mac, false), [0xAA, 0xBB, 0xCC, 0xFF, 0xFE, 0xDD, 0xEE, 0xFF]);
+/// assert_eq!(eui48_to_eui64(mac, true),  [0xA8, 0xBB, 0xCC, 0xFF, 0xFE, 0xDD, 0xEE, 0xFF]);
+/// ```
+pub const fn eui48_to_eui64(mac: [u8; 6], modified: bool) -> [u8; 8] {
+    let first = if modified { mac[0] ^ 0x02 } else { mac[0] };
+    [first, mac[1], mac[2], 0xFF, 0xFE, mac[3], mac[4], mac[5]]
+}
 
-        let fmtd_trimmed = &fmtd[..len];
+/// Converts a 64-bit EUI back into the MAC address it was derived from. This is the inverse of `eui48_to_eui64`.
+///
+/// Returns None if the EUI-64 does not contain the `ff:fe` infix, as it was then not derived from a MAC address.
+///
+/// # Example
+/// ```
+/// # use sqlite3_nettools::mac::eui64_to_eui48;
+/// let eui = [0xA8, 0xBB, 0xCC, 0xFF, 0xFE, 0xDD, 0xEE, 0xFF];
+/// assert_eq!(eui64_to_eui48(eui, true), Some([0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]));
+/// assert_eq!(eui64_to_eui48([0; 8], false), None);
+/// ```
+pub const fn eui64_to_eui48(eui: [u8; 8], modified: bool) -> Option<[u8; 6]> {
+    if eui[3] != 0xFF || eui[4] != 0xFE {
+        return None;
+    }
+    let first = if modified { eui[0] ^ 0x02 } else { eui[0] };
+    Some([first, eui[1], eui[2], eui[5], eui[6], eui[7]])
+}
 
-        let as_str = if cfg!(debug_assertions) {
-            match std::str::from_utf8(fmtd_trimmed) {
-                Ok(s) => s,
-                Err(e) => panic!("found invalid utf8 in freshly formatted MAC address: {:?}", e),
-            }
-        } else {
-            // SAFETY:
-            // All base strings are valid ascii/1-byte UTF8 codepoints
-            // any modifications to the base strings are from a byte buffer of ascii (1-byte) characters
-            // so any characters in the resulting fmtd string are 1-byte characters of valid UTF8
-            unsafe { std::str::from_utf8_unchecked(fmtd_trimmed) }
-        };
+/// A 48-bit MAC address.
+///
+/// Formatted as `aa:bb:cc:dd:ee:ff` by Display, and parsed from any format accepted by `MAC_FORMAT`. With the `eui48`
+/// feature, this converts to and from `eui48::MacAddress`.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MacAddr([u8; 6]);
+
+impl MacAddr {
+    pub const fn new(bytes: [u8; 6]) -> MacAddr {
+        MacAddr(bytes)
+    }
+
+    pub const fn to_array(&self) -> [u8; 6] {
+        self.0
+    }
 
-        // waiting for any kind of const support from SmallString
-        SmallString::from_str(as_str)
-    }
-
-    /// An const version of `MacStyle::format`. Returns a byte buffer, with a string length.
-    /// 
-    /// For use in a const context, the function omits:
-    /// - Trimming output to formatted length: see `MacStyle::length`, or the second value in the returned tuple
-    /// - UTF8 validity: While the trimmed output should always be UTF8, it is not checked in this function.
-    /// 
-    /// # Example
-    /// ```
-    /// # use sqlite3_nettools::mac::MacStyle;
-    /// let style = MacStyle::Colon;
-    /// let (raw, len) = style.format_internal([0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF], false);
-    /// let trimmed = &raw[..len];
-    /// assert_eq!(trimmed, b"aa:bb:cc:dd:ee:ff");
-    /// # assert_eq!(style.length(), len);
-    /// ```
-    #[inline(always)]
-    pub const fn format_internal(&self, mac: [u8; 6], uppercase: bool) -> ([u8; 25], usize) {
-        let mut as_u64 = Oui::from_array(mac).as_int();
-        if matches!(self, MacStyle::InterfaceId | MacStyle::LinkLocal) {
-            as_u64 ^= 0x0000_0200_0000_0000;
+    pub const fn as_bytes(&self) -> &[u8; 6] {
+        &self.0
+    }
+}
+
+impl From<[u8; 6]> for MacAddr {
+    fn from(bytes: [u8; 6]) -> MacAddr {
+        MacAddr(bytes)
+    }
+}
+impl From<MacAddr> for [u8; 6] {
+    fn from(mac: MacAddr) -> [u8; 6] {
+        mac.0
+    }
+}
+
+#[cfg(feature = "eui48")]
+impl From<eui48::MacAddress> for MacAddr {
+    fn from(mac: eui48::MacAddress) -> MacAddr {
+        MacAddr(mac.to_array())
+    }
+}
+#[cfg(feature = "eui48")]
+impl From<MacAddr> for eui48::MacAddress {
+    fn from(mac: MacAddr) -> eui48::MacAddress {
+        eui48::MacAddress::new(mac.0)
+    }
+}
+
+impl FromStr for MacAddr {
+    type Err = ParseMacError;
+    fn from_str(s: &str) -> Result<MacAddr, ParseMacError> {
+        crate::oui::parse_mac_addr(s)
+    }
+}
+
+impl fmt::Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&MacStyle::Colon.format(*self, false))
+    }
+}
+
+impl fmt::Debug for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "MacAddr({self})")
+    }
+}
+
+/// Serialized in its string form, such as `aa:bb:cc:dd:ee:ff`
+#[cfg(feature = "serde")]
+impl serde::Serialize for MacAddr {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for MacAddr {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<MacAddr, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// A hardware address: either a 48-bit MAC address, or a 64-bit EUI (as used by 802.15.4, LoRaWAN, FireWire, etc).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Eui {
+    Mac48(MacAddr),
+    Eui64([u8; 8]),
+}
+impl Eui {
+    /// The raw bytes of the address, 6 or 8 bytes long.
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Eui::Mac48(mac) => mac.as_bytes(),
+            Eui::Eui64(eui) => eui,
         }
+    }
 
-        let style = self.fmt_desc();
-        let mut fmtd = match uppercase {
-            true  => MacStyle::_format_mac::<true >(as_u64, style.offsets, style.base),
-            false => MacStyle::_format_mac::<false>(as_u64, style.offsets, style.base),
-        };
+    /// The numeric value of the address, placed in the least significant bits.
+    ///
+    /// `aa:bb:cc:dd:ee:ff` would be `0x0000aabbccddeeff`, and `aa:bb:cc:dd:ee:ff:00:11` would be `0xaabbccddeeff0011`
+    pub fn as_u64(&self) -> u64 {
+        match self {
+            Eui::Mac48(mac) => Oui::from_addr(*mac).as_int(),
+            Eui::Eui64(eui) => u64::from_be_bytes(*eui),
+        }
+    }
 
-        if uppercase && matches!(self, MacStyle::InterfaceId | MacStyle::LinkLocal) {
-            // ensure the fe80:: prefix and ff:fe internal bytes are capitalized
-            let mut i = 0;
-            while i < fmtd.len() {
-                if fmtd[i].is_ascii_lowercase() {
-                    fmtd[i] = fmtd[i].to_ascii_uppercase();
-                }
-                i += 1;
-            }
+    /// Whether this is a 64-bit EUI, rather than a 48-bit MAC address.
+    pub fn is_eui64(&self) -> bool {
+        matches!(self, Eui::Eui64(_))
+    }
 
-            // above version is const
-            // fmtd.make_ascii_uppercase();
+    /// The 48-bit MAC address used for OUI lookups. For EUI-64s, this is the first 6 bytes, which hold any assigned prefix.
+    pub fn oui_key(&self) -> MacAddr {
+        match self {
+            Eui::Mac48(mac) => *mac,
+            Eui::Eui64([a, b, c, d, e, f, _, _]) => MacAddr::new([*a, *b, *c, *d, *e, *f]),
         }
+    }
 
-        (fmtd, style.length)
+    /// Returns true if bit 1 of Y is 0 in address `xY:xx:xx:...`
+    pub fn is_unicast(&self) -> bool {
+        self.as_bytes()[0] & 1 == 0
+    }
+
+    /// Returns true if bit 1 of Y is 1 in address `xY:xx:xx:...`
+    pub fn is_multicast(&self) -> bool {
+        self.as_bytes()[0] & 1 == 1
+    }
+
+    /// Returns true if bit 2 of Y is 0 in address `xY:xx:xx:...`
+    pub fn is_universal(&self) -> bool {
+        self.as_bytes()[0] & 0b10 == 0
+    }
+
+    /// Returns true if bit 2 of Y is 1 in address `xY:xx:xx:...`
+    pub fn is_local(&self) -> bool {
+        self.as_bytes()[0] & 0b10 == 0b10
+    }
+}
+impl From<MacAddr> for Eui {
+    fn from(mac: MacAddr) -> Eui {
+        Eui::Mac48(mac)
+    }
+}
+impl From<Eui> for EuiBytes {
+    fn from(eui: Eui) -> EuiBytes {
+        match eui {
+            Eui::Mac48(mac) => EuiBytes::Mac48(mac.to_array()),
+            Eui::Eui64(eui) => EuiBytes::Eui64(eui),
+        }
     }
 }
 
-pub fn format_mac_dashed(mac: MacAddress) -> SmallString<[u8; 25]> {
+pub fn format_mac_dashed(mac: MacAddr) -> MacText {
     MacStyle::Plain.format(mac, true)
 }
 
@@ -199,3 +368,68 @@ fn style_formatting() {
         MacStyle::LinkLocal.format(mac, true).as_str()
     );
 }
+
+#[test]
+fn style_formatting_eui64() {
+    let eui = [0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF, 0x00, 0x11];
+    assert_eq!("aabbccddeeff0011", MacStyle::Plain.format_eui64(eui, false).as_str());
+    assert_eq!(
+        "AA:BB:CC:DD:EE:FF:00:11",
+        MacStyle::Colon.format_eui64(eui, true).as_str()
+    );
+    assert_eq!(
+        "0xaabbccddeeff0011",
+        MacStyle::Prefixed.format_eui64(eui, false).as_str()
+    );
+    assert_eq!(
+        "a8bb:ccdd:eeff:0011",
+        MacStyle::InterfaceId.format_eui64(eui, false).as_str()
+    );
+    assert_eq!(
+        "FE80::A8BB:CCDD:EEFF:0011",
+        MacStyle::LinkLocal.format_eui64(eui, true).as_str()
+    );
+}
+
+#[test]
+fn mac_addr_conversions() {
+    let mac: MacAddr = "AABB.CCDD.EEFF".parse().unwrap();
+    assert_eq!(mac, MacAddr::from([0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]));
+    assert_eq!(mac.to_string(), "aa:bb:cc:dd:ee:ff");
+    assert_eq!(format!("{mac:?}"), "MacAddr(aa:bb:cc:dd:ee:ff)");
+    assert!("aa:bb:cc".parse::<MacAddr>().is_err());
+
+    #[cfg(feature = "eui48")]
+    assert_eq!(MacAddr::from(eui48::MacAddress::from(mac)), mac);
+}
+
+#[test]
+fn template_formatting() {
+    let mac = Eui::Mac48(Oui::from_int(0x0000AABBCCDDEEFF).unwrap().as_mac());
+    let eui = Eui::Eui64([0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF, 0x00, 0x11]);
+
+    let tmpl = MacTemplate::parse("xxxx-xxxx-xxxx").unwrap();
+    assert_eq!(Some("aabb-ccdd-eeff"), tmpl.format_eui(mac, false).as_deref());
+    assert_eq!(Some("AABB-CCDD-EEFF"), tmpl.format_eui(mac, true).as_deref());
+    assert_eq!(None, tmpl.format_eui(eui, false));
+
+    let tmpl = MacTemplate::parse("xxxxxxxx/xxxxxxxx").unwrap();
+    assert_eq!(Some("aabbccdd/eeff0011"), tmpl.format_eui(eui, false).as_deref());
+
+    assert!(MacTemplate::parse("xx:xx:xx").is_none());
+    assert!(MacTemplate::parse("hex").is_none());
+}
+
+#[test]
+fn format_specs() {
+    let spec = FormatSpec::from_str("~?~dot").unwrap();
+    assert!(matches!(spec.style, FormatStyle::Named(MacStyle::Dots)));
+    assert!(spec.default_on_bad_fmt && spec.null_on_bad_mac && !spec.uppercase);
+
+    assert!(matches!(FormatSpec::from_str("").unwrap().style, FormatStyle::Named(MacStyle::Colon)));
+    assert!(matches!(FormatSpec::from_str("Canonical"), Err(FormatSpecError::MixedCase)));
+    assert!(matches!(FormatSpec::from_str("~Canonical").unwrap().style, FormatStyle::Named(MacStyle::Dashed)));
+    assert!(matches!(FormatSpec::from_str("XX-XX-XX-XX-XX-XX").unwrap().style, FormatStyle::Template(_)));
+    assert!(matches!(FormatSpec::from_str("dollar"), Err(FormatSpecError::UnknownStyle(_))));
+    assert!(matches!(FormatSpec::from_str("~dollar").unwrap().style, FormatStyle::Named(MacStyle::Colon)));
+}
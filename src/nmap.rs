@@ -0,0 +1,266 @@
+use std::{borrow::Cow, net::IpAddr};
+
+use quick_xml::{events::{BytesStart, Event}, Reader, XmlVersion};
+
+use crate::mac::MacAddr;
+
+/// A host from a scan, as described by a `<host>` element.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanHost {
+    /// Whether the host was found up, such as `up` or `down`
+    pub status: Option<String>,
+    /// Why the host was thought to be up or down, such as `arp-response`
+    pub reason: Option<String>,
+    /// The host's first IPv4 or IPv6 address
+    pub addr: Option<IpAddr>,
+    /// The host's MAC address, only known for hosts on the local network
+    pub mac: Option<MacAddr>,
+    /// The vendor nmap guessed from the MAC address, using its own OUI database
+    pub mac_vendor: Option<String>,
+    /// The host's first hostname, either given on the command line or found through reverse DNS
+    pub hostname: Option<String>,
+    pub ports: Vec<ScanPort>,
+}
+
+/// A port scanned on a host, as described by a `<port>` element. Ports summarized by `<extraports>` aren't listed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanPort {
+    /// The transport protocol, such as `tcp` or `udp`
+    pub protocol: String,
+    pub port: u16,
+    /// The port's state, such as `open` or `filtered`
+    pub state: Option<String>,
+    /// Why the port was given its state, such as `syn-ack`
+    pub reason: Option<String>,
+    pub service: Option<ScanService>,
+}
+
+/// The service detected on a port, as described by a `<service>` element.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanService {
+    /// The service name, such as `ssh`
+    pub name: Option<String>,
+    pub product: Option<String>,
+    pub version: Option<String>,
+    pub extra_info: Option<String>,
+    /// How the service was detected: `probed` when version detection matched it, or `table` when guessed from the port
+    pub method: Option<String>,
+    /// nmap's confidence in the detection, from 0 to 10
+    pub conf: Option<u8>,
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum ParseNmapError {
+    #[error("malformed XML at byte {0}: {1}")]
+    Syntax(usize, String),
+    #[error("expected an nmap XML document (an <nmaprun> element), found <{0}>")]
+    NotNmap(String),
+    #[error("invalid {0} {1:?} at byte {2}")]
+    InvalidValue(&'static str, String, usize),
+}
+
+/// Returns an element's attribute, with its entities replaced.
+fn attr<'a>(tag: &'a BytesStart<'_>, name: &str, offset: usize) -> Result<Option<Cow<'a, str>>, ParseNmapError> {
+    let syntax = |e: &dyn std::fmt::Display| ParseNmapError::Syntax(offset, e.to_string());
+    match tag.try_get_attribute(name).map_err(|e| syntax(&e))? {
+        Some(a) => a.normalized_value(XmlVersion::Implicit1_0).map(Some).map_err(|e| syntax(&e)),
+        None => Ok(None),
+    }
+}
+
+/// Reads the hosts of an nmap XML document (`nmap -oX`), in the order they were written.
+///
+/// Only what describes each host is read: its status, addresses, hostnames, and ports with any service detection
+/// results. Scripts, OS detection, and traceroutes are skipped.
+///
+/// Files from scans that were interrupted are read up to the last complete tag, so the hosts finished before the scan
+/// stopped are still returned.
+pub fn parse_hosts(xml: &str) -> Result<Vec<ScanHost>, ParseNmapError> {
+    // nmap escapes '>' within attributes, so the last one ends the last complete tag
+    let mut reader = Reader::from_str(&xml[..xml.rfind('>').map_or(0, |i| i + 1)]);
+
+    // reads up to the next start, end, or empty element tag, returning it with its byte offset
+    let mut next_tag = move || loop {
+        let offset = reader.buffer_position() as usize;
+        match reader.read_event() {
+            Ok(tag @ (Event::Start(_) | Event::Empty(_) | Event::End(_))) => return Ok(Some((offset, tag))),
+            Ok(Event::Eof) => return Ok(None),
+            Ok(_) => {},
+            Err(e) => return Err(ParseNmapError::Syntax(reader.error_position() as usize, e.to_string())),
+        }
+    };
+    let root = match next_tag()? {
+        Some((_, Event::Start(tag) | Event::Empty(tag))) => tag.name().as_ref().to_owned(),
+        Some((_, Event::End(tag))) => tag.name().as_ref().to_owned(),
+        _ => String::new(),
+    };
+    if root != "nmaprun" {
+        return Err(ParseNmapError::NotNmap(root));
+    }
+
+    let owned = |v: Option<Cow<'_, str>>| v.map(Cow::into_owned);
+    let mut hosts = Vec::new();
+    let mut host: Option<ScanHost> = None;
+    let mut port: Option<ScanPort> = None;
+    while let Some((offset, event)) = next_tag()? {
+        let (tag, empty) = match event {
+            Event::End(tag) => {
+                match tag.name().as_ref() {
+                    "host" => hosts.extend(host.take()),
+                    "port" => {
+                        if let (Some(host), Some(port)) = (host.as_mut(), port.take()) {
+                            host.ports.push(port);
+                        }
+                    },
+                    _ => {},
+                }
+                continue;
+            },
+            Event::Start(tag) => (tag, false),
+            Event::Empty(tag) => (tag, true),
+            _ => continue,
+        };
+        let attr = |name| attr(&tag, name, offset);
+
+        match tag.name().as_ref() {
+            "host" => {
+                if !empty {
+                    host = Some(ScanHost::default());
+                }
+                continue;
+            },
+            "port" if host.is_some() => {
+                let portid = attr("portid")?.unwrap_or_default();
+                let new_port = ScanPort {
+                    protocol: owned(attr("protocol")?).unwrap_or_default(),
+                    port: portid.parse().map_err(|_| ParseNmapError::InvalidValue("port", portid.into_owned(), offset))?,
+                    ..ScanPort::default()
+                };
+                match (empty, host.as_mut()) {
+                    (true, Some(host)) => host.ports.push(new_port),
+                    _ => port = Some(new_port),
+                }
+                continue;
+            },
+            _ => {},
+        }
+
+        // elements describing the current port
+        if let Some(port) = port.as_mut() {
+            match tag.name().as_ref() {
+                "state" => {
+                    port.state = owned(attr("state")?);
+                    port.reason = owned(attr("reason")?);
+                },
+                "service" => {
+                    let conf = attr("conf")?
+                        .map(|c| c.parse().map_err(|_| ParseNmapError::InvalidValue("service confidence", c.into_owned(), offset)))
+                        .transpose()?;
+                    port.service = Some(ScanService {
+                        name: owned(attr("name")?),
+                        product: owned(attr("product")?),
+                        version: owned(attr("version")?),
+                        extra_info: owned(attr("extrainfo")?),
+                        method: owned(attr("method")?),
+                        conf,
+                    });
+                },
+                _ => {},
+            }
+            continue;
+        }
+
+        // elements describing the current host
+        let Some(host) = host.as_mut() else { continue; };
+        match tag.name().as_ref() {
+            "status" => {
+                host.status = owned(attr("state")?);
+                host.reason = owned(attr("reason")?);
+            },
+            "address" => {
+                let addr = attr("addr")?.unwrap_or_default();
+                let invalid = |what| ParseNmapError::InvalidValue(what, addr.to_string(), offset);
+                match attr("addrtype")?.as_deref() {
+                    Some("ipv4" | "ipv6") if host.addr.is_none() => {
+                        host.addr = Some(addr.parse().map_err(|_| invalid("IP address"))?);
+                    },
+                    Some("mac") if host.mac.is_none() => {
+                        host.mac = Some(addr.parse().map_err(|_| invalid("MAC address"))?);
+                        host.mac_vendor = owned(attr("vendor")?);
+                    },
+                    _ => {},
+                }
+            },
+            "hostname" if host.hostname.is_none() => host.hostname = owned(attr("name")?),
+            _ => {},
+        }
+    }
+
+    Ok(hosts)
+}
+
+#[test]
+fn nmap_hosts() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE nmaprun>
+<?xml-stylesheet href="file:///usr/bin/../share/nmap/nmap.xsl" type="text/xsl"?>
+<!-- Nmap 7.94 scan initiated as: nmap -sV -oX scan.xml 192.168.1.0/30 -->
+<nmaprun scanner="nmap" args="nmap -sV -oX scan.xml 192.168.1.0/30" version="7.94">
+<hosthint><status state="up" reason="arp-response"/><address addr="192.168.1.9" addrtype="ipv4"/></hosthint>
+<host starttime="1700000000"><status state="up" reason="arp-response" reason_ttl="0"/>
+<address addr="192.168.1.1" addrtype="ipv4"/>
+<address addr="3C:A6:F6:C4:34:F8" addrtype="mac" vendor="Apple"/>
+<hostnames><hostname name="router.lan" type="PTR"/></hostnames>
+<ports><extraports state="closed" count="998"><extrareasons reason="reset" count="998"/></extraports>
+<port protocol="tcp" portid="22"><state state="open" reason="syn-ack" reason_ttl="64"/><service name="ssh" product="OpenSSH" version="9.6" extrainfo="protocol 2.0 &amp; &quot;more&quot;" method="probed" conf="10"><cpe>cpe:/a:openbsd:openssh:9.6</cpe></service></port>
+<port protocol="udp" portid="53"><state state="open|filtered" reason="no-response"/><service name="domain" method="table" conf="3"/></port>
+</ports>
+<os><osmatch name="Linux 5.X"><osclass type="general purpose"/></osmatch></os>
+</host>
+<host><status state="down" reason="no-response"/><address addr="2001:db8::2" addrtype="ipv6"/></host>
+<runstats><finished time="1700000010"/><hosts up="1" down="1" total="2"/></runstats>
+</nmaprun>
+"#;
+    let hosts = parse_hosts(xml).unwrap();
+    assert_eq!(hosts.len(), 2);
+    assert_eq!(hosts[0].addr, Some("192.168.1.1".parse().unwrap()));
+    assert_eq!(hosts[0].mac, Some(MacAddr::new([0x3c, 0xa6, 0xf6, 0xc4, 0x34, 0xf8])));
+    assert_eq!((hosts[0].mac_vendor.as_deref(), hosts[0].hostname.as_deref()), (Some("Apple"), Some("router.lan")));
+    assert_eq!(hosts[0].ports, vec![
+        ScanPort {
+            protocol: "tcp".into(),
+            port: 22,
+            state: Some("open".into()),
+            reason: Some("syn-ack".into()),
+            service: Some(ScanService {
+                name: Some("ssh".into()),
+                product: Some("OpenSSH".into()),
+                version: Some("9.6".into()),
+                extra_info: Some(r#"protocol 2.0 & "more""#.into()),
+                method: Some("probed".into()),
+                conf: Some(10),
+            }),
+        },
+        ScanPort {
+            protocol: "udp".into(),
+            port: 53,
+            state: Some("open|filtered".into()),
+            reason: Some("no-response".into()),
+            service: Some(ScanService { name: Some("domain".into()), method: Some("table".into()), conf: Some(3), ..Default::default() }),
+        },
+    ]);
+    assert_eq!(hosts[1], ScanHost {
+        status: Some("down".into()),
+        reason: Some("no-response".into()),
+        addr: Some("2001:db8::2".parse().unwrap()),
+        ..Default::default()
+    });
+
+    // an interrupted scan keeps the hosts it finished
+    let cut = xml.find("<host><status state=\"down\"").unwrap() + 20;
+    assert_eq!(parse_hosts(&xml[..cut]).unwrap().len(), 1);
+
+    assert_eq!(parse_hosts("<html><body/></html>"), Err(ParseNmapError::NotNmap("html".into())));
+    assert_eq!(parse_hosts("<nmaprun><host><port portid=\"x\"/></host></nmaprun>"), Err(ParseNmapError::InvalidValue("port", "x".into(), 15)));
+    assert!(matches!(parse_hosts("<nmaprun><host><status state=\"up\"></host></nmaprun>"), Err(ParseNmapError::Syntax(..))));
+}
@@ -1,4 +1,4 @@
-use std::{fmt, num::ParseIntError, str::FromStr, borrow::Cow};
+use std::{fmt, io, num::{NonZeroUsize, ParseIntError}, str::FromStr, borrow::Cow, collections::{BTreeMap, HashMap}, sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex, PoisonError, RwLock}};
 
 // The default rust 'oui' crate doesn't search efficiently, and we can't use it memory-optimized ways.
 //
@@ -8,9 +8,34 @@ use std::{fmt, num::ParseIntError, str::FromStr, borrow::Cow};
 //
 // Other OUI-based crates seem focused on vendor mailing addresses rather than OUI descriptors.
 
-use eui48::{MacAddress, EUI48LEN};
+use hashlink::LruCache;
+use sqlite3_nettools_core::EuiBytes;
 
-use crate::mac::MacStyle;
+use crate::mac::{Eui, MacAddr, MacStyle};
+
+/// A compact, versioned binary encoding of [OuiDb], which loads without any text parsing.
+///
+/// All integers are little-endian. The layout is:
+/// * Header: magic bytes (`NTOUIDB\n`), format version (u32), entry count (u32), string arena length (u32)
+/// * Entries, in prefix order, 28 bytes each: the prefix length in the top byte, the registry code (from 1, for MA-L,
+///   MA-M, MA-S, IAB, and CID, or 0 if unknown) in the next, and address in the lower 48 bits (u64), followed by the
+///   arena offsets of the short name, long name, comment, registrant address, and registrant country (u32 each,
+///   `u32::MAX` if absent). Version 1 entries are 20 bytes, without the registrant fields, and registry codes were
+///   added in version 3.
+/// * String arena: each string as a u16 byte length followed by its UTF-8 bytes. Repeated strings are stored once.
+///
+/// Files are read whole rather than memory-mapped. Loading copies every entry into the database's own arena, so a
+/// mapping would only be held for the duration of the load, saving nothing over reading the file while adding `unsafe`
+/// code that faults if the file is truncated while mapped.
+pub mod binary;
+
+/// IEEE registry CSV parsing, shared with the build script
+mod csv;
+#[cfg(test)]
+use csv::ieee_address_country;
+
+/// Database file parsing and binary encoding, shared with the build script
+mod raw;
 
 #[derive(thiserror::Error, Debug)]
 pub enum ParseMacError {
@@ -18,59 +43,72 @@ pub enum ParseMacError {
     InvalidLength(String),
     #[error("Found an invalid character in MAC {0:?}: {1:?}")]
     InvalidCharacter(String, char),
+    #[error("MAC address blob has a bad length of {} bytes (blob contents: {:x?}). Blobs of 6 bytes (MAC addresses) or 8 bytes (EUI-64s) are expected", .0.len(), if .0.len() < 20 { .0.as_slice() } else { &.0[..20] })]
+    InvalidBlob(Vec<u8>),
+    #[error("MAC address integer is out of the 48-bit range: {0}")]
+    InvalidInteger(i64),
+    #[error("MAC addresses must be provided as text, a blob, or an integer (got {0})")]
+    UnsupportedType(String),
 }
 
-// rolling our own parsing - the built-in mac addr parsing from the eui48 crate is way too slow for DB use.
+// rolling our own parsing - the mac addr parsing from the eui48 crate (which MacAddr replaced) was way too slow for DB use.
 // see: https://github.com/abaumhauer/eui48/pull/32
 // note that our mac addr zero-extension logic wouldn't port over into that PR too well, so use homegrown
 
-pub fn parse_mac_addr(s: &str) -> Result<eui48::MacAddress, ParseMacError> {
+pub fn parse_mac_addr(s: &str) -> Result<MacAddr, ParseMacError> {
     parse_mac_addr_extend(s, false)
 }
 pub fn parse_mac_addr_extend(
-    mut s: &str,
+    s: &str,
     zero_extend: bool,
-) -> Result<eui48::MacAddress, ParseMacError> {
-    let mut raw = smallstr::SmallString::<[u8; 12]>::new();
-    if s.starts_with("0x") {
-        s = &s[2..];
-    }
-    for c in s.chars() {
-        if matches!(c, 'A'..='F' | 'a'..='f' | '0'..='9') {
-            if raw.len() + 1 > raw.capacity() {
-                return Err(ParseMacError::InvalidLength(s.to_owned()));
-            }
-            raw.push(c);
-        } else if !matches!(c, '-' | '.' | ':') {
-            return Err(ParseMacError::InvalidCharacter(s.to_owned(), c));
-        }
-    }
+) -> Result<MacAddr, ParseMacError> {
+    let mac = sqlite3_nettools_core::parse_mac(s, zero_extend).map_err(|e| ParseMacError::from_core(e, s))?;
+    Ok(MacAddr::new(mac))
+}
 
-    if zero_extend {
-        // fill in the end if we were only given the front (like parsing an OUI/prefix)
-        const ZEROS: [char; 12] = ['0'; 12];
-        raw.extend(&ZEROS[raw.len()..]);
+/// Parses either a 48-bit MAC address or a 64-bit EUI, in any of the formats accepted by `parse_mac_addr`.
+///
+/// The width of the address is determined by the number of hexadecimal digits present (12 or 16).
+pub fn parse_eui(s: &str) -> Result<Eui, ParseMacError> {
+    match sqlite3_nettools_core::parse_eui(s).map_err(|e| ParseMacError::from_core(e, s))? {
+        EuiBytes::Mac48(mac) => Ok(Eui::Mac48(MacAddr::new(mac))),
+        EuiBytes::Eui64(eui) => Ok(Eui::Eui64(eui)),
     }
+}
 
-    if raw.len() < 12 {
-        return Err(ParseMacError::InvalidLength(s.to_owned()));
+impl ParseMacError {
+    /// Attaches the address that failed to parse to an error from the `no_std` parsing core.
+    fn from_core(e: sqlite3_nettools_core::ParseError, s: &str) -> ParseMacError {
+        let s = s.strip_prefix("0x").unwrap_or(s).to_owned();
+        match e {
+            sqlite3_nettools_core::ParseError::InvalidLength => ParseMacError::InvalidLength(s),
+            sqlite3_nettools_core::ParseError::InvalidCharacter(c) => ParseMacError::InvalidCharacter(s, c),
+        }
     }
-
-    debug_assert_eq!(raw.len(), 12);
-
-    let mac_int: u64 =
-        u64::from_str_radix(raw.as_str(), 16).expect("prevalidated that all chars are hexidecimal");
-
-    Ok(Oui::from_int(mac_int).unwrap().as_mac())
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OuiMeta<S> {
     short: S,
     long: Option<S>,
     comment: Option<S>,
+    address: Option<S>,
+    country: Option<S>,
+    block_type: Option<BlockType>,
 }
 impl<S> OuiMeta<S> {
+    pub const fn new(short: S, long: Option<S>, comment: Option<S>) -> OuiMeta<S> {
+        OuiMeta { short, long, comment, address: None, country: None, block_type: None }
+    }
+    /// Attaches the registrant's postal address and country code, as published in the IEEE registries.
+    pub fn with_registrant(self, address: Option<S>, country: Option<S>) -> OuiMeta<S> {
+        OuiMeta { address, country, ..self }
+    }
+    /// Attaches the registry the prefix was assigned from, as listed by the IEEE registries.
+    pub fn with_block_type(self, block_type: Option<BlockType>) -> OuiMeta<S> {
+        OuiMeta { block_type, ..self }
+    }
     pub const fn manuf(&self) -> &S {
         &self.short
     }
@@ -80,6 +118,17 @@ impl<S> OuiMeta<S> {
     pub const fn comment(&self) -> Option<&S> {
         self.comment.as_ref()
     }
+    pub const fn address(&self) -> Option<&S> {
+        self.address.as_ref()
+    }
+    pub const fn country(&self) -> Option<&S> {
+        self.country.as_ref()
+    }
+    /// The registry the prefix was assigned from, if recorded by the database. Only the IEEE registry CSVs list it, so
+    /// [Oui::block_type] derives it for entries of Wireshark's manuf file.
+    pub const fn block_type(&self) -> Option<BlockType> {
+        self.block_type
+    }
 }
 impl<'a> OuiMeta<&'a str> {
     pub fn to_owned(&self) -> OuiMeta<String> {
@@ -87,6 +136,9 @@ impl<'a> OuiMeta<&'a str> {
             short: self.short.to_owned(),
             long: self.long.map(|s| s.to_owned()),
             comment: self.comment.map(|s| s.to_owned()),
+            address: self.address.map(|s| s.to_owned()),
+            country: self.country.map(|s| s.to_owned()),
+            block_type: self.block_type,
         }
     }
 }
@@ -96,6 +148,9 @@ impl OuiMeta<String> {
             short: self.short.as_str(),
             long: self.long.as_deref(),
             comment: self.comment.as_deref(),
+            address: self.address.as_deref(),
+            country: self.country.as_deref(),
+            block_type: self.block_type,
         }
     }
 }
@@ -110,6 +165,21 @@ pub enum ParseOuiError {
     PrefixLengthValue(u8, Cow<'static, str>),
     #[error("Attempted to create an OUI/MAC address from a 64-bit integer, but value was out of range. Got 0x{0:>016x}")]
     InvalidIntegerValue(u64),
+    #[error("Attempted to create an OUI prefix from {0} bytes. At most 6 bytes are expected")]
+    InvalidByteLength(usize),
+    #[error("OUI prefix {0} holds many MAC addresses. Only a /48 prefix is a single address")]
+    NotAnAddress(String),
+}
+
+impl ParseOuiError {
+    /// Attaches the prefix that failed to parse to an error from the shared database parser.
+    fn from_raw(e: raw::PrefixError, s: &str) -> ParseOuiError {
+        match e {
+            raw::PrefixError::Length(e) => ParseOuiError::PrefixLengthParsing(e, s.to_owned()),
+            raw::PrefixError::LengthValue(length) => ParseOuiError::PrefixLengthValue(length, Cow::from(s.to_owned())),
+            raw::PrefixError::Mac(e) => ParseMacError::from_core(e, s.split_once('/').map_or(s, |(addr, _)| addr)).into(),
+        }
+    }
 }
 
 #[derive(Clone, Copy, PartialOrd, Ord, PartialEq, Eq)]
@@ -118,7 +188,7 @@ pub struct Oui {
     length: u8,
 }
 impl Oui {
-    pub const fn as_mac(self) -> MacAddress {
+    pub const fn as_mac(self) -> MacAddr {
         let mac_raw_long = u64::to_be_bytes(self.address);
         let mut mac_raw = [0u8; 6];
         mac_raw[0] = mac_raw_long[2];
@@ -131,14 +201,18 @@ impl Oui {
         // copy_from_slice is not const
         // mac_raw.copy_from_slice(&mac_raw_long[2..]);
 
-        MacAddress::new(mac_raw)
+        MacAddr::new(mac_raw)
     }
     pub const fn mask(&self) -> u64 {
-        ((1 << self.length) - 1) << (8 * EUI48LEN - self.length as usize)
+        ((1 << self.length) - 1) << (48 - self.length as usize)
     }
     pub const fn length(&self) -> u8 {
         self.length
     }
+    /// The number of leading bits of an address this prefix matches, as with [Oui::length].
+    pub const fn prefix_len(&self) -> u8 {
+        self.length
+    }
     pub const fn with_length(&self, len: u8) -> Result<Oui, ParseOuiError> {
         if len > 48 {
             return Err(ParseOuiError::PrefixLengthValue(len, Cow::Borrowed("Oui::set_length")));
@@ -156,13 +230,62 @@ impl Oui {
         other.address & self.mask() == self.address
     }
 
+    /// Whether a MAC address falls within this prefix's block.
+    pub fn contains_mac(&self, mac: MacAddr) -> bool {
+        self.contains(&Oui::from_addr(mac))
+    }
+
+    /// The first MAC address of this prefix's block, with all bits after the prefix cleared.
+    pub const fn first(&self) -> MacAddr {
+        Oui { address: self.address & self.mask(), length: 48 }.as_mac()
+    }
+
+    /// The last MAC address of this prefix's block, with all bits after the prefix set.
+    pub const fn last(&self) -> MacAddr {
+        Oui { address: (self.address & self.mask()) | (!self.mask() & 0xFFFF_FFFF_FFFF), length: 48 }.as_mac()
+    }
+
+    /// Iterates over every MAC address within this prefix's block, in order.
+    ///
+    /// Note that a 24-bit prefix holds over 16 million addresses.
+    ///
+    /// ```
+    /// # use sqlite3_nettools::oui::Oui;
+    /// let oui: Oui = "8c:1f:64:cb:2f:f0/44".parse().unwrap();
+    /// assert_eq!(oui.iter().count(), 16);
+    /// assert_eq!(oui.iter().next_back(), Some(oui.last()));
+    /// ```
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = MacAddr> {
+        let (first, last) = (Oui::from_addr(self.first()).address, Oui::from_addr(self.last()).address);
+        (first..=last).map(|address| Oui { address, length: 48 }.as_mac())
+    }
+
+    /// The IEEE assignment class of this prefix, derived from its length and the registry block it falls within.
+    ///
+    /// Returns None for prefix lengths that don't correspond to an IEEE assignment.
+    pub const fn block_type(&self) -> Option<BlockType> {
+        const IAB_PARENTS: [u64; 2] = [0x0050_C200_0000, 0x40D8_5500_0000];
+        match self.length {
+            24 if self.address & 0x0200_0000_0000 != 0 => Some(BlockType::Cid),
+            24 => Some(BlockType::MaL),
+            28 => Some(BlockType::MaM),
+            36 => {
+                let parent = self.address & 0xFFFF_FF00_0000;
+                if parent == IAB_PARENTS[0] || parent == IAB_PARENTS[1] {
+                    Some(BlockType::Iab)
+                } else {
+                    Some(BlockType::MaS)
+                }
+            },
+            _ => None,
+        }
+    }
+
     /// Creates an OUI with length of 48 from an array of bytes. The last byte of the MAC should be the first byte in the array.
     /// 
     /// In other words:
     /// `"AA-BB-CC-DD-EE-FF"` is `0x0000AABBCCDDEEFF` is `[0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]`
     pub const fn from_array(mac: [u8; 6]) -> Oui {
-        // there is currently no way to const-ly retrieve MAC bytes from a eui48::MacAddress
-
         let mut mac_bytes_u64 = [0u8; 8];
 
         mac_bytes_u64[2] = mac[0];
@@ -178,9 +301,26 @@ impl Oui {
             length: 48,
         }
     }
-    pub fn from_addr(mac: MacAddress) -> Oui {
-        // MacAddress::as_bytes() is not const
-        Oui::from_array(mac.as_bytes().try_into().unwrap())
+    pub const fn from_addr(mac: MacAddr) -> Oui {
+        Oui::from_array(mac.to_array())
+    }
+
+    /// Creates a prefix from its leading bytes, as long as those bytes: `[0x3C, 0xA6, 0xF6]` is `3c:a6:f6` (a /24).
+    ///
+    /// Returns Err(ParseOuiError::InvalidByteLength(_)) if given more than 6 bytes.
+    pub const fn from_bytes(prefix: &[u8]) -> Result<Oui, ParseOuiError> {
+        if prefix.len() > 6 {
+            return Err(ParseOuiError::InvalidByteLength(prefix.len()));
+        }
+        let mut mac = [0u8; 6];
+        let mut i = 0;
+        while i < prefix.len() {
+            mac[i] = prefix[i];
+            i += 1;
+        }
+        let mut oui = Oui::from_array(mac);
+        oui.length = prefix.len() as u8 * 8;
+        Ok(oui)
     }
 
     /// Returns the MAC address as a u64.
@@ -190,6 +330,15 @@ impl Oui {
         self.address
     }
 
+    /// Parses a prefix from Wireshark's well-known address (`wka`) database.
+    ///
+    /// Unlike OUI prefixes, these may be as short as 8 bits, and are as long as their digits when no length is given.
+    /// That is, `ff:ff:ff:ff:ff:ff` is a /48 and `00:00:0c:07:ac/40` a /40, while `33:33:00:00:00:00/16` is a /16.
+    pub fn parse_wka(s: &str) -> Result<Oui, ParseOuiError> {
+        let (address, length) = raw::parse_prefix(s, true).map_err(|e| ParseOuiError::from_raw(e, s))?;
+        Ok(Oui { address, length })
+    }
+
     /// Converts a 64-bit integer into a structured OUI with a length of 48 bits.
     ///
     /// Returns Err(ParseOuiError::InvalidIntegerValue(_)) if the address is over 0x0000FFFF_FFFFFF
@@ -203,29 +352,98 @@ impl Oui {
         })
     }
 }
+/// The IEEE registry assignment class of an OUI prefix.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BlockType {
+    /// MAC Address Block Large, a 24-bit prefix (formerly "OUI")
+    MaL,
+    /// MAC Address Block Medium, a 28-bit prefix
+    MaM,
+    /// MAC Address Block Small, a 36-bit prefix (formerly "OUI-36")
+    MaS,
+    /// Individual Address Block, a legacy 36-bit prefix within `00:50:C2` or `40:D8:55`
+    Iab,
+    /// Company ID, a 24-bit prefix with the local bit set, which may not be used for globally unique addresses
+    Cid,
+}
+impl BlockType {
+    /// The IEEE abbreviation for the assignment class
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            BlockType::MaL => "MA-L",
+            BlockType::MaM => "MA-M",
+            BlockType::MaS => "MA-S",
+            BlockType::Iab => "IAB",
+            BlockType::Cid => "CID",
+        }
+    }
+
+    /// Parses an IEEE abbreviation, as returned by [BlockType::as_str]
+    pub fn from_abbreviation(s: &str) -> Option<BlockType> {
+        [BlockType::MaL, BlockType::MaM, BlockType::MaS, BlockType::Iab, BlockType::Cid]
+            .into_iter()
+            .find(|bt| bt.as_str() == s)
+    }
+}
+
 impl FromStr for Oui {
     type Err = ParseOuiError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (oui, length) = match s.split_once('/') {
-            None => (s, 24),
-            Some((oui, slen)) => (
-                oui,
-                slen.parse::<u8>()
-                    .map_err(|e| ParseOuiError::PrefixLengthParsing(e, s.to_owned()))?,
-            ),
-        };
-
-        if !(24..=48).contains(&length) {
-            return Err(ParseOuiError::PrefixLengthValue(length, Cow::from(s.to_owned())));
+        let (address, length) = raw::parse_prefix(s, false).map_err(|e| ParseOuiError::from_raw(e, s))?;
+        Ok(Oui { address, length })
+    }
+}
+/// Serialized in its string form, such as `3c:a6:f6` or `8c:1f:64:cb:20:00/36`
+#[cfg(feature = "serde")]
+impl serde::Serialize for Oui {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&format_args!("{self:?}"))
+    }
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Oui {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Oui, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        // well-known address prefixes may be shorter than 24 bits, and always have an explicit length
+        let oui = if s.contains('/') { Oui::parse_wka(&s) } else { Oui::from_str(&s) };
+        oui.map_err(serde::de::Error::custom)
+    }
+}
+/// A MAC address, as a /48 prefix
+impl From<MacAddr> for Oui {
+    fn from(mac: MacAddr) -> Oui {
+        Oui::from_addr(mac)
+    }
+}
+/// A /48 prefix, as the single MAC address it holds
+impl TryFrom<Oui> for MacAddr {
+    type Error = ParseOuiError;
+    fn try_from(oui: Oui) -> Result<MacAddr, ParseOuiError> {
+        match oui.length {
+            48 => Ok(oui.as_mac()),
+            _ => Err(ParseOuiError::NotAnAddress(oui.to_string())),
         }
-
-        let oui_mac = parse_mac_addr_extend(oui, true).unwrap();
-        let mut address = Oui::from_addr(oui_mac);
-        address.length = length;
-
-        Ok(address)
     }
 }
+/// Formatted as with Debug: `3c:a6:f6` for a 24-bit prefix, or `8c:1f:64:cb:20:00/36` for others. The alternate form
+/// (`{:#}`) always includes the length.
+impl fmt::Display for Oui {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+/// Writes a single entry as a line of a Wireshark `manuf` file, as [OuiDb::write_manuf] does.
+pub(crate) fn write_manuf_line<W: io::Write>(mut w: W, o: Oui, om: &OuiMeta<&str>) -> io::Result<()> {
+    write!(w, "{:?}\t{}", o, om.manuf())?;
+    match (om.manuf_long(), om.comment()) {
+        (None, None) => {},
+        (Some(long), None) => write!(w, "\t{long}")?,
+        (long, Some(comment)) => write!(w, "\t{}\t# {comment}", long.unwrap_or(om.manuf()))?,
+    }
+    writeln!(w)
+}
+
 impl fmt::Debug for Oui {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let formatted = MacStyle::Colon.format(self.as_mac(), false);
@@ -261,14 +479,267 @@ fn check_smallstr_size() {
 
 /// In-memory OUI prefix database
 ///
-/// Lookups should generally be O(log n), as we perform a binary search to locate an OUI prefix, when given a username
-#[derive(Debug, Clone)]
-pub struct OuiDb(Vec<(Oui, OuiMeta<String>)>);
+/// Lookups are a longest-prefix match, performing one hash lookup per distinct prefix length in the database (usually
+/// three: /36, /28, then /24). Entries are also kept in prefix order, for listing.
+///
+/// All names and comments are stored once within a single string arena, rather than as separate allocations per entry.
+#[derive(Debug, Clone, Default)]
+pub struct OuiDb {
+    entries: Vec<(Oui, OuiMeta<ArenaStr>)>,
+    strings: String,
+    index: PrefixIndex,
+}
+
+/// A reference to a string within an [OuiDb]'s string arena.
+#[derive(Debug, Clone, Copy)]
+struct ArenaStr {
+    start: u32,
+    len: u32,
+}
+
+/// Maps each prefix to its entry index, with one table per prefix length, longest first.
+#[derive(Debug, Clone, Default)]
+struct PrefixIndex(Vec<(u64, HashMap<u64, usize>)>);
+
+impl PrefixIndex {
+    fn build<T>(entries: &[(Oui, T)]) -> PrefixIndex {
+        let mut by_length: BTreeMap<u8, HashMap<u64, usize>> = BTreeMap::new();
+        for (i, (o, _om)) in entries.iter().enumerate() {
+            by_length.entry(o.length).or_default().entry(o.address).or_insert(i);
+        }
+        PrefixIndex(by_length.into_iter().rev().map(|(length, table)| (Oui { address: 0, length }.mask(), table)).collect())
+    }
+
+    fn search(&self, mac: Oui) -> Option<usize> {
+        self.0.iter().find_map(|(mask, table)| table.get(&(mac.address & mask)).copied())
+    }
+}
 
 lazy_static::lazy_static! {
-    pub static ref EMBEDDED_DB: OuiDb = {
-        OuiDb::parse_from_string(OuiDb::WIRESHARK_OUI_DB_EMBEDDED).expect("failure parsing embedded wireshark oui database")
+    pub static ref EMBEDDED_DB: Arc<OuiDb> = {
+        let _span = span!(DEBUG, "embedded_oui_db", source = OuiDb::EMBEDDED_DB_SOURCE);
+        // validated by the build script, this can only fail if the build script and binary format fall out of sync
+        let db = OuiDb::from_binary(OuiDb::EMBEDDED_DB_BINARY).expect("failure reading embedded oui database");
+        event!(DEBUG, entries = db.len(), "read embedded oui database");
+        Arc::new(db)
     };
+
+    /// Wireshark's well-known address database at compile time, naming broadcast and protocol multicast addresses.
+    ///
+    /// Empty unless built with the `wka-db` feature.
+    pub static ref EMBEDDED_WKA_DB: Arc<OuiDb> = {
+        Arc::new(OuiDb::from_binary(OuiDb::EMBEDDED_WKA_BINARY).expect("failure reading embedded wka database"))
+    };
+}
+
+/// The sources an OUI database may be loaded from, in order of lookup precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OuiLayerKind {
+    /// Individual prefixes registered on the connection, such as with `OUI_REGISTER`
+    Registered,
+    /// Entries read from a table in the connected database
+    Table,
+    /// A database file (or download) loaded at runtime
+    File,
+    /// The database the connection started with, usually [EMBEDDED_DB]
+    Embedded,
+    /// Wireshark's well-known (mostly multicast) addresses, see [EMBEDDED_WKA_DB]. These come last, so their narrower
+    /// prefixes (such as Cisco's HSRP addresses) never shadow a vendor's own prefix.
+    WellKnown,
+}
+impl OuiLayerKind {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            OuiLayerKind::Registered => "registered",
+            OuiLayerKind::Table => "table",
+            OuiLayerKind::File => "file",
+            OuiLayerKind::WellKnown => "wka",
+            OuiLayerKind::Embedded => "embedded",
+        }
+    }
+}
+
+/// A single OUI database within a lookup chain, along with where it was loaded from.
+#[derive(Debug, Clone)]
+pub struct OuiLayer {
+    pub kind: OuiLayerKind,
+    /// A description of the source within its kind, such as a file path or table name. Empty if there is none.
+    pub source: String,
+    pub db: Arc<OuiDb>,
+}
+impl OuiLayer {
+    /// Where this layer was loaded from, such as `embedded` or `file:/usr/share/wireshark/manuf`
+    pub fn provenance(&self) -> String {
+        match self.source.is_empty() {
+            true => self.kind.as_str().to_owned(),
+            false => format!("{}:{}", self.kind.as_str(), self.source),
+        }
+    }
+}
+
+/// A snapshot of the chain of OUI databases used for lookups, with at most one layer of each kind.
+///
+/// Lookups resolve through each layer in order of precedence (registered prefixes, then a table, then a loaded file,
+/// then the embedded database, then well-known addresses), and use the first layer with a matching prefix.
+///
+/// The most recent lookups are cached, as real datasets repeat the same addresses many times over. As the layers of a
+/// snapshot never change, neither do its cached results: changing a connection's layers starts a new, empty cache.
+#[derive(Debug, Clone)]
+pub struct OuiLayers {
+    layers: Vec<OuiLayer>,
+    cache: LookupCache,
+}
+
+/// The number of recent lookups cached by each snapshot of a connection's layers
+const LOOKUP_CACHE_SIZE: usize = 1024;
+
+/// Recent lookups on a snapshot of layers, as the layer and entry index each resolved to, if any.
+///
+/// Its hit and miss counts are shared by each snapshot of a connection's layers, counting for the connection as a whole.
+struct LookupCache {
+    entries: Mutex<LruCache<MacAddr, Option<(usize, usize)>>>,
+    counts: Arc<CacheCounts>,
+}
+
+#[derive(Debug, Default)]
+struct CacheCounts {
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// How often a connection's OUI lookups were answered from its cache of recent lookups, as reported by `NETTOOLS_STATS`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LookupStats {
+    pub hits: u64,
+    /// Lookups searching the layers, including those skipping the cache while another thread held it
+    pub misses: u64,
+}
+
+impl LookupCache {
+    fn new(counts: Arc<CacheCounts>) -> LookupCache {
+        LookupCache { entries: Mutex::new(LruCache::new(LOOKUP_CACHE_SIZE)), counts }
+    }
+}
+impl Clone for LookupCache {
+    fn clone(&self) -> LookupCache {
+        LookupCache::new(Arc::new(CacheCounts::default()))
+    }
+}
+impl fmt::Debug for LookupCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("LookupCache")
+    }
+}
+
+impl OuiLayers {
+    fn new(layers: Vec<OuiLayer>, counts: Arc<CacheCounts>) -> OuiLayers {
+        OuiLayers { layers, cache: LookupCache::new(counts) }
+    }
+
+    /// Searches each layer in order of precedence, returning the first match along with the layer it was found in.
+    pub fn search_layered(&self, mac: MacAddr) -> Option<(Oui, OuiMeta<&str>, &OuiLayer)> {
+        // a lookup racing another on a different thread skips the cache, rather than waiting on it
+        let mut cache = self.cache.entries.try_lock().ok();
+        let found = match cache.as_mut().and_then(|c| c.get(&mac).copied()) {
+            Some(found) => {
+                self.cache.counts.hits.fetch_add(1, Ordering::Relaxed);
+                found
+            },
+            None => {
+                self.cache.counts.misses.fetch_add(1, Ordering::Relaxed);
+                let key = Oui::from_addr(mac);
+                let found = self.layers.iter().enumerate()
+                    .find_map(|(l, layer)| layer.db.index.search(key).map(|i| (l, i)));
+                if let Some(cache) = cache.as_mut() {
+                    cache.insert(mac, found);
+                }
+                found
+            },
+        };
+        drop(cache);
+
+        let (l, i) = found?;
+        let layer = &self.layers[l];
+        layer.db.entry(i).map(|(o, om)| (o, om, layer))
+    }
+
+    pub fn search_entry(&self, mac: MacAddr) -> Option<(Oui, OuiMeta<&str>)> {
+        self.search_layered(mac).map(|(o, om, _layer)| (o, om))
+    }
+
+    /// The layers of this chain, in order of precedence.
+    pub fn layers(&self) -> &[OuiLayer] {
+        &self.layers
+    }
+
+    /// How often lookups were answered from the cache, across every snapshot of the connection's layers.
+    pub fn cache_stats(&self) -> LookupStats {
+        LookupStats {
+            hits: self.cache.counts.hits.load(Ordering::Relaxed),
+            misses: self.cache.counts.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Zeroes the cache's hit and miss counts, as reported by [OuiLayers::cache_stats].
+    pub fn reset_cache_stats(&self) {
+        self.cache.counts.hits.store(0, Ordering::Relaxed);
+        self.cache.counts.misses.store(0, Ordering::Relaxed);
+    }
+}
+
+/// The OUI database used by a connection's lookup functions. Clones share the same underlying database.
+///
+/// Starts out as the embedded database, and may be layered over at runtime (such as by `OUI_LOAD`). Lookups take a
+/// snapshot of the current layers, so a change never affects a lookup that is already in progress.
+#[derive(Debug, Clone)]
+pub struct SharedOuiDb(Arc<RwLock<Arc<OuiLayers>>>);
+
+impl SharedOuiDb {
+    pub fn new(db: Arc<OuiDb>) -> SharedOuiDb {
+        let base = OuiLayer { kind: OuiLayerKind::Embedded, source: String::new(), db };
+        SharedOuiDb(Arc::new(RwLock::new(Arc::new(OuiLayers::new(vec![base], Arc::default())))))
+    }
+
+    /// Returns a snapshot of the current layers.
+    pub fn load(&self) -> Arc<OuiLayers> {
+        // the lock only guards an Arc swap, so a poisoned lock can't hold a partially updated database
+        self.0.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Sets (or with None, removes) the layer of the given kind, returning the previous one.
+    pub fn set_layer(&self, kind: OuiLayerKind, layer: Option<(String, Arc<OuiDb>)>) -> Option<OuiLayer> {
+        self.update_layer(kind, |_prev| layer)
+    }
+
+    /// Replaces the layer of the given kind with one derived from the current layer, returning the previous one.
+    ///
+    /// The update is atomic, so concurrent updates to the same layer are not lost.
+    pub fn update_layer(
+        &self,
+        kind: OuiLayerKind,
+        f: impl FnOnce(Option<&OuiLayer>) -> Option<(String, Arc<OuiDb>)>,
+    ) -> Option<OuiLayer> {
+        let mut current = self.0.write().unwrap_or_else(PoisonError::into_inner);
+        let mut layers = current.layers.clone();
+
+        let prev = layers.iter().position(|l| l.kind == kind).map(|i| layers.remove(i));
+        if let Some((source, db)) = f(prev.as_ref()) {
+            layers.push(OuiLayer { kind, source, db });
+            layers.sort_by_key(|l| l.kind);
+        }
+
+        *current = Arc::new(OuiLayers::new(layers, Arc::clone(&current.cache.counts)));
+        prev
+    }
+}
+impl Default for SharedOuiDb {
+    fn default() -> SharedOuiDb {
+        let shared = SharedOuiDb::new(Arc::clone(&EMBEDDED_DB));
+        if !EMBEDDED_WKA_DB.is_empty() {
+            shared.set_layer(OuiLayerKind::WellKnown, Some((String::new(), Arc::clone(&EMBEDDED_WKA_DB))));
+        }
+        shared
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -277,18 +748,144 @@ pub enum ParseOuiDbError {
     OuiParsing(usize, #[source] ParseOuiError, String),
     #[error("invalid number of fields in oui db record, expected [2, 4] got {1} (line {0}: {2:?})")]
     BadFieldCount(usize, usize, String),
+    #[error("malformed csv record in ieee registry, expected at least 3 fields (line {0}: {1:?})")]
+    CsvRecord(usize, String),
+    #[error("unknown ieee registry {1:?} (line {0})")]
+    CsvRegistry(usize, String),
+    #[error("invalid assignment {2:?} for ieee registry {1} (line {0})")]
+    CsvAssignment(usize, String, String),
 
     #[cfg(debug_assertions)]
     #[error("entries with duplicate prefix's exist within the OUI database")]
     DuplicatedEntries,
 }
 
+/// Parses each of a database's lines that `filter` keeps, passing `parse` the line's number and filtered contents.
+///
+/// With the `rayon` feature, lines are parsed in parallel. Either way, entries are returned in line order, and the
+/// error reported is that of the first bad line.
+fn parse_lines<'a, T: Send>(
+    txt: &'a str,
+    filter: impl Fn(&'a str) -> Option<&'a str> + Sync,
+    parse: impl Fn(usize, &'a str) -> Result<T, ParseOuiDbError> + Sync,
+) -> Result<Vec<T>, ParseOuiDbError> {
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+
+        // a thread's share of a small file isn't worth the handoff
+        const MIN_LINES: usize = 1024;
+
+        let lines: Vec<&str> = txt.split('\n').collect();
+        let parsed: Vec<Result<T, ParseOuiDbError>> = lines.into_par_iter()
+            .with_min_len(MIN_LINES)
+            .enumerate()
+            .filter_map(|(lnum, l)| filter(l).map(|l| parse(lnum, l)))
+            .collect();
+        parsed.into_iter().collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        txt.split('\n')
+            .enumerate()
+            .filter_map(|(lnum, l)| filter(l).map(|l| parse(lnum, l)))
+            .collect()
+    }
+}
+
+/// Stable sorts entries by prefix, in parallel with the `rayon` feature.
+fn sort_entries<T: Send>(entries: &mut [(Oui, T)]) {
+    #[cfg(feature = "rayon")]
+    rayon::slice::ParallelSliceMut::par_sort_by_key(entries, |(k, _v)| *k);
+    #[cfg(not(feature = "rayon"))]
+    entries.sort_by_key(|(k, _v)| *k);
+}
+
 impl OuiDb {
-    /// The latest copy of Wireshark's OUI database at compile time.
+    /// The OUI database fetched at compile time, pre-parsed into the binary format read by [OuiDb::from_binary].
+    ///
+    /// This is the latest copy of Wireshark's OUI database (https://gitlab.com/wireshark/wireshark/raw/master/manuf),
+    /// or the IEEE registry CSVs when built with the `ieee-oui-db` feature. Holds no entries when built with neither
+    /// that nor the `manuf-db` feature. When built with the `oui-short-names` feature, long names and comments are
+    /// stripped.
+    pub const EMBEDDED_DB_BINARY: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/oui_db.bin"));
+
+    /// Wireshark's well-known address database fetched at compile time, pre-parsed into the binary format read by
+    /// [OuiDb::from_binary]. Holds no entries unless built with the `wka-db` feature.
     ///
-    /// Latest copy is available here: https://gitlab.com/wireshark/wireshark/raw/master/manuf
-    pub const WIRESHARK_OUI_DB_EMBEDDED: &str =
-        include_str!(concat!(env!("OUT_DIR"), "/wireshark_oui_db.txt"));
+    /// Latest copy is available here: https://gitlab.com/wireshark/wireshark/raw/master/wka
+    pub const EMBEDDED_WKA_BINARY: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/wka_db.bin"));
+
+    /// The header line of each IEEE registry CSV download.
+    pub const IEEE_CSV_HEADER: &str = raw::IEEE_CSV_HEADER;
+
+    /// Builds a database from a list of entries. When a prefix is listed more than once, the first entry is kept.
+    pub fn from_entries<S: AsRef<str>>(mut entries: Vec<(Oui, OuiMeta<S>)>) -> OuiDb {
+        // stable sort, so dedup keeps the first of any duplicates
+        entries.sort_by_key(|(k, _v)| *k);
+        entries.dedup_by_key(|(k, _v)| *k);
+        OuiDb::from_sorted(entries)
+    }
+
+    /// Builds the database from entries already sorted and deduplicated by prefix, copying their strings into the arena.
+    fn from_sorted<S: AsRef<str>>(entries: Vec<(Oui, OuiMeta<S>)>) -> OuiDb {
+        /// Appends a string to the arena, unless already present
+        fn intern<'a>(strings: &mut String, interned: &mut HashMap<&'a str, ArenaStr>, s: &'a str) -> ArenaStr {
+            *interned.entry(s).or_insert_with(|| {
+                let start = strings.len();
+                strings.push_str(s);
+                ArenaStr {
+                    start: start.try_into().expect("oui database strings exceed 4GiB"),
+                    len: s.len() as u32,
+                }
+            })
+        }
+
+        let mut strings = String::new();
+        let mut interned = HashMap::new();
+        let mut arena_entries = Vec::with_capacity(entries.len());
+        for (o, om) in &entries {
+            let short = intern(&mut strings, &mut interned, om.short.as_ref());
+            let long = om.long.as_ref().map(|l| intern(&mut strings, &mut interned, l.as_ref()));
+            let comment = om.comment.as_ref().map(|c| intern(&mut strings, &mut interned, c.as_ref()));
+            let address = om.address.as_ref().map(|a| intern(&mut strings, &mut interned, a.as_ref()));
+            let country = om.country.as_ref().map(|c| intern(&mut strings, &mut interned, c.as_ref()));
+            arena_entries.push((*o, OuiMeta { short, long, comment, address, country, block_type: om.block_type }));
+        }
+
+        let index = PrefixIndex::build(&entries);
+        strings.shrink_to_fit();
+        OuiDb { entries: arena_entries, strings, index }
+    }
+
+    /// Resolves an entry's metadata from the string arena.
+    fn meta(&self, om: &OuiMeta<ArenaStr>) -> OuiMeta<&str> {
+        let get = |s: ArenaStr| &self.strings[s.start as usize..(s.start + s.len) as usize];
+        OuiMeta {
+            short: get(om.short),
+            long: om.long.map(get),
+            comment: om.comment.map(get),
+            address: om.address.map(get),
+            country: om.country.map(get),
+            block_type: om.block_type,
+        }
+    }
+
+    /// Consumes the database, returning its entries in prefix order.
+    pub fn into_entries(self) -> Vec<(Oui, OuiMeta<String>)> {
+        self.raw_prefixes().map(|(o, om)| (o, om.to_owned())).collect()
+    }
+
+    /// Where the embedded database was fetched from at build time. Space separated, if built from several files.
+    pub const EMBEDDED_DB_SOURCE: &str = env!("NETTOOLS_MANUF_DB_SOURCE");
+
+    /// The UTC date the embedded database was fetched, as `YYYY-MM-DD`. When built from a copy cached by an earlier
+    /// build, this is when that build fetched it, and when built from a local copy, when the file was last modified.
+    pub const EMBEDDED_DB_DATE: &str = env!("NETTOOLS_MANUF_DB_DATE");
+
+    /// Where the embedded well-known address database was fetched from at build time, or empty without the `wka-db`
+    /// feature.
+    pub const EMBEDDED_WKA_SOURCE: &str = env!("NETTOOLS_WKA_DB_SOURCE");
 
     // TODO: pub fn parse_from_reader<R: BufRead>(txt: R) -> Result<OuiDb, DbParsingError>
 
@@ -296,113 +893,284 @@ impl OuiDb {
     ///
     /// Wireshark's reference OUI database can be found here: https://gitlab.com/wireshark/wireshark/raw/master/manuf
     pub fn parse_from_string(txt: &str) -> Result<OuiDb, ParseOuiDbError> {
-        let mut v: Vec<(Oui, OuiMeta<String>)> = txt
-            .split('\n')
-            .enumerate()
-            .map(|(lnum, l)| (lnum, l.trim()))
-            .filter(|(_, l)| !(l.is_empty() || l.starts_with('#')))
-            .map(|(lnum, l)| {
-                let mut _fields = [""; 8];
-                let fields: &[&str] = {
-                    let mut len = 0;
-                    l.split('\t')
-                        .filter(|f| f.len() > 1)
-                        .enumerate()
-                        .for_each(|(i, part)| {
-                            len = i + 1;
-                            _fields[i] = part.trim();
-                        });
-                    &_fields[..len]
-                };
-                if !(2..=4).contains(&fields.len()) {
-                    return Err(ParseOuiDbError::BadFieldCount(
-                        lnum,
-                        fields.len(),
-                        l.to_owned(),
-                    ));
-                }
-                let ouispec: Oui = fields[0]
-                    .parse()
-                    .map_err(|e| ParseOuiDbError::OuiParsing(lnum, e, l.to_owned()))?;
-                let short = fields[1];
-                let long = fields.get(2).copied();
-                let comment = fields.get(3).map(|s| s.trim_matches('#').trim());
-                Ok((
-                    ouispec,
-                    OuiMeta {
-                        short,
-                        long,
-                        comment,
-                    }
-                    .to_owned(),
-                ))
-            })
-            .collect::<Result<Vec<_>, _>>()?;
+        OuiDb::parse_wireshark(txt, str::parse)
+    }
+
+    /// Parse Wireshark's well-known address database into memory. See [Oui::parse_wka] for how its prefixes differ.
+    ///
+    /// Wireshark's reference well-known address database can be found here: https://gitlab.com/wireshark/wireshark/raw/master/wka
+    pub fn parse_wka(txt: &str) -> Result<OuiDb, ParseOuiDbError> {
+        OuiDb::parse_wireshark(txt, Oui::parse_wka)
+    }
+
+    /// Parses a single line of a Wireshark-format `manuf` file, numbered by `lnum` for error messages. Blank lines and
+    /// comments are None.
+    ///
+    /// This allows a file's entries to be inspected line by line, where [OuiDb::parse_from_string] stops at the first
+    /// bad line.
+    pub fn parse_manuf_line(lnum: usize, line: &str) -> Result<Option<(Oui, OuiMeta<&str>)>, ParseOuiDbError> {
+        let Some(l) = raw::wireshark_line(line) else { return Ok(None); };
+        OuiDb::parse_wireshark_line(lnum, l, str::parse).map(Some)
+    }
+
+    fn parse_wireshark_line(
+        lnum: usize,
+        l: &str,
+        parse_prefix: fn(&str) -> Result<Oui, ParseOuiError>,
+    ) -> Result<(Oui, OuiMeta<&str>), ParseOuiDbError> {
+        let fields = raw::wireshark_fields(l).map_err(|len| ParseOuiDbError::BadFieldCount(lnum, len, l.to_owned()))?;
+        let ouispec: Oui = parse_prefix(fields.prefix)
+            .map_err(|e| ParseOuiDbError::OuiParsing(lnum, e, l.to_owned()))?;
+        Ok((ouispec, OuiMeta::new(fields.short, fields.long, fields.comment)))
+    }
+
+    fn parse_wireshark(txt: &str, parse_prefix: fn(&str) -> Result<Oui, ParseOuiError>) -> Result<OuiDb, ParseOuiDbError> {
+        let mut v: Vec<(Oui, OuiMeta<&str>)> = parse_lines(
+            txt,
+            raw::wireshark_line,
+            |lnum, l| OuiDb::parse_wireshark_line(lnum, l, parse_prefix),
+        )?;
 
         // sort it for binary searching later
-        v.sort_by_key(|(k, _v)| *k);
+        sort_entries(&mut v);
 
+        // duplicates are always dropped, as lookups and the binary format expect unique prefixes
         #[cfg(debug_assertions)]
-        {
-            // no need to error on this if running in release mode
-            // the sourced DB shouldn't have any and it's not worth erroring over anyway
-            // this is primarily for diagnostics
-            let prededup_len = v.len();
-            v.dedup_by_key(|(k, _v)| *k);
-            if prededup_len != v.len() {
-                return Err(ParseOuiDbError::DuplicatedEntries);
-            }
+        let prededup_len = v.len();
+        v.dedup_by_key(|(k, _v)| *k);
+
+        // no need to error on this if running in release mode
+        // the sourced DB shouldn't have any and it's not worth erroring over anyway
+        // this is primarily for diagnostics
+        #[cfg(debug_assertions)]
+        if prededup_len != v.len() {
+            return Err(ParseOuiDbError::DuplicatedEntries);
         }
 
-        // let dbg_str: String = v.iter()
-        //     .enumerate()
-        //     .map(|(i, (o, om))| format!("{:>05}\t{:>012x}/{}\t{:?}\t{:?}\n", i, o.address, o.length, o, om))
-        //     .collect();
-        // std::fs::write("oui_db_dump.txt", dbg_str).unwrap();
-
-        Ok(OuiDb(v))
-    }
-
-    pub fn search_entry(&self, mac: MacAddress) -> Option<(Oui, OuiMeta<&str>)> {
-        let as_oui = Oui::from_addr(mac);
-        // eprintln!("searching MAC {:?} with OUI {:?}", mac, as_oui);
-        let base_i = match self.0.binary_search_by_key(&as_oui, |(o, _om)| *o) {
-            Ok(i) => i, // exact match
-            Err(i) => {
-                // should be n-above our desired entry
-                // should /be/ our desired entry if the prefix is long
-                // may have to iterate towards zero if we are within a longer prefix, and must match for the parent prefix
-                // subtract zero to go to the lower end of our match
-                i - 1
-            }
-        };
-        let mut i = base_i;
-
-        loop {
-            let (o, om) = self.0.get(i)?;
-            if o.contains(&as_oui) {
-                // this is our prefix
-                return Some((*o, om.as_ref()));
-            } else if !o.contains(&as_oui) && o.length <= 24 {
-                // we reached a top-level-prefix (/24) that doesn't contain us - we have none
-                return None;
-            } else {
-                // continue searching upwards for a containing prefix until we find one, or find a top-level that we arent' in
-                i -= 1;
-            }
+        Ok(OuiDb::from_sorted(v))
+    }
+
+    /// Parse the IEEE registry CSV downloads (`oui.csv`, `mam.csv`, `oui36.csv`, `iab.csv`, `cid.csv`) into memory.
+    ///
+    /// Several files may be concatenated together, to produce a single database from every registry. As IEEE only
+    /// publishes the organization's full name, it is used for both the short and long names. The organization's
+    /// address is kept, along with the country code found within it.
+    ///
+    /// The registries are available here: https://standards-oui.ieee.org/
+    pub fn parse_ieee_csv(txt: &str) -> Result<OuiDb, ParseOuiDbError> {
+        let v = parse_lines(
+            txt,
+            raw::ieee_line,
+            |lnum, l| {
+                let entry = raw::ieee_entry(l).map_err(|e| match e {
+                    raw::IeeeError::Record => ParseOuiDbError::CsvRecord(lnum, l.to_owned()),
+                    raw::IeeeError::Registry(registry) => ParseOuiDbError::CsvRegistry(lnum, registry),
+                    raw::IeeeError::Assignment(registry, assignment) => ParseOuiDbError::CsvAssignment(lnum, registry, assignment),
+                })?;
+                let ouispec = Oui { address: entry.address, length: entry.length };
+                let meta = OuiMeta::new(entry.name.to_string(), Some(entry.name.to_string()), None)
+                    .with_registrant(entry.registrant.as_deref().map(str::to_owned), entry.country().map(str::to_owned))
+                    .with_block_type(BlockType::from_abbreviation(raw::REGISTRIES[entry.registry as usize - 1]));
+                Ok((ouispec, meta))
+            },
+        )?;
+
+        // the registries contain a handful of historical duplicate assignments, keep the first listed
+        Ok(OuiDb::from_entries(v))
+    }
+
+    /// Parse either an IEEE registry CSV or a Wireshark-format database, depending on its header.
+    pub fn parse_any(txt: &str) -> Result<OuiDb, ParseOuiDbError> {
+        match txt.trim_start_matches('\u{feff}').starts_with(OuiDb::IEEE_CSV_HEADER) {
+            true => OuiDb::parse_ieee_csv(txt),
+            false => OuiDb::parse_from_string(txt),
         }
     }
 
+    /// Finds the longest prefix containing the MAC address.
+    pub fn search_entry(&self, mac: MacAddr) -> Option<(Oui, OuiMeta<&str>)> {
+        let i = self.index.search(Oui::from_addr(mac))?;
+        self.entry(i)
+    }
+
+    /// The number of prefixes in the database.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entry at index `i`, in prefix order.
+    pub fn entry(&self, i: usize) -> Option<(Oui, OuiMeta<&str>)> {
+        self.entries.get(i).map(|(o, om)| (*o, self.meta(om)))
+    }
+
     pub fn raw_prefixes(&self) -> impl Iterator<Item = (Oui, OuiMeta<&str>)> {
-        self.0.iter().map(|(o, om)| (*o, om.as_ref()))
+        self.entries.iter().map(|(o, om)| (*o, self.meta(om)))
     }
-    pub fn search_prefix(&self, mac: MacAddress) -> Option<Oui> {
+    pub fn search_prefix(&self, mac: MacAddr) -> Option<Oui> {
         self.search_entry(mac).map(|(p, _)| p)
     }
-    pub fn search(&self, mac: MacAddress) -> Option<OuiMeta<&str>> {
+    pub fn search(&self, mac: MacAddr) -> Option<OuiMeta<&str>> {
         self.search_entry(mac).map(|(_, om)| om)
     }
+
+    /// Finds the longest prefix containing each MAC address, yielding the results in input order.
+    pub fn search_many<'a, I>(&'a self, macs: I) -> impl Iterator<Item = Option<(Oui, OuiMeta<&'a str>)>> + 'a
+    where
+        I: IntoIterator<Item = MacAddr>,
+        I::IntoIter: 'a,
+    {
+        macs.into_iter().map(|mac| self.search_entry(mac))
+    }
+
+    /// Finds the longest prefix containing each MAC address, replacing the contents of `out` with the results in input
+    /// order. Reusing `out` across batches avoids reallocating it for each one.
+    pub fn search_slice<'a>(&'a self, macs: &[MacAddr], out: &mut Vec<Option<(Oui, OuiMeta<&'a str>)>>) {
+        out.clear();
+        out.extend(macs.iter().map(|mac| self.search_entry(*mac)));
+    }
+
+    /// Finds the longest prefix containing each MAC address, splitting the lookups across up to `threads` threads.
+    /// Results are returned in input order. With the `wasm` feature, where threads may not be available, the lookups
+    /// are all made on the calling thread.
+    pub fn search_slice_par(&self, macs: &[MacAddr], threads: NonZeroUsize) -> Vec<Option<(Oui, OuiMeta<&str>)>> {
+        let chunk_len = macs.len().div_ceil(threads.get()).max(1);
+        if chunk_len == macs.len() || cfg!(feature = "wasm") {
+            return macs.iter().map(|mac| self.search_entry(*mac)).collect();
+        }
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = macs.chunks(chunk_len)
+                .map(|chunk| scope.spawn(|| chunk.iter().map(|mac| self.search_entry(*mac)).collect::<Vec<_>>()))
+                .collect();
+            handles.into_iter()
+                .flat_map(|h| h.join().expect("oui lookup thread panicked"))
+                .collect()
+        })
+    }
+
+    /// Writes a diagnostic listing of every entry in prefix order, one per line: its index, raw prefix, and parsed form.
+    pub fn write_dump<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+        for (i, (o, om)) in self.raw_prefixes().enumerate() {
+            writeln!(w, "{:>05}\t{:>012x}/{}\t{:?}\t{:?}", i, o.address, o.length, o, om)?;
+        }
+        Ok(())
+    }
+
+    /// Writes every entry in prefix order as a Wireshark `manuf` file, which [OuiDb::parse_any] reads back.
+    ///
+    /// The registrant address and country aren't part of the format, and are left out. An entry with a comment but no
+    /// long name repeats its short name as the long name, as the comment is the fourth field.
+    pub fn write_manuf<W: io::Write>(&self, mut w: W) -> io::Result<()> {
+        for (o, om) in self.raw_prefixes() {
+            write_manuf_line(&mut w, o, &om)?;
+        }
+        Ok(())
+    }
+
+    /// Combines two databases. When both contain the same prefix, `precedence` picks which entry is kept.
+    pub fn merge(&self, other: &OuiDb, precedence: MergePrecedence) -> OuiDb {
+        let (first, second) = match precedence {
+            MergePrecedence::Ours => (self, other),
+            MergePrecedence::Theirs => (other, self),
+        };
+        OuiDb::from_entries(first.raw_prefixes().chain(second.raw_prefixes()).collect())
+    }
+
+    /// Compares this database against a newer one, yielding each prefix that was added, removed, or changed, in prefix
+    /// order.
+    pub fn diff<'a>(&'a self, newer: &'a OuiDb) -> impl Iterator<Item = DiffEntry<'a>> {
+        let mut old = self.raw_prefixes().peekable();
+        let mut new = newer.raw_prefixes().peekable();
+        std::iter::from_fn(move || loop {
+            let ordering = match (old.peek(), new.peek()) {
+                (None, None) => return None,
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (Some((o, _)), Some((n, _))) => o.cmp(n),
+            };
+            match ordering {
+                std::cmp::Ordering::Less => return old.next().map(|(o, om)| DiffEntry::Removed(o, om)),
+                std::cmp::Ordering::Greater => return new.next().map(|(o, om)| DiffEntry::Added(o, om)),
+                std::cmp::Ordering::Equal => {
+                    let (o, old_om) = old.next()?;
+                    let (_, new_om) = new.next()?;
+                    if old_om != new_om {
+                        return Some(DiffEntry::Changed(o, old_om, new_om));
+                    }
+                },
+            }
+        })
+    }
+}
+
+/// Which database's entry is kept by [OuiDb::merge], when both contain the same prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergePrecedence {
+    /// Keep the entry from the database being merged into
+    Ours,
+    /// Keep the entry from the database being merged in
+    Theirs,
+}
+
+/// A difference between two OUI databases, as reported by [OuiDb::diff].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffEntry<'a> {
+    /// The prefix only exists in the newer database
+    Added(Oui, OuiMeta<&'a str>),
+    /// The prefix only exists in the older database
+    Removed(Oui, OuiMeta<&'a str>),
+    /// The prefix's names or comment changed, from the older to the newer entry
+    Changed(Oui, OuiMeta<&'a str>, OuiMeta<&'a str>),
+}
+
+/// A single entry of a serialized [OuiDb], with its prefix alongside its metadata fields
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerdeEntry<S> {
+    prefix: Oui,
+    #[serde(flatten)]
+    meta: OuiMeta<S>,
+}
+
+/// Serialized as a snapshot of its entries in prefix order, each a map of `prefix`, `short`, `long`, `comment`,
+/// `address`, and `country`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for OuiDb {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.raw_prefixes().map(|(prefix, meta)| SerdeEntry { prefix, meta }))
+    }
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for OuiDb {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<OuiDb, D::Error> {
+        let entries = Vec::<SerdeEntry<String>>::deserialize(deserializer)?;
+        Ok(OuiDb::from_entries(entries.into_iter().map(|e| (e.prefix, e.meta)).collect()))
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_roundtrip() {
+    let db = OuiDb::from_entries(vec![
+        ("3c:a6:f6".parse().unwrap(), OuiMeta::new("Apple", Some("Apple, Inc."), None)),
+        ("8c:1f:64:cb:20:00/36".parse().unwrap(), OuiMeta::new("Acme", None, Some("lab")).with_registrant(Some("1 Main St, US"), Some("US"))),
+        (Oui::parse_wka("01:80:c2/16").unwrap(), OuiMeta::new("Bridged", None, None)),
+    ]);
+
+    let json = serde_json::to_string(&db).unwrap();
+    assert!(json.contains(r#"{"prefix":"3c:a6:f6","short":"Apple","long":"Apple, Inc.","comment":null"#));
+    let parsed: OuiDb = serde_json::from_str(&json).unwrap();
+    assert!(parsed.raw_prefixes().eq(db.raw_prefixes()));
+
+    // missing optional fields are read as null
+    let parsed: OuiDb = serde_json::from_str(r#"[{"prefix":"00:11:22:30:00:00/28","short":"Lab"}]"#).unwrap();
+    let (oui, meta) = parsed.raw_prefixes().next().unwrap();
+    assert_eq!(format!("{oui:?}"), "00:11:22:30:00:00/28");
+    assert_eq!(meta, OuiMeta::new("Lab", None, None));
 }
+
 impl FromStr for OuiDb {
     type Err = ParseOuiDbError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
@@ -412,10 +1180,12 @@ impl FromStr for OuiDb {
 
 #[test]
 fn embedded_db_builds() {
-    OuiDb::parse_from_string(OuiDb::WIRESHARK_OUI_DB_EMBEDDED).unwrap();
+    let db = OuiDb::from_binary(OuiDb::EMBEDDED_DB_BINARY).unwrap();
+    assert!(!db.is_empty());
 }
 
 #[test]
+#[cfg_attr(feature = "ieee-oui-db", ignore = "expects Wireshark's manuf naming")]
 fn match_no_long_name() {
     // 00:00:17	Oracle
     let mac = parse_mac_addr("00:00:17:aa:bb:cc").unwrap();
@@ -425,11 +1195,13 @@ fn match_no_long_name() {
             short: "Oracle",
             long: None,
             comment: None,
+            ..Default::default()
         })
     );
 }
 
 #[test]
+#[cfg_attr(any(feature = "ieee-oui-db", feature = "oui-short-names"), ignore = "expects Wireshark's manuf naming")]
 fn match_prefix_zeros() {
     // 00:00:17	Oracle
     let mac = parse_mac_addr("00:00:00:00:00:00").unwrap();
@@ -439,11 +1211,13 @@ fn match_prefix_zeros() {
             short: "00:00:00",
             long: Some("Officially Xerox, but 0:0:0:0:0:0 is more common"),
             comment: None,
+            ..Default::default()
         })
     );
 }
 
 #[test]
+#[cfg_attr(any(feature = "ieee-oui-db", feature = "oui-short-names"), ignore = "expects Wireshark's manuf naming")]
 fn match_prefix_exact() {
     // 2C:23:3A	HewlettP	Hewlett Packard
     let mac = parse_mac_addr("2c:23:3a:00:00:00").unwrap();
@@ -453,11 +1227,13 @@ fn match_prefix_exact() {
             short: "HewlettP",
             long: Some("Hewlett Packard"),
             comment: None,
+            ..Default::default()
         })
     );
 }
 
 #[test]
+#[cfg_attr(any(feature = "ieee-oui-db", feature = "oui-short-names"), ignore = "expects Wireshark's manuf naming")]
 fn match_prefix_basic() {
     // 2C:23:3A	HewlettP	Hewlett Packard
     let mac = parse_mac_addr("2c:23:3a:aa:bb:cc").unwrap();
@@ -467,11 +1243,13 @@ fn match_prefix_basic() {
             short: "HewlettP",
             long: Some("Hewlett Packard"),
             comment: None,
+            ..Default::default()
         })
     );
 }
 
 #[test]
+#[cfg_attr(any(feature = "ieee-oui-db", feature = "oui-short-names"), ignore = "expects Wireshark's manuf naming")]
 fn match_prefix_extended() {
     // 8C:47:6E:30:00:00/28	Shanghai	Shanghai Satellite Communication Technology Co.,Ltd
     let mac = parse_mac_addr("8c:47:6e:3a:bb:cc").unwrap();
@@ -481,11 +1259,13 @@ fn match_prefix_extended() {
             short: "Shanghai",
             long: Some("Shanghai Satellite Communication Technology Co.,Ltd"),
             comment: None,
+            ..Default::default()
         })
     );
 }
 
 #[test]
+#[cfg_attr(any(feature = "ieee-oui-db", feature = "oui-short-names"), ignore = "expects Wireshark's manuf naming")]
 fn match_commented() {
     // 08:00:87	XyplexTe	Xyplex	# terminal servers
     let mac = parse_mac_addr("08:00:87:aa:bb:cc").unwrap();
@@ -495,11 +1275,13 @@ fn match_commented() {
             short: "XyplexTe",
             long: Some("Xyplex"),
             comment: Some("terminal servers"),
+            ..Default::default()
         })
     );
 }
 
 #[test]
+#[cfg_attr(any(feature = "ieee-oui-db", feature = "oui-short-names"), ignore = "expects Wireshark's manuf naming")]
 fn match_unicode() {
     // 8C:1F:64:CB:20:00/36	DyncirSo	Dyncir Soluções Tecnológicas Ltda
     let mac = parse_mac_addr("8c:1f:64:cb:2b:cc").unwrap();
@@ -509,11 +1291,13 @@ fn match_unicode() {
             short: "DyncirSo",
             long: Some("Dyncir Soluções Tecnológicas Ltda"),
             comment: None,
+            ..Default::default()
         })
     );
 }
 
 #[test]
+#[cfg_attr(any(feature = "ieee-oui-db", feature = "oui-short-names"), ignore = "expects Wireshark's manuf naming")]
 fn resolve_mac_to_superprefix_when_missing_subprefix() {
     // 2C:27:9E	IEEERegi	IEEE Registration Authority
     // is split into /28, without a 2C:27:9E:F0:00:00/28 member
@@ -524,14 +1308,270 @@ fn resolve_mac_to_superprefix_when_missing_subprefix() {
             short: "IEEERegi",
             long: Some("IEEE Registration Authority"),
             comment: None,
+            ..Default::default()
         })
     );
 }
 
 #[test]
+fn parse_eui_widths() {
+    assert_eq!(
+        parse_eui("aa:bb:cc:dd:ee:ff").unwrap(),
+        Eui::Mac48(MacAddr::new([0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff]))
+    );
+    assert_eq!(
+        parse_eui("aabb.ccdd.eeff.0011").unwrap(),
+        Eui::Eui64([0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff, 0x00, 0x11])
+    );
+    assert!(parse_eui("aa:bb:cc:dd:ee:ff:00").is_err());
+    assert!(parse_mac_addr("aa:bb:cc:dd:ee:ff:00:11").is_err());
+}
+
+#[test]
+fn block_types() {
+    let block = |mac: &str| EMBEDDED_DB.search_prefix(parse_mac_addr(mac).unwrap()).and_then(|o| o.block_type());
+    assert_eq!(block("3c:a6:f6:c4:34:f8"), Some(BlockType::MaL));
+    assert_eq!(block("8c:1c:da:82:4c:2e"), Some(BlockType::MaM));
+    assert_eq!(block("8c:1f:64:cb:2b:cc"), Some(BlockType::MaS));
+    assert_eq!(block("33:33:00:00:00:01"), None);
+}
+
+#[test]
+fn oui_blocks() {
+    let oui: Oui = "8c:1f:64:cb:20:00/36".parse().unwrap();
+    assert_eq!(oui.to_string(), "8c:1f:64:cb:20:00/36");
+    assert_eq!(format!("{:#}", Oui::from_bytes(&[0x3C, 0xA6, 0xF6]).unwrap()), "3c:a6:f6:00:00:00/24");
+    assert_eq!(oui.prefix_len(), 36);
+    assert_eq!(oui.first(), parse_mac_addr("8c:1f:64:cb:20:00").unwrap());
+    assert_eq!(oui.last(), parse_mac_addr("8c:1f:64:cb:2f:ff").unwrap());
+    assert!(oui.contains_mac(parse_mac_addr("8c:1f:64:cb:2b:cc").unwrap()));
+    assert!(!oui.contains_mac(parse_mac_addr("8c:1f:64:cb:30:00").unwrap()));
+    assert_eq!(oui.iter().count(), 4096);
+    assert!(Oui::from_bytes(&[0; 7]).is_err());
+
+    let mac = parse_mac_addr("3c:a6:f6:c4:34:f8").unwrap();
+    assert_eq!(MacAddr::try_from(Oui::from(mac)).unwrap(), mac);
+    assert!(MacAddr::try_from(oui).is_err());
+}
+
+#[test]
+fn layered_precedence() {
+    let single = |prefix: &str, short: &str| Arc::new(OuiDb::from_entries(vec![
+        (prefix.parse().unwrap(), OuiMeta::new(short.to_owned(), None, None)),
+    ]));
+    let shared = SharedOuiDb::new(single("3C:A6:F6", "Base"));
+    shared.set_layer(OuiLayerKind::Table, Some(("t".to_owned(), single("3C:A6:F6", "Table"))));
+    shared.set_layer(OuiLayerKind::File, Some(("f".to_owned(), single("3C:A6:F6", "File"))));
+
+    let mac = parse_mac_addr("3c:a6:f6:c4:34:f8").unwrap();
+    let layers = shared.load();
+    let (_o, om, layer) = layers.search_layered(mac).unwrap();
+    assert_eq!((*om.manuf(), layer.provenance().as_str()), ("Table", "table:t"));
+
+    shared.set_layer(OuiLayerKind::Table, None);
+    let layers = shared.load();
+    let (_o, om, layer) = layers.search_layered(mac).unwrap();
+    assert_eq!((*om.manuf(), layer.provenance().as_str()), ("File", "file:f"));
+}
+
+#[test]
+fn cached_lookups() {
+    let single = |prefix: &str, short: &str| Arc::new(OuiDb::from_entries(vec![
+        (prefix.parse().unwrap(), OuiMeta::new(short.to_owned(), None, None)),
+    ]));
+    let shared = SharedOuiDb::new(single("3C:A6:F6", "Base"));
+    let hit = parse_mac_addr("3c:a6:f6:c4:34:f8").unwrap();
+    let miss = parse_mac_addr("00:00:5e:00:01:01").unwrap();
+
+    // repeated lookups (including misses) resolve the same once cached
+    let layers = shared.load();
+    for _ in 0..3 {
+        assert_eq!(layers.search_layered(hit).map(|(_o, om, _l)| *om.manuf()), Some("Base"));
+        assert!(layers.search_layered(miss).is_none());
+    }
+
+    // a new layer isn't hidden by the previous snapshot's cache
+    shared.set_layer(OuiLayerKind::Registered, Some(("u".to_owned(), single("00:00:5E", "User"))));
+    let layers = shared.load();
+    assert_eq!(layers.search_layered(miss).map(|(_o, om, _l)| *om.manuf()), Some("User"));
+    assert_eq!(layers.search_layered(hit).map(|(_o, om, _l)| *om.manuf()), Some("Base"));
+}
+
+#[test]
+#[cfg_attr(feature = "ieee-oui-db", ignore = "expects Wireshark's manuf naming")]
 fn match_none() {
     // B0:C5:59	SamsungE	Samsung Electronics Co.,Ltd
     // B0:C5:CA	IEEERegi	IEEE Registration Authority
     let mac = parse_mac_addr("b0:c5:5a:aa:bb:cc").unwrap();
     assert_eq!(EMBEDDED_DB.search(mac), None);
 }
+
+#[test]
+fn parse_ieee_registries() {
+    let csv = "\u{feff}Registry,Assignment,Organization Name,Organization Address\r
+MA-L,3CA6F6,Apple Inc.,1 Infinite Loop Cupertino CA US 95014 \r
+MA-L,080030,NETWORK RESEARCH CORPORATION,2380 N. ROSE AVENUE OXNARD CA US 93010 \r
+MA-L,080030,CERN,CH-1211 GENEVE SUISSE/SWITZ  CH 023 \r
+Registry,Assignment,Organization Name,Organization Address
+MA-M,8C1CDA8,\"Shenzhen \"\"Example\"\", Ltd.\",Guangdong CN 518000
+MA-S,8C1F64CB2,Lab Devices,\"Street 1, Town\"
+";
+    let db = OuiDb::parse_ieee_csv(csv).unwrap();
+    assert_eq!(db.len(), 4);
+
+    let manuf = |mac: &str| db.search(parse_mac_addr(mac).unwrap()).map(|om| om.manuf().to_string());
+    assert_eq!(manuf("3c:a6:f6:c4:34:f8").as_deref(), Some("Apple Inc."));
+    assert_eq!(manuf("08:00:30:00:00:01").as_deref(), Some("NETWORK RESEARCH CORPORATION"));
+    assert_eq!(manuf("8c:1c:da:82:4c:2e").as_deref(), Some("Shenzhen \"Example\", Ltd."));
+    assert_eq!(manuf("8c:1f:64:cb:2b:cc").as_deref(), Some("Lab Devices"));
+    assert_eq!(manuf("8c:1f:64:cb:3b:cc"), None);
+    assert_eq!(db.search_prefix(parse_mac_addr("8c:1c:da:82:4c:2e").unwrap()).map(|o| o.length()), Some(28));
+
+    let registrant = |mac: &str| db.search(parse_mac_addr(mac).unwrap()).map(|om| (om.address().copied(), om.country().copied()));
+    assert_eq!(registrant("3c:a6:f6:c4:34:f8"), Some((Some("1 Infinite Loop Cupertino CA US 95014"), Some("US"))));
+    assert_eq!(registrant("8c:1c:da:82:4c:2e"), Some((Some("Guangdong CN 518000"), Some("CN"))));
+    assert_eq!(registrant("8c:1f:64:cb:2b:cc"), Some((Some("Street 1, Town"), None)));
+    let block = |mac: &str| db.search(parse_mac_addr(mac).unwrap()).and_then(|om| om.block_type());
+    assert_eq!(block("3c:a6:f6:c4:34:f8"), Some(BlockType::MaL));
+    assert_eq!(block("8c:1f:64:cb:2b:cc"), Some(BlockType::MaS));
+    assert_eq!(ieee_address_country("Eindhoven NL 5656 AE"), Some("NL"));
+    assert_eq!(ieee_address_country("Reading Berkshire GB RG2 6GB"), Some("GB"));
+
+    assert!(OuiDb::parse_any(csv).is_ok());
+    assert!(matches!(OuiDb::parse_ieee_csv("MA-X,3CA6F6,Apple"), Err(ParseOuiDbError::CsvRegistry(0, _))));
+    assert!(matches!(OuiDb::parse_ieee_csv("MA-M,3CA6F6,Apple"), Err(ParseOuiDbError::CsvAssignment(0, _, _))));
+    assert!(matches!(OuiDb::parse_ieee_csv("MA-L,\"3CA6F6,Apple"), Err(ParseOuiDbError::CsvRecord(0, _))));
+}
+
+#[test]
+fn longest_prefix_match() {
+    let db = OuiDb::from_entries(vec![
+        ("33:33:00".parse::<Oui>().unwrap().with_length(16).unwrap(), OuiMeta::new("IPv6mcast".to_owned(), None, None)),
+        ("33:33:00".parse().unwrap(), OuiMeta::new("Reserved".to_owned(), None, None)),
+        ("33:33:00:00:00:01/48".parse().unwrap(), OuiMeta::new("AllNodes".to_owned(), None, None)),
+        ("33:33:00:00:00:00/40".parse().unwrap(), OuiMeta::new("Low".to_owned(), None, None)),
+    ]);
+    let manuf = |mac: &str| db.search(parse_mac_addr(mac).unwrap()).map(|om| *om.manuf());
+    assert_eq!(manuf("33:33:ff:00:00:01"), Some("IPv6mcast"));
+    assert_eq!(manuf("33:33:00:aa:00:01"), Some("Reserved"));
+    assert_eq!(manuf("33:33:00:00:00:02"), Some("Low"));
+    assert_eq!(manuf("33:33:00:00:00:01"), Some("AllNodes"));
+    assert_eq!(manuf("33:34:00:00:00:01"), None);
+}
+
+#[test]
+fn search_many_in_order() {
+    let db = OuiDb::from_entries(vec![
+        ("3C:A6:F6".parse().unwrap(), OuiMeta::new("Corp".to_owned(), None, None)),
+        ("8C:1F:64:CB:20:00/36".parse().unwrap(), OuiMeta::new("Lab".to_owned(), None, None)),
+    ]);
+    let macs: Vec<MacAddr> = ["8c:1f:64:cb:2b:cc", "00:00:00:00:00:01", "3c:a6:f6:c4:34:f8"]
+        .into_iter()
+        .cycle()
+        .take(300)
+        .map(|m| parse_mac_addr(m).unwrap())
+        .collect();
+    let expected: Vec<_> = macs.iter().map(|m| db.search_entry(*m)).collect();
+    assert_eq!(expected[..3].iter().map(|e| e.as_ref().map(|(_o, om)| *om.manuf())).collect::<Vec<_>>(), [Some("Lab"), None, Some("Corp")]);
+
+    assert_eq!(db.search_many(macs.iter().copied()).collect::<Vec<_>>(), expected);
+    let mut out = vec![None; 5];
+    db.search_slice(&macs, &mut out);
+    assert_eq!(out, expected);
+    for threads in [1, 4, 1000] {
+        assert_eq!(db.search_slice_par(&macs, NonZeroUsize::new(threads).unwrap()), expected);
+    }
+    assert!(db.search_slice_par(&[], NonZeroUsize::new(4).unwrap()).is_empty());
+}
+
+#[test]
+fn merge_and_diff() {
+    let db = |entries: &[(&str, &str)]| OuiDb::from_entries(entries.iter()
+        .map(|(prefix, short)| (prefix.parse().unwrap(), OuiMeta::new(*short, None, None)))
+        .collect());
+    let old = db(&[("00:00:01", "Xerox"), ("3C:A6:F6", "Apple"), ("8C:1F:64:CB:20:00/36", "Lab")]);
+    let new = db(&[("00:00:01", "Xerox"), ("3C:A6:F6", "AppleInc"), ("B0:C5:59", "SamsungE")]);
+
+    let merged = old.merge(&new, MergePrecedence::Ours);
+    assert_eq!(merged.len(), 4);
+    assert_eq!(merged.search(parse_mac_addr("3c:a6:f6:00:00:01").unwrap()).map(|om| *om.manuf()), Some("Apple"));
+    let merged = old.merge(&new, MergePrecedence::Theirs);
+    assert_eq!(merged.search(parse_mac_addr("3c:a6:f6:00:00:01").unwrap()).map(|om| *om.manuf()), Some("AppleInc"));
+
+    let short = |s| OuiMeta::new(s, None, None);
+    assert_eq!(old.diff(&new).collect::<Vec<_>>(), vec![
+        DiffEntry::Changed("3C:A6:F6".parse().unwrap(), short("Apple"), short("AppleInc")),
+        DiffEntry::Removed("8C:1F:64:CB:20:00/36".parse().unwrap(), short("Lab")),
+        DiffEntry::Added("B0:C5:59".parse().unwrap(), short("SamsungE")),
+    ]);
+    assert_eq!(new.diff(&new).count(), 0);
+}
+
+#[test]
+fn parse_wka_prefixes() {
+    let db = OuiDb::parse_wka("# Well-known addresses.
+00-00-0C-07-AC/40\tAll-HSRP-routers
+01-80-C2-00-00-00\tSpanning-tree-(for-bridges)_00
+33-33-00-00-00-00/16\tIPv6mcast
+FF-FF-FF-FF-FF-FF\tBroadcast
+").unwrap();
+    let manuf = |mac: &str| db.search(parse_mac_addr(mac).unwrap()).map(|om| *om.manuf());
+    assert_eq!(manuf("ff:ff:ff:ff:ff:ff"), Some("Broadcast"));
+    assert_eq!(manuf("ff:ff:ff:ff:ff:fe"), None);
+    assert_eq!(manuf("01:80:c2:00:00:00"), Some("Spanning-tree-(for-bridges)_00"));
+    assert_eq!(manuf("01:80:c2:00:00:0e"), None);
+    assert_eq!(manuf("33:33:ff:00:00:01"), Some("IPv6mcast"));
+    assert_eq!(manuf("00:00:0c:07:ac:01"), Some("All-HSRP-routers"));
+    assert_eq!(EMBEDDED_WKA_DB.is_empty(), !cfg!(feature = "wka-db"));
+
+    // prefixes shorter than 8 bits would match whole swathes of unrelated addresses
+    assert!(Oui::parse_wka("00:00:00/0").is_err());
+    assert!(Oui::parse_wka("01:00:00/7").is_err());
+    assert!(Oui::parse_wka("0").is_err());
+    assert_eq!(Oui::parse_wka("01:00:00/8").unwrap().length(), 8);
+
+    // well-known addresses are searched after the vendor layers, so HSRP's /40 doesn't shadow Cisco's OUI
+    let shared = SharedOuiDb::new(Arc::new(OuiDb::from_entries(vec![
+        ("00:00:0c".parse::<Oui>().unwrap(), OuiMeta::new("Cisco", None, None)),
+    ])));
+    shared.set_layer(OuiLayerKind::WellKnown, Some((String::new(), Arc::new(db))));
+    let layered = |mac: &str| shared.load().search_entry(parse_mac_addr(mac).unwrap()).map(|(_o, om)| om.manuf().to_string());
+    assert_eq!(layered("00:00:0c:07:ac:01").as_deref(), Some("Cisco"));
+    assert_eq!(layered("ff:ff:ff:ff:ff:ff").as_deref(), Some("Broadcast"));
+}
+
+#[test]
+fn search_before_first_entry() {
+    let db = OuiDb::from_entries(vec![
+        ("3C:A6:F6".parse().unwrap(), OuiMeta::new("Corp".to_owned(), None, None)),
+        ("8C:1F:64:CB:20:00/36".parse().unwrap(), OuiMeta::new("Lab".to_owned(), None, None)),
+    ]);
+    assert_eq!(db.search(parse_mac_addr("08:00:87:aa:bb:cc").unwrap()), None);
+    assert_eq!(db.search(parse_mac_addr("8c:1f:64:cb:00:00").unwrap()), None);
+    assert_eq!(db.search(parse_mac_addr("3c:a6:f6:c4:34:f8").unwrap()).map(|om| *om.manuf()), Some("Corp"));
+    assert!("zz:zz".parse::<Oui>().is_err());
+}
+
+#[test]
+fn parse_hostile_lines() {
+    let fields = "3C:A6:F6\tApple".to_owned() + &"\txx".repeat(12);
+    assert!(matches!(OuiDb::parse_from_string(&fields), Err(ParseOuiDbError::BadFieldCount(0, 14, _))));
+    assert!(matches!("3c:a6:f6:aa:bb:cc:dd".parse::<Oui>(), Err(ParseOuiError::MacParsing(_))));
+    assert!(Oui::parse_wka("0123456789abcdef0/4").is_err());
+    assert!(OuiDb::parse_ieee_csv("MA-L,\"3CA6F6,Apple\n").is_err());
+}
+
+#[test]
+fn parse_large_files() {
+    // enough lines to be split between threads with the `rayon` feature
+    let mut txt: String = (0..5000u64).rev().map(|i| format!("{:06X}\tVendor{i}\n", i << 4)).collect();
+    let db = OuiDb::parse_from_string(&txt).unwrap();
+    assert_eq!(db.len(), 5000);
+    assert!(db.raw_prefixes().map(|(o, _om)| o).is_sorted());
+    assert_eq!(db.search(parse_mac_addr("00:00:10:00:00:01").unwrap()).map(|om| *om.manuf()), Some("Vendor1"));
+
+    // the first bad line is reported, regardless of which was parsed first
+    txt.push_str("bad\tline\n");
+    txt.insert_str(txt.find("Vendor2000\n").unwrap() + 11, "zz:zz:zz\tBad\n");
+    assert!(matches!(OuiDb::parse_from_string(&txt), Err(ParseOuiDbError::OuiParsing(3000, _, _))));
+}
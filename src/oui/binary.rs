@@ -0,0 +1,171 @@
+use std::io;
+#[cfg(not(target_family = "wasm"))]
+use std::path::Path;
+
+use super::{BlockType, Oui, OuiDb, OuiMeta};
+
+pub use super::raw::{MAGIC, VERSION};
+use super::raw::{entry_len, registry_code, HEADER_LEN, NONE, REGISTRIES};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReadOuiBinError {
+    #[error("unable to read binary oui database: {0}")]
+    Io(#[from] io::Error),
+    #[error("not a binary oui database (bad magic bytes)")]
+    BadMagic,
+    #[error("unsupported binary oui database version {0}, expected at most {VERSION}")]
+    UnsupportedVersion(u32),
+    #[error("binary oui database is truncated, expected {0} bytes but found {1}")]
+    Truncated(usize, usize),
+    #[error("invalid prefix in binary oui database entry {0}")]
+    BadPrefix(usize),
+    #[error("invalid string reference in binary oui database entry {0}")]
+    BadString(usize),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WriteOuiBinError {
+    #[error("binary oui database string is longer than {} bytes: {0:?}", u16::MAX)]
+    StringTooLong(String),
+    #[error("binary oui database strings exceed {} bytes", u32::MAX)]
+    ArenaTooLarge,
+}
+
+impl OuiDb {
+    /// Returns true if the data starts with the magic bytes of a binary OUI database.
+    pub fn is_binary(data: &[u8]) -> bool {
+        data.starts_with(MAGIC)
+    }
+
+    /// Serializes the database into the compact binary format read by [OuiDb::from_binary].
+    pub fn to_binary(&self) -> Result<Vec<u8>, WriteOuiBinError> {
+        let entries = self.raw_prefixes()
+            .map(|(oui, om)| {
+                let registry = om.block_type().and_then(|bt| registry_code(bt.as_str())).unwrap_or(0);
+                (oui.address, oui.length, registry, om.short, [om.long, om.comment, om.address, om.country])
+            });
+        super::raw::write_binary(self.raw_prefixes().count(), entries).map_err(|e| match e {
+            super::raw::WriteError::StringTooLong(s) => WriteOuiBinError::StringTooLong(s),
+            super::raw::WriteError::ArenaTooLarge => WriteOuiBinError::ArenaTooLarge,
+        })
+    }
+
+    /// Reads a database in the binary format produced by [OuiDb::to_binary].
+    pub fn from_binary(data: &[u8]) -> Result<OuiDb, ReadOuiBinError> {
+        let u32_at = |i: usize| u32::from_le_bytes(data[i..i + 4].try_into().unwrap());
+
+        if !OuiDb::is_binary(data) {
+            return Err(ReadOuiBinError::BadMagic);
+        }
+        if data.len() < HEADER_LEN {
+            return Err(ReadOuiBinError::Truncated(HEADER_LEN, data.len()));
+        }
+        let version = u32_at(8);
+        if !(1..=VERSION).contains(&version) {
+            return Err(ReadOuiBinError::UnsupportedVersion(version));
+        }
+        let entry_len = entry_len(version);
+        let count = u32_at(12) as usize;
+        let arena_len = u32_at(16) as usize;
+
+        let expected = count.checked_mul(entry_len)
+            .and_then(|n| n.checked_add(HEADER_LEN + arena_len))
+            .unwrap_or(usize::MAX);
+        if data.len() != expected {
+            return Err(ReadOuiBinError::Truncated(expected, data.len()));
+        }
+        let arena = &data[HEADER_LEN + count * entry_len..];
+
+        let string_at = |i: usize, off: u32| -> Result<&str, ReadOuiBinError> {
+            let off = off as usize;
+            let len = arena.get(off..off + 2).ok_or(ReadOuiBinError::BadString(i))?;
+            let len = u16::from_le_bytes([len[0], len[1]]) as usize;
+            let bytes = arena.get(off + 2..off + 2 + len).ok_or(ReadOuiBinError::BadString(i))?;
+            std::str::from_utf8(bytes).map_err(|_| ReadOuiBinError::BadString(i))
+        };
+        let opt_string_at = |i: usize, off: u32| match off {
+            NONE => Ok(None),
+            off => string_at(i, off).map(Some),
+        };
+
+        let entries = (0..count)
+            .map(|i| {
+                let base = HEADER_LEN + i * entry_len;
+                let key = u64::from_le_bytes(data[base..base + 8].try_into().unwrap());
+                let oui = Oui::from_int(key & 0x0000_FFFF_FFFF_FFFF)
+                    .and_then(|o| o.with_length((key >> 56) as u8))
+                    .map_err(|_| ReadOuiBinError::BadPrefix(i))?;
+                let mut meta = OuiMeta::new(
+                    string_at(i, u32_at(base + 8))?,
+                    opt_string_at(i, u32_at(base + 12))?,
+                    opt_string_at(i, u32_at(base + 16))?,
+                );
+                if version >= 2 {
+                    meta = meta.with_registrant(opt_string_at(i, u32_at(base + 20))?, opt_string_at(i, u32_at(base + 24))?);
+                }
+                // earlier versions left the registry code's byte zeroed
+                let block_type = match (key >> 48) as u8 {
+                    0 => None,
+                    code => Some(REGISTRIES.get(code as usize - 1)
+                        .and_then(|r| BlockType::from_abbreviation(r))
+                        .ok_or(ReadOuiBinError::BadPrefix(i))?),
+                };
+                meta = meta.with_block_type(block_type);
+                Ok((oui, meta))
+            })
+            .collect::<Result<Vec<_>, ReadOuiBinError>>()?;
+
+        Ok(OuiDb::from_entries(entries))
+    }
+
+    /// Reads a binary OUI database file. The file is read whole, not memory-mapped (see [the format](super::binary)).
+    #[cfg(not(target_family = "wasm"))]
+    pub fn load_binary_file(path: impl AsRef<Path>) -> Result<OuiDb, ReadOuiBinError> {
+        OuiDb::from_binary(&std::fs::read(path)?)
+    }
+
+    /// Writes the database to a file, in the binary format read by [OuiDb::load_binary_file].
+    #[cfg(not(target_family = "wasm"))]
+    pub fn save_binary_file(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let data = self.to_binary().map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        std::fs::write(path, data)
+    }
+}
+
+#[test]
+fn binary_roundtrip() {
+    let db = OuiDb::from_entries(vec![
+        ("3C:A6:F6".parse().unwrap(), OuiMeta::new("Apple".to_owned(), Some("Apple, Inc.".to_owned()), None)),
+        ("3C:A6:F7".parse().unwrap(), OuiMeta::new("Apple".to_owned(), None, Some("second block".to_owned()))),
+        ("8C:1F:64:CB:20:00/36".parse().unwrap(), OuiMeta::new("Lab".to_owned(), None, None)
+            .with_registrant(Some("Moscow RU".to_owned()), Some("RU".to_owned()))
+            .with_block_type(Some(BlockType::MaS))),
+    ]);
+    let data = db.to_binary().unwrap();
+    assert!(OuiDb::is_binary(&data));
+
+    let read = OuiDb::from_binary(&data).unwrap();
+    assert_eq!(read.raw_prefixes().collect::<Vec<_>>(), db.raw_prefixes().collect::<Vec<_>>());
+
+    assert!(matches!(OuiDb::from_binary(b"manuf\n"), Err(ReadOuiBinError::BadMagic)));
+    assert!(matches!(OuiDb::from_binary(&data[..data.len() - 1]), Err(ReadOuiBinError::Truncated(_, _))));
+    let mut future = data.clone();
+    future[8] = 4;
+    assert!(matches!(OuiDb::from_binary(&future), Err(ReadOuiBinError::UnsupportedVersion(4))));
+    let mut bad_registry = data.clone();
+    bad_registry[HEADER_LEN + 6] = 9;
+    assert!(matches!(OuiDb::from_binary(&bad_registry), Err(ReadOuiBinError::BadPrefix(0))));
+
+    // version 1 entries lack the registrant fields
+    let mut v1 = MAGIC.to_vec();
+    v1.extend_from_slice(&1u32.to_le_bytes());
+    v1.extend_from_slice(&1u32.to_le_bytes());
+    v1.extend_from_slice(&5u32.to_le_bytes());
+    v1.extend_from_slice(&((24u64 << 56) | 0x3CA6F6_000000).to_le_bytes());
+    v1.extend_from_slice(&0u32.to_le_bytes());
+    v1.extend_from_slice(&NONE.to_le_bytes());
+    v1.extend_from_slice(&NONE.to_le_bytes());
+    v1.extend_from_slice(b"\x03\x00Foo");
+    let read = OuiDb::from_binary(&v1).unwrap();
+    assert_eq!(read.raw_prefixes().map(|(_o, om)| om).collect::<Vec<_>>(), vec![OuiMeta::new("Foo", None, None)]);
+}
@@ -0,0 +1,61 @@
+// Shared with build.rs, so this must only depend on std
+
+use std::borrow::Cow;
+
+/// Splits a single CSV record into its fields, unescaping any quoted fields.
+///
+/// Returns None if a quoted field is left unterminated.
+pub fn csv_fields(line: &str) -> Option<Vec<Cow<'_, str>>> {
+    let mut fields = Vec::new();
+    let mut rest = line;
+    loop {
+        match rest.strip_prefix('"') {
+            Some(quoted) => {
+                // find the closing quote, skipping over escaped ("") quotes
+                let mut end = 0;
+                loop {
+                    end += quoted[end..].find('"')?;
+                    match quoted[end + 1..].starts_with('"') {
+                        true => end += 2,
+                        false => break,
+                    }
+                }
+                let field = &quoted[..end];
+                fields.push(match field.contains("\"\"") {
+                    true => Cow::Owned(field.replace("\"\"", "\"")),
+                    false => Cow::Borrowed(field),
+                });
+                rest = &quoted[end + 1..];
+                match rest.strip_prefix(',') {
+                    Some(r) => rest = r,
+                    None if rest.is_empty() => return Some(fields),
+                    None => return None,
+                }
+            },
+            None => match rest.split_once(',') {
+                Some((field, r)) => {
+                    fields.push(Cow::Borrowed(field));
+                    rest = r;
+                },
+                None => {
+                    fields.push(Cow::Borrowed(rest));
+                    return Some(fields);
+                },
+            },
+        }
+    }
+}
+
+/// Finds the ISO 3166 country code within an IEEE registry address, such as `US` in
+/// `1 Infinite Loop Cupertino CA US 95014`.
+///
+/// IEEE joins the address lines with spaces, with the country code following the city and state and preceding any
+/// postal code. The country is taken as the last two-letter uppercase word not preceded by a word containing digits,
+/// which skips over the letters within postal codes such as `5656 AE`.
+pub fn ieee_address_country(address: &str) -> Option<&str> {
+    let words: Vec<&str> = address.split_whitespace().collect();
+    (0..words.len()).rev()
+        .filter(|&i| words[i].len() == 2 && words[i].bytes().all(|b| b.is_ascii_uppercase()))
+        .find(|&i| i == 0 || !words[i - 1].bytes().any(|b| b.is_ascii_digit()))
+        .map(|i| words[i])
+}
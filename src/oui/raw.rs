@@ -0,0 +1,227 @@
+// Shared with build.rs, so this must only depend on std, sqlite3-nettools-core, and the sibling csv module
+
+use std::{borrow::Cow, collections::HashMap, num::ParseIntError};
+
+use super::csv::{csv_fields, ieee_address_country};
+
+/// The header line of each IEEE registry CSV download.
+pub const IEEE_CSV_HEADER: &str = "Registry,Assignment,Organization Name,Organization Address";
+
+/// Why a database prefix could not be parsed
+#[derive(Debug)]
+pub enum PrefixError {
+    Length(ParseIntError),
+    LengthValue(u8),
+    Mac(sqlite3_nettools_core::ParseError),
+}
+
+/// Parses a prefix as written in a Wireshark database, such as `00:11:22` or `00:11:22:30:00:00/28`, into its address
+/// (in the lower 48 bits) and length.
+///
+/// OUI prefixes are 24 to 48 bits, and are 24 bits when no length is given. Well-known address (`wka`) prefixes may be
+/// as short as 8 bits, and are as long as their digits when no length is given.
+pub fn parse_prefix(s: &str, wka: bool) -> Result<(u64, u8), PrefixError> {
+    let (addr, length) = match s.split_once('/') {
+        None => (s, None),
+        Some((addr, slen)) => (addr, Some(slen.parse::<u8>().map_err(PrefixError::Length)?)),
+    };
+    // shorter prefixes would match whole swathes of unrelated addresses
+    let min_length = if wka { 8 } else { 24 };
+    if let Some(length) = length.filter(|l| !(min_length..=48).contains(l)) {
+        return Err(PrefixError::LengthValue(length));
+    }
+
+    let mac = sqlite3_nettools_core::parse_mac(addr, true).map_err(PrefixError::Mac)?;
+    let length = match (length, wka) {
+        (Some(length), _) => length,
+        (None, false) => 24,
+        (None, true) => sqlite3_nettools_core::hex_value(addr).map_err(PrefixError::Mac)?.1 as u8 * 4,
+    };
+    if length < min_length {
+        return Err(PrefixError::LengthValue(length));
+    }
+    let address = mac.iter().fold(0u64, |acc, b| (acc << 8) | u64::from(*b));
+    Ok((address, length))
+}
+
+/// Trims a line of a Wireshark-format file, or returns None if it holds no entry.
+pub fn wireshark_line(l: &str) -> Option<&str> {
+    Some(l.trim()).filter(|l| !(l.is_empty() || l.starts_with('#')))
+}
+
+/// The tab separated fields of a Wireshark database entry
+#[derive(Debug, Clone, Copy)]
+pub struct WiresharkFields<'a> {
+    pub prefix: &'a str,
+    pub short: &'a str,
+    pub long: Option<&'a str>,
+    pub comment: Option<&'a str>,
+}
+
+/// Splits a line trimmed by [wireshark_line] into its fields. Returns the number of fields found if there aren't 2 to 4.
+pub fn wireshark_fields(l: &str) -> Result<WiresharkFields<'_>, usize> {
+    // count every field, so a line with too many is reported rather than overrunning `fields`
+    let mut fields = [""; 4];
+    let mut len = 0;
+    for (i, part) in l.split('\t').filter(|f| f.len() > 1).enumerate() {
+        len = i + 1;
+        if let Some(field) = fields.get_mut(i) {
+            *field = part.trim();
+        }
+    }
+    if !(2..=4).contains(&len) {
+        return Err(len);
+    }
+    Ok(WiresharkFields {
+        prefix: fields[0],
+        short: fields[1],
+        long: Some(fields[2]).filter(|_| len > 2),
+        comment: Some(fields[3]).filter(|_| len > 3).map(|s| s.trim_matches('#').trim()),
+    })
+}
+
+/// Trims a line of the IEEE registry CSVs, or returns None if it holds no entry (such as a file's header line).
+pub fn ieee_line(l: &str) -> Option<&str> {
+    Some(l.trim_start_matches('\u{feff}').trim()).filter(|l| !(l.is_empty() || l.starts_with(IEEE_CSV_HEADER)))
+}
+
+/// Why an IEEE registry CSV record could not be parsed
+#[derive(Debug)]
+pub enum IeeeError {
+    /// The record isn't valid CSV, or has fewer than 3 fields
+    Record,
+    Registry(String),
+    Assignment(String, String),
+}
+
+/// The IEEE registries, in the order of their codes within binary entries. Codes start from 1, with 0 marking an entry
+/// whose registry wasn't recorded.
+pub const REGISTRIES: [&str; 5] = ["MA-L", "MA-M", "MA-S", "IAB", "CID"];
+
+/// The binary entry code of a registry listed by [REGISTRIES]
+pub fn registry_code(registry: &str) -> Option<u8> {
+    REGISTRIES.iter().position(|r| *r == registry).map(|i| i as u8 + 1)
+}
+
+/// An entry of the IEEE registry CSVs
+#[derive(Debug)]
+pub struct IeeeEntry<'a> {
+    /// The registry the entry was assigned from, as its code within [REGISTRIES]
+    pub registry: u8,
+    /// The assignment, in the upper bits of the lower 48
+    pub address: u64,
+    pub length: u8,
+    pub name: Cow<'a, str>,
+    /// The registrant's postal address, if published
+    pub registrant: Option<Cow<'a, str>>,
+}
+
+impl IeeeEntry<'_> {
+    /// The ISO 3166 country code within the registrant's address
+    pub fn country(&self) -> Option<&str> {
+        self.registrant.as_deref().and_then(ieee_address_country)
+    }
+}
+
+/// Parses a line of the IEEE registry CSVs trimmed by [ieee_line].
+pub fn ieee_entry(l: &str) -> Result<IeeeEntry<'_>, IeeeError> {
+    let mut fields = csv_fields(l).filter(|f| f.len() >= 3).ok_or(IeeeError::Record)?.into_iter();
+    let (registry, assignment, name) = (fields.next().unwrap(), fields.next().unwrap(), fields.next().unwrap());
+    let registrant = fields.next().filter(|a| !a.trim().is_empty());
+
+    let registry = registry.trim();
+    let code = registry_code(registry).ok_or_else(|| IeeeError::Registry(registry.to_owned()))?;
+    let length: u8 = match registry {
+        "MA-M" => 28,
+        "MA-S" | "IAB" => 36,
+        _ => 24,
+    };
+    let assignment = assignment.trim();
+    let value = u64::from_str_radix(assignment, 16).ok()
+        .filter(|_| assignment.len() == length as usize / 4 && assignment.bytes().all(|b| b.is_ascii_hexdigit()))
+        .ok_or_else(|| IeeeError::Assignment(registry.to_owned(), assignment.to_owned()))?;
+
+    fn trim(s: Cow<'_, str>) -> Cow<'_, str> {
+        match s {
+            Cow::Borrowed(s) => Cow::Borrowed(s.trim()),
+            Cow::Owned(s) => Cow::Owned(s.trim().to_owned()),
+        }
+    }
+    Ok(IeeeEntry {
+        registry: code,
+        address: value << (48 - length),
+        length,
+        name: trim(name),
+        registrant: registrant.map(trim),
+    })
+}
+
+/// The first bytes of every binary OUI database.
+pub const MAGIC: &[u8; 8] = b"NTOUIDB\n";
+
+/// The binary format version written by [write_binary].
+pub const VERSION: u32 = 3;
+
+/// Arena offset marking an absent string.
+pub const NONE: u32 = u32::MAX;
+
+pub const HEADER_LEN: usize = 20;
+
+/// The length of each binary entry, by format version
+pub const fn entry_len(version: u32) -> usize {
+    match version {
+        1 => 20,
+        _ => 28,
+    }
+}
+
+/// Why a database could not be written in the binary format
+#[derive(Debug)]
+pub enum WriteError {
+    StringTooLong(String),
+    ArenaTooLarge,
+}
+
+/// An entry to write in the binary format: its address (in the lower 48 bits), length, registry code (0 if unknown),
+/// and short name, followed by its long name, comment, registrant address, and registrant country.
+pub type BinaryEntry<'a> = (u64, u8, u8, &'a str, [Option<&'a str>; 4]);
+
+/// Serializes entries into the binary format, as documented on `oui::binary`. Entries must already be sorted and
+/// deduplicated by prefix, and `count` of them.
+pub fn write_binary<'a>(count: usize, entries: impl IntoIterator<Item = BinaryEntry<'a>>) -> Result<Vec<u8>, WriteError> {
+    /// Appends a string to the arena, unless already present, returning its offset
+    fn intern<'a>(arena: &mut Vec<u8>, offsets: &mut HashMap<&'a str, u32>, s: &'a str) -> Result<u32, WriteError> {
+        if let Some(off) = offsets.get(s) {
+            return Ok(*off);
+        }
+        let len: u16 = s.len().try_into().map_err(|_| WriteError::StringTooLong(s.to_owned()))?;
+        let off: u32 = arena.len().try_into().ok().filter(|o| *o != NONE).ok_or(WriteError::ArenaTooLarge)?;
+        arena.extend_from_slice(&len.to_le_bytes());
+        arena.extend_from_slice(s.as_bytes());
+        offsets.insert(s, off);
+        Ok(off)
+    }
+
+    let mut arena: Vec<u8> = Vec::new();
+    let mut offsets: HashMap<&str, u32> = HashMap::new();
+
+    let mut body = Vec::with_capacity(count * entry_len(VERSION));
+    for (address, length, registry, short, optional) in entries {
+        body.extend_from_slice(&((u64::from(length) << 56) | (u64::from(registry) << 48) | address).to_le_bytes());
+        body.extend_from_slice(&intern(&mut arena, &mut offsets, short)?.to_le_bytes());
+        for s in optional {
+            let off = s.map(|s| intern(&mut arena, &mut offsets, s)).transpose()?.unwrap_or(NONE);
+            body.extend_from_slice(&off.to_le_bytes());
+        }
+    }
+
+    debug_assert_eq!(body.len(), count * entry_len(VERSION));
+    let mut out = Vec::with_capacity(HEADER_LEN + body.len() + arena.len());
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    out.extend_from_slice(&(count as u32).to_le_bytes());
+    out.extend_from_slice(&(arena.len() as u32).to_le_bytes());
+    out.extend_from_slice(&body);
+    out.extend_from_slice(&arena);
+    Ok(out)
+}
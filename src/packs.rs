@@ -0,0 +1,171 @@
+// Each pack is written by build.rs as `{name}.tsv`: one entry per line, with its fields separated by tabs. As the
+// build script checks each pack before writing it, reading them can only fail if the two fall out of sync.
+
+#[cfg(feature = "special-registry-db")]
+use ipnet::IpNet;
+
+/// Splits an embedded pack into the fields of each entry
+#[cfg(any(feature = "services-db", feature = "protocols-db", feature = "special-registry-db"))]
+fn pack_entries(tsv: &'static str) -> impl Iterator<Item = Vec<&'static str>> {
+    tsv.lines().map(|l| l.split('\t').collect())
+}
+
+/// Describes an embedded pack, as listed by [embedded]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmbeddedPack {
+    /// The pack's name, as used by its `NETTOOLS_{NAME}_PATH` build variable
+    pub name: &'static str,
+    /// Where the pack was fetched from at build time, marked `(cached)` if from an earlier build's download
+    pub source: &'static str,
+    /// The number of entries within the pack
+    pub entries: usize,
+}
+
+/// Lists the packs embedded in this build, as chosen by their cargo features.
+pub fn embedded() -> Vec<EmbeddedPack> {
+    vec![
+        #[cfg(feature = "services-db")]
+        EmbeddedPack { name: "services", source: SERVICES_SOURCE, entries: SERVICES.len() },
+        #[cfg(feature = "protocols-db")]
+        EmbeddedPack { name: "protocols", source: PROTOCOLS_SOURCE, entries: PROTOCOLS.len() },
+        #[cfg(feature = "special-registry-db")]
+        EmbeddedPack { name: "special-registry", source: SPECIAL_REGISTRY_SOURCE, entries: SPECIAL_REGISTRY.len() },
+    ]
+}
+
+/// A service name registered for a port, or a range of ports
+#[cfg(feature = "services-db")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Service {
+    pub name: &'static str,
+    pub first_port: u16,
+    pub last_port: u16,
+    /// The transport protocols the service is registered for, lowercase and slash separated, such as `tcp/udp`
+    pub transports: &'static str,
+    pub description: Option<&'static str>,
+}
+
+#[cfg(feature = "services-db")]
+impl Service {
+    /// Whether the service is registered for the port over the transport protocol (such as `tcp`, case insensitive)
+    pub fn matches(&self, port: u16, transport: &str) -> bool {
+        (self.first_port..=self.last_port).contains(&port)
+            && self.transports.split('/').any(|t| t.eq_ignore_ascii_case(transport))
+    }
+}
+
+/// Where the embedded services pack was fetched from at build time.
+#[cfg(feature = "services-db")]
+pub const SERVICES_SOURCE: &str = env!("NETTOOLS_SERVICES_DB_SOURCE");
+
+#[cfg(feature = "services-db")]
+lazy_static::lazy_static! {
+    /// Wireshark's service names for each port, in the order listed by its `services` file, embedded with the
+    /// `services-db` feature.
+    ///
+    /// Latest copy is available here: https://gitlab.com/wireshark/wireshark/raw/master/services
+    pub static ref SERVICES: Vec<Service> = pack_entries(include_str!(concat!(env!("OUT_DIR"), "/services.tsv")))
+        .map(|f| Service {
+            name: f[0],
+            first_port: f[1].parse().expect("malformed embedded services pack"),
+            last_port: f[2].parse().expect("malformed embedded services pack"),
+            transports: f[3],
+            description: Some(f[4]).filter(|d| !d.is_empty()),
+        })
+        .collect();
+}
+
+/// An IP protocol number assignment
+#[cfg(feature = "protocols-db")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Protocol {
+    pub number: u8,
+    /// The protocol's keyword, such as `TCP`. Empty for numbers reserved for experimentation or testing.
+    pub keyword: &'static str,
+    pub name: &'static str,
+    /// Whether the protocol is an IPv6 extension header
+    pub ipv6_extension: bool,
+}
+
+/// Where the embedded protocol numbers pack was fetched from at build time.
+#[cfg(feature = "protocols-db")]
+pub const PROTOCOLS_SOURCE: &str = env!("NETTOOLS_PROTOCOLS_DB_SOURCE");
+
+#[cfg(feature = "protocols-db")]
+lazy_static::lazy_static! {
+    /// IANA's assigned internet protocol numbers, sorted by number, embedded with the `protocols-db` feature.
+    ///
+    /// Latest copy is available here: https://www.iana.org/assignments/protocol-numbers/protocol-numbers-1.csv
+    pub static ref PROTOCOLS: Vec<Protocol> = pack_entries(include_str!(concat!(env!("OUT_DIR"), "/protocols.tsv")))
+        .map(|f| Protocol {
+            number: f[0].parse().expect("malformed embedded protocols pack"),
+            keyword: f[1],
+            name: f[2],
+            ipv6_extension: f[3] == "1",
+        })
+        .collect();
+}
+
+/// An entry of the IPv4 or IPv6 special-purpose address registry (RFC 6890). Its flags are None where the registry
+/// lists them as not applicable.
+#[cfg(feature = "special-registry-db")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpecialBlock {
+    pub network: IpNet,
+    pub name: &'static str,
+    /// The RFCs defining the block, such as `[RFC1122], Section 3.2.1.3`
+    pub rfc: &'static str,
+    pub source: Option<bool>,
+    pub destination: Option<bool>,
+    pub forwardable: Option<bool>,
+    pub globally_reachable: Option<bool>,
+    pub reserved_by_protocol: Option<bool>,
+}
+
+/// Where the embedded special-purpose address registries were fetched from at build time. Space separated.
+#[cfg(feature = "special-registry-db")]
+pub const SPECIAL_REGISTRY_SOURCE: &str = env!("NETTOOLS_SPECIAL_REGISTRY_DB_SOURCE");
+
+#[cfg(feature = "special-registry-db")]
+lazy_static::lazy_static! {
+    /// IANA's IPv4 and IPv6 special-purpose address registries, in the order listed, embedded with the
+    /// `special-registry-db` feature.
+    ///
+    /// Latest copies are available here: https://www.iana.org/assignments/iana-ipv4-special-registry/ and
+    /// https://www.iana.org/assignments/iana-ipv6-special-registry/
+    pub static ref SPECIAL_REGISTRY: Vec<SpecialBlock> = {
+        let flag = |f: &str| match f {
+            "" => None,
+            f => Some(f == "1"),
+        };
+        pack_entries(include_str!(concat!(env!("OUT_DIR"), "/special-registry.tsv")))
+            .map(|f| SpecialBlock {
+                network: f[0].parse().expect("malformed embedded special-registry pack"),
+                name: f[1],
+                rfc: f[2],
+                source: flag(f[3]),
+                destination: flag(f[4]),
+                forwardable: flag(f[5]),
+                globally_reachable: flag(f[6]),
+                reserved_by_protocol: flag(f[7]),
+            })
+            .collect()
+    };
+}
+
+#[test]
+fn embedded_packs() {
+    let packs = embedded();
+    assert_eq!(packs.iter().any(|p| p.name == "services"), cfg!(feature = "services-db"));
+    assert!(packs.iter().all(|p| p.entries > 0 && !p.source.is_empty()));
+
+    #[cfg(feature = "services-db")]
+    assert!(SERVICES.iter().any(|s| s.name == "http" && s.matches(80, "TCP")));
+    #[cfg(feature = "protocols-db")]
+    assert_eq!(PROTOCOLS.iter().find(|p| p.number == 6).map(|p| p.keyword), Some("TCP"));
+    #[cfg(feature = "special-registry-db")]
+    {
+        let loopback = SPECIAL_REGISTRY.iter().find(|b| b.network == "127.0.0.0/8".parse::<IpNet>().unwrap()).unwrap();
+        assert_eq!(loopback.globally_reachable, Some(false));
+    }
+}
@@ -0,0 +1,381 @@
+use std::{fmt, str::FromStr, sync::{Arc, Mutex, PoisonError}};
+
+use rusqlite::Connection;
+
+use crate::{oui::{OuiDb, EMBEDDED_DB}, settings::Settings};
+#[cfg(test)]
+use crate::test_util::{query_row, query_text, AUTO_REGISTER};
+
+/// A group of related functions, which may be registered independently of the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FunctionGroup {
+    /// `MAC_*` functions, `WOL_PACKET`, `LINKLOCAL_MATCHES_MAC`, `UUID_MAC`, `MANUF_NORMALIZE`, `MANUF_ASCII`, and the
+    /// `MACADDR` collation
+    Mac,
+    /// `IP_*`, `IP6_*`, and `IPB_*` functions, `CIDR_INTERSECT`, `SUBNET_UTILIZATION`, `IP_ROLLUP`,
+    /// `COUNT_DISTINCT_PREFIXES`, `FLOW_HASH`, and the `DNSBL_CHECK`, `FCRDNS`, and `RDAP` lookups
+    Inet,
+    /// `TCP_*`, `ETHERTYPE_*`, `VLAN_*`, and `MPLS_LABELS` packet field decoding, `PORTRANGE_CONTAINS`, the
+    /// `zeek_conn` and `netflow_file` log readers, and the `nmap_xml` scan results table
+    Packet,
+    /// `OUI_*` database management, the `oui` and `oui_prefixes` virtual tables, and `ETHERS_LOAD` and `ETHERS_NAME`
+    Oui,
+    /// ClickHouse's IP functions, such as `IPv4NumToString` and `isIPAddressInRange`, for porting queries. Only
+    /// registered when chosen
+    ClickHouse,
+    /// Splunk's `CIDRMATCH`, and MySQL's lowercase `inet_aton` family, for running searches translated from SIEMs. Only
+    /// registered when chosen
+    Siem,
+}
+
+impl FunctionGroup {
+    pub const ALL: [FunctionGroup; 6] = [
+        FunctionGroup::Mac, FunctionGroup::Inet, FunctionGroup::Packet, FunctionGroup::Oui,
+        FunctionGroup::ClickHouse, FunctionGroup::Siem,
+    ];
+
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            FunctionGroup::Mac => "mac",
+            FunctionGroup::Inet => "inet",
+            FunctionGroup::Packet => "packet",
+            FunctionGroup::Oui => "oui",
+            FunctionGroup::ClickHouse => "clickhouse",
+            FunctionGroup::Siem => "siem",
+        }
+    }
+
+    /// Whether the group is registered when no groups are chosen. Compatibility groups, which mirror other systems'
+    /// function names, must be chosen explicitly.
+    pub const fn is_default(&self) -> bool {
+        !matches!(self, FunctionGroup::ClickHouse | FunctionGroup::Siem)
+    }
+}
+
+impl fmt::Display for FunctionGroup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for FunctionGroup {
+    type Err = RegistrationConfigError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FunctionGroup::ALL.into_iter()
+            .find(|g| g.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| RegistrationConfigError::UnknownGroup(s.to_owned()))
+    }
+}
+
+/// How the names of registered functions, collations, and virtual tables are cased.
+///
+/// SQLite matches these names case-insensitively, so this only affects how they are listed (such as by
+/// `PRAGMA function_list`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Casing {
+    /// Names as documented, such as `MAC_FORMAT` and `oui_prefixes`
+    #[default]
+    Preserve,
+    Upper,
+    Lower,
+}
+
+impl FromStr for Casing {
+    type Err = RegistrationConfigError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "preserve" => Ok(Casing::Preserve),
+            "upper" => Ok(Casing::Upper),
+            "lower" => Ok(Casing::Lower),
+            _ => Err(RegistrationConfigError::UnknownCasing(s.to_owned())),
+        }
+    }
+}
+
+/// What to do with a function when another of the same name and number of arguments is already registered on the
+/// connection, such as by another extension.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum OnConflict {
+    /// Replace the existing function, as SQLite does by default
+    #[default]
+    Overwrite,
+    /// Keep the existing function, leaving this extension's out
+    Skip,
+    /// Register this extension's function under its name with another prefix, such as `NT_`. If that name is also
+    /// taken, the function is skipped.
+    Prefix(String),
+}
+
+impl FromStr for OnConflict {
+    type Err = RegistrationConfigError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.split_once(':') {
+            Some((kind, prefix)) if kind.eq_ignore_ascii_case("prefix") && !prefix.trim().is_empty() => {
+                Ok(OnConflict::Prefix(prefix.trim().to_owned()))
+            },
+            _ if s.eq_ignore_ascii_case("overwrite") => Ok(OnConflict::Overwrite),
+            _ if s.eq_ignore_ascii_case("skip") => Ok(OnConflict::Skip),
+            _ => Err(RegistrationConfigError::UnknownConflict(s.to_owned())),
+        }
+    }
+}
+
+/// How a colliding function was registered, according to [OnConflict].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    Overwritten,
+    Skipped,
+    /// Registered under this name instead
+    Renamed(String),
+}
+
+/// A function found already registered on the connection, under the name and number of arguments of one of this
+/// extension's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collision {
+    /// The name this extension's function would have been registered under
+    pub name: String,
+    /// The number of arguments, or -1 for any number
+    pub n_arg: i32,
+    pub resolution: Resolution,
+}
+
+/// The collisions met while registering a connection's functions, as reported by `NETTOOLS_SELFTEST`. Clones share
+/// the same list.
+#[derive(Debug, Clone, Default)]
+pub struct SharedCollisions(Arc<Mutex<Vec<Collision>>>);
+
+impl SharedCollisions {
+    pub fn new() -> SharedCollisions {
+        SharedCollisions::default()
+    }
+
+    pub fn push(&self, collision: Collision) {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).push(collision);
+    }
+
+    /// Returns each collision, in the order they were met.
+    pub fn collisions(&self) -> Vec<Collision> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RegistrationConfigError {
+    #[error("unknown function group {0:?}, expected one of mac, inet, packet, oui, clickhouse, or siem")]
+    UnknownGroup(String),
+    #[error("unknown name casing {0:?}, expected one of preserve, upper, or lower")]
+    UnknownCasing(String),
+    #[error("unknown conflict handling {0:?}, expected one of overwrite, skip, or prefix:NAME")]
+    UnknownConflict(String),
+}
+
+/// Chooses which functions to register on a connection, and how to name them.
+///
+/// By default, every group (except for the compatibility groups, see [FunctionGroup::is_default]) is registered under
+/// its documented name. Choosing any groups with [Registration::group] registers only those groups. When loaded as an extension, these are read from the environment (see
+/// [Registration::from_env]).
+///
+/// ```
+/// use sqlite3_nettools::{Registration, FunctionGroup};
+///
+/// let conn = rusqlite::Connection::open_in_memory()?;
+/// Registration::builder()
+///     .group(FunctionGroup::Mac)
+///     .prefix("NT_")
+///     .register(&conn)?;
+/// let mac: String = conn.query_row("SELECT NT_MAC_FORMAT('3ca6f6c434f8', 'colon')", [], |r| r.get(0))?;
+/// assert_eq!(mac, "3c:a6:f6:c4:34:f8");
+/// assert!(conn.query_row("SELECT IP_FORMAT('10.0.0.1')", [], |r| r.get::<_, String>(0)).is_err());
+/// # Ok::<(), rusqlite::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Registration {
+    groups: Vec<FunctionGroup>,
+    prefix: String,
+    casing: Casing,
+    on_conflict: OnConflict,
+}
+
+impl Registration {
+    pub fn builder() -> Registration {
+        Registration::default()
+    }
+
+    /// Reads the registration options from the environment, as used when loaded as an extension:
+    /// * `NETTOOLS_GROUPS` - A comma separated list of groups to register, such as `mac,oui`
+    /// * `NETTOOLS_PREFIX` - A prefix for every name, such as `NT_`
+    /// * `NETTOOLS_CASING` - One of `preserve`, `upper`, or `lower`
+    /// * `NETTOOLS_ON_CONFLICT` - One of `overwrite`, `skip`, or `prefix:NAME` (such as `prefix:NT_`)
+    pub fn from_env() -> Result<Registration, RegistrationConfigError> {
+        let var = |name| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+
+        let mut reg = Registration::builder();
+        if let Some(groups) = var("NETTOOLS_GROUPS") {
+            for group in groups.split(',') {
+                reg = reg.group(group.parse()?);
+            }
+        }
+        if let Some(prefix) = var("NETTOOLS_PREFIX") {
+            reg = reg.prefix(prefix.trim());
+        }
+        if let Some(casing) = var("NETTOOLS_CASING") {
+            reg = reg.casing(casing.parse()?);
+        }
+        if let Some(on_conflict) = var("NETTOOLS_ON_CONFLICT") {
+            reg = reg.on_conflict(on_conflict.parse()?);
+        }
+        Ok(reg)
+    }
+
+    /// Registers this group of functions. May be called more than once.
+    pub fn group(mut self, group: FunctionGroup) -> Registration {
+        if !self.groups.contains(&group) {
+            self.groups.push(group);
+        }
+        self
+    }
+
+    /// Prefixes the name of every function, collation, and virtual table, to avoid collisions with other extensions.
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Registration {
+        self.prefix = prefix.into();
+        self
+    }
+
+    pub fn casing(mut self, casing: Casing) -> Registration {
+        self.casing = casing;
+        self
+    }
+
+    /// Chooses what to do with functions already registered under the same name and number of arguments, such as by
+    /// another extension. Each collision is reported by `NETTOOLS_SELFTEST`.
+    pub fn on_conflict(mut self, on_conflict: OnConflict) -> Registration {
+        self.on_conflict = on_conflict;
+        self
+    }
+
+    pub fn conflict_handling(&self) -> &OnConflict {
+        &self.on_conflict
+    }
+
+    /// Whether the group of functions will be registered.
+    pub fn includes(&self, group: FunctionGroup) -> bool {
+        match self.groups.is_empty() {
+            true => group.is_default(),
+            false => self.groups.contains(&group),
+        }
+    }
+
+    /// The name a function, collation, or virtual table is registered under.
+    pub fn name(&self, name: &str) -> String {
+        self.cased(format!("{}{}", self.prefix, name))
+    }
+
+    /// The name a colliding function is registered under with [OnConflict::Prefix], given the name it collided under.
+    pub fn alternate_name(&self, registered: &str) -> Option<String> {
+        match &self.on_conflict {
+            OnConflict::Prefix(prefix) => Some(self.cased(format!("{prefix}{registered}"))),
+            _ => None,
+        }
+    }
+
+    fn cased(&self, name: String) -> String {
+        match self.casing {
+            Casing::Preserve => name,
+            Casing::Upper => name.to_ascii_uppercase(),
+            Casing::Lower => name.to_ascii_lowercase(),
+        }
+    }
+
+    /// Registers the chosen functions on a connection.
+    pub fn register(&self, dbconn: &Connection) -> rusqlite::Result<()> {
+        crate::register_with(dbconn, RegistrationOptions { registration: self.clone(), ..Default::default() })
+    }
+
+    /// Registers the chosen functions on every connection opened from now on, as with [auto_register](crate::auto_register).
+    pub fn auto_register(&self) -> rusqlite::Result<()> {
+        crate::auto_register_with(RegistrationOptions { registration: self.clone(), ..Default::default() })
+    }
+}
+
+/// Options for registering functions on a connection with [register_with](crate::register_with).
+#[derive(Debug, Clone)]
+pub struct RegistrationOptions {
+    /// The OUI database the connection's lookups start with, before any are loaded at runtime
+    pub oui_db: Arc<OuiDb>,
+    /// Whether Wireshark's well-known addresses ([EMBEDDED_WKA_DB](crate::oui::EMBEDDED_WKA_DB)) are looked up after
+    /// `oui_db`, when built with the `wka-db` feature
+    pub well_known: bool,
+    /// Which functions to register, and under what names
+    pub registration: Registration,
+    /// The connection's initial defaults, as later changed with `NETTOOLS_SET`
+    pub settings: Settings,
+}
+
+impl Default for RegistrationOptions {
+    fn default() -> RegistrationOptions {
+        RegistrationOptions {
+            oui_db: Arc::clone(&EMBEDDED_DB),
+            well_known: true,
+            registration: Registration::default(),
+            settings: Settings::default(),
+        }
+    }
+}
+
+#[test]
+fn registration_names() {
+    let reg = Registration::builder().prefix("nt_").casing(Casing::Upper);
+    assert_eq!(reg.name("MAC_FORMAT"), "NT_MAC_FORMAT");
+    assert_eq!(reg.name("oui_prefixes"), "NT_OUI_PREFIXES");
+    assert_eq!(Registration::builder().name("oui_prefixes"), "oui_prefixes");
+
+    assert!(FunctionGroup::ALL.iter().all(|g| Registration::builder().includes(*g) == g.is_default()));
+    let reg = Registration::builder().group(FunctionGroup::Mac).group("OUI".parse().unwrap());
+    assert!(reg.includes(FunctionGroup::Oui) && !reg.includes(FunctionGroup::Inet));
+    assert!(matches!("ipv4".parse::<FunctionGroup>(), Err(RegistrationConfigError::UnknownGroup(_))));
+}
+
+#[test]
+fn registration_conflicts() {
+    let other_extension = |conn: &Connection| {
+        let flags = rusqlite::functions::FunctionFlags::SQLITE_UTF8;
+        conn.create_scalar_function("mac_format", 1, flags, |_| Ok("other")).unwrap();
+    };
+    let text = |conn: &Connection, sql: &str| query_text(conn, sql).unwrap();
+
+    let conn = Connection::open_in_memory().unwrap();
+    other_extension(&conn);
+    Registration::builder().on_conflict(OnConflict::Skip).register(&conn).unwrap();
+    assert_eq!(text(&conn, "SELECT MAC_FORMAT('3ca6f6c434f8')"), "other");
+    assert_eq!(text(&conn, "SELECT MAC_FORMAT('3ca6f6c434f8', 'colon')"), "3c:a6:f6:c4:34:f8");
+    let report = text(&conn, "SELECT NETTOOLS_SELFTEST() ->> '$.checks[3].detail.collisions'");
+    assert_eq!(report, r#"[{"n_arg":1,"name":"MAC_FORMAT","resolution":"skipped"}]"#);
+
+    let conn = Connection::open_in_memory().unwrap();
+    other_extension(&conn);
+    Registration::builder().on_conflict("prefix:nt_".parse().unwrap()).register(&conn).unwrap();
+    assert_eq!(text(&conn, "SELECT MAC_FORMAT('3ca6f6c434f8')"), "other");
+    assert_eq!(text(&conn, "SELECT nt_MAC_FORMAT('3ca6f6c434f8')"), "3c:a6:f6:c4:34:f8");
+
+    let conn = Connection::open_in_memory().unwrap();
+    other_extension(&conn);
+    Registration::builder().register(&conn).unwrap();
+    assert_eq!(text(&conn, "SELECT MAC_FORMAT('3ca6f6c434f8')"), "3c:a6:f6:c4:34:f8");
+
+    assert!(matches!("prefix:".parse::<OnConflict>(), Err(RegistrationConfigError::UnknownConflict(_))));
+}
+
+#[test]
+fn registration_auto_register() {
+    let _lock = AUTO_REGISTER.lock().unwrap_or_else(PoisonError::into_inner);
+
+    // as sqlite3_nettools_permanent_init does with the options read from the environment
+    Registration::builder().prefix("perm_").casing(Casing::Lower).auto_register().unwrap();
+    let conn = Connection::open_in_memory().unwrap();
+    assert!(crate::cancel_auto_register());
+    assert_eq!(query_text(&conn, "SELECT perm_mac_format('3ca6f6c434f8', 'colon')").as_deref(), Some("3c:a6:f6:c4:34:f8"));
+    assert!(query_row::<bool>(&conn, "SELECT perm_oui_db_size() > 0"));
+    assert!(conn.query_row("SELECT MAC_FORMAT('3ca6f6c434f8')", [], |_| Ok(())).is_err());
+}
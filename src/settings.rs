@@ -0,0 +1,159 @@
+use std::{fmt, net::{IpAddr, SocketAddr}, str::FromStr, sync::{Arc, PoisonError, RwLock}};
+
+/// How IPv6 addresses are written by `IP_FORMAT_DEFAULT`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Ipv6Style {
+    /// The shortest form, with the longest run of zero groups elided: `fe80::2:3:0:aabb`
+    #[default]
+    Compressed,
+    /// All eight groups, with each padded to four digits: `fe80:0000:0000:0000:0002:0003:0000:aabb`
+    Expanded,
+}
+
+impl Ipv6Style {
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Ipv6Style::Compressed => "compressed",
+            Ipv6Style::Expanded => "expanded",
+        }
+    }
+}
+
+impl fmt::Display for Ipv6Style {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Ipv6Style {
+    type Err = SettingError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "compressed" => Ok(Ipv6Style::Compressed),
+            "expanded" => Ok(Ipv6Style::Expanded),
+            _ => Err(SettingError::UnknownIpv6Style(s.to_owned())),
+        }
+    }
+}
+
+/// Defaults used by a connection's functions when an argument is omitted, as changed with `NETTOOLS_SET`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Settings {
+    /// The format specifier used by `MAC_FORMAT_DEFAULT`, or None for its built-in default
+    pub mac_style: Option<String>,
+    /// How `IP_FORMAT_DEFAULT` writes IPv6 addresses, or None for [Ipv6Style::Compressed]
+    pub ipv6_style: Option<Ipv6Style>,
+    /// The DNS server queried by functions making DNS lookups, or None for the system's configured server
+    pub dns_server: Option<SocketAddr>,
+    /// Whether each function's calls, errors, and time are counted for `NETTOOLS_STATS`
+    pub stats: bool,
+}
+
+impl Settings {
+    /// Returns a setting's value as text, or None when unset.
+    pub fn get(&self, name: &str) -> Result<Option<String>, SettingError> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "mac_style" => Ok(self.mac_style.clone()),
+            "ipv6_style" => Ok(self.ipv6_style.map(|s| s.to_string())),
+            "dns_server" => Ok(self.dns_server.map(|s| s.to_string())),
+            "stats" => Ok(self.stats.then(|| "1".to_owned())),
+            _ => Err(SettingError::UnknownSetting(name.to_owned())),
+        }
+    }
+
+    /// Changes (or with None, unsets) a setting from its text value. The MAC style isn't checked here, as its format
+    /// specifiers are parsed by `MAC_FORMAT_DEFAULT`.
+    pub fn set(&mut self, name: &str, value: Option<&str>) -> Result<(), SettingError> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "mac_style" => self.mac_style = value.map(str::to_owned),
+            "ipv6_style" => self.ipv6_style = value.map(str::parse).transpose()?,
+            "dns_server" => self.dns_server = value.map(parse_dns_server).transpose()?,
+            "stats" => self.stats = value.map(parse_switch).transpose()?.unwrap_or(false),
+            _ => return Err(SettingError::UnknownSetting(name.to_owned())),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SettingError {
+    #[error("unknown setting {0:?}, expected one of mac_style, ipv6_style, dns_server, or stats")]
+    UnknownSetting(String),
+    #[error("unknown IPv6 style {0:?}, expected one of compressed or expanded")]
+    UnknownIpv6Style(String),
+    #[error("invalid DNS server {0:?}, expected an IP address, optionally with a port")]
+    BadDnsServer(String),
+    #[error("invalid switch {0:?}, expected 0 or 1")]
+    BadSwitch(String),
+}
+
+/// Reads a DNS server's address, defaulting to port 53.
+fn parse_dns_server(s: &str) -> Result<SocketAddr, SettingError> {
+    let s = s.trim();
+    s.parse::<SocketAddr>()
+        .or_else(|_| s.parse::<IpAddr>().map(|addr| SocketAddr::new(addr, 53)))
+        .map_err(|_| SettingError::BadDnsServer(s.to_owned()))
+}
+
+/// Reads a setting turning something on (`1`) or off (`0`).
+fn parse_switch(s: &str) -> Result<bool, SettingError> {
+    match s.trim() {
+        "1" => Ok(true),
+        "0" => Ok(false),
+        s => Err(SettingError::BadSwitch(s.to_owned())),
+    }
+}
+
+/// The settings used by a connection's functions. Clones share the same settings.
+///
+/// Functions take a snapshot of the current settings, so a change never affects a call already in progress.
+#[derive(Debug, Clone, Default)]
+pub struct SharedSettings(Arc<RwLock<Arc<Settings>>>);
+
+impl SharedSettings {
+    pub fn new(settings: Settings) -> SharedSettings {
+        SharedSettings(Arc::new(RwLock::new(Arc::new(settings))))
+    }
+
+    /// Returns a snapshot of the current settings.
+    pub fn load(&self) -> Arc<Settings> {
+        // the lock only guards an Arc swap, so a poisoned lock can't hold partially updated settings
+        self.0.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Updates the settings, keeping the previous settings if `f` fails.
+    pub fn update<E>(&self, f: impl FnOnce(&mut Settings) -> Result<(), E>) -> Result<(), E> {
+        let mut current = self.0.write().unwrap_or_else(PoisonError::into_inner);
+        let mut settings = Settings::clone(&current);
+        f(&mut settings)?;
+        *current = Arc::new(settings);
+        Ok(())
+    }
+}
+
+#[test]
+fn settings_by_name() {
+    let mut settings = Settings::default();
+    settings.set("IPv6_Style", Some("Expanded")).unwrap();
+    settings.set("mac_style", Some("DASH")).unwrap();
+    assert_eq!(settings.ipv6_style, Some(Ipv6Style::Expanded));
+    assert_eq!(settings.get("mac_style").unwrap().as_deref(), Some("DASH"));
+    assert!(settings.set("ipv6_style", Some("short")).is_err());
+    assert!(settings.set("color", Some("blue")).is_err());
+
+    settings.set("ipv6_style", None).unwrap();
+    assert_eq!(settings.get("ipv6_style").unwrap(), None);
+
+    settings.set("dns_server", Some("9.9.9.9")).unwrap();
+    assert_eq!(settings.get("dns_server").unwrap().as_deref(), Some("9.9.9.9:53"));
+    settings.set("dns_server", Some("[2620:fe::fe]:5353")).unwrap();
+    assert_eq!(settings.get("dns_server").unwrap().as_deref(), Some("[2620:fe::fe]:5353"));
+    assert!(settings.set("dns_server", Some("dns.example")).is_err());
+
+    settings.set("stats", Some("1")).unwrap();
+    assert!(settings.stats);
+    assert_eq!(settings.get("stats").unwrap().as_deref(), Some("1"));
+    assert!(settings.set("stats", Some("yes")).is_err());
+    settings.set("stats", None).unwrap();
+    assert!(!settings.stats);
+}
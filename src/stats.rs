@@ -0,0 +1,117 @@
+use std::{sync::{atomic::{AtomicBool, AtomicU64, Ordering}, Arc, Mutex, PoisonError}, time::{Duration, Instant}};
+
+/// The running counts for one of a connection's functions. Each arity of a function shares the same counts.
+#[derive(Debug, Default)]
+pub struct FunctionCounts {
+    calls: AtomicU64,
+    errors: AtomicU64,
+    nanos: AtomicU64,
+}
+
+impl FunctionCounts {
+    /// Counts a call that began at `started`, and whether it raised an error.
+    pub fn record(&self, started: Instant, failed: bool) {
+        let nanos = u64::try_from(started.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.errors.fetch_add(u64::from(failed), Ordering::Relaxed);
+        self.nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+
+    fn reset(&self) {
+        self.calls.store(0, Ordering::Relaxed);
+        self.errors.store(0, Ordering::Relaxed);
+        self.nanos.store(0, Ordering::Relaxed);
+    }
+}
+
+/// A snapshot of a function's counts, as reported by `NETTOOLS_STATS`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionStats {
+    /// The name the function was registered under, including any prefix
+    pub name: String,
+    /// The number of calls, counting each row given to an aggregate function as a call
+    pub calls: u64,
+    /// The number of calls raising an error, such as for an argument that couldn't be parsed
+    pub errors: u64,
+    /// The total time spent within the function's calls
+    pub time: Duration,
+}
+
+/// Each function's counts, by the name it was registered under
+type Functions = Vec<(String, Arc<FunctionCounts>)>;
+
+/// How a connection's functions have been used, as reported by `NETTOOLS_STATS`. Clones share the same counts.
+///
+/// Functions are only counted while enabled (with `NETTOOLS_SET('stats', 1)`), sparing every call the time it takes to
+/// measure it otherwise.
+#[derive(Debug, Clone, Default)]
+pub struct SharedStats {
+    functions: Arc<Mutex<Functions>>,
+    enabled: Arc<AtomicBool>,
+}
+
+impl SharedStats {
+    pub fn new() -> SharedStats {
+        SharedStats::default()
+    }
+
+    /// Whether functions are being counted.
+    pub fn enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Starts or stops counting functions, keeping the counts so far.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Returns the counts for a function by its registered name, adding them if they don't exist yet.
+    pub fn function(&self, name: &str) -> Arc<FunctionCounts> {
+        // the lock only guards adding functions at registration, so a poisoned lock holds nothing partially updated
+        let mut functions = self.functions.lock().unwrap_or_else(PoisonError::into_inner);
+        match functions.iter().find(|(n, _)| n == name) {
+            Some((_, counts)) => Arc::clone(counts),
+            None => {
+                let counts = Arc::new(FunctionCounts::default());
+                functions.push((name.to_owned(), Arc::clone(&counts)));
+                counts
+            },
+        }
+    }
+
+    /// Returns the counts of each function called at least once, in the order they were registered.
+    pub fn functions(&self) -> Vec<FunctionStats> {
+        self.functions.lock().unwrap_or_else(PoisonError::into_inner).iter()
+            .map(|(name, counts)| FunctionStats {
+                name: name.clone(),
+                calls: counts.calls.load(Ordering::Relaxed),
+                errors: counts.errors.load(Ordering::Relaxed),
+                time: Duration::from_nanos(counts.nanos.load(Ordering::Relaxed)),
+            })
+            .filter(|f| f.calls > 0)
+            .collect()
+    }
+
+    /// Zeroes every function's counts.
+    pub fn reset(&self) {
+        for (_, counts) in self.functions.lock().unwrap_or_else(PoisonError::into_inner).iter() {
+            counts.reset();
+        }
+    }
+}
+
+#[test]
+fn function_counts() {
+    let stats = SharedStats::new();
+    let format = stats.function("MAC_FORMAT");
+    format.record(Instant::now(), false);
+    stats.function("MAC_FORMAT").record(Instant::now(), true);
+    stats.function("MAC_MANUF");
+
+    let functions = stats.functions();
+    assert_eq!(functions.len(), 1);
+    assert_eq!((functions[0].name.as_str(), functions[0].calls, functions[0].errors), ("MAC_FORMAT", 2, 1));
+
+    stats.reset();
+    assert!(stats.functions().is_empty());
+}
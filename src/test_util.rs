@@ -0,0 +1,73 @@
+//! Helpers shared by the tests of the SQL functions
+
+use std::sync::Mutex;
+
+use rusqlite::{types::{FromSql, Value}, Connection};
+
+/// Opens an in-memory connection with the default functions registered.
+pub fn conn() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    crate::register(&conn).unwrap();
+    conn
+}
+
+/// Held by tests calling [auto_register](crate::auto_register), which changes how every connection opened in the
+/// process is registered.
+pub static AUTO_REGISTER: Mutex<()> = Mutex::new(());
+
+/// Runs a query for a single value, such as `SELECT MAC_FORMAT(...)`.
+pub fn query_row<T: FromSql>(conn: &Connection, sql: &str) -> T {
+    conn.query_row(sql, [], |r| r.get(0)).unwrap()
+}
+
+/// Runs a query for a single text value, or None if it's NULL.
+pub fn query_text(conn: &Connection, sql: &str) -> Option<String> {
+    query_row(conn, sql)
+}
+
+/// Runs a query expected to fail, returning its error message.
+pub fn query_err(conn: &Connection, sql: &str) -> String {
+    conn.query_row(sql, [], |r| r.get::<_, Value>(0)).unwrap_err().to_string()
+}
+
+/// A path in the temporary directory, removed (with anything under it) when dropped, so fixtures don't leak when a
+/// test panics.
+pub struct TempPath(std::path::PathBuf);
+
+impl TempPath {
+    /// A path for `name`, made unique to this process. Nothing is created.
+    pub fn new(name: &str) -> TempPath {
+        TempPath(std::env::temp_dir().join(format!("nettools-{}-{name}", std::process::id())))
+    }
+
+    /// Writes `contents` to a new file at the path for `name`.
+    pub fn file(name: &str, contents: impl AsRef<[u8]>) -> TempPath {
+        let path = TempPath::new(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    /// The path as a string, for passing as an SQL parameter.
+    pub fn as_str(&self) -> &str {
+        self.0.to_str().unwrap()
+    }
+}
+
+impl std::ops::Deref for TempPath {
+    type Target = std::path::Path;
+    fn deref(&self) -> &std::path::Path {
+        &self.0
+    }
+}
+
+impl AsRef<std::path::Path> for TempPath {
+    fn as_ref(&self) -> &std::path::Path {
+        &self.0
+    }
+}
+
+impl Drop for TempPath {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0).or_else(|_| std::fs::remove_file(&self.0));
+    }
+}
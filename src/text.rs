@@ -0,0 +1,80 @@
+use std::fmt;
+
+use rusqlite::types::{ToSql, ToSqlOutput, ValueRef};
+
+/// A text result, returned to SQLite by reference rather than through an owned `String`.
+///
+/// SQLite copies the text into its own memory as the function returns, so a result formatted into a stack buffer
+/// (such as [TextBuf] or a `SmallString`) reaches the query without a heap allocation on the Rust side.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SqlText<T>(pub T);
+
+impl<T: AsRef<str>> ToSql for SqlText<T> {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Borrowed(ValueRef::Text(self.0.as_ref().as_bytes())))
+    }
+}
+
+/// A fixed-capacity string buffer, written to with `write!`. Writes beyond its capacity fail with [fmt::Error].
+///
+/// ```
+/// # use sqlite3_nettools::text::TextBuf;
+/// use std::fmt::Write;
+///
+/// let mut buf = TextBuf::<8>::new();
+/// write!(buf, "{}/{}", "10.0", 8).unwrap();
+/// assert_eq!(buf.as_str(), "10.0/8");
+/// assert!(write!(buf, "{}", "abc").is_err());
+/// ```
+#[derive(Clone, Copy)]
+pub struct TextBuf<const N: usize> {
+    buf: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> TextBuf<N> {
+    pub const fn new() -> TextBuf<N> {
+        TextBuf { buf: [0; N], len: 0 }
+    }
+
+    pub fn as_str(&self) -> &str {
+        // only whole `str`s are ever copied in
+        std::str::from_utf8(&self.buf[..self.len]).unwrap()
+    }
+}
+
+impl<const N: usize> Default for TextBuf<N> {
+    fn default() -> TextBuf<N> {
+        TextBuf::new()
+    }
+}
+
+impl<const N: usize> fmt::Write for TextBuf<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > N {
+            return Err(fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+impl<const N: usize> AsRef<str> for TextBuf<N> {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> fmt::Display for TextBuf<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<const N: usize> fmt::Debug for TextBuf<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
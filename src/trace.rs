@@ -0,0 +1,26 @@
+// Instrumentation through the `tracing` crate, for host applications to see what the extension is doing. Without the
+// `tracing` feature, these expand to nothing, and the fields given to them are never evaluated.
+
+/// Emits an event at a [tracing::Level] (such as `DEBUG`), taking the same fields and message as `tracing::event!`
+macro_rules! event {
+    ($level: ident, $($arg: tt)+) => {{
+        #[cfg(feature = "tracing")]
+        tracing::event!(tracing::Level::$level, $($arg)+);
+    }};
+}
+
+/// Enters a span at a [tracing::Level] (such as `DEBUG`) until the returned guard is dropped, taking the same name
+/// and fields as `tracing::span!`
+macro_rules! span {
+    ($level: ident, $($arg: tt)+) => {{
+        #[cfg(feature = "tracing")]
+        let entered = tracing::span!(tracing::Level::$level, $($arg)+).entered();
+        #[cfg(not(feature = "tracing"))]
+        let entered = $crate::trace::Entered;
+        entered
+    }};
+}
+
+/// Stands in for an entered span when built without the `tracing` feature
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct Entered;